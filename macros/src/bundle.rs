@@ -1,10 +1,8 @@
-use std::borrow::Cow;
-
 use proc_macro2::TokenStream as TokenStream2;
 use quote::quote;
 use syn::{DeriveInput, Error, Result};
 
-use crate::common::{member_as_idents, struct_fields};
+use crate::common::{bundle_field_kinds, member_as_idents, struct_fields, BundleField};
 
 pub fn derive(input: DeriveInput) -> Result<TokenStream2> {
     let ident = input.ident;
@@ -17,32 +15,76 @@ pub fn derive(input: DeriveInput) -> Result<TokenStream2> {
             ))
         }
     };
-    let (tys, field_members) = struct_fields(&data.fields);
-    let field_idents = member_as_idents(&field_members);
+    let (all_tys, all_members) = struct_fields(&data.fields);
+    let kinds = bundle_field_kinds(&data.fields)?;
+    let fields = Fields::new(&all_tys, &all_members, &kinds);
     let generics = add_additional_bounds_to_generic_params(input.generics);
 
-    let dyn_bundle_code = gen_dynamic_bundle_impl(&ident, &generics, &field_members, &tys);
-    let bundle_code = if tys.is_empty() {
-        gen_unit_struct_bundle_impl(ident, &generics)
+    let dyn_bundle_code = gen_dynamic_bundle_impl(&ident, &generics, &fields);
+    let bundle_code = if fields.tys.is_empty() && fields.nested_tys.is_empty() {
+        gen_unit_struct_bundle_impl(ident, &generics, &fields)
     } else {
-        gen_bundle_impl(&ident, &generics, &field_members, &field_idents, &tys)
+        gen_bundle_impl(&ident, &generics, &fields)
     };
     let mut ts = dyn_bundle_code;
     ts.extend(bundle_code);
     Ok(ts)
 }
 
+/// Struct fields partitioned by their `#[bundle(..)]` attributes
+struct Fields<'a> {
+    tys: Vec<&'a syn::Type>,
+    members: Vec<syn::Member>,
+    nested_tys: Vec<&'a syn::Type>,
+    nested_members: Vec<syn::Member>,
+    skipped_members: Vec<syn::Member>,
+}
+
+impl<'a> Fields<'a> {
+    fn new(tys: &[&'a syn::Type], members: &[syn::Member], kinds: &[BundleField]) -> Self {
+        let mut fields = Fields {
+            tys: Vec::new(),
+            members: Vec::new(),
+            nested_tys: Vec::new(),
+            nested_members: Vec::new(),
+            skipped_members: Vec::new(),
+        };
+        for ((&ty, member), &kind) in tys.iter().zip(members).zip(kinds) {
+            match kind {
+                BundleField::Component => {
+                    fields.tys.push(ty);
+                    fields.members.push(member.clone());
+                }
+                BundleField::Nested => {
+                    fields.nested_tys.push(ty);
+                    fields.nested_members.push(member.clone());
+                }
+                BundleField::Skip => fields.skipped_members.push(member.clone()),
+            }
+        }
+        fields
+    }
+}
+
 fn gen_dynamic_bundle_impl(
     ident: &syn::Ident,
     generics: &syn::Generics,
-    field_members: &[syn::Member],
-    tys: &[&syn::Type],
+    fields: &Fields<'_>,
 ) -> TokenStream2 {
+    let Fields {
+        tys,
+        members: field_members,
+        nested_tys,
+        nested_members,
+        ..
+    } = fields;
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
     quote! {
         unsafe impl #impl_generics ::hecs::DynamicBundle for #ident #ty_generics #where_clause {
             fn has<__hecs__T: ::hecs::Component>(&self) -> bool {
-                false #(|| ::core::any::TypeId::of::<#tys>() == ::core::any::TypeId::of::<__hecs__T>())*
+                false
+                    #(|| ::core::any::TypeId::of::<#tys>() == ::core::any::TypeId::of::<__hecs__T>())*
+                    #(|| <#nested_tys as ::hecs::DynamicBundle>::has::<__hecs__T>(&self.#nested_members))*
             }
 
             fn key(&self) -> ::core::option::Option<::core::any::TypeId> {
@@ -63,6 +105,9 @@ fn gen_dynamic_bundle_impl(
                     f((&mut self.#field_members as *mut #tys).cast::<u8>(), ::hecs::TypeInfo::of::<#tys>());
                     ::core::mem::forget(self.#field_members);
                 )*
+                #(
+                    <#nested_tys as ::hecs::DynamicBundle>::put(self.#nested_members, &mut f);
+                )*
             }
         }
     }
@@ -71,12 +116,73 @@ fn gen_dynamic_bundle_impl(
 fn gen_bundle_impl(
     ident: &syn::Ident,
     generics: &syn::Generics,
-    field_members: &[syn::Member],
-    field_idents: &[Cow<syn::Ident>],
-    tys: &[&syn::Type],
+    fields: &Fields<'_>,
 ) -> TokenStream2 {
-    let num_tys = tys.len();
+    let Fields {
+        tys,
+        members: field_members,
+        nested_tys,
+        nested_members,
+        skipped_members,
+    } = fields;
+    let field_idents = member_as_idents(field_members);
+    let nested_idents = member_as_idents(nested_members);
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let (with_static_ids_body, with_static_type_info_body) = if nested_tys.is_empty() {
+        gen_flat_type_lists(generics, tys)
+    } else {
+        gen_nested_type_lists(ident, generics, tys, nested_tys)
+    };
+    quote! {
+        unsafe impl #impl_generics ::hecs::Bundle for #ident #ty_generics #where_clause {
+            #[allow(non_camel_case_types)]
+            fn with_static_ids<__hecs__T>(f: impl ::core::ops::FnOnce(&[::core::any::TypeId]) -> __hecs__T) -> __hecs__T {
+                #with_static_ids_body
+            }
+
+            #[allow(non_camel_case_types)]
+            fn with_static_type_info<__hecs__T>(f: impl ::core::ops::FnOnce(&[::hecs::TypeInfo]) -> __hecs__T) -> __hecs__T {
+                #with_static_type_info_body
+            }
+
+            unsafe fn get(
+                mut f: impl ::core::ops::FnMut(::hecs::TypeInfo) -> ::core::option::Option<::core::ptr::NonNull<u8>>,
+            ) -> ::core::result::Result<Self, ::hecs::MissingComponent> {
+                #(
+                    let #field_idents = f(::hecs::TypeInfo::of::<#tys>())
+                            .ok_or_else(::hecs::MissingComponent::new::<#tys>)?
+                            .cast::<#tys>()
+                            .as_ptr();
+                )*
+                #(
+                    // Nested bundles read their components as soon as they're all found, so check
+                    // that every nested bundle is complete before extracting any of them
+                    if !<#nested_tys as ::hecs::Bundle>::with_static_type_info(|info| {
+                        info.iter().all(|&ty| f(ty).is_some())
+                    }) {
+                        return <#nested_tys as ::hecs::Bundle>::get(&mut f)
+                            .map(|_| ::core::unreachable!());
+                    }
+                )*
+                #(
+                    let #nested_idents = <#nested_tys as ::hecs::Bundle>::get(&mut f)?;
+                )*
+                ::core::result::Result::Ok(Self {
+                    #( #field_members: #field_idents.read(), )*
+                    #( #nested_members: #nested_idents, )*
+                    #( #skipped_members: ::core::default::Default::default(), )*
+                })
+            }
+        }
+    }
+}
+
+/// Generate `with_static_ids` and `with_static_type_info` bodies for a bundle of components
+fn gen_flat_type_lists(
+    generics: &syn::Generics,
+    tys: &[&syn::Type],
+) -> (TokenStream2, TokenStream2) {
+    let num_tys = tys.len();
     let with_static_ids_inner = quote! {
         {
             let mut tys = [#((::core::mem::align_of::<#tys>(), ::core::any::TypeId::of::<#tys>())),*];
@@ -104,37 +210,59 @@ fn gen_bundle_impl(
             f(&#with_static_ids_inner)
         }
     };
-    quote! {
-        unsafe impl #impl_generics ::hecs::Bundle for #ident #ty_generics #where_clause {
-            #[allow(non_camel_case_types)]
-            fn with_static_ids<__hecs__T>(f: impl ::core::ops::FnOnce(&[::core::any::TypeId]) -> __hecs__T) -> __hecs__T {
-                #with_static_ids_body
-            }
-
-            #[allow(non_camel_case_types)]
-            fn with_static_type_info<__hecs__T>(f: impl ::core::ops::FnOnce(&[::hecs::TypeInfo]) -> __hecs__T) -> __hecs__T {
-                let mut info: [::hecs::TypeInfo; #num_tys] = [#(::hecs::TypeInfo::of::<#tys>()),*];
-                info.sort_unstable();
-                f(&info)
-            }
+    let with_static_type_info_body = quote! {
+        let mut info: [::hecs::TypeInfo; #num_tys] = [#(::hecs::TypeInfo::of::<#tys>()),*];
+        info.sort_unstable();
+        f(&info)
+    };
+    (with_static_ids_body, with_static_type_info_body)
+}
 
-            unsafe fn get(
-                mut f: impl ::core::ops::FnMut(::hecs::TypeInfo) -> ::core::option::Option<::core::ptr::NonNull<u8>>,
-            ) -> ::core::result::Result<Self, ::hecs::MissingComponent> {
-                #(
-                    let #field_idents = f(::hecs::TypeInfo::of::<#tys>())
-                            .ok_or_else(::hecs::MissingComponent::new::<#tys>)?
-                            .cast::<#tys>()
-                            .as_ptr();
-                )*
-                ::core::result::Result::Ok(Self { #( #field_members: #field_idents.read(), )* })
-            }
+/// Generate `with_static_ids` and `with_static_type_info` bodies for a bundle which flattens
+/// nested bundles, whose sizes aren't known to the macro
+fn gen_nested_type_lists(
+    ident: &syn::Ident,
+    generics: &syn::Generics,
+    tys: &[&syn::Type],
+    nested_tys: &[&syn::Type],
+) -> (TokenStream2, TokenStream2) {
+    let (_, ty_generics, _) = generics.split_for_impl();
+    let with_static_ids_inner = quote! {
+        <#ident #ty_generics as ::hecs::Bundle>::with_static_type_info(|info| {
+            info.iter()
+                .map(|x| x.id())
+                .collect::<::hecs::alloc::vec::Vec<_>>()
+        })
+    };
+    let with_static_ids_body = if generics.params.is_empty() {
+        quote! {
+            static ELEMENTS: ::hecs::spin::lazy::Lazy<::hecs::alloc::vec::Vec<::core::any::TypeId>> = ::hecs::spin::lazy::Lazy::new(|| {
+                #with_static_ids_inner
+            });
+            f(&ELEMENTS)
         }
-    }
+    } else {
+        quote! {
+            f(&#with_static_ids_inner)
+        }
+    };
+    let with_static_type_info_body = quote! {
+        let mut info = ::hecs::alloc::vec::Vec::<::hecs::TypeInfo>::new();
+        #( info.push(::hecs::TypeInfo::of::<#tys>()); )*
+        #( <#nested_tys as ::hecs::Bundle>::with_static_type_info(|x| info.extend_from_slice(x)); )*
+        info.sort_unstable();
+        f(&info)
+    };
+    (with_static_ids_body, with_static_type_info_body)
 }
 
 // no reason to generate a static for unit structs
-fn gen_unit_struct_bundle_impl(ident: syn::Ident, generics: &syn::Generics) -> TokenStream2 {
+fn gen_unit_struct_bundle_impl(
+    ident: syn::Ident,
+    generics: &syn::Generics,
+    fields: &Fields<'_>,
+) -> TokenStream2 {
+    let skipped_members = &fields.skipped_members;
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
     quote! {
         unsafe impl #impl_generics ::hecs::Bundle for #ident #ty_generics #where_clause {
@@ -146,7 +274,10 @@ fn gen_unit_struct_bundle_impl(ident: syn::Ident, generics: &syn::Generics) -> T
             unsafe fn get(
                 mut f: impl ::core::ops::FnMut(::hecs::TypeInfo) -> ::core::option::Option<::core::ptr::NonNull<u8>>,
             ) -> ::core::result::Result<Self, ::hecs::MissingComponent> {
-                ::core::result::Result::Ok(Self {/* for some reason this works for all unit struct variations */})
+                ::core::result::Result::Ok(Self {
+                    /* for some reason this works for all unit struct variations */
+                    #( #skipped_members: ::core::default::Default::default(), )*
+                })
             }
         }
     }
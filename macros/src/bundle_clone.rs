@@ -2,7 +2,7 @@ use proc_macro2::TokenStream as TokenStream2;
 use quote::quote;
 use syn::{DeriveInput, Error, Result};
 
-use crate::common::struct_fields;
+use crate::common::{bundle_field_kinds, struct_fields, BundleField};
 
 pub fn derive(input: DeriveInput) -> Result<TokenStream2> {
     let ident = input.ident;
@@ -15,10 +15,29 @@ pub fn derive(input: DeriveInput) -> Result<TokenStream2> {
             ))
         }
     };
-    let (tys, field_members) = struct_fields(&data.fields);
+    let (all_tys, all_members) = struct_fields(&data.fields);
+    let kinds = bundle_field_kinds(&data.fields)?;
+    let select = |kind| {
+        all_tys
+            .iter()
+            .zip(&all_members)
+            .zip(&kinds)
+            .filter(move |(_, &k)| k == kind)
+            .map(|((&ty, member), _)| (ty, member.clone()))
+            .unzip::<_, _, Vec<_>, Vec<_>>()
+    };
+    let (tys, field_members) = select(BundleField::Component);
+    let (nested_tys, nested_members) = select(BundleField::Nested);
     let generics = add_additional_bounds_to_generic_params(input.generics);
 
-    let dyn_bundle_code = gen_dynamic_bundle_impl(&ident, &generics, &field_members, &tys);
+    let dyn_bundle_code = gen_dynamic_bundle_impl(
+        &ident,
+        &generics,
+        &field_members,
+        &tys,
+        &nested_members,
+        &nested_tys,
+    );
     Ok(dyn_bundle_code)
 }
 
@@ -27,6 +46,8 @@ fn gen_dynamic_bundle_impl(
     generics: &syn::Generics,
     field_members: &[syn::Member],
     tys: &[&syn::Type],
+    nested_members: &[syn::Member],
+    nested_tys: &[&syn::Type],
 ) -> TokenStream2 {
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
     quote! {
@@ -41,6 +62,9 @@ fn gen_dynamic_bundle_impl(
                     );
                     ::core::mem::forget(self.#field_members);
                 )*
+                #(
+                    <#nested_tys as ::hecs::DynamicBundleClone>::put_with_clone(self.#nested_members, &mut f);
+                )*
             }
         }
     }
@@ -38,3 +38,40 @@ pub fn member_as_idents(members: &[syn::Member]) -> Vec<Cow<'_, syn::Ident>> {
         })
         .collect()
 }
+
+/// How `#[derive(Bundle)]` treats a struct field, per its `#[bundle(..)]` attribute
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum BundleField {
+    /// A single component
+    Component,
+    /// A bundle whose components are flattened into the outer bundle
+    Nested,
+    /// Not stored in the world; reconstructed with `Default` on extraction
+    Skip,
+}
+
+pub fn bundle_field_kinds(fields: &syn::Fields) -> syn::Result<Vec<BundleField>> {
+    fields
+        .iter()
+        .map(|field| {
+            let mut kind = BundleField::Component;
+            for attr in field.attrs.iter().filter(|a| a.path().is_ident("bundle")) {
+                attr.parse_nested_meta(|meta| {
+                    let new = if meta.path.is_ident("nested") {
+                        BundleField::Nested
+                    } else if meta.path.is_ident("skip") {
+                        BundleField::Skip
+                    } else {
+                        return Err(meta.error("expected `nested` or `skip`"));
+                    };
+                    if kind != BundleField::Component {
+                        return Err(meta.error("conflicting bundle field attributes"));
+                    }
+                    kind = new;
+                    Ok(())
+                })?;
+            }
+            Ok(kind)
+        })
+        .collect()
+}
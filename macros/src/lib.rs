@@ -34,7 +34,43 @@ use syn::{parse_macro_input, DeriveInput};
 /// let e = world.spawn(Foo { x: 42, y: 'a' });
 /// assert_eq!(*world.get::<&i32>(e).unwrap(), 42);
 /// ```
-#[proc_macro_derive(Bundle)]
+///
+/// # Field attributes
+///
+/// - `#[bundle(nested)]` flattens a field which is itself a `Bundle` into the outer bundle, so its
+///   components are stored individually.
+/// - `#[bundle(skip)]` omits a field from the world entirely. Skipped fields are initialized with
+///   [`Default`] when the bundle is extracted, e.g. by `World::remove`.
+///
+/// ```
+/// # use hecs::*;
+/// #[derive(Bundle)]
+/// struct Physics {
+///     position: [f32; 2],
+///     velocity: [f32; 3],
+/// }
+///
+/// #[derive(Bundle)]
+/// struct Character {
+///     name: &'static str,
+///     #[bundle(nested)]
+///     physics: Physics,
+///     #[bundle(skip)]
+///     scratch: Vec<u8>,
+/// }
+///
+/// let mut world = World::new();
+/// let e = world.spawn(Character {
+///     name: "Ferris",
+///     physics: Physics { position: [0.0; 2], velocity: [1.0; 3] },
+///     scratch: vec![1, 2, 3],
+/// });
+/// assert_eq!(*world.get::<&[f32; 3]>(e).unwrap(), [1.0; 3]);
+/// assert!(world.get::<&Vec<u8>>(e).is_err());
+/// let character = world.remove::<Character>(e).unwrap();
+/// assert!(character.scratch.is_empty());
+/// ```
+#[proc_macro_derive(Bundle, attributes(bundle))]
 pub fn derive_bundle(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     match bundle::derive(input) {
@@ -49,11 +85,13 @@ pub fn derive_bundle(input: TokenStream) -> TokenStream {
 /// This is an extension macro for bundles which allow them to be cloned, and
 /// subsequently used in `EntityBuilderClone::add_bundle`.
 ///
-/// Requires that all fields of the struct implement [`Clone`].
+/// Requires that all fields of the struct implement [`Clone`]. Respects the `#[bundle(nested)]` and
+/// `#[bundle(skip)]` field attributes of `derive(Bundle)`; nested bundles must also implement
+/// `DynamicBundleClone`.
 ///
 /// The trait Bundle must also be implemented to be able to be used in
 /// entity builder.
-#[proc_macro_derive(DynamicBundleClone)]
+#[proc_macro_derive(DynamicBundleClone, attributes(bundle))]
 pub fn derive_dynamic_bundle_clone(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     match bundle_clone::derive(input) {
@@ -88,7 +126,30 @@ pub fn derive_dynamic_bundle_clone(input: TokenStream) -> TokenStream {
 ///     }
 /// );
 /// ```
-#[proc_macro_derive(Query)]
+///
+/// # Field attributes
+///
+/// - `#[query(satisfies(Q))]` on a `bool` field sets it to whether the entity matches the query
+///   `Q`, as if by `Satisfies<Q>`, without borrowing anything.
+///
+/// ```
+/// # use hecs::*;
+/// struct Frozen;
+///
+/// #[derive(Query)]
+/// struct Mobile<'a> {
+///     position: &'a mut [f32; 2],
+///     #[query(satisfies(&'a Frozen))]
+///     frozen: bool,
+/// }
+///
+/// let mut world = World::new();
+/// let a = world.spawn(([0.0f32; 2],));
+/// let b = world.spawn(([0.0f32; 2], Frozen));
+/// assert!(!world.query_one_mut::<Mobile>(a).unwrap().frozen);
+/// assert!(world.query_one_mut::<Mobile>(b).unwrap().frozen);
+/// ```
+#[proc_macro_derive(Query, attributes(query))]
 pub fn derive_query(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     match query::derive(input) {
@@ -36,30 +36,34 @@ pub fn derive(input: DeriveInput) -> Result<TokenStream2> {
         ));
     }
 
-    let (fields, queries) = match data.fields {
+    let (fields, queries): (Vec<_>, Vec<_>) = match data.fields {
         syn::Fields::Named(ref fields) => fields
             .named
             .iter()
             .map(|f| {
-                (
+                Ok((
                     syn::Member::Named(f.ident.clone().unwrap()),
-                    query_ty(&lifetime, &f.ty),
-                )
+                    field_query(&lifetime, f)?,
+                ))
             })
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
             .unzip(),
         syn::Fields::Unnamed(ref fields) => fields
             .unnamed
             .iter()
             .enumerate()
             .map(|(i, f)| {
-                (
+                Ok((
                     syn::Member::Unnamed(syn::Index {
                         index: i as u32,
                         span: Span::call_site(),
                     }),
-                    query_ty(&lifetime, &f.ty),
-                )
+                    field_query(&lifetime, f)?,
+                ))
             })
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
             .unzip(),
         syn::Fields::Unit => (Vec::new(), Vec::new()),
     };
@@ -159,6 +163,29 @@ pub fn derive(input: DeriveInput) -> Result<TokenStream2> {
                     #(#fetches::borrow(archetype, state.#fields);)*
                 }
 
+                #[allow(unused_variables, unused_mut, unused_assignments)]
+                fn try_borrow(archetype: &::hecs::Archetype, state: Self::State) -> bool {
+                    // Release the first `taken` fields' borrows
+                    let release = |taken: usize| {
+                        let mut i = 0;
+                        #(
+                            if i < taken {
+                                #fetches::release(archetype, state.#fields);
+                            }
+                            i += 1;
+                        )*
+                    };
+                    let mut taken = 0;
+                    #(
+                        if !#fetches::try_borrow(archetype, state.#fields) {
+                            release(taken);
+                            return false;
+                        }
+                        taken += 1;
+                    )*
+                    true
+                }
+
                 #[allow(unused_variables)]
                 fn prepare(archetype: &::hecs::Archetype) -> ::core::option::Option<Self::State> {
                     ::core::option::Option::Some(#state_ident {
@@ -188,11 +215,49 @@ pub fn derive(input: DeriveInput) -> Result<TokenStream2> {
                         <#fetches as ::hecs::Fetch>::for_each_borrow(&mut f);
                     )*
                 }
+
+                #[allow(unused_variables)]
+                #[inline(always)]
+                fn prefetch(&self, n: usize) {
+                    #(<#fetches as ::hecs::Fetch>::prefetch(&self.#fields, n);)*
+                }
             }
         };
     })
 }
 
+/// The query that populates `field`, honoring `#[query(satisfies(Q))]`
+fn field_query(lifetime: &Lifetime, field: &syn::Field) -> Result<TokenStream2> {
+    let mut satisfies = None;
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("query")) {
+        attr.parse_nested_meta(|meta| {
+            if !meta.path.is_ident("satisfies") {
+                return Err(meta.error("expected `satisfies`"));
+            }
+            if satisfies.is_some() {
+                return Err(meta.error("conflicting query field attributes"));
+            }
+            let content;
+            syn::parenthesized!(content in meta.input);
+            satisfies = Some(content.parse::<Type>()?);
+            Ok(())
+        })?;
+    }
+    let Some(query) = satisfies else {
+        return Ok(query_ty(lifetime, &field.ty));
+    };
+    let is_bool =
+        matches!(&field.ty, Type::Path(p) if p.qself.is_none() && p.path.is_ident("bool"));
+    if !is_bool {
+        return Err(Error::new_spanned(
+            &field.ty,
+            "fields with `#[query(satisfies(..))]` must have type `bool`",
+        ));
+    }
+    let query = query_ty(lifetime, &query);
+    Ok(quote! { ::hecs::Satisfies<#query> })
+}
+
 fn query_ty(lifetime: &Lifetime, ty: &Type) -> TokenStream2 {
     struct Visitor<'a> {
         replace: &'a Lifetime,
@@ -10,13 +10,14 @@ use crate::alloc::boxed::Box;
 use crate::alloc::{vec, vec::Vec};
 use core::any::{type_name, TypeId};
 use core::fmt;
-use core::hash::{BuildHasher, BuildHasherDefault, Hasher};
+use core::hash::{BuildHasher, BuildHasherDefault, Hash, Hasher};
 use core::ops::{Deref, DerefMut};
+use core::panic::Location;
 use core::ptr::{self, NonNull};
 
 use hashbrown::HashMap;
 
-use crate::borrow::AtomicBorrow;
+use crate::borrow::{AtomicBorrow, BorrowState};
 use crate::query::Fetch;
 use crate::{Access, Component, ComponentRef, Query};
 
@@ -32,6 +33,10 @@ pub struct Archetype {
     entities: Box<[u32]>,
     /// One allocation per type, in the same order as `types`
     data: Box<[Data]>,
+    /// Alignment of future allocations for each type, in the same order as `types`
+    align: Box<[usize]>,
+    /// Incremented whenever the set or order of entities changes
+    version: u64,
 }
 
 impl Archetype {
@@ -57,6 +62,7 @@ impl Archetype {
         let max_align = types.first().map_or(1, |ty| ty.layout.align());
         Self::assert_type_info(&types);
         let component_count = types.len();
+        let align = types.iter().map(|ty| ty.layout.align()).collect();
         Self {
             index: OrderedTypeIdMap::new(types.iter().enumerate().map(|(i, ty)| (ty.id, i))),
             type_ids: types.iter().map(|ty| ty.id()).collect(),
@@ -64,11 +70,10 @@ impl Archetype {
             entities: Box::new([]),
             len: 0,
             data: (0..component_count)
-                .map(|_| Data {
-                    state: AtomicBorrow::new(),
-                    storage: NonNull::new(max_align as *mut u8).unwrap(),
-                })
+                .map(|_| Data::new(NonNull::new(max_align as *mut u8).unwrap(), max_align))
                 .collect(),
+            align,
+            version: 0,
         }
     }
 
@@ -82,6 +87,7 @@ impl Archetype {
             }
         }
         self.len = 0;
+        self.version += 1;
     }
 
     /// Whether this archetype contains `T` components
@@ -94,6 +100,21 @@ impl Archetype {
         self.index.contains_key(&id)
     }
 
+    /// Whether this archetype contains every component type in `mask`
+    ///
+    /// Most non-matching archetypes are rejected by comparing a few machine words, without looking
+    /// up individual types.
+    pub fn matches(&self, mask: &ComponentMask) -> bool {
+        self.index.bloom.contains_all(&mask.bloom)
+            && mask.types.iter().all(|id| self.index.contains_key(id))
+    }
+
+    /// Whether this archetype contains any component type in `mask`
+    pub fn intersects(&self, mask: &ComponentMask) -> bool {
+        self.index.bloom.intersects(&mask.bloom)
+            && mask.types.iter().any(|id| self.index.contains_key(id))
+    }
+
     /// Find the state index associated with `T`, if present
     pub(crate) fn get_state<T: Component>(&self) -> Option<usize> {
         self.index.get(&TypeId::of::<T>()).copied()
@@ -113,30 +134,134 @@ impl Archetype {
     /// `T` must be a shared or unique reference to a component type.
     ///
     /// Useful for efficient serialization.
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
     pub fn get<'a, T: ComponentRef<'a>>(&'a self) -> Option<T::Column> {
         T::get_column(self)
     }
 
+    /// Borrow the `T` components of these entities, if present
+    ///
+    /// Equivalent to [`get::<&T>`](Self::get). The borrow participates in the same dynamic borrow
+    /// checking as queries, so external schedulers may hand out columns of distinct archetypes or
+    /// components to concurrent jobs. Panics if the column is already borrowed uniquely.
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
+    pub fn borrow_column<T: Component>(&self) -> Option<ArchetypeColumn<'_, T>> {
+        ArchetypeColumn::new(self)
+    }
+
+    /// Uniquely borrow the `T` components of these entities, if present
+    ///
+    /// Equivalent to [`get::<&mut T>`](Self::get). Panics if the column is already borrowed.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let e = world.spawn((1, true));
+    /// let archetype = world.archetype(world.archetype_id(e).unwrap());
+    /// let mut ints = archetype.borrow_column_mut::<i32>().unwrap();
+    /// let bools = archetype.borrow_column::<bool>().unwrap();
+    /// ints[0] += bools.len() as i32;
+    /// drop(ints);
+    /// assert_eq!(*world.get::<&i32>(e).unwrap(), 2);
+    /// ```
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
+    pub fn borrow_column_mut<T: Component>(&self) -> Option<ArchetypeColumnMut<'_, T>> {
+        ArchetypeColumnMut::new(self)
+    }
+
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
     pub(crate) fn borrow<T: Component>(&self, state: usize) {
         assert_eq!(self.types[state].id, TypeId::of::<T>());
 
-        if !self.data[state].state.borrow() {
-            panic!("{} already borrowed uniquely", type_name::<T>());
+        let data = &self.data[state];
+        if !data.state.borrow() {
+            panic!(
+                "{} already borrowed uniquely{}",
+                type_name::<T>(),
+                data.last_borrow()
+            );
         }
+        data.record_borrow();
     }
 
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
     pub(crate) unsafe fn borrow_raw(&self, state: usize) {
-        if !self.data[state].state.borrow() {
-            panic!("state index {} already borrowed uniquely", state);
+        let data = &self.data[state];
+        if !data.state.borrow() {
+            panic!(
+                "state index {} already borrowed uniquely{}",
+                state,
+                data.last_borrow()
+            );
         }
+        data.record_borrow();
     }
 
+    /// Like `borrow_raw`, but returns `false` instead of panicking if the column is borrowed
+    /// uniquely
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
+    pub(crate) unsafe fn try_borrow_raw(&self, state: usize) -> bool {
+        let data = &self.data[state];
+        if !data.state.borrow() {
+            return false;
+        }
+        data.record_borrow();
+        true
+    }
+
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
+    pub(crate) unsafe fn borrow_raw_mut(&self, state: usize) {
+        let data = &self.data[state];
+        if !data.state.borrow_mut() {
+            panic!(
+                "state index {} already borrowed{}",
+                state,
+                data.last_borrow()
+            );
+        }
+        data.record_borrow();
+    }
+
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
     pub(crate) fn borrow_mut<T: Component>(&self, state: usize) {
         assert_eq!(self.types[state].id, TypeId::of::<T>());
 
-        if !self.data[state].state.borrow_mut() {
-            panic!("{} already borrowed", type_name::<T>());
+        let data = &self.data[state];
+        if !data.state.borrow_mut() {
+            panic!(
+                "{} already borrowed{}",
+                type_name::<T>(),
+                data.last_borrow()
+            );
+        }
+        data.record_borrow();
+    }
+
+    /// Like `borrow`, but returns `false` instead of panicking if the column is borrowed uniquely
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
+    pub(crate) fn try_borrow<T: Component>(&self, state: usize) -> bool {
+        assert_eq!(self.types[state].id, TypeId::of::<T>());
+
+        let data = &self.data[state];
+        if !data.state.borrow() {
+            return false;
         }
+        data.record_borrow();
+        true
+    }
+
+    /// Like `borrow_mut`, but returns `false` instead of panicking if the column is borrowed
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
+    pub(crate) fn try_borrow_mut<T: Component>(&self, state: usize) -> bool {
+        assert_eq!(self.types[state].id, TypeId::of::<T>());
+
+        let data = &self.data[state];
+        if !data.state.borrow_mut() {
+            return false;
+        }
+        data.record_borrow();
+        true
     }
 
     pub(crate) fn release<T: Component>(&self, state: usize) {
@@ -181,6 +306,12 @@ impl Archetype {
     #[inline]
     pub(crate) fn set_entity_id(&mut self, index: usize, id: u32) {
         self.entities[index] = id;
+        self.version += 1;
+    }
+
+    /// Changes whenever entities are added, removed, or reordered
+    pub(crate) fn version(&self) -> u64 {
+        self.version
     }
 
     pub(crate) fn types(&self) -> &[TypeInfo] {
@@ -209,6 +340,53 @@ impl Archetype {
         self.types.iter().map(|typeinfo| typeinfo.id)
     }
 
+    /// [`TypeInfo`]s of the components of entities in this archetype
+    ///
+    /// Sorted by descending alignment, then by [`TypeId`].
+    pub fn component_type_info(&self) -> &[TypeInfo] {
+        &self.types
+    }
+
+    /// How each column of components is currently borrowed, in the same order as
+    /// [`component_type_info`](Self::component_type_info)
+    ///
+    /// Each column is borrowed independently, so queries accessing disjoint component types never
+    /// conflict even within the same archetype. Useful for diagnosing unexpected borrow conflicts.
+    /// Borrows may be acquired or released concurrently, so the result is only a snapshot.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// world.spawn((123, true));
+    /// let mut query = world.query::<&mut i32>();
+    /// let _ = query.iter();
+    /// let archetype = world.archetypes().find(|a| a.has::<i32>()).unwrap();
+    /// for (ty, state) in archetype.borrow_state() {
+    ///     if ty.id() == std::any::TypeId::of::<i32>() {
+    ///         assert_eq!(state, BorrowState::Unique);
+    ///     } else {
+    ///         assert_eq!(state, BorrowState::Unborrowed);
+    ///     }
+    /// }
+    /// ```
+    pub fn borrow_state(&self) -> impl ExactSizeIterator<Item = (TypeInfo, BorrowState)> + '_ {
+        self.types
+            .iter()
+            .zip(&*self.data)
+            .map(|(ty, data)| (*ty, data.state.state()))
+    }
+
+    /// Find the state index associated with `ty`, if present
+    pub(crate) fn get_state_dynamic(&self, ty: TypeId) -> Option<usize> {
+        self.index.get(&ty).copied()
+    }
+
+    /// Get the address of the first component in column `state`
+    pub(crate) fn get_base_dynamic(&self, state: usize) -> NonNull<u8> {
+        self.data[state].storage
+    }
+
     /// `index` must be in-bounds or just past the end
     pub(crate) unsafe fn get_dynamic(
         &self,
@@ -235,12 +413,14 @@ impl Archetype {
 
         self.entities[self.len as usize] = id;
         self.len += 1;
+        self.version += 1;
         self.len - 1
     }
 
     pub(crate) unsafe fn set_len(&mut self, len: u32) {
         debug_assert!(len <= self.capacity());
         self.len = len;
+        self.version += 1;
     }
 
     pub(crate) fn reserve(&mut self, additional: u32) {
@@ -260,6 +440,20 @@ impl Archetype {
         self.grow_exact(self.capacity().max(min_increment))
     }
 
+    /// Allocate the column at `state` with at least `align` alignment from now on
+    ///
+    /// Reallocates the archetype's storage if necessary.
+    pub(crate) fn set_column_align(&mut self, state: usize, align: usize) {
+        let align = align.max(self.types[state].layout.align());
+        if self.align[state] == align {
+            return;
+        }
+        self.align[state] = align;
+        if self.capacity() > 0 {
+            self.grow_exact(0);
+        }
+    }
+
     /// Increase capacity by exactly `increment`
     fn grow_exact(&mut self, increment: u32) {
         let old_count = self.len as usize;
@@ -273,13 +467,13 @@ impl Archetype {
             .types
             .iter()
             .zip(&*self.data)
-            .map(|(info, old)| {
+            .zip(&*self.align)
+            .map(|((info, old), &align)| {
                 let storage = if info.layout.size() == 0 {
-                    NonNull::new(info.layout.align() as *mut u8).unwrap()
+                    NonNull::new(align as *mut u8).unwrap()
                 } else {
                     let layout =
-                        Layout::from_size_align(info.layout.size() * new_cap, info.layout.align())
-                            .unwrap();
+                        Layout::from_size_align(info.layout.size() * new_cap, align).unwrap();
                     unsafe {
                         let mem = alloc(layout);
                         let mem = NonNull::new(mem)
@@ -292,10 +486,8 @@ impl Archetype {
                         mem
                     }
                 };
-                Data {
-                    state: AtomicBorrow::new(), // &mut self guarantees no outstanding borrows
-                    storage,
-                }
+                // &mut self guarantees no outstanding borrows
+                Data::new(storage, align)
             })
             .collect::<Box<[_]>>();
 
@@ -310,8 +502,7 @@ impl Archetype {
                 unsafe {
                     dealloc(
                         data.storage.as_ptr(),
-                        Layout::from_size_align(info.layout.size() * old_cap, info.layout.align())
-                            .unwrap(),
+                        Layout::from_size_align(info.layout.size() * old_cap, data.align).unwrap(),
                     );
                 }
             }
@@ -334,6 +525,7 @@ impl Archetype {
             }
         }
         self.len = last;
+        self.version += 1;
         if index != last {
             self.entities[index as usize] = self.entities[last as usize];
             Some(self.entities[last as usize])
@@ -358,6 +550,7 @@ impl Archetype {
             }
         }
         self.len -= 1;
+        self.version += 1;
         if index != last {
             self.entities[index as usize] = self.entities[last as usize];
             Some(self.entities[last as usize])
@@ -408,6 +601,7 @@ impl Archetype {
                 )
         }
         self.len += other.len;
+        self.version += 1;
         other.len = 0;
     }
 
@@ -425,7 +619,7 @@ impl Archetype {
 impl Drop for Archetype {
     fn drop(&mut self) {
         self.clear();
-        if self.entities.len() == 0 {
+        if self.entities.is_empty() {
             return;
         }
         for (info, data) in self.types.iter().zip(&*self.data) {
@@ -435,7 +629,7 @@ impl Drop for Archetype {
                         data.storage.as_ptr(),
                         Layout::from_size_align_unchecked(
                             info.layout.size() * self.entities.len(),
-                            info.layout.align(),
+                            data.align,
                         ),
                     );
                 }
@@ -447,6 +641,55 @@ impl Drop for Archetype {
 struct Data {
     state: AtomicBorrow,
     storage: NonNull<u8>,
+    /// Alignment `storage` was allocated with
+    align: usize,
+    /// Where the column was most recently borrowed
+    #[cfg(feature = "borrow-diagnostics")]
+    last_borrow: spin::Mutex<Option<&'static Location<'static>>>,
+}
+
+impl Data {
+    fn new(storage: NonNull<u8>, align: usize) -> Self {
+        Self {
+            state: AtomicBorrow::new(),
+            storage,
+            align,
+            #[cfg(feature = "borrow-diagnostics")]
+            last_borrow: spin::Mutex::new(None),
+        }
+    }
+
+    #[inline(always)]
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
+    fn record_borrow(&self) {
+        #[cfg(feature = "borrow-diagnostics")]
+        {
+            *self.last_borrow.lock() = Some(Location::caller());
+        }
+    }
+
+    fn last_borrow(&self) -> LastBorrow {
+        #[cfg(feature = "borrow-diagnostics")]
+        {
+            LastBorrow(*self.last_borrow.lock())
+        }
+        #[cfg(not(feature = "borrow-diagnostics"))]
+        {
+            LastBorrow(None)
+        }
+    }
+}
+
+/// Describes a column's most recent borrow in panic messages, if known
+struct LastBorrow(Option<&'static Location<'static>>);
+
+impl fmt::Display for LastBorrow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(location) => write!(f, " (most recently borrowed at {})", location),
+            None => Ok(()),
+        }
+    }
 }
 
 /// A hasher optimized for hashing a single TypeId.
@@ -493,17 +736,27 @@ impl Hasher for TypeIdHasher {
 /// faster no-op hash.
 pub type TypeIdMap<V> = HashMap<TypeId, V, BuildHasherDefault<TypeIdHasher>>;
 
-struct OrderedTypeIdMap<V>(Box<[(TypeId, V)]>);
+struct OrderedTypeIdMap<V> {
+    entries: Box<[(TypeId, V)]>,
+    bloom: TypeBloom,
+}
 
 impl<V> OrderedTypeIdMap<V> {
     fn new(iter: impl Iterator<Item = (TypeId, V)>) -> Self {
-        let mut vals = iter.collect::<Box<[_]>>();
-        vals.sort_unstable_by_key(|(id, _)| *id);
-        Self(vals)
+        let mut entries = iter.collect::<Box<[_]>>();
+        entries.sort_unstable_by_key(|(id, _)| *id);
+        let mut bloom = TypeBloom::default();
+        for (id, _) in entries.iter() {
+            bloom.insert(*id);
+        }
+        Self { entries, bloom }
     }
 
     fn search(&self, id: &TypeId) -> Option<usize> {
-        self.0.binary_search_by_key(id, |(id, _)| *id).ok()
+        if !self.bloom.may_contain(*id) {
+            return None;
+        }
+        self.entries.binary_search_by_key(id, |(id, _)| *id).ok()
     }
 
     fn contains_key(&self, id: &TypeId) -> bool {
@@ -511,7 +764,91 @@ impl<V> OrderedTypeIdMap<V> {
     }
 
     fn get(&self, id: &TypeId) -> Option<&V> {
-        self.search(id).map(move |idx| &self.0[idx].1)
+        self.search(id).map(move |idx| &self.entries[idx].1)
+    }
+}
+
+const BLOOM_WORDS: usize = 4;
+
+/// Fixed-size summary of a set of types, with one bit set per type
+///
+/// Distinct types may share a bit, so a clear bit proves absence but a set bit must be confirmed.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+struct TypeBloom([u64; BLOOM_WORDS]);
+
+impl TypeBloom {
+    fn bit(id: TypeId) -> (usize, u64) {
+        let mut hasher = TypeIdHasher::default();
+        id.hash(&mut hasher);
+        let hash = hasher.finish();
+        ((hash >> 6) as usize % BLOOM_WORDS, 1 << (hash & 63))
+    }
+
+    fn insert(&mut self, id: TypeId) {
+        let (word, bit) = Self::bit(id);
+        self.0[word] |= bit;
+    }
+
+    fn may_contain(&self, id: TypeId) -> bool {
+        let (word, bit) = Self::bit(id);
+        self.0[word] & bit != 0
+    }
+
+    fn contains_all(&self, other: &Self) -> bool {
+        self.0.iter().zip(&other.0).all(|(x, y)| x & y == *y)
+    }
+
+    fn intersects(&self, other: &Self) -> bool {
+        self.0.iter().zip(&other.0).any(|(x, y)| x & y != 0)
+    }
+}
+
+/// A set of component types that can be cheaply tested against an [`Archetype`]
+///
+/// Useful for external schedulers which repeatedly filter many archetypes by the same set of
+/// types. See [`Archetype::matches`] and [`Archetype::intersects`].
+///
+/// # Example
+/// ```
+/// # use hecs::*;
+/// # use core::any::TypeId;
+/// let mut world = World::new();
+/// world.spawn((123, true));
+/// world.spawn((456,));
+/// let mask = ComponentMask::from_types([TypeId::of::<i32>(), TypeId::of::<bool>()]);
+/// assert_eq!(world.archetypes().filter(|a| a.matches(&mask)).count(), 1);
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ComponentMask {
+    bloom: TypeBloom,
+    /// Sorted and deduplicated
+    types: Box<[TypeId]>,
+}
+
+impl ComponentMask {
+    /// Construct a mask containing each of `types`
+    pub fn from_types(types: impl IntoIterator<Item = TypeId>) -> Self {
+        let mut types = types.into_iter().collect::<Vec<_>>();
+        types.sort_unstable();
+        types.dedup();
+        let mut bloom = TypeBloom::default();
+        for &id in &types {
+            bloom.insert(id);
+        }
+        Self {
+            bloom,
+            types: types.into_boxed_slice(),
+        }
+    }
+
+    /// The component types in this mask, in sorted order
+    pub fn types(&self) -> &[TypeId] {
+        &self.types
+    }
+
+    /// Whether `id` is in this mask
+    pub fn contains(&self, id: TypeId) -> bool {
+        self.bloom.may_contain(id) && self.types.binary_search(&id).is_ok()
     }
 }
 
@@ -569,6 +906,14 @@ impl TypeInfo {
         self.layout
     }
 
+    /// Name of the component type, recorded only in debug builds
+    pub(crate) fn type_name(&self) -> Option<&'static str> {
+        #[cfg(debug_assertions)]
+        return Some(self.type_name);
+        #[cfg(not(debug_assertions))]
+        None
+    }
+
     /// Directly call the destructor on a pointer to data of this component type.
     ///
     /// # Safety
@@ -618,6 +963,7 @@ pub struct ArchetypeColumn<'a, T: Component> {
 }
 
 impl<'a, T: Component> ArchetypeColumn<'a, T> {
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
     pub(crate) fn new(archetype: &'a Archetype) -> Option<Self> {
         let state = archetype.get_state::<T>()?;
         let ptr = archetype.get_base::<T>(state);
@@ -665,6 +1011,7 @@ pub struct ArchetypeColumnMut<'a, T: Component> {
 }
 
 impl<'a, T: Component> ArchetypeColumnMut<'a, T> {
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
     pub(crate) fn new(archetype: &'a Archetype) -> Option<Self> {
         let state = archetype.get_state::<T>()?;
         let ptr = archetype.get_base::<T>(state);
@@ -1,5 +1,5 @@
 use crate::alloc::collections::BinaryHeap;
-use core::{any::TypeId, fmt, mem::MaybeUninit, slice};
+use core::{any::TypeId, fmt, mem::MaybeUninit, ops::Range, slice};
 
 use crate::{
     archetype::{TypeIdMap, TypeInfo},
@@ -18,6 +18,16 @@ impl ColumnBatchType {
         Self::default()
     }
 
+    /// Create a type with exactly the components of `archetype`
+    ///
+    /// Includes components whose types are only known at runtime, making it easy to mirror an
+    /// existing archetype, e.g. when copying entities between worlds.
+    pub fn from_archetype(archetype: &Archetype) -> Self {
+        Self {
+            types: archetype.types().iter().cloned().collect(),
+        }
+    }
+
     /// Update to include `T` components
     pub fn add<T: Component>(&mut self) -> &mut Self {
         self.types.push(TypeInfo::of::<T>());
@@ -76,6 +86,44 @@ impl ColumnBatchBuilder {
         })
     }
 
+    /// Append clones of the `T` components at `rows` in `archetype`
+    ///
+    /// Returns the number of components appended, which is less than the number of rows if the
+    /// batch is filled first, or `None` if either the batch or `archetype` lacks `T`. Panics if
+    /// `rows` is out of bounds or the column is uniquely borrowed.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut source = World::new();
+    /// let a = source.spawn((1, "abc"));
+    /// source.spawn((2, "def"));
+    /// let archetype = source.archetype(source.archetype_id(a).unwrap());
+    ///
+    /// let mut batch = ColumnBatchType::from_archetype(archetype).into_batch(archetype.len());
+    /// assert_eq!(batch.fill_from::<i32>(archetype, 0..archetype.len()), Some(2));
+    /// assert_eq!(batch.fill_from::<&str>(archetype, 0..archetype.len()), Some(2));
+    /// let mut dest = World::new();
+    /// dest.spawn_column_batch(batch.build().unwrap());
+    /// assert_eq!(dest.len(), 2);
+    /// ```
+    pub fn fill_from<T: Component + Clone>(
+        &mut self,
+        archetype: &Archetype,
+        rows: Range<u32>,
+    ) -> Option<u32> {
+        let column = archetype.get::<&T>()?;
+        let mut writer = self.writer::<T>()?;
+        let mut n = 0;
+        for x in &column[rows.start as usize..rows.end as usize] {
+            if writer.push(x.clone()).is_err() {
+                break;
+            }
+            n += 1;
+        }
+        Some(n)
+    }
+
     /// Finish the batch, failing if any components are missing
     pub fn build(mut self) -> Result<ColumnBatch, BatchIncomplete> {
         let mut archetype = self.archetype.take().unwrap();
@@ -131,6 +179,37 @@ impl<T> BatchWriter<'_, T> {
         }
     }
 
+    /// Add clones of as many components from `xs` as there's space for
+    ///
+    /// Returns the number of components added. Much faster than calling `push` per component,
+    /// especially for `Copy` types.
+    pub fn push_slice(&mut self, xs: &[T]) -> u32
+    where
+        T: Clone,
+    {
+        let n = xs.len().min(self.storage.len());
+        for (x, slot) in xs[..n].iter().zip(self.storage.by_ref()) {
+            *slot = MaybeUninit::new(x.clone());
+        }
+        *self.fill += n as u32;
+        n as u32
+    }
+
+    /// Add components from `iter` until it's exhausted or there's no space remaining
+    ///
+    /// Returns the number of components added. Items beyond the remaining space are never taken
+    /// from `iter`, so pass [`Iterator::by_ref`] to recover them.
+    pub fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) -> u32 {
+        let remaining = self.storage.len();
+        let mut n = 0;
+        for (x, slot) in iter.into_iter().take(remaining).zip(self.storage.by_ref()) {
+            *slot = MaybeUninit::new(x);
+            n += 1;
+        }
+        *self.fill += n;
+        n
+    }
+
     /// How many components have been added so far
     pub fn fill(&self) -> u32 {
         *self.fill
@@ -164,4 +243,46 @@ mod tests {
         let mut writer = builder.writer::<usize>().unwrap();
         assert!(writer.push(42).is_err());
     }
+
+    #[test]
+    fn bulk_push() {
+        let mut types = ColumnBatchType::new();
+        types.add::<u32>();
+        let mut builder = types.into_batch(5);
+        let mut writer = builder.writer::<u32>().unwrap();
+        assert_eq!(writer.push_slice(&[1, 2]), 2);
+        let mut iter = 3..;
+        assert_eq!(writer.extend(iter.by_ref()), 3);
+        assert_eq!(iter.next(), Some(6));
+        assert_eq!(writer.fill(), 5);
+        assert_eq!(writer.push_slice(&[7]), 0);
+        assert_eq!(writer.extend(Some(8)), 0);
+        let batch = builder.build().unwrap();
+        let mut world = crate::World::new();
+        let entities = world
+            .spawn_column_batch(batch)
+            .collect::<crate::alloc::vec::Vec<_>>();
+        for (i, e) in entities.into_iter().enumerate() {
+            assert_eq!(*world.get::<&u32>(e).unwrap(), i as u32 + 1);
+        }
+    }
+
+    #[test]
+    fn fill_from_archetype() {
+        use crate::World;
+
+        let mut world = World::new();
+        let a = world.spawn((1u32, 2.0f32));
+        world.spawn((3u32, 4.0f32));
+        let archetype = world.archetype(world.archetype_id(a).unwrap());
+        let mut batch = ColumnBatchType::from_archetype(archetype).into_batch(1);
+        assert_eq!(batch.fill_from::<u32>(archetype, 1..2), Some(1));
+        assert_eq!(batch.fill_from::<f32>(archetype, 0..2), Some(1));
+        assert_eq!(batch.fill_from::<bool>(archetype, 0..2), None);
+        let batch = batch.build().unwrap();
+        let mut dest = World::new();
+        let e = dest.spawn_column_batch(batch).next().unwrap();
+        assert_eq!(*dest.get::<&u32>(e).unwrap(), 3);
+        assert_eq!(*dest.get::<&f32>(e).unwrap(), 2.0);
+    }
 }
@@ -64,6 +64,32 @@ impl AtomicBorrow {
         let value = self.0.fetch_and(!UNIQUE_BIT, Ordering::Release);
         debug_assert_ne!(value & UNIQUE_BIT, 0, "unique release of shared borrow");
     }
+
+    /// Snapshot of the current borrows, which may be outdated by the time it's inspected
+    pub fn state(&self) -> BorrowState {
+        let value = self.0.load(Ordering::Relaxed);
+        if value & UNIQUE_BIT != 0 {
+            // Any counter bits belong to shared borrows which are about to be rolled back
+            BorrowState::Unique
+        } else if value == 0 {
+            BorrowState::Unborrowed
+        } else {
+            BorrowState::Shared(value)
+        }
+    }
+}
+
+/// How a column of components is currently borrowed
+///
+/// See [`Archetype::borrow_state`](crate::Archetype::borrow_state).
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum BorrowState {
+    /// Not borrowed
+    Unborrowed,
+    /// Borrowed by the given number of shared references
+    Shared(usize),
+    /// Borrowed by a unique reference
+    Unique,
 }
 
 #[cfg(test)]
@@ -98,4 +124,19 @@ mod tests {
         counter.release_mut();
         assert!(counter.borrow());
     }
+
+    #[test]
+    fn test_state() {
+        let counter = AtomicBorrow::new();
+        assert_eq!(counter.state(), BorrowState::Unborrowed);
+        counter.borrow();
+        counter.borrow();
+        assert_eq!(counter.state(), BorrowState::Shared(2));
+        counter.release();
+        counter.release();
+        counter.borrow_mut();
+        assert_eq!(counter.state(), BorrowState::Unique);
+        counter.release_mut();
+        assert_eq!(counter.state(), BorrowState::Unborrowed);
+    }
 }
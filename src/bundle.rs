@@ -42,6 +42,15 @@ pub unsafe trait DynamicBundle {
         None
     }
 
+    /// Returns a precomputed hash of the sorted set of component types, if known
+    ///
+    /// Allows bundles whose types aren't statically known, but which are spawned repeatedly, to
+    /// skip hashing their type set on every spawn. Distinct type sets may collide.
+    #[doc(hidden)]
+    fn key_hash(&self) -> Option<u64> {
+        None
+    }
+
     /// Checks if the Bundle contains the given `T`:
     ///
     /// ```
@@ -134,6 +143,11 @@ impl MissingComponent {
     pub fn new<T: Component>() -> Self {
         Self(type_name::<T>())
     }
+
+    /// Construct an error representing a missing component identified only by its `TypeId`
+    pub(crate) fn dynamic() -> Self {
+        Self("dynamically typed")
+    }
 }
 
 impl fmt::Display for MissingComponent {
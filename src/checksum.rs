@@ -0,0 +1,140 @@
+use core::any::TypeId;
+use core::hash::{Hash, Hasher};
+use core::ptr::NonNull;
+
+use crate::alloc::vec::Vec;
+use crate::archetype::{Archetype, TypeIdMap};
+use crate::{Component, World};
+
+/// Type-erased form of `Hash::hash` for a registered component type
+type HashFn = unsafe fn(*const u8, &mut dyn Hasher);
+
+/// Set of component types which contribute to [`World::checksum`]
+///
+/// Components are hashed in registration order, so peers comparing checksums must register the
+/// same types in the same order.
+///
+/// # Example
+/// ```
+/// # use hecs::*;
+/// let mut registry = HashRegistry::new();
+/// registry.register::<i32>().register::<bool>();
+///
+/// let mut a = World::new();
+/// let mut b = World::new();
+/// for world in [&mut a, &mut b] {
+///     world.spawn((1, true));
+///     world.spawn((2, 'x'));
+/// }
+/// let checksum = |world: &World| {
+///     let mut hasher = std::collections::hash_map::DefaultHasher::new();
+///     world.checksum(&registry, &mut hasher);
+///     std::hash::Hasher::finish(&hasher)
+/// };
+/// assert_eq!(checksum(&a), checksum(&b));
+/// ```
+#[derive(Default)]
+pub struct HashRegistry {
+    types: Vec<(TypeId, HashFn)>,
+    indices: TypeIdMap<usize>,
+}
+
+impl HashRegistry {
+    /// Create a registry containing no component types
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Include `C` components in checksums
+    ///
+    /// Registering a type more than once has no effect.
+    pub fn register<C: Component + Hash>(&mut self) -> &mut Self {
+        let id = TypeId::of::<C>();
+        if self.indices.contains_key(&id) {
+            return self;
+        }
+        self.indices.insert(id, self.types.len());
+        self.types.push((id, |ptr, mut state| unsafe {
+            (*ptr.cast::<C>()).hash(&mut state)
+        }));
+        self
+    }
+
+    /// Whether `C` components are included in checksums
+    pub fn contains<C: Component>(&self) -> bool {
+        self.indices.contains_key(&TypeId::of::<C>())
+    }
+}
+
+/// Registered columns of an archetype, borrowed until dropped
+struct Columns<'a> {
+    archetype: &'a Archetype,
+    /// For each registered type in registration order, its base pointer and size, if present
+    columns: Vec<Option<(NonNull<u8>, usize, HashFn)>>,
+    /// Archetype-local indices of borrowed columns
+    states: Vec<usize>,
+}
+
+impl<'a> Columns<'a> {
+    fn new(archetype: &'a Archetype, registry: &HashRegistry) -> Self {
+        // Built in place so that borrows are released if a later borrow panics
+        let mut result = Self {
+            archetype,
+            columns: Vec::with_capacity(registry.types.len()),
+            states: Vec::new(),
+        };
+        for &(id, hash) in &registry.types {
+            let column = archetype
+                .types()
+                .iter()
+                .position(|ty| ty.id() == id)
+                .and_then(|state| {
+                    let size = archetype.types()[state].layout().size();
+                    let base = unsafe { archetype.get_dynamic(id, size, 0)? };
+                    unsafe {
+                        archetype.borrow_raw(state);
+                    }
+                    result.states.push(state);
+                    Some((base, size, hash))
+                });
+            result.columns.push(column);
+        }
+        result
+    }
+}
+
+impl Drop for Columns<'_> {
+    fn drop(&mut self) {
+        for &state in &self.states {
+            unsafe {
+                self.archetype.release_raw(state);
+            }
+        }
+    }
+}
+
+pub(crate) fn checksum(world: &World, registry: &HashRegistry, state: &mut dyn Hasher) {
+    let archetypes = world
+        .archetypes_inner()
+        .iter()
+        .map(|archetype| Columns::new(archetype, registry))
+        .collect::<Vec<_>>();
+    for (id, meta) in world.entities_meta().iter().enumerate() {
+        if meta.location.index == u32::MAX {
+            continue;
+        }
+        state.write_u32(id as u32);
+        state.write_u32(meta.generation.get());
+        let index = meta.location.index as usize;
+        let columns = &archetypes[meta.location.archetype as usize].columns;
+        for (type_index, column) in columns.iter().enumerate() {
+            let Some((base, size, hash)) = *column else {
+                continue;
+            };
+            state.write_u32(type_index as u32);
+            unsafe {
+                hash(base.as_ptr().add(size * index), state);
+            }
+        }
+    }
+}
@@ -11,6 +11,7 @@ use core::ops::Range;
 use core::ptr::{self, NonNull};
 
 use crate::alloc::alloc::{alloc, dealloc, Layout};
+use crate::alloc::boxed::Box;
 use crate::alloc::vec::Vec;
 use crate::archetype::TypeInfo;
 use crate::{align, DynamicBundle};
@@ -101,6 +102,24 @@ impl CommandBuffer {
         self.insert(entity, (component,));
     }
 
+    /// Add a component of type `ty`, moved from `component`, to `entity`, if it exists
+    ///
+    /// Like [`insert_one`](Self::insert_one), but for components whose type is only known at
+    /// runtime.
+    ///
+    /// # Safety
+    ///
+    /// `component` must point to a valid instance of the type described by `ty`, which is moved into
+    /// the command buffer and must not be used or dropped afterwards.
+    pub unsafe fn insert_dynamic(&mut self, entity: Entity, ty: TypeInfo, component: *mut u8) {
+        let first_component = self.components.len();
+        self.add_inner(component, ty);
+        self.cmds.push(Cmd::SpawnOrInsert(EntityIndex {
+            entity: Some(entity),
+            components: first_component..self.components.len(),
+        }));
+    }
+
     /// Remove components from `entity` if they exist
     ///
     /// When removing a single component, see [`remove_one`](Self::remove_one) for convenience.
@@ -121,6 +140,14 @@ impl CommandBuffer {
         self.remove::<(T,)>(ent);
     }
 
+    /// Remove the component of type `ty` from `entity` if it exists
+    ///
+    /// Like [`remove_one`](Self::remove_one), but for components whose type is only known at
+    /// runtime.
+    pub fn remove_dynamic(&mut self, entity: Entity, ty: TypeId) {
+        self.cmds.push(Cmd::RemoveDynamic(entity, ty));
+    }
+
     /// Despawn `entity` from World
     pub fn despawn(&mut self, entity: Entity) {
         self.cmds.push(Cmd::Despawn(entity));
@@ -142,6 +169,29 @@ impl CommandBuffer {
         }));
     }
 
+    /// Run `f` on the world, after any previously recorded commands
+    ///
+    /// Useful for deferred operations which can't be expressed as other commands, such as
+    /// inserting a component only if the entity lacks it.
+    ///
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let entity = world.spawn((1,));
+    /// let mut cmd = CommandBuffer::new();
+    /// cmd.insert_one(entity, true);
+    /// cmd.run(move |world| {
+    ///     if world.satisfies::<&bool>(entity).unwrap_or(false) {
+    ///         *world.get::<&mut i32>(entity).unwrap() += 1;
+    ///     }
+    /// });
+    /// cmd.run_on(&mut world);
+    /// assert_eq!(*world.get::<&i32>(entity).unwrap(), 2);
+    /// ```
+    pub fn run(&mut self, f: impl FnOnce(&mut World) + Send + 'static) {
+        self.cmds.push(Cmd::Run(Box::new(f)));
+    }
+
     /// Run recorded commands on `world`, clearing the command buffer
     pub fn run_on(&mut self, world: &mut World) {
         for i in 0..self.cmds.len() {
@@ -161,9 +211,13 @@ impl CommandBuffer {
                 Cmd::Remove(remove) => {
                     (remove.remove)(world, remove.entity);
                 }
+                Cmd::RemoveDynamic(entity, ty) => {
+                    let _ = world.remove_dynamic_drop(entity, &[ty]);
+                }
                 Cmd::Despawn(entity) => {
                     let _ = world.despawn(entity);
                 }
+                Cmd::Run(f) => f(world),
             }
         }
         // Wipe out component references so `clear` doesn't try to double-free
@@ -294,7 +348,11 @@ struct RemovedComps {
 enum Cmd {
     SpawnOrInsert(EntityIndex),
     Remove(RemovedComps),
+    RemoveDynamic(Entity, TypeId),
     Despawn(Entity),
+    // Only `Send` is required for `CommandBuffer: Sync`, as closures are only accessed through
+    // `&mut CommandBuffer`
+    Run(Box<dyn FnOnce(&mut World) + Send>),
 }
 
 #[cfg(test)]
@@ -367,4 +425,54 @@ mod tests {
         cmd.run_on(&mut world);
         assert_eq!(*world.get::<&i32>(a).unwrap(), 42);
     }
+
+    #[test]
+    fn dynamic_insert_remove() {
+        let mut world = World::new();
+        let a = world.spawn((1i32,));
+        let rc = std::sync::Arc::new(());
+        let mut cmd = CommandBuffer::new();
+        let mut component = mem::ManuallyDrop::new(rc.clone());
+        unsafe {
+            cmd.insert_dynamic(
+                a,
+                TypeInfo::of::<std::sync::Arc<()>>(),
+                (&mut *component as *mut std::sync::Arc<()>).cast(),
+            );
+        }
+        let mut flag = true;
+        unsafe {
+            cmd.insert_dynamic(a, TypeInfo::of::<bool>(), (&mut flag as *mut bool).cast());
+        }
+        cmd.run_on(&mut world);
+        assert!(world.satisfies::<(&i32, &bool)>(a).unwrap());
+        assert_eq!(std::sync::Arc::strong_count(&rc), 2);
+
+        cmd.remove_dynamic(a, TypeId::of::<std::sync::Arc<()>>());
+        cmd.remove_dynamic(a, TypeId::of::<char>());
+        cmd.run_on(&mut world);
+        assert!(!world.satisfies::<&std::sync::Arc<()>>(a).unwrap());
+        assert!(world.satisfies::<(&i32, &bool)>(a).unwrap());
+        assert_eq!(std::sync::Arc::strong_count(&rc), 1);
+    }
+
+    #[test]
+    fn run_in_order() {
+        let mut world = World::new();
+        let a = world.spawn((1i32,));
+        let mut cmd = CommandBuffer::new();
+        cmd.run(move |world| *world.get::<&mut i32>(a).unwrap() *= 10);
+        cmd.insert_one(a, 2i32);
+        cmd.run(move |world| *world.get::<&mut i32>(a).unwrap() *= 10);
+        cmd.despawn(a);
+        cmd.run(move |world| assert!(!world.contains(a)));
+        cmd.run_on(&mut world);
+        assert!(cmd.cmds.is_empty());
+
+        let b = world.spawn((1i32,));
+        cmd.insert_one(b, 2i32);
+        cmd.run(move |world| *world.get::<&mut i32>(b).unwrap() *= 10);
+        cmd.run_on(&mut world);
+        assert_eq!(*world.get::<&i32>(b).unwrap(), 20);
+    }
 }
@@ -0,0 +1,119 @@
+use core::any::{type_name, TypeId};
+use core::fmt;
+
+use crate::archetype::{Archetype, TypeIdMap};
+use crate::Component;
+
+/// Type-erased form of `Debug::fmt` for a registered component type, reading the value from the
+/// supplied pointer
+type DebugFn = unsafe fn(*const u8, &mut fmt::Formatter<'_>) -> fmt::Result;
+
+/// Set of component types which may be formatted given only their [`TypeId`]
+///
+/// Used with [`EntityRef::debug_dump`](crate::EntityRef::debug_dump) to list an entity's
+/// components in a human-readable form, e.g. for an in-game inspector.
+///
+/// # Example
+/// ```
+/// # use hecs::*;
+/// #[derive(Debug)]
+/// struct Health(u32);
+///
+/// let mut registry = DebugRegistry::new();
+/// registry.register::<Health>();
+///
+/// let mut world = World::new();
+/// let e = world.spawn((Health(10),));
+/// let dump = format!("{:?}", world.entity(e).unwrap().debug_dump(&registry));
+/// assert!(dump.contains("Health(10)"));
+/// ```
+#[derive(Default)]
+pub struct DebugRegistry {
+    types: TypeIdMap<(&'static str, DebugFn)>,
+}
+
+impl DebugRegistry {
+    /// Create a registry containing no component types
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow `C` components to be formatted
+    pub fn register<C: Component + fmt::Debug>(&mut self) -> &mut Self {
+        self.types.insert(
+            TypeId::of::<C>(),
+            (type_name::<C>(), |ptr, f| unsafe {
+                fmt::Debug::fmt(&*ptr.cast::<C>(), f)
+            }),
+        );
+        self
+    }
+
+    /// Whether components of type `id` may be formatted
+    pub fn contains(&self, id: TypeId) -> bool {
+        self.types.contains_key(&id)
+    }
+
+    /// Name of the registered component type `id`
+    pub fn type_name(&self, id: TypeId) -> Option<&'static str> {
+        self.types.get(&id).map(|x| x.0)
+    }
+}
+
+/// Human-readable listing of an entity's components
+///
+/// Obtained from [`EntityRef::debug_dump`](crate::EntityRef::debug_dump). Formats as a map from
+/// component type names to values. Components whose types were not registered, or which are
+/// currently borrowed uniquely, are listed without their values.
+pub struct EntityDebug<'a> {
+    pub(crate) archetype: &'a Archetype,
+    pub(crate) index: u32,
+    pub(crate) registry: &'a DebugRegistry,
+}
+
+impl fmt::Debug for EntityDebug<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        struct Value<'a> {
+            archetype: &'a Archetype,
+            state: usize,
+            index: u32,
+            debug: DebugFn,
+        }
+
+        impl fmt::Debug for Value<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let archetype = self.archetype;
+                unsafe {
+                    if !archetype.try_borrow_raw(self.state) {
+                        return f.write_str("<borrowed>");
+                    }
+                    let size = archetype.types()[self.state].layout().size();
+                    let ptr = archetype
+                        .get_base_dynamic(self.state)
+                        .as_ptr()
+                        .add(size * self.index as usize);
+                    let result = (self.debug)(ptr, f);
+                    archetype.release_raw(self.state);
+                    result
+                }
+            }
+        }
+
+        let mut map = f.debug_map();
+        for (state, ty) in self.archetype.types().iter().enumerate() {
+            match self.registry.types.get(&ty.id()) {
+                Some(&(name, debug)) => map.entry(
+                    &format_args!("{}", name),
+                    &Value {
+                        archetype: self.archetype,
+                        state,
+                        index: self.index,
+                        debug,
+                    },
+                ),
+                None => map.entry(&ty.id(), &format_args!("<unregistered>")),
+            };
+        }
+        map.finish()
+    }
+}
@@ -0,0 +1,61 @@
+use core::any::TypeId;
+
+use crate::archetype::{TypeIdMap, TypeInfo};
+use crate::Component;
+
+/// Type-erased form of `Default::default` for a registered component type, writing the value to
+/// the supplied pointer
+type DefaultFn = unsafe fn(*mut u8);
+
+/// Set of component types which may be default-constructed given only their [`TypeId`]
+///
+/// Used with [`EntityBuilder::add_default`](crate::EntityBuilder::add_default) and
+/// [`World::spawn_defaults`](crate::World::spawn_defaults) to instantiate components whose types
+/// are only known at runtime, e.g. when loading a list of component types from a data file.
+///
+/// # Example
+/// ```
+/// # use hecs::*;
+/// # use std::any::TypeId;
+/// #[derive(Default)]
+/// struct Health(u32);
+///
+/// let mut registry = DefaultRegistry::new();
+/// registry.register::<Health>().register::<bool>();
+///
+/// let mut world = World::new();
+/// let e = world.spawn_defaults(&registry, &[TypeId::of::<Health>(), TypeId::of::<bool>()]);
+/// assert_eq!(world.get::<&Health>(e).unwrap().0, 0);
+/// assert!(!*world.get::<&bool>(e).unwrap());
+/// ```
+#[derive(Default)]
+pub struct DefaultRegistry {
+    types: TypeIdMap<(TypeInfo, DefaultFn)>,
+}
+
+impl DefaultRegistry {
+    /// Create a registry containing no component types
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow `C` components to be default-constructed
+    pub fn register<C: Component + Default>(&mut self) -> &mut Self {
+        self.types.insert(
+            TypeId::of::<C>(),
+            (TypeInfo::of::<C>(), |ptr| unsafe {
+                ptr.cast::<C>().write(C::default())
+            }),
+        );
+        self
+    }
+
+    /// Whether components of type `id` may be default-constructed
+    pub fn contains(&self, id: TypeId) -> bool {
+        self.types.contains_key(&id)
+    }
+
+    pub(crate) fn get(&self, id: TypeId) -> Option<(TypeInfo, DefaultFn)> {
+        self.types.get(&id).copied()
+    }
+}
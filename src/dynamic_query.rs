@@ -0,0 +1,360 @@
+use core::any::TypeId;
+use core::marker::PhantomData;
+use core::ptr::NonNull;
+use core::slice::Iter as SliceIter;
+
+use crate::alloc::vec::Vec;
+use crate::archetype::TypeInfo;
+use crate::{Access, Archetype, Component, Entity, World};
+
+/// A query whose component types are determined at runtime
+///
+/// Useful for scripting layers and other code which cannot name component types statically.
+/// Constructed with [`DynamicQuery::builder`] and executed with [`World::query_dynamic`].
+///
+/// # Example
+/// ```
+/// # use hecs::*;
+/// # use core::any::TypeId;
+/// let mut world = World::new();
+/// let a = world.spawn((123, true, "abc"));
+/// let b = world.spawn((456, false));
+/// let query = DynamicQuery::builder()
+///     .read(TypeId::of::<bool>())
+///     .write(TypeId::of::<i32>())
+///     .without(TypeId::of::<&str>())
+///     .build();
+/// let mut borrow = world.query_dynamic(&query);
+/// for (entity, mut item) in borrow.iter() {
+///     assert_eq!(entity, b);
+///     assert_eq!(item.get::<bool>(), Some(&false));
+///     *item.get_mut::<i32>().unwrap() += 1;
+/// }
+/// drop(borrow);
+/// assert_eq!(*world.get::<&i32>(b).unwrap(), 457);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DynamicQuery {
+    read: Vec<TypeId>,
+    write: Vec<TypeId>,
+    with: Vec<TypeId>,
+    without: Vec<TypeId>,
+}
+
+impl DynamicQuery {
+    /// Begin constructing a query
+    pub fn builder() -> DynamicQueryBuilder {
+        DynamicQueryBuilder {
+            query: Self::default(),
+        }
+    }
+
+    /// Whether entities in `archetype` match this query
+    pub fn matches(&self, archetype: &Archetype) -> bool {
+        self.read
+            .iter()
+            .chain(&self.write)
+            .chain(&self.with)
+            .all(|&ty| archetype.has_dynamic(ty))
+            && !self.without.iter().any(|&ty| archetype.has_dynamic(ty))
+    }
+}
+
+/// Constructs a [`DynamicQuery`]
+///
+/// A type requested for both reading and writing is accessed uniquely.
+#[derive(Debug, Clone)]
+pub struct DynamicQueryBuilder {
+    query: DynamicQuery,
+}
+
+impl DynamicQueryBuilder {
+    /// Match only entities with a component of type `ty`, and borrow it immutably
+    pub fn read(mut self, ty: TypeId) -> Self {
+        self.query.read.push(ty);
+        self
+    }
+
+    /// Match only entities with a component of type `ty`, and borrow it uniquely
+    pub fn write(mut self, ty: TypeId) -> Self {
+        self.query.write.push(ty);
+        self
+    }
+
+    /// Match only entities with a component of type `ty`, without borrowing it
+    pub fn with(mut self, ty: TypeId) -> Self {
+        self.query.with.push(ty);
+        self
+    }
+
+    /// Match only entities without a component of type `ty`
+    pub fn without(mut self, ty: TypeId) -> Self {
+        self.query.without.push(ty);
+        self
+    }
+
+    /// Finish constructing the query
+    pub fn build(mut self) -> DynamicQuery {
+        let query = &mut self.query;
+        for ids in [
+            &mut query.read,
+            &mut query.write,
+            &mut query.with,
+            &mut query.without,
+        ] {
+            ids.sort_unstable();
+            ids.dedup();
+        }
+        let write = &query.write;
+        query.read.retain(|ty| write.binary_search(ty).is_err());
+        self.query
+    }
+}
+
+/// A column borrowed by a [`DynamicQueryBorrow`]
+#[derive(Copy, Clone)]
+struct Column {
+    ty: TypeInfo,
+    state: usize,
+    base: NonNull<u8>,
+    unique: bool,
+}
+
+impl Column {
+    fn access(&self) -> Access {
+        if self.unique {
+            Access::Write
+        } else {
+            Access::Read
+        }
+    }
+}
+
+/// Columns borrowed from a single archetype
+struct ArchetypeColumns {
+    archetype: usize,
+    columns: Vec<Column>,
+}
+
+/// A borrow of the components matched by a [`DynamicQuery`]
+///
+/// Constructed by [`World::query_dynamic`]. Components are borrowed for as long as this value
+/// exists.
+pub struct DynamicQueryBorrow<'w> {
+    world: &'w World,
+    archetypes: Vec<ArchetypeColumns>,
+}
+
+impl<'w> DynamicQueryBorrow<'w> {
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
+    pub(crate) fn new(world: &'w World, query: &DynamicQuery) -> Self {
+        // Borrows are recorded as they are acquired so that a panic releases exactly those
+        let mut this = Self {
+            world,
+            archetypes: Vec::new(),
+        };
+        let access = query
+            .read
+            .iter()
+            .map(|&ty| (ty, false))
+            .chain(query.write.iter().map(|&ty| (ty, true)));
+        for (index, archetype) in world.archetypes_inner().iter().enumerate() {
+            if archetype.is_empty() || !query.matches(archetype) {
+                continue;
+            }
+            this.archetypes.push(ArchetypeColumns {
+                archetype: index,
+                columns: Vec::with_capacity(query.read.len() + query.write.len()),
+            });
+            for (ty, unique) in access.clone() {
+                let state = archetype.get_state_dynamic(ty).unwrap();
+                unsafe {
+                    if unique {
+                        archetype.borrow_raw_mut(state);
+                    } else {
+                        archetype.borrow_raw(state);
+                    }
+                }
+                this.archetypes.last_mut().unwrap().columns.push(Column {
+                    ty: archetype.types()[state],
+                    state,
+                    base: archetype.get_base_dynamic(state),
+                    unique,
+                });
+            }
+        }
+        this
+    }
+
+    /// Iterate over the matched entities
+    // The lifetime narrowing here is required for soundness.
+    pub fn iter(&mut self) -> DynamicQueryIter<'_> {
+        DynamicQueryIter {
+            world: self.world,
+            archetypes: self.archetypes.iter(),
+            current: None,
+        }
+    }
+}
+
+impl Drop for DynamicQueryBorrow<'_> {
+    fn drop(&mut self) {
+        let archetypes = self.world.archetypes_inner();
+        for borrowed in &self.archetypes {
+            let archetype = &archetypes[borrowed.archetype];
+            for column in &borrowed.columns {
+                unsafe {
+                    if column.unique {
+                        archetype.release_raw_mut(column.state);
+                    } else {
+                        archetype.release_raw(column.state);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Iterator over the entities matched by a [`DynamicQuery`]
+pub struct DynamicQueryIter<'q> {
+    world: &'q World,
+    archetypes: SliceIter<'q, ArchetypeColumns>,
+    current: Option<(&'q Archetype, &'q [Column], u32)>,
+}
+
+impl<'q> Iterator for DynamicQueryIter<'q> {
+    type Item = (Entity, DynamicItem<'q>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((archetype, columns, ref mut row)) = self.current {
+                if *row < archetype.len() {
+                    let index = *row;
+                    *row += 1;
+                    let id = archetype.entity_id(index);
+                    let entity = self.world.entities_meta()[id as usize].entity(id);
+                    return Some((
+                        entity,
+                        DynamicItem {
+                            columns,
+                            row: index,
+                        },
+                    ));
+                }
+            }
+            let borrowed = self.archetypes.next()?;
+            self.current = Some((
+                &self.world.archetypes_inner()[borrowed.archetype],
+                &borrowed.columns,
+                0,
+            ));
+        }
+    }
+}
+
+/// Components of a single entity matched by a [`DynamicQuery`]
+pub struct DynamicItem<'q> {
+    columns: &'q [Column],
+    row: u32,
+}
+
+impl<'q> DynamicItem<'q> {
+    fn column(&self, ty: TypeId) -> Option<&Column> {
+        self.columns.iter().find(|column| column.ty.id() == ty)
+    }
+
+    fn address(&self, column: &Column) -> NonNull<u8> {
+        unsafe {
+            NonNull::new_unchecked(
+                column
+                    .base
+                    .as_ptr()
+                    .add(column.ty.layout().size() * self.row as usize),
+            )
+        }
+    }
+
+    /// Borrow the `T` component, if it was requested for reading or writing
+    pub fn get<T: Component>(&self) -> Option<&T> {
+        let ptr = self.get_raw(TypeId::of::<T>())?;
+        unsafe { Some(&*ptr.cast::<T>()) }
+    }
+
+    /// Uniquely borrow the `T` component, if it was requested for writing
+    pub fn get_mut<T: Component>(&mut self) -> Option<&mut T> {
+        let ptr = self.get_raw_mut(TypeId::of::<T>())?;
+        unsafe { Some(&mut *ptr.cast::<T>()) }
+    }
+
+    /// Get the address of the component of type `ty`, if it was requested for reading or writing
+    pub fn get_raw(&self, ty: TypeId) -> Option<*const u8> {
+        let column = self.column(ty)?;
+        Some(self.address(column).as_ptr().cast_const())
+    }
+
+    /// Get the address of the component of type `ty`, if it was requested for writing
+    ///
+    /// The component may be modified through the returned pointer for as long as this item is
+    /// borrowed.
+    pub fn get_raw_mut(&mut self, ty: TypeId) -> Option<*mut u8> {
+        let column = self.column(ty).filter(|column| column.unique)?;
+        Some(self.address(column).as_ptr())
+    }
+
+    /// Iterate over the type, address, and access of each component requested by the query
+    ///
+    /// Components with [`Access::Write`] may be modified through their address for as long as
+    /// this item is not otherwise used.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (TypeId, *const u8, Access)> + '_ {
+        self.columns.iter().map(move |column| {
+            let ptr = self.address(column).as_ptr().cast_const();
+            (column.ty.id(), ptr, column.access())
+        })
+    }
+
+    /// Extract the component of type `ty`, if it was requested for reading or writing
+    ///
+    /// Unlike [`get_raw`](Self::get_raw), the result carries the component's [`TypeInfo`], and
+    /// remains valid for as long as the query is borrowed.
+    pub fn take_raw(self, ty: TypeId) -> Option<DynamicComponentRaw<'q>> {
+        let column = self.column(ty)?;
+        Some(DynamicComponentRaw {
+            info: column.ty,
+            ptr: self.address(column),
+            access: column.access(),
+            _marker: PhantomData,
+        })
+    }
+}
+
+/// A component of a [`DynamicItem`] with its type erased
+///
+/// Obtained from [`DynamicItem::take_raw`].
+pub struct DynamicComponentRaw<'q> {
+    info: TypeInfo,
+    ptr: NonNull<u8>,
+    access: Access,
+    _marker: PhantomData<&'q mut u8>,
+}
+
+impl DynamicComponentRaw<'_> {
+    /// Type, layout, and destructor of the component
+    pub fn type_info(&self) -> TypeInfo {
+        self.info
+    }
+
+    /// Whether the component may be modified
+    pub fn access(&self) -> Access {
+        self.access
+    }
+
+    /// Address of the component
+    pub fn as_ptr(&self) -> *const u8 {
+        self.ptr.as_ptr().cast_const()
+    }
+
+    /// Address of the component, if it was requested for writing
+    pub fn as_mut_ptr(&mut self) -> Option<*mut u8> {
+        (self.access == Access::Write).then_some(self.ptr.as_ptr())
+    }
+}
@@ -1,3 +1,5 @@
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
 use alloc::vec::Vec;
 use core::cmp;
 use core::convert::TryFrom;
@@ -6,6 +8,7 @@ use core::num::{NonZeroU32, NonZeroU64};
 use core::ops::Range;
 use core::sync::atomic::{AtomicIsize, Ordering};
 use core::{fmt, mem};
+use hashbrown::HashMap;
 #[cfg(feature = "std")]
 use std::error::Error;
 
@@ -15,10 +18,22 @@ use std::error::Error;
 ///
 /// Enable the `serde` feature on the crate to make this `Serialize`able. Some applications may be
 /// able to save space by only serializing the output of `Entity::id`.
-#[derive(Clone, Copy, Hash, Eq, Ord, PartialEq, PartialOrd)]
+///
+/// Enable the `world-id-checks` feature to have each handle remember the `World` that allocated
+/// it, and panic when it's passed to a different `World`. Handles obtained from
+/// `Entity::from_bits` or deserialization don't know their `World` and are never rejected. This
+/// grows `Entity` from 8 to 16 bytes, so the feature is best reserved for debugging.
+#[derive(Clone, Copy)]
+#[cfg_attr(
+    not(feature = "world-id-checks"),
+    derive(Hash, Eq, Ord, PartialEq, PartialOrd)
+)]
 pub struct Entity {
     pub(crate) id: u32,
     pub(crate) generation: NonZeroU32,
+    /// ID of the `World` that allocated this entity, or 0 if unknown
+    #[cfg(feature = "world-id-checks")]
+    pub(crate) world: u64,
 }
 
 impl Entity {
@@ -32,8 +47,25 @@ impl Entity {
             None => unreachable!(),
         },
         id: u32::MAX,
+        #[cfg(feature = "world-id-checks")]
+        world: 0,
     };
 
+    /// Construct a handle tagged with the ID of the `World` it belongs to
+    ///
+    /// The tag is discarded unless the `world-id-checks` feature is enabled.
+    #[inline(always)]
+    pub(crate) fn from_parts(id: u32, generation: NonZeroU32, world: u64) -> Self {
+        #[cfg(not(feature = "world-id-checks"))]
+        let _ = world;
+        Self {
+            id,
+            generation,
+            #[cfg(feature = "world-id-checks")]
+            world,
+        }
+    }
+
     /// Convert to a form convenient for passing outside of rust
     ///
     /// No particular structure is guaranteed for the returned bits.
@@ -60,6 +92,8 @@ impl Entity {
                 None => return None,
             },
             id: bits as u32,
+            #[cfg(feature = "world-id-checks")]
+            world: 0,
         })
     }
 
@@ -75,6 +109,51 @@ impl Entity {
     }
 }
 
+// Handles for the same entity compare equal regardless of whether they know their world
+#[cfg(feature = "world-id-checks")]
+impl PartialEq for Entity {
+    fn eq(&self, other: &Self) -> bool {
+        (self.id, self.generation) == (other.id, other.generation)
+    }
+}
+
+#[cfg(feature = "world-id-checks")]
+impl Eq for Entity {}
+
+#[cfg(feature = "world-id-checks")]
+impl PartialOrd for Entity {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(feature = "world-id-checks")]
+impl Ord for Entity {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        (self.id, self.generation).cmp(&(other.id, other.generation))
+    }
+}
+
+#[cfg(feature = "world-id-checks")]
+impl core::hash::Hash for Entity {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+        self.generation.hash(state);
+    }
+}
+
+/// Panic if `entity` is known to belong to a world other than `world`
+#[cfg(feature = "world-id-checks")]
+#[inline]
+#[track_caller]
+pub(crate) fn check_world(world: u64, entity: Entity) {
+    assert!(
+        entity.world == 0 || entity.world == world,
+        "entity {:?} was used with a World other than the one it belongs to",
+        entity
+    );
+}
+
 impl fmt::Debug for Entity {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}v{}", self.id, self.generation)
@@ -119,6 +198,12 @@ pub struct ReserveEntitiesIterator<'a> {
 
     // New Entity IDs to hand out, outside the range of meta.len().
     id_range: core::ops::Range<u32>,
+
+    // ID of the world the entities are reserved in
+    world: u64,
+
+    // Generation of new Entity IDs
+    generation: NonZeroU32,
 }
 
 impl<'a> Iterator for ReserveEntitiesIterator<'a> {
@@ -127,15 +212,11 @@ impl<'a> Iterator for ReserveEntitiesIterator<'a> {
     fn next(&mut self) -> Option<Self::Item> {
         self.id_iter
             .next()
-            .map(|&id| Entity {
-                generation: self.meta[id as usize].generation,
-                id,
-            })
+            .map(|&id| Entity::from_parts(id, self.meta[id as usize].generation, self.world))
             .or_else(|| {
-                self.id_range.next().map(|id| Entity {
-                    generation: NonZeroU32::new(1).unwrap(),
-                    id,
-                })
+                self.id_range
+                    .next()
+                    .map(|id| Entity::from_parts(id, self.generation, self.world))
             })
     }
 
@@ -147,6 +228,56 @@ impl<'a> Iterator for ReserveEntitiesIterator<'a> {
 
 impl<'a> ExactSizeIterator for ReserveEntitiesIterator<'a> {}
 
+/// A block of consecutive entity IDs from [`World::reserve_entities_contiguous`]
+///
+/// Like other reserved entities, these become real entities on the next
+/// [`World::flush`](crate::World::flush) or operation that implies one.
+///
+/// [`World::reserve_entities_contiguous`]: crate::World::reserve_entities_contiguous
+#[derive(Debug, Clone)]
+pub struct ReservedBlock {
+    ids: Range<u32>,
+    world: u64,
+    generation: NonZeroU32,
+}
+
+impl ReservedBlock {
+    /// The reserved IDs
+    ///
+    /// Suitable for indexing external per-entity storage by `entity.id() - ids().start`.
+    pub fn ids(&self) -> Range<u32> {
+        self.ids.clone()
+    }
+
+    /// Number of reserved entities
+    pub fn len(&self) -> u32 {
+        self.ids.end - self.ids.start
+    }
+
+    /// Whether no entities were reserved
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// The `index`th reserved entity, if `index < self.len()`
+    pub fn get(&self, index: u32) -> Option<Entity> {
+        if index >= self.len() {
+            return None;
+        }
+        Some(self.entity(self.ids.start + index))
+    }
+
+    /// Iterate over the reserved entities in ID order
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = Entity> + '_ {
+        self.ids.clone().map(move |id| self.entity(id))
+    }
+
+    fn entity(&self, id: u32) -> Entity {
+        // Never-before-allocated IDs all start at the same generation
+        Entity::from_parts(id, self.generation, self.world)
+    }
+}
+
 #[derive(Default)]
 pub(crate) struct Entities {
     pub meta: Vec<EntityMeta>,
@@ -189,13 +320,110 @@ pub(crate) struct Entities {
     pending: Vec<u32>,
     free_cursor: AtomicIsize,
     len: u32,
+    /// Recently freed IDs, oldest first, which may not yet be reused
+    quarantine: VecDeque<u32>,
+    /// Number of IDs which must be freed after an ID before it returns to the freelist
+    quarantine_len: usize,
+    /// ID of the world these entities belong to, used to tag handles
+    world: u64,
+    /// Highest generation of any ID forgotten by `compact`, which new IDs must exceed so that
+    /// stale handles to forgotten IDs can't refer to new entities
+    retired_generation: u32,
+    hooks: AllocHooks,
+}
+
+/// Callbacks registered through `World::on_entities_resize` and `World::on_id_recycle`
+#[derive(Default)]
+struct AllocHooks {
+    resize: Vec<Box<dyn FnMut(u32) + Send + Sync>>,
+    recycle: Vec<Box<dyn FnMut(u32) + Send + Sync>>,
+}
+
+impl AllocHooks {
+    #[inline]
+    fn resize(&mut self, len: usize) {
+        for f in &mut self.resize {
+            f(len as u32);
+        }
+    }
+
+    #[inline]
+    fn recycle(&mut self, id: u32) {
+        for f in &mut self.recycle {
+            f(id);
+        }
+    }
 }
 
 impl Entities {
+    pub fn new(world: u64) -> Self {
+        Self {
+            world,
+            ..Self::default()
+        }
+    }
+
+    /// Generation of newly-allocated IDs
+    fn first_generation(&self) -> NonZeroU32 {
+        NonZeroU32::new(self.retired_generation.wrapping_add(1))
+            .unwrap_or_else(|| NonZeroU32::new(1).unwrap())
+    }
+
+    /// Metadata for a newly-allocated ID
+    fn new_meta(&self) -> EntityMeta {
+        EntityMeta {
+            generation: self.first_generation(),
+            #[cfg(feature = "world-id-checks")]
+            world: self.world,
+            ..EntityMeta::EMPTY
+        }
+    }
+
+    /// Panic if `entity` is known to belong to a different world
+    #[inline(always)]
+    fn check_world(&self, entity: Entity) {
+        #[cfg(feature = "world-id-checks")]
+        check_world(
+            self.meta
+                .get(entity.id as usize)
+                .map_or(self.world, |meta| meta.world),
+            entity,
+        );
+        #[cfg(not(feature = "world-id-checks"))]
+        let _ = entity;
+    }
+
+    /// Call `f` with the new length of `meta` whenever it changes
+    pub fn on_resize(&mut self, f: impl FnMut(u32) + Send + Sync + 'static) {
+        self.hooks.resize.push(Box::new(f));
+    }
+
+    /// Call `f` with each previously freed ID when it's allocated again
+    pub fn on_recycle(&mut self, f: impl FnMut(u32) + Send + Sync + 'static) {
+        self.hooks.recycle.push(Box::new(f));
+    }
+
+    /// Delay reuse of each freed ID until at least `len` other IDs have been freed
+    pub fn set_quarantine_len(&mut self, len: usize) {
+        self.quarantine_len = len;
+        self.release_quarantined();
+    }
+
+    /// Move IDs which have completed their quarantine onto the freelist
+    fn release_quarantined(&mut self) {
+        if self.quarantine.len() <= self.quarantine_len {
+            return;
+        }
+        let excess = self.quarantine.len() - self.quarantine_len;
+        self.pending.extend(self.quarantine.drain(..excess));
+        let new_free_cursor = self.pending.len() as isize;
+        *self.free_cursor.get_mut() = new_free_cursor;
+    }
+
     /// Reserve entity IDs concurrently
     ///
     /// Storage for entity generation and location is lazily allocated by calling `flush`.
-    pub fn reserve_entities(&self, count: u32) -> ReserveEntitiesIterator {
+    pub fn reserve_entities(&self, count: u32) -> ReserveEntitiesIterator<'_> {
         // Use one atomic subtract to grab a range of new IDs. The range might be
         // entirely nonnegative, meaning all IDs come from the freelist, or entirely
         // negative, meaning they are all new IDs to allocate, or a mix of both.
@@ -233,9 +461,43 @@ impl Entities {
             meta: &self.meta[..],
             id_iter: self.pending[freelist_range].iter(),
             id_range: new_id_start..new_id_end,
+            world: self.world,
+            generation: self.first_generation(),
         }
     }
 
+    /// Reserve `count` consecutive, never-before-allocated entity IDs concurrently
+    ///
+    /// Fails if previously freed IDs are awaiting reuse, since those must be handed out first.
+    pub fn reserve_entities_contiguous(
+        &self,
+        count: u32,
+    ) -> Result<ReservedBlock, NonContiguousReservation> {
+        let mut cursor = self.free_cursor.load(Ordering::Relaxed);
+        loop {
+            if cursor > 0 {
+                return Err(NonContiguousReservation);
+            }
+            // Claim the next `count` IDs beyond those already reserved, as in `reserve_entities`
+            match self.free_cursor.compare_exchange_weak(
+                cursor,
+                cursor - count as isize,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => break,
+                Err(actual) => cursor = actual,
+            }
+        }
+        let base = self.meta.len() as isize;
+        let end = u32::try_from(base - cursor + count as isize).expect("too many entities");
+        Ok(ReservedBlock {
+            ids: end - count..end,
+            world: self.world,
+            generation: self.first_generation(),
+        })
+    }
+
     /// Reserve one entity ID concurrently
     ///
     /// Equivalent to `self.reserve_entities(1).next().unwrap()`, but more efficient.
@@ -244,20 +506,18 @@ impl Entities {
         if n > 0 {
             // Allocate from the freelist.
             let id = self.pending[(n - 1) as usize];
-            Entity {
-                generation: self.meta[id as usize].generation,
-                id,
-            }
+            Entity::from_parts(id, self.meta[id as usize].generation, self.world)
         } else {
             // Grab a new ID, outside the range of `meta.len()`. `flush()` must
             // eventually be called to make it valid.
             //
             // As `self.free_cursor` goes more and more negative, we return IDs farther
             // and farther beyond `meta.len()`.
-            Entity {
-                generation: NonZeroU32::new(1).unwrap(),
-                id: u32::try_from(self.meta.len() as isize - n).expect("too many entities"),
-            }
+            Entity::from_parts(
+                u32::try_from(self.meta.len() as isize - n).expect("too many entities"),
+                self.first_generation(),
+                self.world,
+            )
         }
     }
 
@@ -279,17 +539,20 @@ impl Entities {
         if let Some(id) = self.pending.pop() {
             let new_free_cursor = self.pending.len() as isize;
             *self.free_cursor.get_mut() = new_free_cursor;
-            Entity {
-                generation: self.meta[id as usize].generation,
-                id,
+            let meta = &mut self.meta[id as usize];
+            #[cfg(feature = "world-id-checks")]
+            {
+                meta.world = self.world;
             }
+            let generation = meta.generation;
+            self.hooks.recycle(id);
+            Entity::from_parts(id, generation, self.world)
         } else {
             let id = u32::try_from(self.meta.len()).expect("too many entities");
-            self.meta.push(EntityMeta::EMPTY);
-            Entity {
-                generation: NonZeroU32::new(1).unwrap(),
-                id,
-            }
+            let meta = self.new_meta();
+            self.meta.push(meta);
+            self.hooks.resize(self.meta.len());
+            meta.entity(id)
         }
     }
 
@@ -306,20 +569,30 @@ impl Entities {
         );
         let pending_end = self.pending.len().saturating_sub(n as usize);
         for &id in &self.pending[pending_end..] {
-            self.meta[id as usize].location = Location {
+            let meta = &mut self.meta[id as usize];
+            meta.location = Location {
                 archetype,
                 index: first_index,
             };
+            #[cfg(feature = "world-id-checks")]
+            {
+                meta.world = self.world;
+            }
+            self.hooks.recycle(id);
             first_index += 1;
         }
 
         let fresh_start = self.meta.len() as u32;
+        let new_meta = self.new_meta();
         self.meta.extend(
             (first_index..(first_index + fresh)).map(|index| EntityMeta {
-                generation: NonZeroU32::new(1).unwrap(),
                 location: Location { archetype, index },
+                ..new_meta
             }),
         );
+        if fresh != 0 {
+            self.hooks.resize(self.meta.len());
+        }
 
         self.len += n;
 
@@ -346,13 +619,20 @@ impl Entities {
             self.pending.extend((self.meta.len() as u32)..entity.id);
             let new_free_cursor = self.pending.len() as isize;
             *self.free_cursor.get_mut() = new_free_cursor;
-            self.meta.resize(entity.id as usize + 1, EntityMeta::EMPTY);
+            self.meta.resize(entity.id as usize + 1, self.new_meta());
+            self.hooks.resize(self.meta.len());
             self.len += 1;
             None
         } else if let Some(index) = self.pending.iter().position(|item| *item == entity.id) {
             self.pending.swap_remove(index);
             let new_free_cursor = self.pending.len() as isize;
             *self.free_cursor.get_mut() = new_free_cursor;
+            self.hooks.recycle(entity.id);
+            self.len += 1;
+            None
+        } else if let Some(index) = self.quarantine.iter().position(|item| *item == entity.id) {
+            self.quarantine.remove(index);
+            self.hooks.recycle(entity.id);
             self.len += 1;
             None
         } else {
@@ -362,7 +642,17 @@ impl Entities {
             ))
         };
 
-        self.meta[entity.id as usize].generation = entity.generation;
+        let meta = &mut self.meta[entity.id as usize];
+        meta.generation = entity.generation;
+        // Handles from other worlds remain valid when resurrected here, e.g. when cloning a world
+        #[cfg(feature = "world-id-checks")]
+        {
+            meta.world = if entity.world == 0 {
+                self.world
+            } else {
+                entity.world
+            };
+        }
 
         loc
     }
@@ -372,6 +662,7 @@ impl Entities {
     /// Must not be called while reserved entities are awaiting `flush()`.
     pub fn free(&mut self, entity: Entity) -> Result<Location, NoSuchEntity> {
         self.verify_flushed();
+        self.check_world(entity);
 
         let meta = self.meta.get_mut(entity.id as usize).ok_or(NoSuchEntity)?;
         if meta.generation != entity.generation || meta.location.index == u32::MAX {
@@ -383,15 +674,79 @@ impl Entities {
 
         let loc = mem::replace(&mut meta.location, EntityMeta::EMPTY.location);
 
-        self.pending.push(entity.id);
-
-        let new_free_cursor = self.pending.len() as isize;
-        *self.free_cursor.get_mut() = new_free_cursor;
         self.len -= 1;
+        if self.quarantine_len == 0 {
+            self.pending.push(entity.id);
+            let new_free_cursor = self.pending.len() as isize;
+            *self.free_cursor.get_mut() = new_free_cursor;
+        } else {
+            self.quarantine.push_back(entity.id);
+            self.release_quarantined();
+        }
 
         Ok(loc)
     }
 
+    /// Advance the generation of a live entity, returning the new handle and its location
+    pub fn bump_generation(&mut self, entity: Entity) -> Result<(Entity, Location), NoSuchEntity> {
+        self.verify_flushed();
+        self.check_world(entity);
+
+        let meta = self.meta.get_mut(entity.id as usize).ok_or(NoSuchEntity)?;
+        if meta.generation != entity.generation || meta.location.index == u32::MAX {
+            return Err(NoSuchEntity);
+        }
+
+        meta.generation = NonZeroU32::new(u32::from(meta.generation).wrapping_add(1))
+            .unwrap_or_else(|| NonZeroU32::new(1).unwrap());
+        Ok((meta.entity(entity.id), meta.location))
+    }
+
+    /// Move live entities with IDs of at least `len` into the free IDs below it, then forget all
+    /// higher IDs
+    ///
+    /// Returns the old and new handle of each moved entity, and its location.
+    pub fn compact(&mut self) -> Vec<(Entity, Entity, Location)> {
+        self.verify_flushed();
+
+        let len = self.len as usize;
+        let mut moves = Vec::new();
+        let mut free = 0;
+        for old in len..self.meta.len() {
+            let old_meta = self.meta[old];
+            if old_meta.location.index == u32::MAX {
+                continue;
+            }
+            while self.meta[free].location.index != u32::MAX {
+                free += 1;
+            }
+            // A free ID's generation has never been handed out, so no stale handle can match it.
+            let slot = &mut self.meta[free];
+            slot.location = old_meta.location;
+            #[cfg(feature = "world-id-checks")]
+            {
+                slot.world = self.world;
+            }
+            moves.push((
+                old_meta.entity(old as u32),
+                slot.entity(free as u32),
+                old_meta.location,
+            ));
+            self.hooks.recycle(free as u32);
+            free += 1;
+        }
+        // Stale handles to forgotten IDs, whether despawned or moved, must never match a new entity
+        if let Some(max) = self.meta[len..].iter().map(|x| x.generation.get()).max() {
+            self.retired_generation = self.retired_generation.max(max);
+        }
+        self.meta.truncate(len);
+        self.hooks.resize(len);
+        self.pending.clear();
+        self.quarantine.clear();
+        *self.free_cursor.get_mut() = 0;
+        moves
+    }
+
     /// Ensure at least `n` allocations can succeed without reallocating
     pub fn reserve(&mut self, additional: u32) {
         self.verify_flushed();
@@ -403,7 +758,20 @@ impl Entities {
         }
     }
 
+    /// Preallocate bookkeeping for up to `max` entity IDs
+    pub fn reserve_exact(&mut self, max: u32) {
+        self.meta.reserve_exact(max as usize);
+        self.pending.reserve_exact(max as usize);
+    }
+
+    /// Whether `alloc` would need a brand new ID, bringing the total to more than `max`
+    pub fn alloc_exceeds(&mut self, max: u32) -> bool {
+        self.verify_flushed();
+        self.pending.is_empty() && self.meta.len() >= max as usize
+    }
+
     pub fn contains(&self, entity: Entity) -> bool {
+        self.check_world(entity);
         match self.meta.get(entity.id as usize) {
             Some(meta) => {
                 meta.generation == entity.generation
@@ -423,7 +791,9 @@ impl Entities {
 
     pub fn clear(&mut self) {
         self.meta.clear();
+        self.hooks.resize(0);
         self.pending.clear();
+        self.quarantine.clear();
         *self.free_cursor.get_mut() = 0;
         self.len = 0;
     }
@@ -431,7 +801,9 @@ impl Entities {
     /// Access the location storage of an entity
     ///
     /// Must not be called on pending entities.
+    #[cfg(test)]
     pub fn get_mut(&mut self, entity: Entity) -> Result<&mut Location, NoSuchEntity> {
+        self.check_world(entity);
         let meta = self.meta.get_mut(entity.id as usize).ok_or(NoSuchEntity)?;
         if meta.generation == entity.generation && meta.location.index != u32::MAX {
             Ok(&mut meta.location)
@@ -442,10 +814,11 @@ impl Entities {
 
     /// Returns `Ok(Location { archetype: 0, index: undefined })` for pending entities
     pub fn get(&self, entity: Entity) -> Result<Location, NoSuchEntity> {
+        self.check_world(entity);
         if self.meta.len() <= entity.id as usize {
             // Check if this could have been obtained from `reserve_entity`
             let free = self.free_cursor.load(Ordering::Relaxed);
-            if entity.generation.get() == 1
+            if entity.generation == self.first_generation()
                 && free < 0
                 && (entity.id as isize) < (free.abs() + self.meta.len() as isize)
             {
@@ -464,6 +837,16 @@ impl Entities {
         Ok(meta.location)
     }
 
+    /// The live entity with `id`, if any
+    pub fn resolve_id(&self, id: u32) -> Option<Entity> {
+        let entity = match self.meta.get(id as usize) {
+            Some(meta) => meta.entity(id),
+            // Pending entities will have the first generation.
+            None => Entity::from_parts(id, self.first_generation(), self.world),
+        };
+        self.contains(entity).then_some(entity)
+    }
+
     /// Panics if the given id would represent an index outside of `meta`.
     ///
     /// # Safety
@@ -472,11 +855,7 @@ impl Entities {
         let meta_len = self.meta.len();
 
         if meta_len > id as usize {
-            let meta = &self.meta[id as usize];
-            Entity {
-                generation: meta.generation,
-                id,
-            }
+            self.meta[id as usize].entity(id)
         } else {
             // See if it's pending, but not yet flushed.
             let free_cursor = self.free_cursor.load(Ordering::Relaxed);
@@ -484,10 +863,7 @@ impl Entities {
 
             if meta_len + num_pending > id as usize {
                 // Pending entities will have the first generation.
-                Entity {
-                    generation: NonZeroU32::new(1).unwrap(),
-                    id,
-                }
+                Entity::from_parts(id, self.first_generation(), self.world)
             } else {
                 panic!("entity id is out of range");
             }
@@ -500,7 +876,7 @@ impl Entities {
 
     /// Allocates space for entities previously reserved with `reserve_entity` or
     /// `reserve_entities`, then initializes each one using the supplied function.
-    pub fn flush(&mut self, mut init: impl FnMut(u32, &mut Location)) {
+    pub fn flush(&mut self, mut init: impl FnMut(Entity, &mut Location)) {
         let free_cursor = *self.free_cursor.get_mut();
 
         let new_free_cursor = if free_cursor >= 0 {
@@ -508,11 +884,12 @@ impl Entities {
         } else {
             let old_meta_len = self.meta.len();
             let new_meta_len = old_meta_len + -free_cursor as usize;
-            self.meta.resize(new_meta_len, EntityMeta::EMPTY);
+            self.meta.resize(new_meta_len, self.new_meta());
+            self.hooks.resize(new_meta_len);
 
             self.len += -free_cursor as u32;
             for (id, meta) in self.meta.iter_mut().enumerate().skip(old_meta_len) {
-                init(id as u32, &mut meta.location);
+                init(meta.entity(id as u32), &mut meta.location);
             }
 
             *self.free_cursor.get_mut() = 0;
@@ -521,7 +898,31 @@ impl Entities {
 
         self.len += (self.pending.len() - new_free_cursor) as u32;
         for id in self.pending.drain(new_free_cursor..) {
-            init(id, &mut self.meta[id as usize].location);
+            let meta = &mut self.meta[id as usize];
+            #[cfg(feature = "world-id-checks")]
+            {
+                meta.world = self.world;
+            }
+            self.hooks.recycle(id);
+            init(meta.entity(id), &mut meta.location);
+        }
+    }
+
+    /// Number of reserved entities which have not yet been flushed
+    pub fn pending_reserved(&self) -> usize {
+        (self.pending.len() as isize - self.free_cursor.load(Ordering::Relaxed)) as usize
+    }
+
+    /// Reserved entities which have not yet been flushed
+    pub fn reserved(&self) -> ReserveEntitiesIterator<'_> {
+        let free_cursor = self.free_cursor.load(Ordering::Relaxed);
+        let base = self.meta.len() as u32;
+        ReserveEntitiesIterator {
+            meta: &self.meta[..],
+            id_iter: self.pending[free_cursor.max(0) as usize..].iter(),
+            id_range: base..base + (-free_cursor).max(0) as u32,
+            world: self.world,
+            generation: self.first_generation(),
         }
     }
 
@@ -529,12 +930,51 @@ impl Entities {
     pub fn len(&self) -> u32 {
         self.len
     }
+
+    pub fn stats(&self, margin: u32) -> EntityStats {
+        let threshold = u32::MAX - margin;
+        let mut stats = EntityStats {
+            slots: self.meta.len() as u32,
+            free: self.meta.len() as u32 - self.len,
+            max_generation: 0,
+            near_wraparound: 0,
+        };
+        for meta in &self.meta {
+            let generation = meta.generation.get();
+            stats.max_generation = stats.max_generation.max(generation);
+            if generation >= threshold {
+                stats.near_wraparound += 1;
+            }
+        }
+        stats
+    }
+}
+
+/// Summary of entity ID usage in a [`World`](crate::World)
+///
+/// Obtained from [`World::entity_stats`](crate::World::entity_stats). Useful for monitoring the
+/// risk of handle collisions described in the [`World`](crate::World) documentation.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct EntityStats {
+    /// Number of entity IDs that have ever been allocated
+    pub slots: u32,
+    /// Number of allocated entity IDs not currently in use by a live entity
+    pub free: u32,
+    /// Highest generation of any entity ID
+    ///
+    /// For a free ID, this is the generation it will have when next allocated.
+    pub max_generation: u32,
+    /// Number of entity IDs whose generation is within the requested margin of wrapping around
+    pub near_wraparound: u32,
 }
 
 #[derive(Copy, Clone)]
 pub(crate) struct EntityMeta {
     pub generation: NonZeroU32,
     pub location: Location,
+    /// ID of the world whose handles may refer to this entity
+    #[cfg(feature = "world-id-checks")]
+    pub world: u64,
 }
 
 impl EntityMeta {
@@ -547,7 +987,30 @@ impl EntityMeta {
             archetype: 0,
             index: u32::MAX, // dummy value, to be filled in
         },
+        #[cfg(feature = "world-id-checks")]
+        world: 0,
     };
+
+    /// Handle to the entity with ID `id` which this metadata describes
+    #[inline(always)]
+    pub fn entity(&self, id: u32) -> Entity {
+        Entity {
+            id,
+            generation: self.generation,
+            #[cfg(feature = "world-id-checks")]
+            world: self.world,
+        }
+    }
+
+    /// Whether `entity` refers to the current occupant of this metadata's ID
+    ///
+    /// Panics if `entity` belongs to another world and `world-id-checks` is enabled.
+    #[inline(always)]
+    pub fn matches(&self, entity: Entity) -> bool {
+        #[cfg(feature = "world-id-checks")]
+        check_world(self.world, entity);
+        self.generation == entity.generation
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -556,6 +1019,50 @@ pub(crate) struct Location {
     pub index: u32,
 }
 
+/// Maps the handles of entities renumbered by [`World::compact_ids`](crate::World::compact_ids)
+/// to their new handles
+#[derive(Debug, Default, Clone)]
+pub struct EntityMap {
+    map: HashMap<Entity, Entity>,
+}
+
+impl EntityMap {
+    /// The new handle of `old`, if it was renumbered
+    pub fn get(&self, old: Entity) -> Option<Entity> {
+        self.map.get(&old).copied()
+    }
+
+    /// The current handle of an entity that was live at the time of renumbering
+    ///
+    /// Returns `old` unchanged if it was not renumbered.
+    pub fn map(&self, old: Entity) -> Entity {
+        self.get(old).unwrap_or(old)
+    }
+
+    /// Iterate over the old and new handles of every renumbered entity
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (Entity, Entity)> + '_ {
+        self.map.iter().map(|(&old, &new)| (old, new))
+    }
+
+    /// Number of renumbered entities
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Whether no entities were renumbered
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+impl FromIterator<(Entity, Entity)> for EntityMap {
+    fn from_iter<I: IntoIterator<Item = (Entity, Entity)>>(iter: I) -> Self {
+        Self {
+            map: iter.into_iter().collect(),
+        }
+    }
+}
+
 /// Error indicating that no entity with a particular ID exists
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct NoSuchEntity;
@@ -569,6 +1076,20 @@ impl fmt::Display for NoSuchEntity {
 #[cfg(feature = "std")]
 impl Error for NoSuchEntity {}
 
+/// Error indicating that a contiguous block of entity IDs could not be reserved because freed IDs
+/// are awaiting reuse
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct NonContiguousReservation;
+
+impl fmt::Display for NonContiguousReservation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad("freed entity IDs must be reused before reserving a contiguous block")
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for NonContiguousReservation {}
+
 #[derive(Clone)]
 pub(crate) struct AllocManyState {
     pub pending_end: usize,
@@ -598,11 +1119,18 @@ mod tests {
     use rand::{rngs::StdRng, Rng, SeedableRng};
 
     #[test]
-    fn entity_bits_roundtrip() {
-        let e = Entity {
-            generation: NonZeroU32::new(0xDEADBEEF).unwrap(),
-            id: 0xBAADF00D,
+    fn entity_size() {
+        let expected = if cfg!(feature = "world-id-checks") {
+            16
+        } else {
+            8
         };
+        assert_eq!(core::mem::size_of::<Entity>(), expected);
+    }
+
+    #[test]
+    fn entity_bits_roundtrip() {
+        let e = Entity::from_parts(0xBAADF00D, NonZeroU32::new(0xDEADBEEF).unwrap(), 0);
         assert_eq!(Entity::from_bits(e.to_bits().into()).unwrap(), e);
     }
 
@@ -639,13 +1167,14 @@ mod tests {
                 let id = rng.gen_range(0..first_unused);
 
                 let generation = id_to_gen.remove(&id);
-                let entity = Entity {
+                let entity = Entity::from_parts(
                     id,
-                    generation: NonZeroU32::new(
+                    NonZeroU32::new(
                         generation.unwrap_or_else(|| NonZeroU32::new(1).unwrap().get()),
                     )
                     .unwrap(),
-                };
+                    0,
+                );
 
                 assert_eq!(e.free(entity).is_ok(), generation.is_some());
                 if generation.is_some() {
@@ -699,10 +1228,7 @@ mod tests {
         // to be located in the free list.
         assert_eq!(e.meta.len(), 1);
         assert!(e
-            .alloc_at(Entity {
-                id: 3,
-                generation: NonZeroU32::new(2).unwrap(),
-            })
+            .alloc_at(Entity::from_parts(3, NonZeroU32::new(2).unwrap(), 0))
             .is_none());
         e.meta[entity.id as usize].location.index = 0;
         assert_eq!(e.pending.len(), 2);
@@ -727,14 +1253,16 @@ mod tests {
         for _ in 0..3 {
             let entity = e.reserve_entity();
             assert!(e.contains(entity));
-            assert!(!e.contains(Entity {
-                id: entity.id,
-                generation: NonZeroU32::new(2).unwrap(),
-            }));
-            assert!(!e.contains(Entity {
-                id: entity.id + 1,
-                generation: NonZeroU32::new(1).unwrap(),
-            }));
+            assert!(!e.contains(Entity::from_parts(
+                entity.id,
+                NonZeroU32::new(2).unwrap(),
+                0
+            )));
+            assert!(!e.contains(Entity::from_parts(
+                entity.id + 1,
+                NonZeroU32::new(1).unwrap(),
+                0
+            )));
         }
     }
 
@@ -779,9 +1307,9 @@ mod tests {
         assert_eq!(*e.free_cursor.get_mut(), -6);
 
         let mut flushed = Vec::new();
-        e.flush(|id, loc| {
+        e.flush(|entity, loc| {
             loc.index = 0;
-            flushed.push(id);
+            flushed.push(entity.id);
         });
         flushed.sort_unstable();
 
@@ -827,14 +1355,38 @@ mod tests {
     fn alloc_at_regression() {
         let mut e = Entities::default();
         assert!(e
-            .alloc_at(Entity {
-                generation: NonZeroU32::new(1).unwrap(),
-                id: 1
-            })
+            .alloc_at(Entity::from_parts(1, NonZeroU32::new(1).unwrap(), 0))
             .is_none());
-        assert!(!e.contains(Entity {
-            generation: NonZeroU32::new(1).unwrap(),
-            id: 0
-        }));
+        assert!(!e.contains(Entity::from_parts(0, NonZeroU32::new(1).unwrap(), 0)));
+    }
+
+    #[test]
+    fn quarantine() {
+        let mut e = Entities::default();
+        e.set_quarantine_len(2);
+        let ids = (0..3)
+            .map(|_| {
+                let entity = e.alloc();
+                e.meta[entity.id as usize].location.index = 0;
+                entity
+            })
+            .collect::<Vec<_>>();
+
+        e.free(ids[0]).unwrap();
+        e.free(ids[1]).unwrap();
+        assert!(!e.contains(ids[0]));
+        // Both freed IDs are quarantined, so a new ID is allocated
+        assert_eq!(e.alloc().id, 3);
+
+        e.free(ids[2]).unwrap();
+        // Only the oldest freed ID has completed its quarantine
+        let reused = e.alloc();
+        assert_eq!(reused.id, ids[0].id);
+        assert_ne!(reused.generation, ids[0].generation);
+        assert_eq!(e.alloc().id, 4);
+
+        // Quarantined IDs may still be allocated explicitly
+        assert!(e.alloc_at(ids[1]).is_none());
+        assert_eq!(e.len(), 4);
     }
 }
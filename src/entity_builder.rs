@@ -9,12 +9,14 @@ use crate::alloc::alloc::{alloc, dealloc, Layout};
 use crate::alloc::vec::Vec;
 use crate::bundle::{DynamicBundleClone, DynamicClone};
 use core::any::TypeId;
+use core::fmt;
+use core::hash::{BuildHasher, Hash, Hasher};
 use core::ptr::{self, NonNull};
 
 use hashbrown::hash_map::Entry;
 
 use crate::archetype::{TypeIdMap, TypeInfo};
-use crate::{align, Component, ComponentRef, ComponentRefShared, DynamicBundle};
+use crate::{align, Component, ComponentRef, ComponentRefShared, DefaultRegistry, DynamicBundle};
 
 /// Helper for incrementally constructing a bundle of components with dynamic component types
 ///
@@ -31,7 +33,8 @@ use crate::{align, Component, ComponentRef, ComponentRefShared, DynamicBundle};
 /// ```
 #[derive(Default)]
 pub struct EntityBuilder {
-    inner: Common<()>,
+    /// Components, with the means to clone those added by `add_cloneable`
+    inner: Common<Option<DynamicClone>>,
 }
 
 impl EntityBuilder {
@@ -40,6 +43,17 @@ impl EntityBuilder {
         Self::default()
     }
 
+    /// Create a builder with storage preallocated for `components` components totaling `bytes`
+    /// bytes
+    ///
+    /// Storage is retained when the builder is cleared, including when an entity is built, so a
+    /// suitably sized builder never allocates.
+    pub fn with_capacity(components: usize, bytes: usize) -> Self {
+        Self {
+            inner: Common::with_capacity(components, bytes),
+        }
+    }
+
     /// Add `component` to the entity.
     ///
     /// If the bundle already contains a component of type `T`, it will
@@ -55,7 +69,53 @@ impl EntityBuilder {
     /// will replace the old component and the old component will be dropped.
     pub fn add_bundle(&mut self, bundle: impl DynamicBundle) -> &mut Self {
         unsafe {
-            bundle.put(|ptr, ty| self.inner.add(ptr, ty, ()));
+            bundle.put(|ptr, ty| self.inner.add(ptr, ty, None));
+        }
+        self
+    }
+
+    /// Add `component` to the entity, allowing it to be spawned repeatedly by
+    /// [`build_ref`](Self::build_ref)
+    ///
+    /// Otherwise equivalent to [`add`](Self::add).
+    pub fn add_cloneable<T: Component + Clone>(&mut self, component: T) -> &mut Self {
+        self.add_bundle_cloneable((component,))
+    }
+
+    /// Add all components in `bundle` to the entity, allowing them to be spawned repeatedly by
+    /// [`build_ref`](Self::build_ref)
+    ///
+    /// Otherwise equivalent to [`add_bundle`](Self::add_bundle).
+    pub fn add_bundle_cloneable(&mut self, bundle: impl DynamicBundleClone) -> &mut Self {
+        unsafe {
+            bundle.put_with_clone(|ptr, ty, cloneable| self.inner.add(ptr, ty, Some(cloneable)));
+        }
+        self
+    }
+
+    /// Add a default-constructed component of type `id` to the entity
+    ///
+    /// Like [`add`](Self::add), but for a component type known only at runtime. Panics if `id` was
+    /// not registered with `registry`.
+    pub fn add_default(&mut self, registry: &DefaultRegistry, id: TypeId) -> &mut Self {
+        let (ty, construct) = registry
+            .get(id)
+            .expect("component type not registered for default construction");
+        let layout = ty.layout();
+        unsafe {
+            if layout.size() == 0 {
+                let ptr = layout.align() as *mut u8;
+                construct(ptr);
+                self.inner.add(ptr, ty, None);
+            } else {
+                let ptr = alloc(layout);
+                if ptr.is_null() {
+                    crate::alloc::alloc::handle_alloc_error(layout);
+                }
+                construct(ptr);
+                self.inner.add(ptr, ty, None);
+                dealloc(ptr, layout);
+            }
         }
         self
     }
@@ -63,6 +123,7 @@ impl EntityBuilder {
     /// Construct a `Bundle` suitable for spawning
     pub fn build(&mut self) -> BuiltEntity<'_> {
         self.inner.info.sort_unstable_by_key(|x| x.0);
+        self.inner.ids.clear();
         self.inner
             .ids
             .extend(self.inner.info.iter().map(|x| x.0.id()));
@@ -71,6 +132,40 @@ impl EntityBuilder {
         }
     }
 
+    /// Construct a `Bundle` which clones the builder's components each time it's spawned
+    ///
+    /// Unlike [`build`](Self::build), leaves the builder's components in place, so the same
+    /// entity can be spawned many times without adding them again. Fails if any component was
+    /// added by a method other than [`add_cloneable`](Self::add_cloneable) or
+    /// [`add_bundle_cloneable`](Self::add_bundle_cloneable).
+    ///
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let mut builder = EntityBuilder::new();
+    /// builder.add_cloneable(123).add_cloneable("abc");
+    /// let bundle = builder.build_ref().unwrap();
+    /// let e = world.spawn(bundle);
+    /// let f = world.spawn(bundle);
+    /// assert_eq!(*world.get::<&i32>(f).unwrap(), 123);
+    /// builder.add(true);
+    /// assert!(builder.build_ref().is_err());
+    /// ```
+    pub fn build_ref(&mut self) -> Result<BuiltEntityRef<'_>, NotCloneable> {
+        if let Some(&(ty, _, _)) = self.inner.info.iter().find(|x| x.2.is_none()) {
+            return Err(NotCloneable(ty.id(), ty.type_name()));
+        }
+        let inner = &mut self.inner;
+        inner.info.sort_unstable_by_key(|x| x.0);
+        // Sorting invalidates the index of each type, which must remain usable
+        for (index, &(ty, _, _)) in inner.info.iter().enumerate() {
+            inner.indices.insert(ty.id(), index);
+        }
+        inner.ids.clear();
+        inner.ids.extend(inner.info.iter().map(|x| x.0.id()));
+        Ok(BuiltEntityRef { builder: inner })
+    }
+
     /// Checks to see if the component of type `T` exists
     pub fn has<T: Component>(&self) -> bool {
         self.inner.has::<T>()
@@ -95,6 +190,21 @@ impl EntityBuilder {
         self.inner.component_types()
     }
 
+    /// Number of components in the entity builder
+    pub fn len(&self) -> usize {
+        self.inner.info.len()
+    }
+
+    /// Whether the entity builder has no components
+    pub fn is_empty(&self) -> bool {
+        self.inner.info.is_empty()
+    }
+
+    /// Remove and return the `T` component, if it exists
+    pub fn remove<T: Component>(&mut self) -> Option<T> {
+        self.inner.remove::<T>()
+    }
+
     /// Drop previously `add`ed components
     ///
     /// The builder is cleared implicitly when an entity is built, so this doesn't usually need to
@@ -104,10 +214,59 @@ impl EntityBuilder {
     }
 }
 
+/// A collection of cleared [`EntityBuilder`]s, retaining their storage for reuse
+///
+/// Useful when builders are needed in several places at once, e.g. by reentrant code, which
+/// otherwise can't share a single builder. See [`World::spawn_into`](crate::World::spawn_into).
+///
+/// ```
+/// # use hecs::*;
+/// let mut pool = BuilderPool::new();
+/// let mut builder = pool.take();
+/// builder.add(123);
+/// let mut world = World::new();
+/// let e = world.spawn(builder.build());
+/// pool.give(builder);
+/// assert_eq!(pool.len(), 1);
+/// assert_eq!(*world.get::<&i32>(e).unwrap(), 123);
+/// ```
+#[derive(Default)]
+pub struct BuilderPool {
+    free: Vec<EntityBuilder>,
+}
+
+impl BuilderPool {
+    /// Create an empty pool
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remove a builder from the pool, or create a new one if the pool is empty
+    pub fn take(&mut self) -> EntityBuilder {
+        self.free.pop().unwrap_or_default()
+    }
+
+    /// Clear `builder` and return it to the pool
+    pub fn give(&mut self, mut builder: EntityBuilder) {
+        builder.clear();
+        self.free.push(builder);
+    }
+
+    /// Number of builders available for reuse
+    pub fn len(&self) -> usize {
+        self.free.len()
+    }
+
+    /// Whether no builders are available for reuse
+    pub fn is_empty(&self) -> bool {
+        self.free.is_empty()
+    }
+}
+
 /// The output of an [`EntityBuilder`], suitable for passing to
 /// [`World::spawn`](crate::World::spawn) or [`World::insert`](crate::World::insert)
 pub struct BuiltEntity<'a> {
-    builder: &'a mut Common<()>,
+    builder: &'a mut Common<Option<DynamicClone>>,
 }
 
 unsafe impl DynamicBundle for BuiltEntity<'_> {
@@ -125,7 +284,7 @@ unsafe impl DynamicBundle for BuiltEntity<'_> {
     }
 
     unsafe fn put(self, mut f: impl FnMut(*mut u8, TypeInfo)) {
-        for (ty, offset, ()) in self.builder.info.drain(..) {
+        for (ty, offset, _) in self.builder.info.drain(..) {
             let ptr = self.builder.storage.as_ptr().add(offset);
             f(ptr, ty);
         }
@@ -140,6 +299,63 @@ impl Drop for BuiltEntity<'_> {
     }
 }
 
+/// The output of [`EntityBuilder::build_ref`], suitable for passing to
+/// [`World::spawn`](crate::World::spawn) or [`World::insert`](crate::World::insert) any number of
+/// times
+///
+/// Each use clones the builder's components.
+#[derive(Copy, Clone)]
+pub struct BuiltEntityRef<'a> {
+    builder: &'a Common<Option<DynamicClone>>,
+}
+
+unsafe impl DynamicBundle for BuiltEntityRef<'_> {
+    fn has<T: Component>(&self) -> bool {
+        self.builder.has::<T>()
+    }
+
+    fn with_ids<T>(&self, f: impl FnOnce(&[TypeId]) -> T) -> T {
+        f(&self.builder.ids)
+    }
+
+    #[doc(hidden)]
+    fn type_info(&self) -> Vec<TypeInfo> {
+        self.builder.info.iter().map(|x| x.0).collect()
+    }
+
+    unsafe fn put(self, mut f: impl FnMut(*mut u8, TypeInfo)) {
+        for &(_, offset, clone) in &self.builder.info {
+            let ptr = self.builder.storage.as_ptr().add(offset);
+            (clone.unwrap().func)(ptr, &mut f);
+        }
+    }
+}
+
+/// Error indicating that an [`EntityBuilder`] contains a component which it cannot clone
+///
+/// Returned by [`EntityBuilder::build_ref`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct NotCloneable(TypeId, Option<&'static str>);
+
+impl NotCloneable {
+    /// Type of the component which cannot be cloned
+    pub fn type_id(&self) -> TypeId {
+        self.0
+    }
+}
+
+impl fmt::Display for NotCloneable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.1 {
+            Some(name) => write!(f, "{} component was not added as cloneable", name),
+            None => f.write_str("component was not added as cloneable"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for NotCloneable {}
+
 /// Variant of [`EntityBuilder`] that clones components on use
 ///
 /// ```
@@ -224,6 +440,21 @@ impl EntityBuilderClone {
         self.inner.component_types()
     }
 
+    /// Number of components in the entity builder
+    pub fn len(&self) -> usize {
+        self.inner.info.len()
+    }
+
+    /// Whether the entity builder has no components
+    pub fn is_empty(&self) -> bool {
+        self.inner.info.is_empty()
+    }
+
+    /// Remove and return the `T` component, if it exists
+    pub fn remove<T: Component>(&mut self) -> Option<T> {
+        self.inner.remove::<T>()
+    }
+
     /// Drop previously `add`ed components
     ///
     /// The builder is cleared implicitly when an entity is built, so this doesn't usually need to
@@ -238,9 +469,13 @@ impl EntityBuilderClone {
 /// Built from, and convertible back into, [`EntityBuilderClone`]. `DynamicBundle` is implemented
 /// for *references to* this type, allowing it to be e.g. spawned repeatedly.
 #[derive(Clone)]
-pub struct BuiltEntityClone(Common<DynamicClone>);
+pub struct BuiltEntityClone(Common<DynamicClone>, u64);
 
 unsafe impl DynamicBundle for &'_ BuiltEntityClone {
+    fn key_hash(&self) -> Option<u64> {
+        Some(self.1)
+    }
+
     fn has<T: Component>(&self) -> bool {
         self.0.has::<T>()
     }
@@ -274,7 +509,9 @@ impl From<EntityBuilderClone> for BuiltEntityClone {
     fn from(mut x: EntityBuilderClone) -> Self {
         x.inner.info.sort_unstable_by_key(|y| y.0);
         x.inner.ids.extend(x.inner.info.iter().map(|y| y.0.id()));
-        Self(x.inner)
+        let mut hasher = foldhash::fast::FixedState::default().build_hasher();
+        x.inner.ids.hash(&mut hasher);
+        Self(x.inner, hasher.finish())
     }
 }
 
@@ -285,6 +522,89 @@ impl From<BuiltEntityClone> for EntityBuilderClone {
     }
 }
 
+/// Components removed from an entity by [`World::remove_dynamic`](crate::World::remove_dynamic)
+///
+/// Owns the removed components, dropping them when it's dropped unless they're moved into another
+/// entity by passing it to e.g. [`World::insert`](crate::World::insert).
+///
+/// ```
+/// # use hecs::*;
+/// # use core::any::TypeId;
+/// let mut world = World::new();
+/// let a = world.spawn((123, true, "abc"));
+/// let b = world.spawn(());
+/// let taken = world.remove_dynamic(a, &[TypeId::of::<i32>(), TypeId::of::<bool>()]).unwrap();
+/// assert_eq!(taken.len(), 2);
+/// assert_eq!(taken.get::<&i32>().map(|x| *x), Some(123));
+/// world.insert(b, taken).unwrap();
+/// assert!(world.satisfies::<(&i32, &bool)>(b).unwrap());
+/// ```
+#[derive(Default)]
+pub struct TakenBundle {
+    inner: Common<()>,
+}
+
+impl TakenBundle {
+    /// Move in a component, which must sort after every component already present
+    pub(crate) unsafe fn push(&mut self, ptr: *mut u8, ty: TypeInfo) {
+        debug_assert!(self.inner.info.last().map_or(true, |x| x.0 < ty));
+        self.inner.add(ptr, ty, ());
+        self.inner.ids.push(ty.id());
+    }
+
+    /// Checks to see if the component of type `T` exists
+    pub fn has<T: Component>(&self) -> bool {
+        self.inner.has::<T>()
+    }
+
+    /// Borrow a shared reference `T` to some component type, if it exists
+    pub fn get<'a, T: ComponentRefShared<'a>>(&'a self) -> Option<T> {
+        self.inner.get::<T>()
+    }
+
+    /// Borrow a shared or unique reference `T` to some component type, if it exists
+    pub fn get_mut<'a, T: ComponentRef<'a>>(&'a mut self) -> Option<T> {
+        self.inner.get_mut::<T>()
+    }
+
+    /// Enumerate the types of the components
+    pub fn component_types(&self) -> impl Iterator<Item = TypeId> + '_ {
+        self.inner.component_types()
+    }
+
+    /// Number of components
+    pub fn len(&self) -> usize {
+        self.inner.info.len()
+    }
+
+    /// Whether there are no components
+    pub fn is_empty(&self) -> bool {
+        self.inner.info.is_empty()
+    }
+}
+
+unsafe impl DynamicBundle for TakenBundle {
+    fn has<T: Component>(&self) -> bool {
+        self.inner.has::<T>()
+    }
+
+    fn with_ids<T>(&self, f: impl FnOnce(&[TypeId]) -> T) -> T {
+        f(&self.inner.ids)
+    }
+
+    #[doc(hidden)]
+    fn type_info(&self) -> Vec<TypeInfo> {
+        self.inner.info.iter().map(|x| x.0).collect()
+    }
+
+    unsafe fn put(mut self, mut f: impl FnMut(*mut u8, TypeInfo)) {
+        for (ty, offset, ()) in self.inner.info.drain(..) {
+            let ptr = self.inner.storage.as_ptr().add(offset);
+            f(ptr, ty);
+        }
+    }
+}
+
 struct Common<M> {
     storage: NonNull<u8>,
     layout: Layout,
@@ -295,6 +615,23 @@ struct Common<M> {
 }
 
 impl<M> Common<M> {
+    fn with_capacity(components: usize, bytes: usize) -> Self {
+        let mut result = Self {
+            info: Vec::with_capacity(components),
+            ids: Vec::with_capacity(components),
+            indices: TypeIdMap::with_capacity_and_hasher(components, Default::default()),
+            ..Self::default()
+        };
+        if bytes != 0 {
+            unsafe {
+                let (storage, layout) = Self::grow(bytes, 0, result.layout.align(), result.storage);
+                result.storage = storage;
+                result.layout = layout;
+            }
+        }
+        result
+    }
+
     fn has<T: Component>(&self) -> bool {
         self.indices.contains_key(&TypeId::of::<T>())
     }
@@ -321,6 +658,16 @@ impl<M> Common<M> {
         self.info.iter().map(|(info, _, _)| info.id())
     }
 
+    /// Move the `T` component out, leaving its storage unused until the next `clear`
+    fn remove<T: Component>(&mut self) -> Option<T> {
+        let index = self.indices.remove(&TypeId::of::<T>())?;
+        let (_, offset, _) = self.info.swap_remove(index);
+        if let Some(&(ty, _, _)) = self.info.get(index) {
+            self.indices.insert(ty.id(), index);
+        }
+        unsafe { Some(self.storage.as_ptr().add(offset).cast::<T>().read()) }
+    }
+
     unsafe fn grow(
         min_size: usize,
         cursor: usize,
@@ -356,6 +703,7 @@ impl<M> Common<M> {
 
                 // Overwrite the old value with our new one.
                 ptr::copy_nonoverlapping(ptr, storage, ty.layout().size());
+                self.info[index].2 = meta;
             }
             Entry::Vacant(vacant) => {
                 let offset = align(self.cursor, ty.layout().align());
@@ -1,26 +1,35 @@
 use core::any::TypeId;
+use core::cell::Cell;
 use core::fmt::{self, Debug, Display, Formatter};
 use core::marker::PhantomData;
 use core::ops::{Deref, DerefMut, FnOnce};
 use core::ptr::NonNull;
 
-use crate::archetype::Archetype;
+use crate::archetype::{Archetype, TypeInfo};
+use crate::entities::EntityMeta;
 use crate::{
-    ArchetypeColumn, ArchetypeColumnMut, Component, Entity, Fetch, MissingComponent, Query,
-    QueryOne,
+    ArchetypeColumn, ArchetypeColumnMut, ArchetypeId, BorrowConflict, Component, DebugRegistry,
+    Entity, EntityDebug, Fetch, MissingComponent, Query, QueryOne, World,
 };
 
 /// Handle to an entity with any component types
 #[derive(Copy, Clone)]
 pub struct EntityRef<'a> {
+    meta: &'a [EntityMeta],
     archetype: &'a Archetype,
     entity: Entity,
     index: u32,
 }
 
 impl<'a> EntityRef<'a> {
-    pub(crate) unsafe fn new(archetype: &'a Archetype, entity: Entity, index: u32) -> Self {
+    pub(crate) unsafe fn new(
+        meta: &'a [EntityMeta],
+        archetype: &'a Archetype,
+        entity: Entity,
+        index: u32,
+    ) -> Self {
         Self {
+            meta,
             archetype,
             entity,
             index,
@@ -33,6 +42,11 @@ impl<'a> EntityRef<'a> {
         self.entity
     }
 
+    /// Identify the archetype containing this entity
+    pub fn archetype_id(&self) -> ArchetypeId {
+        ArchetypeId(self.meta[self.entity.id() as usize].location.archetype)
+    }
+
     /// Determine whether this entity would satisfy the query `Q` without borrowing any components
     pub fn satisfies<Q: Query>(&self) -> bool {
         Q::Fetch::access(self.archetype).is_some()
@@ -61,10 +75,30 @@ impl<'a> EntityRef<'a> {
     ///
     /// Panics if `T` is a unique reference and the component is already borrowed, or if the
     /// component is already uniquely borrowed.
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
     pub fn get<T: ComponentRef<'a>>(&self) -> Option<T::Ref> {
         T::get_component(*self)
     }
 
+    /// Like [`get`](Self::get), but returns an error rather than panicking if the component is
+    /// already borrowed incompatibly
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let a = world.spawn((42,));
+    /// let e = world.entity(a).unwrap();
+    /// let shared = e.get::<&i32>().unwrap();
+    /// assert!(e.try_get::<&mut i32>().is_err());
+    /// drop(shared);
+    /// assert_eq!(*e.try_get::<&mut i32>().unwrap().unwrap(), 42);
+    /// assert!(e.try_get::<&bool>().unwrap().is_none());
+    /// ```
+    pub fn try_get<T: ComponentRef<'a>>(&self) -> Result<Option<T::Ref>, BorrowConflict> {
+        T::try_get_component(*self)
+    }
+
     /// Run a query against this entity
     ///
     /// Equivalent to invoking [`World::query_one`](crate::World::query_one) on the entity. May
@@ -82,7 +116,7 @@ impl<'a> EntityRef<'a> {
     /// assert_eq!(*number, 246);
     /// ```
     pub fn query<Q: Query>(&self) -> QueryOne<'a, Q> {
-        unsafe { QueryOne::new(self.archetype, self.index) }
+        unsafe { QueryOne::new(self.meta, self.archetype, self.index) }
     }
 
     /// Enumerate the types of the entity's components
@@ -95,6 +129,28 @@ impl<'a> EntityRef<'a> {
         self.archetype.types().iter().map(|ty| ty.id())
     }
 
+    /// Enumerate this entity's component types, including their layouts
+    ///
+    /// Like [`component_types`](Self::component_types), but yielding the full [`TypeInfo`].
+    pub fn component_infos(&self) -> impl ExactSizeIterator<Item = TypeInfo> + 'a {
+        self.archetype.types().iter().copied()
+    }
+
+    /// List this entity's components in a human-readable form
+    ///
+    /// The result implements [`Debug`], formatting each component whose type is
+    /// in `registry`. Components are borrowed only while being formatted.
+    pub fn debug_dump<'r>(&self, registry: &'r DebugRegistry) -> EntityDebug<'r>
+    where
+        'a: 'r,
+    {
+        EntityDebug {
+            archetype: self.archetype,
+            index: self.index,
+            registry,
+        }
+    }
+
     /// Number of components in this entity
     pub fn len(&self) -> usize {
         self.archetype.types().len()
@@ -117,6 +173,7 @@ pub struct Ref<'a, T: ?Sized> {
 }
 
 impl<'a, T: Component> Ref<'a, T> {
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
     pub(crate) unsafe fn new(
         archetype: &'a Archetype,
         index: u32,
@@ -128,6 +185,39 @@ impl<'a, T: Component> Ref<'a, T> {
             _phantom: PhantomData,
         })
     }
+
+    /// Like `new`, but returns `Ok(None)` if `T` is absent
+    pub(crate) unsafe fn try_new(
+        archetype: &'a Archetype,
+        index: u32,
+    ) -> Result<Option<Self>, BorrowConflict> {
+        let Some(state) = archetype.get_state::<T>() else {
+            return Ok(None);
+        };
+        if !archetype.try_borrow::<T>(state) {
+            return Err(BorrowConflict::new::<&T>());
+        }
+        Ok(Some(Self {
+            borrow: ComponentBorrow { archetype, state },
+            target: NonNull::new_unchecked(
+                archetype.get_base::<T>(state).as_ptr().add(index as usize),
+            ),
+            _phantom: PhantomData,
+        }))
+    }
+
+    /// # Safety
+    ///
+    /// `state` must have been obtained from `archetype.get_state::<T>()`, and `index` must be in
+    /// bounds.
+    pub(crate) unsafe fn from_state(archetype: &'a Archetype, state: usize, index: u32) -> Self {
+        let (target, borrow) = ComponentBorrow::for_state::<T>(archetype, state, index);
+        Self {
+            borrow,
+            target,
+            _phantom: PhantomData,
+        }
+    }
 }
 
 unsafe impl<T: ?Sized + Sync> Send for Ref<'_, T> {}
@@ -204,6 +294,7 @@ pub struct RefMut<'a, T: ?Sized> {
 }
 
 impl<'a, T: Component> RefMut<'a, T> {
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
     pub(crate) unsafe fn new(
         archetype: &'a Archetype,
         index: u32,
@@ -215,6 +306,39 @@ impl<'a, T: Component> RefMut<'a, T> {
             _phantom: PhantomData,
         })
     }
+
+    /// Like `new`, but returns `Ok(None)` if `T` is absent
+    pub(crate) unsafe fn try_new(
+        archetype: &'a Archetype,
+        index: u32,
+    ) -> Result<Option<Self>, BorrowConflict> {
+        let Some(state) = archetype.get_state::<T>() else {
+            return Ok(None);
+        };
+        if !archetype.try_borrow_mut::<T>(state) {
+            return Err(BorrowConflict::new::<&mut T>());
+        }
+        Ok(Some(Self {
+            borrow: ComponentBorrowMut { archetype, state },
+            target: NonNull::new_unchecked(
+                archetype.get_base::<T>(state).as_ptr().add(index as usize),
+            ),
+            _phantom: PhantomData,
+        }))
+    }
+
+    /// # Safety
+    ///
+    /// `state` must have been obtained from `archetype.get_state::<T>()`, and `index` must be in
+    /// bounds.
+    pub(crate) unsafe fn from_state(archetype: &'a Archetype, state: usize, index: u32) -> Self {
+        let (target, borrow) = ComponentBorrowMut::for_state::<T>(archetype, state, index);
+        Self {
+            borrow,
+            target,
+            _phantom: PhantomData,
+        }
+    }
 }
 
 unsafe impl<T: ?Sized + Send> Send for RefMut<'_, T> {}
@@ -280,6 +404,98 @@ impl<'a, T: ?Sized + Display> Display for RefMut<'a, T> {
     }
 }
 
+/// A handle to an entity's `T` component which does not hold a borrow
+///
+/// Cheaper to [`upgrade`](Self::upgrade) repeatedly than looking the component up with
+/// [`World::get`] every time, because the component's location in the world's storage is cached
+/// between calls.
+///
+/// # Example
+/// ```
+/// # use hecs::*;
+/// let mut world = World::new();
+/// let a = world.spawn((42, true));
+/// let editing = WeakRef::<i32>::new(a);
+/// *editing.upgrade_mut(&world).unwrap() += 1;
+/// world.insert_one(a, "abc").unwrap();
+/// assert_eq!(*editing.upgrade(&world).unwrap(), 43);
+/// world.despawn(a).unwrap();
+/// assert!(editing.upgrade(&world).is_none());
+/// ```
+pub struct WeakRef<T: Component> {
+    entity: Entity,
+    /// World ID, archetype index, and `T`'s state index in that archetype from the last upgrade
+    cache: Cell<Option<(u64, u32, usize)>>,
+    _phantom: PhantomData<fn() -> T>,
+}
+
+impl<T: Component> WeakRef<T> {
+    /// Refer to the `T` component of `entity`, whether or not it currently has one
+    pub fn new(entity: Entity) -> Self {
+        Self {
+            entity,
+            cache: Cell::new(None),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// The entity whose component is referred to
+    pub fn entity(&self) -> Entity {
+        self.entity
+    }
+
+    /// Borrow the component, if the entity still exists in `world` and has a `T`
+    ///
+    /// Panics if the component is already uniquely borrowed.
+    pub fn upgrade<'a>(&self, world: &'a World) -> Option<Ref<'a, T>> {
+        let (archetype, state, index) = self.locate(world)?;
+        unsafe { Some(Ref::from_state(archetype, state, index)) }
+    }
+
+    /// Uniquely borrow the component, if the entity still exists in `world` and has a `T`
+    ///
+    /// Panics if the component is already borrowed.
+    pub fn upgrade_mut<'a>(&self, world: &'a World) -> Option<RefMut<'a, T>> {
+        let (archetype, state, index) = self.locate(world)?;
+        unsafe { Some(RefMut::from_state(archetype, state, index)) }
+    }
+
+    fn locate<'a>(&self, world: &'a World) -> Option<(&'a Archetype, usize, u32)> {
+        let loc = world.location(self.entity).ok()?;
+        let archetype = &world.archetypes_inner()[loc.archetype as usize];
+        let world_id = world.memo().0;
+        let state = match self.cache.get() {
+            Some((cached_world, cached_archetype, state))
+                if cached_world == world_id && cached_archetype == loc.archetype =>
+            {
+                state
+            }
+            _ => {
+                let state = archetype.get_state::<T>()?;
+                self.cache.set(Some((world_id, loc.archetype, state)));
+                state
+            }
+        };
+        Some((archetype, state, loc.index))
+    }
+}
+
+impl<T: Component> Clone for WeakRef<T> {
+    fn clone(&self) -> Self {
+        Self {
+            entity: self.entity,
+            cache: self.cache.clone(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: Component> Debug for WeakRef<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("WeakRef").field(&self.entity).finish()
+    }
+}
+
 /// `&T` or `&mut T` where `T` is some component type
 ///
 /// The interface of this trait is a private implementation detail.
@@ -298,8 +514,13 @@ pub trait ComponentRef<'a> {
 
     /// Fetch the component from `entity`
     #[doc(hidden)]
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
     fn get_component(entity: EntityRef<'a>) -> Option<Self::Ref>;
 
+    /// Fetch the component from `entity`, failing on borrow conflicts instead of panicking
+    #[doc(hidden)]
+    fn try_get_component(entity: EntityRef<'a>) -> Result<Option<Self::Ref>, BorrowConflict>;
+
     /// Construct from a raw pointer
     ///
     /// # Safety
@@ -310,6 +531,7 @@ pub trait ComponentRef<'a> {
 
     /// Borrow a column from an archetype
     #[doc(hidden)]
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
     fn get_column(archetype: &'a Archetype) -> Option<Self::Column>;
 }
 
@@ -324,6 +546,10 @@ impl<'a, T: Component> ComponentRef<'a> for &'a T {
         Some(unsafe { Ref::new(entity.archetype, entity.index).ok()? })
     }
 
+    fn try_get_component(entity: EntityRef<'a>) -> Result<Option<Self::Ref>, BorrowConflict> {
+        unsafe { Ref::try_new(entity.archetype, entity.index) }
+    }
+
     unsafe fn from_raw(raw: *mut Self::Component) -> Self {
         &*raw
     }
@@ -344,6 +570,10 @@ impl<'a, T: Component> ComponentRef<'a> for &'a mut T {
         Some(unsafe { RefMut::new(entity.archetype, entity.index).ok()? })
     }
 
+    fn try_get_component(entity: EntityRef<'a>) -> Result<Option<Self::Ref>, BorrowConflict> {
+        unsafe { RefMut::try_new(entity.archetype, entity.index) }
+    }
+
     unsafe fn from_raw(raw: *mut Self::Component) -> Self {
         &mut *raw
     }
@@ -372,6 +602,7 @@ impl<'a> ComponentBorrow<'a> {
     // This method is unsafe as if the `index` is out of bounds,
     // then this will cause undefined behavior as the returned
     // `target` will point to undefined memory.
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
     unsafe fn for_component<T: Component>(
         archetype: &'a Archetype,
         index: u32,
@@ -379,13 +610,22 @@ impl<'a> ComponentBorrow<'a> {
         let state = archetype
             .get_state::<T>()
             .ok_or_else(MissingComponent::new::<T>)?;
+        Ok(Self::for_state::<T>(archetype, state, index))
+    }
 
+    /// Like `for_component`, but with a `state` previously obtained from `get_state::<T>`
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
+    unsafe fn for_state<T: Component>(
+        archetype: &'a Archetype,
+        state: usize,
+        index: u32,
+    ) -> (NonNull<T>, Self) {
         let target =
             NonNull::new_unchecked(archetype.get_base::<T>(state).as_ptr().add(index as usize));
 
         archetype.borrow::<T>(state);
 
-        Ok((target, Self { archetype, state }))
+        (target, Self { archetype, state })
     }
 }
 
@@ -419,6 +659,7 @@ impl<'a> ComponentBorrowMut<'a> {
     // This method is unsafe as if the `index` is out of bounds,
     // then this will cause undefined behavior as the returned
     // `target` will point to undefined memory.
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
     unsafe fn for_component<T: Component>(
         archetype: &'a Archetype,
         index: u32,
@@ -426,13 +667,22 @@ impl<'a> ComponentBorrowMut<'a> {
         let state = archetype
             .get_state::<T>()
             .ok_or_else(MissingComponent::new::<T>)?;
+        Ok(Self::for_state::<T>(archetype, state, index))
+    }
 
+    /// Like `for_component`, but with a `state` previously obtained from `get_state::<T>`
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
+    unsafe fn for_state<T: Component>(
+        archetype: &'a Archetype,
+        state: usize,
+        index: u32,
+    ) -> (NonNull<T>, Self) {
         let target =
             NonNull::new_unchecked(archetype.get_base::<T>(state).as_ptr().add(index as usize));
 
         archetype.borrow_mut::<T>(state);
 
-        Ok((target, Self { archetype, state }))
+        (target, Self { archetype, state })
     }
 }
 
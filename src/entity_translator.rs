@@ -0,0 +1,73 @@
+use hashbrown::HashMap;
+
+use crate::{Entity, WorldId};
+
+/// Records which entity each entity moved from another [`World`](crate::World) became
+///
+/// Populated by [`World::transfer`](crate::World::transfer). Components referring to other
+/// entities by handle can be remapped by looking up the handles they contain with
+/// [`translate`](Self::translate), allowing such references to remain valid when entities are
+/// streamed between worlds, in any order. Persists across any number of transfers; call
+/// [`forget`](Self::forget) or [`clear`](Self::clear) to release entries no longer of interest.
+///
+/// # Example
+/// ```
+/// # use hecs::*;
+/// struct Target(Entity);
+///
+/// let mut source = World::new();
+/// let mut dest = World::new();
+/// let mut translator = EntityTranslator::new();
+/// let a = source.spawn((123,));
+/// let b = source.spawn((Target(a),));
+///
+/// let new_b = source.transfer(b, &mut dest, &mut translator).unwrap();
+/// let new_a = source.transfer(a, &mut dest, &mut translator).unwrap();
+/// for (_, target) in dest.query_mut::<&mut Target>() {
+///     target.0 = translator.translate(source.id(), target.0).unwrap();
+/// }
+/// assert_eq!(dest.get::<&Target>(new_b).unwrap().0, new_a);
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct EntityTranslator {
+    map: HashMap<(WorldId, Entity), Entity>,
+}
+
+impl EntityTranslator {
+    /// Create an empty translator
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record that `old` from the world identified by `source` is now known as `new`
+    ///
+    /// Returns the entity previously recorded for `old`, if any.
+    pub fn record(&mut self, source: WorldId, old: Entity, new: Entity) -> Option<Entity> {
+        self.map.insert((source, old), new)
+    }
+
+    /// The entity that `old` from the world identified by `source` became, if recorded
+    pub fn translate(&self, source: WorldId, old: Entity) -> Option<Entity> {
+        self.map.get(&(source, old)).copied()
+    }
+
+    /// Forget what `old` from the world identified by `source` became, returning it
+    pub fn forget(&mut self, source: WorldId, old: Entity) -> Option<Entity> {
+        self.map.remove(&(source, old))
+    }
+
+    /// Forget every recorded entity
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    /// Number of recorded entities
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Whether no entities are recorded
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
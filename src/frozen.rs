@@ -0,0 +1,103 @@
+use crate::query::{QueryIter, View};
+use crate::{
+    Component, ComponentError, Entity, Fetch, MissingComponent, Query, QueryOneError, QueryShared,
+    World,
+};
+
+/// A [`World`] which can no longer be modified, permitting read-only access without dynamic
+/// borrow checking
+///
+/// Constructed by [`World::freeze`]. Because no unique borrows can ever be taken, queries skip
+/// the bookkeeping otherwise required to detect conflicting access. Wrap in an `Arc` to share
+/// between threads, and use `Arc::try_unwrap` followed by [`thaw`](Self::thaw) to recover the
+/// `World` once every other reference is gone.
+///
+/// # Example
+/// ```
+/// # use hecs::*;
+/// let mut world = World::new();
+/// let a = world.spawn((123, true));
+/// let frozen = std::sync::Arc::new(world.freeze());
+///
+/// let reader = {
+///     let frozen = frozen.clone();
+///     std::thread::spawn(move || frozen.query::<&i32>().map(|(_, &x)| x).sum::<i32>())
+/// };
+/// assert_eq!(*frozen.get::<i32>(a).unwrap(), 123);
+/// assert_eq!(reader.join().unwrap(), 123);
+///
+/// let mut world = std::sync::Arc::try_unwrap(frozen).ok().unwrap().thaw();
+/// world.despawn(a).unwrap();
+/// ```
+pub struct FrozenWorld {
+    world: World,
+}
+
+impl FrozenWorld {
+    pub(crate) fn new(mut world: World) -> Self {
+        // Reserved entities can't be flushed once frozen
+        world.flush();
+        Self { world }
+    }
+
+    /// Recover the mutable [`World`]
+    pub fn thaw(self) -> World {
+        self.world
+    }
+
+    /// Iterate over all entities that have certain components, using shared borrows only
+    ///
+    /// Equivalent to [`World::query`], without dynamic borrow checking.
+    pub fn query<Q: Query + QueryShared>(&self) -> QueryIter<'_, Q> {
+        // Safety: a frozen world is never uniquely borrowed
+        unsafe { QueryIter::new(&self.world) }
+    }
+
+    /// Provide random access to any entity for a given query
+    ///
+    /// Equivalent to [`World::view`], without dynamic borrow checking.
+    pub fn view<Q: Query + QueryShared>(&self) -> View<'_, Q> {
+        // Safety: a frozen world is never uniquely borrowed
+        unsafe { View::new(self.world.entities_meta(), self.world.archetypes_inner()) }
+    }
+
+    /// Query a single entity
+    ///
+    /// Like [`World::query_one_mut`], but limited to shared borrows.
+    pub fn query_one<Q: Query + QueryShared>(
+        &self,
+        entity: Entity,
+    ) -> Result<Q::Item<'_>, QueryOneError> {
+        let loc = self.world.location(entity)?;
+        let archetype = &self.world.archetypes_inner()[loc.archetype as usize];
+        let state = Q::Fetch::prepare(archetype).ok_or(QueryOneError::Unsatisfied)?;
+        let fetch = Q::Fetch::execute(archetype, state);
+        // Safety: a frozen world is never uniquely borrowed
+        unsafe { Ok(Q::get(&fetch, loc.index as usize)) }
+    }
+
+    /// Access a single component of an entity
+    pub fn get<T: Component>(&self, entity: Entity) -> Result<&T, ComponentError> {
+        self.query_one::<&T>(entity).map_err(|e| match e {
+            QueryOneError::NoSuchEntity => ComponentError::NoSuchEntity,
+            QueryOneError::Unsatisfied => {
+                ComponentError::MissingComponent(MissingComponent::new::<T>())
+            }
+        })
+    }
+
+    /// Whether `entity` still exists
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.world.contains(entity)
+    }
+
+    /// Number of currently live entities
+    pub fn len(&self) -> u32 {
+        self.world.len()
+    }
+
+    /// Whether no entities are live
+    pub fn is_empty(&self) -> bool {
+        self.world.is_empty()
+    }
+}
@@ -0,0 +1,181 @@
+use core::any::TypeId;
+use core::hash::Hash;
+use core::slice::Iter as SliceIter;
+
+use hashbrown::HashMap;
+
+use crate::alloc::vec::Vec;
+use crate::entities::EntityMeta;
+use crate::query::Fetch;
+use crate::{Archetype, ArchetypeColumn, Component, Entity, Query, World};
+
+/// Cached grouping of a query's results by the value of a component, for use with
+/// [`QueryBorrow::group_by`](crate::QueryBorrow::group_by)
+///
+/// Remembers the location of every entity in each group, so that grouping results frame after
+/// frame needn't hash every key. Each time the index is used, it's checked against the world at a
+/// cost proportional to the number of archetypes plus an equality comparison per grouped entity,
+/// and rebuilt only if an entity has since been added to, removed from, or moved within an
+/// archetype, or if any grouped entity's key has changed.
+///
+/// Best suited to keys which change rarely, like materials or physics islands.
+pub struct GroupIndex<K> {
+    /// The world and archetype generation the index reflects
+    memo: (u64, u32),
+    /// Fetch type of the query the index was built for
+    fetch: Option<TypeId>,
+    /// Version of each archetype when the index was last built
+    versions: Vec<u64>,
+    /// Each distinct key, with the archetype index and row of every entity having it
+    groups: Vec<(K, Vec<(u32, u32)>)>,
+    /// Index of each key in `groups`, retained between rebuilds to reuse its allocation
+    lookup: HashMap<K, usize>,
+}
+
+impl<K: Component + Clone + Eq + Hash> Default for GroupIndex<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Component + Clone + Eq + Hash> GroupIndex<K> {
+    /// Create an index which is not yet attached to any world
+    pub fn new() -> Self {
+        Self {
+            // This memo will not match any world as the first ID will be 1.
+            memo: (0, 0),
+            fetch: None,
+            versions: Vec::new(),
+            groups: Vec::new(),
+            lookup: HashMap::new(),
+        }
+    }
+
+    /// Bring the index up to date with the results of `Q` in `world`
+    ///
+    /// `Q` must already be borrowed, so that `K` is not concurrently borrowed uniquely.
+    pub(crate) fn refresh<Q: Query>(&mut self, world: &World) {
+        let memo = world.memo();
+        let archetypes = world.archetypes_inner();
+        let fetch = TypeId::of::<Q::Fetch>();
+        if self.memo != memo
+            || self.fetch != Some(fetch)
+            || !archetypes
+                .iter()
+                .map(|x| x.version())
+                .eq(self.versions.iter().copied())
+            || !self.keys_current(archetypes)
+        {
+            self.rebuild::<Q>(archetypes);
+            self.memo = memo;
+            self.fetch = Some(fetch);
+        }
+    }
+
+    /// Whether every grouped entity still has the key of its group
+    fn keys_current(&self, archetypes: &[Archetype]) -> bool {
+        for (key, members) in &self.groups {
+            let mut column: Option<(u32, ArchetypeColumn<'_, K>)> = None;
+            for &(archetype, row) in members {
+                let keys = match column {
+                    Some((x, ref keys)) if x == archetype => keys,
+                    _ => {
+                        let Some(keys) = archetypes[archetype as usize].get::<&K>() else {
+                            return false;
+                        };
+                        &column.insert((archetype, keys)).1
+                    }
+                };
+                if keys[row as usize] != *key {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    fn rebuild<Q: Query>(&mut self, archetypes: &[Archetype]) {
+        self.versions.clear();
+        self.versions.extend(archetypes.iter().map(|x| x.version()));
+        self.groups.clear();
+        self.lookup.clear();
+        for (index, archetype) in archetypes.iter().enumerate() {
+            if Q::Fetch::prepare(archetype).is_none() {
+                continue;
+            }
+            let Some(keys) = archetype.get::<&K>() else {
+                continue;
+            };
+            for (row, key) in keys.iter().enumerate() {
+                let group = match self.lookup.get(key) {
+                    Some(&group) => group,
+                    None => {
+                        self.lookup.insert(key.clone(), self.groups.len());
+                        self.groups.push((key.clone(), Vec::new()));
+                        self.groups.len() - 1
+                    }
+                };
+                self.groups[group].1.push((index as u32, row as u32));
+            }
+        }
+    }
+
+    /// The groups, each with the locations of its members
+    pub(crate) fn groups(&self) -> &[(K, Vec<(u32, u32)>)] {
+        &self.groups
+    }
+}
+
+/// Iterator over the results of a single group, from
+/// [`QueryBorrow::group_by`](crate::QueryBorrow::group_by)
+pub struct GroupIter<'q, Q: Query> {
+    meta: &'q [EntityMeta],
+    archetypes: &'q [Archetype],
+    members: SliceIter<'q, (u32, u32)>,
+    /// Archetype index and fetch for the most recently visited member
+    fetch: Option<(u32, Q::Fetch)>,
+}
+
+impl<'q, Q: Query> GroupIter<'q, Q> {
+    /// # Safety
+    ///
+    /// `Q` must be borrowed for `'q`, and no other iterator may yield any of `members`.
+    pub(crate) unsafe fn new(
+        meta: &'q [EntityMeta],
+        archetypes: &'q [Archetype],
+        members: &'q [(u32, u32)],
+    ) -> Self {
+        Self {
+            meta,
+            archetypes,
+            members: members.iter(),
+            fetch: None,
+        }
+    }
+}
+
+impl<'q, Q: Query> Iterator for GroupIter<'q, Q> {
+    type Item = (Entity, Q::Item<'q>);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let &(index, row) = self.members.next()?;
+        let archetype = &self.archetypes[index as usize];
+        if self.fetch.as_ref().map_or(true, |&(x, _)| x != index) {
+            let state = Q::Fetch::prepare(archetype).unwrap();
+            self.fetch = Some((index, Q::Fetch::execute(archetype, state)));
+        }
+        let fetch = &self.fetch.as_ref().unwrap().1;
+        let id = archetype.entity_id(row);
+        Some((self.meta[id as usize].entity(id), unsafe {
+            Q::get(fetch, row as usize)
+        }))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.members.len();
+        (n, Some(n))
+    }
+}
+
+impl<'q, Q: Query> ExactSizeIterator for GroupIter<'q, Q> {}
@@ -68,50 +68,111 @@ macro_rules! smaller_tuples_too {
     };
 }
 
+/// Like `smaller_tuples_too`, but only for tuples longer than `[$base]`
+///
+/// Calls m!(A, B, C) and m!(A, B, C, D) for i.e. (m, [B A] D C).
+#[cfg(feature = "large-tuples")]
+macro_rules! larger_tuples_only {
+    ($m: ident, [$($base: tt)*]) => {};
+    ($m: ident, [$($base: tt)*] $next: tt $($rest: tt)*) => {
+        larger_tuples_only!{$m, [$($base)*] $($rest)*}
+        reverse_apply!{$m [$next $($rest)* $($base)*]}
+    };
+}
+
 mod archetype;
 mod batch;
 mod borrow;
 mod bundle;
 mod change_tracker;
+mod checksum;
 mod command_buffer;
+mod debug_registry;
+mod default_registry;
+mod dynamic_query;
 mod entities;
 mod entity_builder;
 mod entity_ref;
+mod entity_translator;
+mod frozen;
+mod group_index;
+mod materialized_view;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod names;
+mod observer;
 mod query;
+mod query_cursor;
 mod query_one;
+mod query_scope;
+mod required;
 #[cfg(any(feature = "row-serialize", feature = "column-serialize"))]
 pub mod serialize;
+mod shared;
 mod take;
+#[cfg(feature = "trace")]
+mod trace;
+mod trait_query;
+mod value_index;
 mod world;
 
-pub use archetype::{Archetype, ArchetypeColumn, ArchetypeColumnMut, TypeIdMap, TypeInfo};
+pub use archetype::{
+    Archetype, ArchetypeColumn, ArchetypeColumnMut, ComponentMask, TypeIdMap, TypeInfo,
+};
 pub use batch::{BatchIncomplete, BatchWriter, ColumnBatch, ColumnBatchBuilder, ColumnBatchType};
+pub use borrow::BorrowState;
 pub use bundle::{
     bundle_satisfies_query, dynamic_bundle_satisfies_query, Bundle, DynamicBundle,
     DynamicBundleClone, MissingComponent,
 };
 pub use change_tracker::{ChangeTracker, Changes};
+pub use checksum::HashRegistry;
 pub use command_buffer::CommandBuffer;
-pub use entities::{Entity, NoSuchEntity};
-pub use entity_builder::{BuiltEntity, BuiltEntityClone, EntityBuilder, EntityBuilderClone};
-pub use entity_ref::{ComponentRef, ComponentRefShared, EntityRef, Ref, RefMut};
+pub use debug_registry::{DebugRegistry, EntityDebug};
+pub use default_registry::DefaultRegistry;
+pub use dynamic_query::{
+    DynamicComponentRaw, DynamicItem, DynamicQuery, DynamicQueryBorrow, DynamicQueryBuilder,
+    DynamicQueryIter,
+};
+pub use entities::{
+    Entity, EntityMap, EntityStats, NoSuchEntity, NonContiguousReservation, ReservedBlock,
+};
+pub use entity_builder::{
+    BuilderPool, BuiltEntity, BuiltEntityClone, BuiltEntityRef, EntityBuilder, EntityBuilderClone,
+    NotCloneable, TakenBundle,
+};
+pub use entity_ref::{ComponentRef, ComponentRefShared, EntityRef, Ref, RefMut, WeakRef};
+pub use entity_translator::EntityTranslator;
+pub use frozen::FrozenWorld;
+pub use group_index::{GroupIndex, GroupIter};
+pub use materialized_view::{MaterializedView, MaterializedViewBorrow, MaterializedViewIter};
+#[cfg(feature = "metrics")]
+pub use metrics::QueryMetrics;
 pub use query::{
-    Access, Batch, BatchedIter, Or, PreparedQuery, PreparedQueryBorrow, PreparedQueryIter,
-    PreparedView, Query, QueryBorrow, QueryIter, QueryMut, QueryShared, Satisfies, View,
-    ViewBorrow, With, Without,
+    Access, Alias, Aliased, AllOf, AnyOf, ArchetypeMatch, Batch, BatchedIter, BorrowConflict,
+    Borrows, DisjointQueries, Not, Or, PreparedQuery, PreparedQueryBorrow, PreparedQueryIter,
+    PreparedView, Query, QueryArchetype, QueryBorrow, QueryCloned, QueryExplanation, QueryIter,
+    QueryMut, QueryShared, QuerySlice, Satisfies, SplitItem, View, ViewBorrow, ViewPart,
+    ViewPartIter, With, Without,
 };
-pub use query_one::QueryOne;
+pub use query_cursor::{QueryCursor, QueryCursorIter};
+pub use query_one::{QueryOne, QueryOneShared, Siblings};
+pub use query_scope::QueryScope;
+pub use shared::{Shared, SharedPool};
 pub use take::TakenEntity;
+#[cfg(feature = "trace")]
+pub use trace::{TraceEvent, TraceKind};
+pub use trait_query::{QueryTrait, TraitRegistry};
 pub use world::{
-    ArchetypesGeneration, Component, ComponentError, Iter, QueryOneError, SpawnBatchIter,
-    SpawnColumnBatchIter, World,
+    ArchetypeId, ArchetypesGeneration, CapacityExhausted, Component, ComponentError, InsertError,
+    Iter, NoSuchIndex, QueryOneError, SpawnBatchIter, SpawnColumnBatchIter, World, WorldId,
 };
 
 // Unstable implementation details needed by the macros
 #[doc(hidden)]
 pub use bundle::DynamicClone;
 #[doc(hidden)]
-pub use query::Fetch;
+pub use query::{Fetch, FetchTuple};
 
 #[cfg(feature = "macros")]
 pub use hecs_macros::{Bundle, DynamicBundleClone, Query};
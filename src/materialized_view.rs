@@ -0,0 +1,207 @@
+use core::slice::Iter as SliceIter;
+
+use crate::alloc::vec::Vec;
+use crate::query::{assert_borrow, Fetch};
+use crate::{Archetype, Entity, Query, World};
+
+/// A query whose matching entities are cached between uses
+///
+/// Like [`PreparedQuery`](crate::PreparedQuery), but also remembers every matching entity and
+/// where its components are stored, so iteration walks a flat list rather than visiting each
+/// archetype. The list is checked against the world each time the view is used, at a cost
+/// proportional to the number of matching archetypes, and rebuilt only if an entity has since been
+/// added to, removed from, or moved within one of them.
+///
+/// Best suited to queries which are iterated many times between changes to the set of entities
+/// they match, such as UI layout or static geometry. For queries over frequently spawned or
+/// despawned entities, the cost of rebuilding outweighs the benefit.
+///
+/// # Example
+/// ```
+/// # use hecs::*;
+/// let mut world = World::new();
+/// let a = world.spawn((123, true));
+/// let b = world.spawn((456,));
+/// let mut view = MaterializedView::<&i32>::new();
+/// assert_eq!(view.query(&world).iter().len(), 2);
+/// world.despawn(a).unwrap();
+/// let results = view.query(&world).iter().map(|(e, &i)| (e, i)).collect::<Vec<_>>();
+/// assert_eq!(results, [(b, 456)]);
+/// ```
+pub struct MaterializedView<Q: Query> {
+    /// The world and archetype generation the view reflects
+    memo: (u64, u32),
+    /// Matching archetype indices, with their versions when `rows` was last built
+    archetypes: Vec<(usize, u64, <Q::Fetch as Fetch>::State)>,
+    /// Index into `archetypes`, component index within the archetype, and entity
+    rows: Vec<(u32, u32, Entity)>,
+    /// Scratch space for fetches corresponding to `archetypes`
+    fetch: Vec<Q::Fetch>,
+}
+
+impl<Q: Query> Default for MaterializedView<Q> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Q: Query> MaterializedView<Q> {
+    /// Create a view which is not yet attached to any world
+    pub fn new() -> Self {
+        Self {
+            // This memo will not match any world as the first ID will be 1.
+            memo: (0, 0),
+            archetypes: Vec::new(),
+            rows: Vec::new(),
+            fetch: Vec::new(),
+        }
+    }
+
+    /// Bring the cached results up to date with `world`
+    fn refresh(&mut self, world: &World) {
+        let memo = world.memo();
+        let archetypes = world.archetypes_inner();
+        let mut stale = false;
+        if self.memo.0 != memo.0 {
+            self.archetypes.clear();
+            self.memo = (memo.0, 0);
+            stale = true;
+        }
+        for (idx, archetype) in archetypes.iter().enumerate().skip(self.memo.1 as usize) {
+            if let Some(state) = Q::Fetch::prepare(archetype) {
+                self.archetypes.push((idx, archetype.version(), state));
+                stale = true;
+            }
+        }
+        self.memo = memo;
+        for (idx, version, _) in &mut self.archetypes {
+            let current = archetypes[*idx].version();
+            if *version != current {
+                *version = current;
+                stale = true;
+            }
+        }
+        if !stale {
+            return;
+        }
+
+        let meta = world.entities_meta();
+        self.rows.clear();
+        for (i, &(idx, _, _)) in self.archetypes.iter().enumerate() {
+            let archetype = &archetypes[idx];
+            for (row, &id) in archetype.ids().iter().enumerate() {
+                self.rows
+                    .push((i as u32, row as u32, meta[id as usize].entity(id)));
+            }
+        }
+    }
+
+    /// Compute fetches for the current state of each matching archetype
+    fn execute(&mut self, archetypes: &[Archetype]) {
+        self.fetch.clear();
+        self.fetch.extend(
+            self.archetypes
+                .iter()
+                .map(|&(idx, _, state)| Q::Fetch::execute(&archetypes[idx], state)),
+        );
+    }
+
+    /// Query `world`, using dynamic borrow checking
+    ///
+    /// This will panic if it would violate an existing unique reference
+    /// or construct an invalid unique reference.
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
+    pub fn query<'q>(&'q mut self, world: &'q World) -> MaterializedViewBorrow<'q, Q> {
+        self.refresh(world);
+        let archetypes = world.archetypes_inner();
+        for &(idx, _, state) in &self.archetypes {
+            if archetypes[idx].is_empty() {
+                continue;
+            }
+            Q::Fetch::borrow(&archetypes[idx], state);
+        }
+        self.execute(archetypes);
+        MaterializedViewBorrow {
+            archetypes,
+            view: self,
+        }
+    }
+
+    /// Query a uniquely borrowed world
+    ///
+    /// Avoids the cost of the dynamic borrow checking performed by [`query`][Self::query].
+    pub fn query_mut<'q>(&'q mut self, world: &'q mut World) -> MaterializedViewIter<'q, Q> {
+        assert_borrow::<Q>();
+        self.refresh(world);
+        self.execute(world.archetypes_inner());
+        unsafe { MaterializedViewIter::new(&self.rows, &self.fetch) }
+    }
+}
+
+/// Combined borrow of a [`MaterializedView`] and a [`World`]
+pub struct MaterializedViewBorrow<'q, Q: Query> {
+    archetypes: &'q [Archetype],
+    view: &'q mut MaterializedView<Q>,
+}
+
+impl<'q, Q: Query> MaterializedViewBorrow<'q, Q> {
+    /// Execute the query
+    // The lifetime narrowing here is required for soundness.
+    pub fn iter(&mut self) -> MaterializedViewIter<'_, Q> {
+        unsafe { MaterializedViewIter::new(&self.view.rows, &self.view.fetch) }
+    }
+}
+
+impl<Q: Query> Drop for MaterializedViewBorrow<'_, Q> {
+    fn drop(&mut self) {
+        for &(idx, _, state) in &self.view.archetypes {
+            if self.archetypes[idx].is_empty() {
+                continue;
+            }
+            Q::Fetch::release(&self.archetypes[idx], state);
+        }
+    }
+}
+
+/// Iterates over all entities matching a [`MaterializedView`]
+pub struct MaterializedViewIter<'q, Q: Query> {
+    rows: SliceIter<'q, (u32, u32, Entity)>,
+    fetch: &'q [Q::Fetch],
+}
+
+impl<'q, Q: Query> MaterializedViewIter<'q, Q> {
+    /// # Safety
+    ///
+    /// `'q` must be sufficient to guarantee that `Q` cannot violate borrow safety, either with
+    /// dynamic borrow checks or by representing exclusive access to the `World`.
+    unsafe fn new(rows: &'q [(u32, u32, Entity)], fetch: &'q [Q::Fetch]) -> Self {
+        Self {
+            rows: rows.iter(),
+            fetch,
+        }
+    }
+}
+
+unsafe impl<'q, Q: Query> Send for MaterializedViewIter<'q, Q> where for<'a> Q::Item<'a>: Send {}
+unsafe impl<'q, Q: Query> Sync for MaterializedViewIter<'q, Q> where for<'a> Q::Item<'a>: Send {}
+
+impl<'q, Q: Query> Iterator for MaterializedViewIter<'q, Q> {
+    type Item = (Entity, Q::Item<'q>);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let &(archetype, row, entity) = self.rows.next()?;
+        let fetch = unsafe { self.fetch.get_unchecked(archetype as usize) };
+        Some((entity, unsafe { Q::get(fetch, row as usize) }))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.rows.size_hint()
+    }
+}
+
+impl<Q: Query> ExactSizeIterator for MaterializedViewIter<'_, Q> {
+    fn len(&self) -> usize {
+        self.rows.len()
+    }
+}
@@ -0,0 +1,75 @@
+use alloc::vec::Vec;
+use core::any::TypeId;
+
+use spin::Mutex;
+
+use crate::archetype::TypeIdMap;
+
+/// Per-query counters accumulated by a `World`
+#[derive(Default)]
+pub(crate) struct Metrics {
+    queries: Mutex<TypeIdMap<QueryMetrics>>,
+}
+
+impl Metrics {
+    /// Account for an iteration of the query named `name` starting
+    pub(crate) fn execute(&self, fetch: TypeId, name: &'static str) {
+        self.queries
+            .lock()
+            .entry(fetch)
+            .or_insert_with(|| QueryMetrics::new(fetch, name))
+            .executions += 1;
+    }
+
+    /// Account for the query identified by `fetch` visiting an archetype of `len` entities
+    pub(crate) fn visit(&self, fetch: TypeId, name: &'static str, len: u32) {
+        let mut queries = self.queries.lock();
+        let metrics = queries
+            .entry(fetch)
+            .or_insert_with(|| QueryMetrics::new(fetch, name));
+        metrics.archetypes += 1;
+        metrics.entities += u64::from(len);
+    }
+
+    /// Copy out the counters for every query recorded
+    pub(crate) fn snapshot(&self) -> Vec<QueryMetrics> {
+        self.queries.lock().values().copied().collect()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.queries.get_mut().clear();
+    }
+}
+
+/// Counters for a single query type, recorded by a [`World`](crate::World) with the `metrics`
+/// feature enabled
+///
+/// See [`World::query_metrics`](crate::World::query_metrics).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct QueryMetrics {
+    /// `TypeId` of the query's `Fetch`, which identifies the query independent of lifetimes
+    pub fetch: TypeId,
+    /// Name of a query type with this `Fetch`, for display
+    pub name: &'static str,
+    /// Number of times iteration over the query's results began
+    pub executions: u64,
+    /// Total number of archetypes matched across all executions
+    pub archetypes: u64,
+    /// Total number of entities in matched archetypes across all executions
+    ///
+    /// Includes entities in matched archetypes that weren't reached because iteration was
+    /// abandoned early.
+    pub entities: u64,
+}
+
+impl QueryMetrics {
+    fn new(fetch: TypeId, name: &'static str) -> Self {
+        Self {
+            fetch,
+            name,
+            executions: 0,
+            archetypes: 0,
+            entities: 0,
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use crate::alloc::boxed::Box;
+use hashbrown::HashMap;
+
+use crate::Entity;
+
+/// Bidirectional map between entities and unique names
+#[derive(Default)]
+pub(crate) struct NameIndex {
+    by_name: HashMap<Box<str>, Entity>,
+    by_id: HashMap<u32, Box<str>>,
+}
+
+impl NameIndex {
+    /// Name `entity`, displacing any existing owner of `name` and any previous name of `entity`
+    pub(crate) fn insert(&mut self, entity: Entity, name: Box<str>) {
+        self.remove(entity.id());
+        if let Some(previous) = self.by_name.insert(name.clone(), entity) {
+            self.by_id.remove(&previous.id());
+        }
+        self.by_id.insert(entity.id(), name);
+    }
+
+    /// Forget the name of the entity with ID `id`, if any
+    pub(crate) fn remove(&mut self, id: u32) -> Option<Box<str>> {
+        if self.by_id.is_empty() {
+            return None;
+        }
+        let name = self.by_id.remove(&id)?;
+        self.by_name.remove(&name);
+        Some(name)
+    }
+
+    pub(crate) fn get(&self, id: u32) -> Option<&str> {
+        self.by_id.get(&id).map(|x| &**x)
+    }
+
+    pub(crate) fn find(&self, name: &str) -> Option<Entity> {
+        self.by_name.get(name).copied()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.by_name.clear();
+        self.by_id.clear();
+    }
+}
@@ -0,0 +1,108 @@
+use crate::alloc::boxed::Box;
+use crate::alloc::vec::Vec;
+use hashbrown::HashMap;
+
+use crate::entities::Entities;
+use crate::{Archetype, Entity, Query};
+
+/// Callbacks registered through `World::on_match` and `World::on_unmatch`
+#[derive(Default)]
+pub(crate) struct Observers {
+    observers: Vec<Observer>,
+    /// Entities whose archetype has changed since observers were last run, with the archetype
+    /// each was in beforehand, if any
+    changed: HashMap<Entity, Option<u32>>,
+    /// Entities removed by `World::clear` since observers were last run, with the archetype each
+    /// was in beforehand
+    removed: Vec<(Entity, u32)>,
+}
+
+impl Observers {
+    pub(crate) fn register<Q: Query>(
+        &mut self,
+        on_match: bool,
+        callback: impl FnMut(Entity) + Send + Sync + 'static,
+    ) {
+        self.observers.push(Observer {
+            matches: Archetype::satisfies::<Q>,
+            cache: Vec::new(),
+            on_match,
+            callback: Box::new(callback),
+        });
+    }
+
+    /// Account for `entity` having been moved out of archetype `from`, or created if `None`
+    #[inline]
+    pub(crate) fn changed(&mut self, entity: Entity, from: Option<u32>) {
+        if self.observers.is_empty() {
+            return;
+        }
+        self.changed.entry(entity).or_insert(from);
+    }
+
+    /// Account for every entity being removed by `World::clear`
+    ///
+    /// Cleared entities' handles may be reused, so their pending changes are resolved now rather
+    /// than merged with changes to the new entities.
+    pub(crate) fn clear(&mut self, archetypes: &[Archetype], entities: &Entities) {
+        if self.observers.is_empty() {
+            return;
+        }
+        for (index, archetype) in archetypes.iter().enumerate() {
+            for &id in archetype.ids() {
+                let entity = entities.meta[id as usize].entity(id);
+                if !self.changed.contains_key(&entity) {
+                    self.removed.push((entity, index as u32));
+                }
+            }
+        }
+        self.removed.extend(
+            self.changed
+                .drain()
+                .filter_map(|(entity, from)| Some((entity, from?))),
+        );
+    }
+
+    /// Invoke the callbacks of observers whose query has started or stopped matching an entity
+    pub(crate) fn run(&mut self, archetypes: &[Archetype], entities: &Entities) {
+        for (entity, from) in self.removed.drain(..) {
+            for observer in &mut self.observers {
+                if !observer.on_match && observer.matches(archetypes, from) {
+                    (observer.callback)(entity);
+                }
+            }
+        }
+        for (entity, from) in self.changed.drain() {
+            let to = entities.get(entity).ok().map(|loc| loc.archetype);
+            if from == to {
+                continue;
+            }
+            for observer in &mut self.observers {
+                let before = from.map_or(false, |x| observer.matches(archetypes, x));
+                let after = to.map_or(false, |x| observer.matches(archetypes, x));
+                if before != after && after == observer.on_match {
+                    (observer.callback)(entity);
+                }
+            }
+        }
+    }
+}
+
+struct Observer {
+    matches: fn(&Archetype) -> bool,
+    /// Whether `matches` holds for each archetype, computed lazily
+    cache: Vec<Option<bool>>,
+    /// Whether `callback` runs when the query starts matching, rather than when it stops
+    on_match: bool,
+    callback: Box<dyn FnMut(Entity) + Send + Sync>,
+}
+
+impl Observer {
+    fn matches(&mut self, archetypes: &[Archetype], archetype: u32) -> bool {
+        let archetype = archetype as usize;
+        if self.cache.len() <= archetype {
+            self.cache.resize(archetypes.len(), None);
+        }
+        *self.cache[archetype].get_or_insert_with(|| (self.matches)(&archetypes[archetype]))
+    }
+}
@@ -5,14 +5,18 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
-use core::any::TypeId;
+use core::any::{type_name, TypeId};
+use core::fmt;
+use core::hash::Hash;
 use core::marker::PhantomData;
+use core::mem;
 use core::ptr::NonNull;
 use core::slice::Iter as SliceIter;
 
 use crate::alloc::{boxed::Box, vec::Vec};
-use crate::archetype::Archetype;
+use crate::archetype::{Archetype, TypeInfo};
 use crate::entities::EntityMeta;
+use crate::group_index::{GroupIndex, GroupIter};
 use crate::{Component, Entity, World};
 
 /// A collection of component types to fetch from a [`World`](crate::World)
@@ -42,9 +46,39 @@ pub trait Query {
 #[allow(clippy::missing_safety_doc)]
 pub unsafe trait QueryShared {}
 
+/// A [`Query`] whose results can be cloned into owned values
+///
+/// Implemented for shared references to [`Clone`] components, and for `Option`s and tuples of such
+/// queries. Used by [`QueryBorrow::iter_cloned`].
+pub trait QueryCloned: Query {
+    /// Owned form of [`Query::Item`]
+    type Cloned;
+
+    /// Clone the data referenced by `item`
+    fn cloned(item: Self::Item<'_>) -> Self::Cloned;
+}
+
+/// A [`Query`] whose results for every entity in an archetype can be accessed as slices
+///
+/// Implemented for `&T`, `&mut T`, and tuples of such queries. Used by
+/// [`QueryMut::for_each_chunk`].
+pub trait QuerySlice: Query {
+    /// Slices of the components matched by the query
+    ///
+    /// This is the same type as the query itself, except with slices in place of references.
+    type Slice<'a>;
+
+    #[doc(hidden)]
+    /// Access the first `len` items in this archetype as slices
+    ///
+    /// # Safety
+    /// Subject to the same requirements as [`Query::get`], with `len` bounding every index.
+    unsafe fn get_slice<'a>(fetch: &Self::Fetch, len: usize) -> Self::Slice<'a>;
+}
+
 /// Streaming iterators over contiguous homogeneous ranges of components
 #[allow(clippy::missing_safety_doc)]
-pub unsafe trait Fetch: Clone + Sized {
+pub unsafe trait Fetch: Clone + Sized + 'static {
     /// The type of the data which can be cached to speed up retrieving
     /// the relevant type states from a matching [`Archetype`]
     type State: Copy;
@@ -56,7 +90,17 @@ pub unsafe trait Fetch: Clone + Sized {
     fn access(archetype: &Archetype) -> Option<Access>;
 
     /// Acquire dynamic borrows from `archetype`
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
     fn borrow(archetype: &Archetype, state: Self::State);
+    /// Like `borrow`, but returns `false`, having acquired nothing, rather than panicking on
+    /// conflict
+    ///
+    /// The default implementation panics just like `borrow`.
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
+    fn try_borrow(archetype: &Archetype, state: Self::State) -> bool {
+        Self::borrow(archetype, state);
+        true
+    }
     /// Look up state for `archetype` if it should be traversed
     fn prepare(archetype: &Archetype) -> Option<Self::State>;
     /// Construct a `Fetch` for `archetype` based on the associated state
@@ -66,6 +110,30 @@ pub unsafe trait Fetch: Clone + Sized {
 
     /// Invoke `f` for every component type that may be borrowed and whether the borrow is unique
     fn for_each_borrow(f: impl FnMut(TypeId, bool));
+
+    /// Hint that the components at index `n` will be accessed soon
+    ///
+    /// Must not dereference anything; `n` may be out of bounds.
+    #[inline(always)]
+    fn prefetch(&self, n: usize) {
+        let _ = n;
+    }
+}
+
+/// Issue a software prefetch for the cache line containing `ptr`, if supported by the target
+#[inline(always)]
+fn prefetch<T>(ptr: *const T) {
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        use core::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+        _mm_prefetch::<_MM_HINT_T0>(ptr.cast());
+    }
+    #[cfg(all(target_arch = "x86", target_feature = "sse"))]
+    unsafe {
+        use core::arch::x86::{_mm_prefetch, _MM_HINT_T0};
+        _mm_prefetch::<_MM_HINT_T0>(ptr.cast());
+    }
+    let _ = ptr;
 }
 
 /// Type of access a [`Query`] may have to an [`Archetype`]
@@ -79,7 +147,133 @@ pub enum Access {
     Write,
 }
 
-impl<'a, T: Component> Query for &'a T {
+/// The component types a [`Query`] may borrow, and how
+///
+/// Useful for external schedulers to determine in advance whether queries may run concurrently.
+///
+/// # Example
+/// ```
+/// # use hecs::*;
+/// # use std::any::TypeId;
+/// let a = Borrows::of::<(&i32, &mut bool)>();
+/// assert_eq!(a.get(TypeId::of::<bool>()), Some(Access::Write));
+/// assert!(a.conflicts(&Borrows::of::<&bool>()));
+/// assert!(!a.conflicts(&Borrows::of::<(&i32, &u8)>()));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Borrows(Vec<(TypeId, Access)>);
+
+impl Borrows {
+    /// Determine the borrows that may be performed by `Q`
+    pub fn of<Q: Query>() -> Self {
+        let mut borrows = Vec::<(TypeId, Access)>::new();
+        Q::Fetch::for_each_borrow(|id, unique| {
+            let access = if unique { Access::Write } else { Access::Read };
+            match borrows.binary_search_by_key(&id, |x| x.0) {
+                Ok(i) => borrows[i].1 = borrows[i].1.max(access),
+                Err(i) => borrows.insert(i, (id, access)),
+            }
+        });
+        Self(borrows)
+    }
+
+    /// Iterate over the borrowed component types and the strongest access to each, ordered by
+    /// `TypeId`
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (TypeId, Access)> + '_ {
+        self.0.iter().copied()
+    }
+
+    /// How components of type `id` may be borrowed, if at all
+    pub fn get(&self, id: TypeId) -> Option<Access> {
+        let i = self.0.binary_search_by_key(&id, |x| x.0).ok()?;
+        Some(self.0[i].1)
+    }
+
+    /// Whether any component type is borrowed by both `self` and `other`, uniquely by at least one
+    pub fn conflicts(&self, other: &Borrows) -> bool {
+        self.0.iter().any(|&(id, access)| match other.get(id) {
+            None => false,
+            Some(other) => access == Access::Write || other == Access::Write,
+        })
+    }
+}
+
+/// How a [`Query`] relates to each archetype of a world
+///
+/// Obtained from [`QueryBorrow::explain`]. Useful for understanding why a query does or doesn't
+/// visit certain entities, e.g. when combining [`With`], [`Without`], and [`Satisfies`].
+///
+/// # Example
+/// ```
+/// # use hecs::*;
+/// let mut world = World::new();
+/// world.spawn((123, true));
+/// world.spawn((456,));
+/// let explanation = world.query::<Without<&i32, &bool>>().explain();
+/// let matched = explanation.matched().collect::<Vec<_>>();
+/// assert_eq!(matched.len(), 1);
+/// assert_eq!(matched[0].len(), 1);
+/// assert_eq!(matched[0].access(), Some(Access::Read));
+/// ```
+#[derive(Debug, Clone)]
+pub struct QueryExplanation(Vec<ArchetypeMatch>);
+
+impl QueryExplanation {
+    fn new<Q: Query>(archetypes: &[Archetype]) -> Self {
+        Self(
+            archetypes
+                .iter()
+                .map(|archetype| ArchetypeMatch {
+                    types: archetype.types().to_vec(),
+                    len: archetype.len(),
+                    access: Q::Fetch::access(archetype),
+                })
+                .collect(),
+        )
+    }
+
+    /// Every archetype in the world, in the order the query visits them, whether or not it matches
+    pub fn archetypes(&self) -> impl ExactSizeIterator<Item = &ArchetypeMatch> + '_ {
+        self.0.iter()
+    }
+
+    /// Archetypes whose entities satisfy the query
+    pub fn matched(&self) -> impl Iterator<Item = &ArchetypeMatch> + '_ {
+        self.0.iter().filter(|x| x.access.is_some())
+    }
+}
+
+/// An archetype described by a [`QueryExplanation`]
+#[derive(Debug, Clone)]
+pub struct ArchetypeMatch {
+    types: Vec<TypeInfo>,
+    len: u32,
+    access: Option<Access>,
+}
+
+impl ArchetypeMatch {
+    /// Component types of the archetype's entities
+    pub fn types(&self) -> &[TypeInfo] {
+        &self.types
+    }
+
+    /// Number of entities in the archetype
+    pub fn len(&self) -> u32 {
+        self.len
+    }
+
+    /// Whether the archetype contains no entities
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// How the query accesses the archetype, or `None` if its entities don't satisfy the query
+    pub fn access(&self) -> Option<Access> {
+        self.access
+    }
+}
+
+impl<T: Component> Query for &T {
     type Item<'q> = &'q T;
 
     type Fetch = FetchRead<T>;
@@ -89,7 +283,23 @@ impl<'a, T: Component> Query for &'a T {
     }
 }
 
-unsafe impl<'a, T> QueryShared for &'a T {}
+unsafe impl<T> QueryShared for &T {}
+
+impl<T: Component> QuerySlice for &'_ T {
+    type Slice<'a> = &'a [T];
+
+    unsafe fn get_slice<'a>(fetch: &FetchRead<T>, len: usize) -> &'a [T] {
+        core::slice::from_raw_parts(fetch.0.as_ptr(), len)
+    }
+}
+
+impl<T: Component + Clone> QueryCloned for &'_ T {
+    type Cloned = T;
+
+    fn cloned(item: Self::Item<'_>) -> T {
+        item.clone()
+    }
+}
 
 #[doc(hidden)]
 pub struct FetchRead<T>(NonNull<T>);
@@ -112,6 +322,9 @@ unsafe impl<T: Component> Fetch for FetchRead<T> {
     fn borrow(archetype: &Archetype, state: Self::State) {
         archetype.borrow::<T>(state);
     }
+    fn try_borrow(archetype: &Archetype, state: Self::State) -> bool {
+        archetype.try_borrow::<T>(state)
+    }
     fn prepare(archetype: &Archetype) -> Option<Self::State> {
         archetype.get_state::<T>()
     }
@@ -125,6 +338,11 @@ unsafe impl<T: Component> Fetch for FetchRead<T> {
     fn for_each_borrow(mut f: impl FnMut(TypeId, bool)) {
         f(TypeId::of::<T>(), false);
     }
+
+    #[inline(always)]
+    fn prefetch(&self, n: usize) {
+        prefetch(self.0.as_ptr().wrapping_add(n));
+    }
 }
 
 impl<T> Clone for FetchRead<T> {
@@ -134,7 +352,7 @@ impl<T> Clone for FetchRead<T> {
     }
 }
 
-impl<'a, T: Component> Query for &'a mut T {
+impl<T: Component> Query for &mut T {
     type Item<'q> = &'q mut T;
 
     type Fetch = FetchWrite<T>;
@@ -144,6 +362,14 @@ impl<'a, T: Component> Query for &'a mut T {
     }
 }
 
+impl<T: Component> QuerySlice for &'_ mut T {
+    type Slice<'a> = &'a mut [T];
+
+    unsafe fn get_slice<'a>(fetch: &FetchWrite<T>, len: usize) -> &'a mut [T] {
+        core::slice::from_raw_parts_mut(fetch.0.as_ptr(), len)
+    }
+}
+
 #[doc(hidden)]
 pub struct FetchWrite<T>(NonNull<T>);
 
@@ -165,6 +391,9 @@ unsafe impl<T: Component> Fetch for FetchWrite<T> {
     fn borrow(archetype: &Archetype, state: Self::State) {
         archetype.borrow_mut::<T>(state);
     }
+    fn try_borrow(archetype: &Archetype, state: Self::State) -> bool {
+        archetype.try_borrow_mut::<T>(state)
+    }
     #[allow(clippy::needless_question_mark)]
     fn prepare(archetype: &Archetype) -> Option<Self::State> {
         Some(archetype.get_state::<T>()?)
@@ -179,6 +408,11 @@ unsafe impl<T: Component> Fetch for FetchWrite<T> {
     fn for_each_borrow(mut f: impl FnMut(TypeId, bool)) {
         f(TypeId::of::<T>(), true);
     }
+
+    #[inline(always)]
+    fn prefetch(&self, n: usize) {
+        prefetch(self.0.as_ptr().wrapping_add(n));
+    }
 }
 
 impl<T> Clone for FetchWrite<T> {
@@ -200,6 +434,14 @@ impl<T: Query> Query for Option<T> {
 
 unsafe impl<T: QueryShared> QueryShared for Option<T> {}
 
+impl<T: QueryCloned> QueryCloned for Option<T> {
+    type Cloned = Option<T::Cloned>;
+
+    fn cloned(item: Self::Item<'_>) -> Self::Cloned {
+        item.map(T::cloned)
+    }
+}
+
 #[doc(hidden)]
 #[derive(Clone)]
 pub struct TryFetch<T>(Option<T>);
@@ -220,6 +462,9 @@ unsafe impl<T: Fetch> Fetch for TryFetch<T> {
             T::borrow(archetype, state);
         }
     }
+    fn try_borrow(archetype: &Archetype, state: Self::State) -> bool {
+        state.map_or(true, |state| T::try_borrow(archetype, state))
+    }
     fn prepare(archetype: &Archetype) -> Option<Self::State> {
         Some(T::prepare(archetype))
     }
@@ -235,6 +480,13 @@ unsafe impl<T: Fetch> Fetch for TryFetch<T> {
     fn for_each_borrow(f: impl FnMut(TypeId, bool)) {
         T::for_each_borrow(f);
     }
+
+    #[inline(always)]
+    fn prefetch(&self, n: usize) {
+        if let Some(fetch) = &self.0 {
+            fetch.prefetch(n);
+        }
+    }
 }
 
 /// Holds an `L`, or an `R`, or both
@@ -357,7 +609,32 @@ unsafe impl<L: Fetch, R: Fetch> Fetch for FetchOr<L, R> {
     }
 
     fn borrow(archetype: &Archetype, state: Self::State) {
-        state.map(|l| L::borrow(archetype, l), |r| R::borrow(archetype, r));
+        // Not `map`, so that borrow diagnostics can track the caller
+        match state {
+            Or::Left(l) => L::borrow(archetype, l),
+            Or::Right(r) => R::borrow(archetype, r),
+            Or::Both(l, r) => {
+                L::borrow(archetype, l);
+                R::borrow(archetype, r);
+            }
+        }
+    }
+
+    fn try_borrow(archetype: &Archetype, state: Self::State) -> bool {
+        match state {
+            Or::Left(l) => L::try_borrow(archetype, l),
+            Or::Right(r) => R::try_borrow(archetype, r),
+            Or::Both(l, r) => {
+                if !L::try_borrow(archetype, l) {
+                    return false;
+                }
+                if !R::try_borrow(archetype, r) {
+                    L::release(archetype, l);
+                    return false;
+                }
+                true
+            }
+        }
     }
 
     fn prepare(archetype: &Archetype) -> Option<Self::State> {
@@ -376,6 +653,18 @@ unsafe impl<L: Fetch, R: Fetch> Fetch for FetchOr<L, R> {
         L::for_each_borrow(&mut f);
         R::for_each_borrow(&mut f);
     }
+
+    #[inline(always)]
+    fn prefetch(&self, n: usize) {
+        match &self.0 {
+            Or::Left(l) => l.prefetch(n),
+            Or::Right(r) => r.prefetch(n),
+            Or::Both(l, r) => {
+                l.prefetch(n);
+                r.prefetch(n);
+            }
+        }
+    }
 }
 
 /// Transforms query `Q` by skipping entities satisfying query `R`
@@ -409,6 +698,14 @@ impl<Q: Query, R: Query> Query for Without<Q, R> {
 
 unsafe impl<Q: QueryShared, R> QueryShared for Without<Q, R> {}
 
+impl<Q: QueryCloned, R: Query> QueryCloned for Without<Q, R> {
+    type Cloned = Q::Cloned;
+
+    fn cloned(item: Self::Item<'_>) -> Self::Cloned {
+        Q::cloned(item)
+    }
+}
+
 #[doc(hidden)]
 pub struct FetchWithout<F, G>(F, PhantomData<fn(G)>);
 
@@ -430,6 +727,9 @@ unsafe impl<F: Fetch, G: Fetch> Fetch for FetchWithout<F, G> {
     fn borrow(archetype: &Archetype, state: Self::State) {
         F::borrow(archetype, state)
     }
+    fn try_borrow(archetype: &Archetype, state: Self::State) -> bool {
+        F::try_borrow(archetype, state)
+    }
     fn prepare(archetype: &Archetype) -> Option<Self::State> {
         if G::access(archetype).is_some() {
             return None;
@@ -446,6 +746,11 @@ unsafe impl<F: Fetch, G: Fetch> Fetch for FetchWithout<F, G> {
     fn for_each_borrow(f: impl FnMut(TypeId, bool)) {
         F::for_each_borrow(f);
     }
+
+    #[inline(always)]
+    fn prefetch(&self, n: usize) {
+        self.0.prefetch(n);
+    }
 }
 
 impl<F: Clone, G> Clone for FetchWithout<F, G> {
@@ -488,6 +793,14 @@ impl<Q: Query, R: Query> Query for With<Q, R> {
 
 unsafe impl<Q: QueryShared, R> QueryShared for With<Q, R> {}
 
+impl<Q: QueryCloned, R: Query> QueryCloned for With<Q, R> {
+    type Cloned = Q::Cloned;
+
+    fn cloned(item: Self::Item<'_>) -> Self::Cloned {
+        Q::cloned(item)
+    }
+}
+
 #[doc(hidden)]
 pub struct FetchWith<F, G>(F, PhantomData<fn(G)>);
 
@@ -509,6 +822,9 @@ unsafe impl<F: Fetch, G: Fetch> Fetch for FetchWith<F, G> {
     fn borrow(archetype: &Archetype, state: Self::State) {
         F::borrow(archetype, state)
     }
+    fn try_borrow(archetype: &Archetype, state: Self::State) -> bool {
+        F::try_borrow(archetype, state)
+    }
     fn prepare(archetype: &Archetype) -> Option<Self::State> {
         G::access(archetype)?;
         F::prepare(archetype)
@@ -523,79 +839,402 @@ unsafe impl<F: Fetch, G: Fetch> Fetch for FetchWith<F, G> {
     fn for_each_borrow(f: impl FnMut(TypeId, bool)) {
         F::for_each_borrow(f);
     }
+
+    #[inline(always)]
+    fn prefetch(&self, n: usize) {
+        self.0.prefetch(n);
+    }
+}
+
+impl<F: Clone, G> Clone for FetchWith<F, G> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self(self.0.clone(), PhantomData)
+    }
+}
+
+/// A type which can stand in for the component type `Target` when fetched with [`Aliased`]
+///
+/// Lets code which prefers a semantic newtype and code which prefers the raw type it wraps share
+/// one column of components.
+///
+/// # Safety
+///
+/// `Self` must have the same size, alignment, and validity invariants as `Target`, e.g. by being a
+/// `#[repr(transparent)]` wrapper around it. Because that relationship is symmetric, a wrapped
+/// type may also be declared an alias of its wrapper.
+///
+/// Like components, aliases must be `Send + Sync`, since they may be accessed through shared
+/// borrows of the world from any thread. An alias introducing interior mutability is therefore
+/// rejected:
+///
+/// ```compile_fail
+/// # use hecs::*;
+/// # use core::cell::Cell;
+/// #[repr(transparent)]
+/// struct Racy(Cell<i32>);
+/// unsafe impl Alias for Racy {
+///     type Target = i32;
+/// }
+/// ```
+pub unsafe trait Alias: Send + Sync + 'static {
+    /// The component type actually stored in the world
+    type Target: Component;
+}
+
+/// Query transformer that fetches `A::Target` components as the [`Alias`] `A`
+///
+/// `Aliased<&A>` and `Aliased<&mut A>` borrow `A::Target` exactly as `&A::Target` and
+/// `&mut A::Target` would, so they conflict with the same queries.
+///
+/// # Example
+/// ```
+/// # use hecs::*;
+/// #[derive(Debug, PartialEq)]
+/// #[repr(transparent)]
+/// struct Velocity([f32; 3]);
+///
+/// unsafe impl Alias for Velocity {
+///     type Target = [f32; 3];
+/// }
+///
+/// let mut world = World::new();
+/// let e = world.spawn(([1.0f32, 0.0, 0.0],));
+/// for (_, v) in world.query_mut::<Aliased<&mut Velocity>>() {
+///     v.0[1] = 2.0;
+/// }
+/// assert_eq!(*world.get::<&[f32; 3]>(e).unwrap(), [1.0, 2.0, 0.0]);
+/// ```
+pub struct Aliased<Q>(PhantomData<Q>);
+
+impl<A: Alias> Query for Aliased<&'_ A> {
+    type Item<'q> = &'q A;
+
+    type Fetch = FetchRead<A::Target>;
+
+    unsafe fn get<'q>(fetch: &Self::Fetch, n: usize) -> &'q A {
+        &*fetch.0.as_ptr().add(n).cast::<A>()
+    }
+}
+
+unsafe impl<A> QueryShared for Aliased<&'_ A> {}
+
+impl<A: Alias> Query for Aliased<&'_ mut A> {
+    type Item<'q> = &'q mut A;
+
+    type Fetch = FetchWrite<A::Target>;
+
+    unsafe fn get<'q>(fetch: &Self::Fetch, n: usize) -> &'q mut A {
+        &mut *fetch.0.as_ptr().add(n).cast::<A>()
+    }
+}
+
+/// A query that matches all entities, yielding `bool`s indicating whether each satisfies query `Q`
+///
+/// Does not borrow any components, making it faster and more concurrency-friendly than `Option<Q>`.
+///
+/// # Example
+/// ```
+/// # use hecs::*;
+/// let mut world = World::new();
+/// let a = world.spawn((123, true, "abc"));
+/// let b = world.spawn((456, false));
+/// let c = world.spawn((42, "def"));
+/// let entities = world.query::<Satisfies<&bool>>()
+///     .iter()
+///     .map(|(e, x)| (e, x))
+///     .collect::<Vec<_>>();
+/// assert_eq!(entities.len(), 3);
+/// assert!(entities.contains(&(a, true)));
+/// assert!(entities.contains(&(b, true)));
+/// assert!(entities.contains(&(c, false)));
+/// ```
+pub struct Satisfies<Q>(PhantomData<Q>);
+
+impl<Q: Query> Query for Satisfies<Q> {
+    type Item<'q> = bool;
+
+    type Fetch = FetchSatisfies<Q::Fetch>;
+
+    unsafe fn get<'q>(fetch: &Self::Fetch, _: usize) -> Self::Item<'q> {
+        fetch.0
+    }
+}
+
+unsafe impl<Q> QueryShared for Satisfies<Q> {}
+
+impl<Q: Query> QueryCloned for Satisfies<Q> {
+    type Cloned = bool;
+
+    fn cloned(item: bool) -> bool {
+        item
+    }
+}
+
+#[doc(hidden)]
+pub struct FetchSatisfies<F>(bool, PhantomData<F>);
+
+unsafe impl<F: Fetch> Fetch for FetchSatisfies<F> {
+    type State = bool;
+
+    fn dangling() -> Self {
+        Self(false, PhantomData)
+    }
+
+    fn access(_archetype: &Archetype) -> Option<Access> {
+        Some(Access::Iterate)
+    }
+
+    fn borrow(_archetype: &Archetype, _state: Self::State) {}
+    fn prepare(archetype: &Archetype) -> Option<Self::State> {
+        Some(F::prepare(archetype).is_some())
+    }
+    fn execute(_archetype: &Archetype, state: Self::State) -> Self {
+        Self(state, PhantomData)
+    }
+    fn release(_archetype: &Archetype, _state: Self::State) {}
+
+    fn for_each_borrow(_: impl FnMut(TypeId, bool)) {}
+}
+
+impl<T> Clone for FetchSatisfies<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self(self.0, PhantomData)
+    }
+}
+
+/// A query that matches entities which do not satisfy query `Q`, yielding `()`
+///
+/// Unlike [`Without`], requires no payload query, so it matches entities with no components at
+/// all. Does not borrow any components.
+///
+/// # Example
+/// ```
+/// # use hecs::*;
+/// let mut world = World::new();
+/// let a = world.spawn((123, true));
+/// let b = world.spawn((456,));
+/// let c = world.spawn(());
+/// let entities = world.query::<Not<&bool>>()
+///     .iter()
+///     .map(|(e, ())| e)
+///     .collect::<Vec<_>>();
+/// assert_eq!(entities.len(), 2);
+/// assert!(entities.contains(&b));
+/// assert!(entities.contains(&c));
+/// ```
+pub struct Not<Q>(PhantomData<Q>);
+
+impl<Q: Query> Query for Not<Q> {
+    type Item<'q> = ();
+
+    type Fetch = FetchNot<Q::Fetch>;
+
+    unsafe fn get<'q>(_: &Self::Fetch, _: usize) -> Self::Item<'q> {}
+}
+
+unsafe impl<Q> QueryShared for Not<Q> {}
+
+impl<Q: Query> QueryCloned for Not<Q> {
+    type Cloned = ();
+
+    fn cloned(_: ()) {}
+}
+
+#[doc(hidden)]
+pub struct FetchNot<F>(PhantomData<F>);
+
+unsafe impl<F: Fetch> Fetch for FetchNot<F> {
+    type State = ();
+
+    fn dangling() -> Self {
+        Self(PhantomData)
+    }
+
+    fn access(archetype: &Archetype) -> Option<Access> {
+        match F::access(archetype) {
+            Some(_) => None,
+            None => Some(Access::Iterate),
+        }
+    }
+
+    fn borrow(_archetype: &Archetype, _state: Self::State) {}
+    fn prepare(archetype: &Archetype) -> Option<Self::State> {
+        match F::prepare(archetype) {
+            Some(_) => None,
+            None => Some(()),
+        }
+    }
+    fn execute(_archetype: &Archetype, _state: Self::State) -> Self {
+        Self(PhantomData)
+    }
+    fn release(_archetype: &Archetype, _state: Self::State) {}
+
+    fn for_each_borrow(_: impl FnMut(TypeId, bool)) {}
+}
+
+impl<T> Clone for FetchNot<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self(PhantomData)
+    }
+}
+
+/// A query that matches entities satisfying at least one of the queries in the tuple `T`,
+/// yielding `()`
+///
+/// Equivalent to a nested chain of [`Or`]s used as a filter, e.g. with [`With`], but more concise,
+/// faster to compile, and stops checking an archetype at the first matching query. Does not
+/// borrow any components.
+///
+/// # Example
+/// ```
+/// # use hecs::*;
+/// let mut world = World::new();
+/// let a = world.spawn((123, true));
+/// let b = world.spawn((456, "abc"));
+/// let c = world.spawn((789, 1.0f32));
+/// let entities = world.query::<With<&i32, AnyOf<(&bool, &&str, &u8)>>>()
+///     .iter()
+///     .map(|(e, &i)| (e, i))
+///     .collect::<Vec<_>>();
+/// assert_eq!(entities.len(), 2);
+/// assert!(entities.contains(&(a, 123)));
+/// assert!(entities.contains(&(b, 456)));
+/// ```
+pub struct AnyOf<T>(PhantomData<T>);
+
+impl<T: Query> Query for AnyOf<T>
+where
+    T::Fetch: FetchTuple,
+{
+    type Item<'q> = ();
+
+    type Fetch = FetchAnyOf<T::Fetch>;
+
+    unsafe fn get<'q>(_: &Self::Fetch, _: usize) -> Self::Item<'q> {}
+}
+
+unsafe impl<T> QueryShared for AnyOf<T> {}
+
+impl<T: Query> QueryCloned for AnyOf<T>
+where
+    T::Fetch: FetchTuple,
+{
+    type Cloned = ();
+
+    fn cloned(_: ()) {}
+}
+
+/// Helper for [`AnyOf`], implemented for tuples of [`Fetch`]es
+#[doc(hidden)]
+pub trait FetchTuple: Fetch {
+    /// Whether any element's `access` is `Some`, checked in order
+    fn any_access(archetype: &Archetype) -> bool;
+    /// Whether any element's `prepare` is `Some`, checked in order
+    fn any_prepare(archetype: &Archetype) -> bool;
+}
+
+#[doc(hidden)]
+pub struct FetchAnyOf<F>(PhantomData<F>);
+
+unsafe impl<F: FetchTuple> Fetch for FetchAnyOf<F> {
+    type State = ();
+
+    fn dangling() -> Self {
+        Self(PhantomData)
+    }
+
+    fn access(archetype: &Archetype) -> Option<Access> {
+        F::any_access(archetype).then_some(Access::Iterate)
+    }
+
+    fn borrow(_archetype: &Archetype, _state: Self::State) {}
+    fn prepare(archetype: &Archetype) -> Option<Self::State> {
+        F::any_prepare(archetype).then_some(())
+    }
+    fn execute(_archetype: &Archetype, _state: Self::State) -> Self {
+        Self(PhantomData)
+    }
+    fn release(_archetype: &Archetype, _state: Self::State) {}
+
+    fn for_each_borrow(_: impl FnMut(TypeId, bool)) {}
 }
 
-impl<F: Clone, G> Clone for FetchWith<F, G> {
+impl<T> Clone for FetchAnyOf<T> {
     #[inline]
     fn clone(&self) -> Self {
-        Self(self.0.clone(), PhantomData)
+        Self(PhantomData)
     }
 }
 
-/// A query that matches all entities, yielding `bool`s indicating whether each satisfies query `Q`
+/// A query that matches entities satisfying every query in the tuple `T`, yielding `()`
 ///
-/// Does not borrow any components, making it faster and more concurrency-friendly than `Option<Q>`.
+/// Like [`Satisfies`], but filtering rather than yielding a `bool`, and stops checking an
+/// archetype at the first query that doesn't match. Does not borrow any components.
 ///
 /// # Example
 /// ```
 /// # use hecs::*;
 /// let mut world = World::new();
 /// let a = world.spawn((123, true, "abc"));
-/// let b = world.spawn((456, false));
-/// let c = world.spawn((42, "def"));
-/// let entities = world.query::<Satisfies<&bool>>()
+/// let b = world.spawn((456, true));
+/// let entities = world.query::<With<&i32, AllOf<(&bool, &&str)>>>()
 ///     .iter()
-///     .map(|(e, x)| (e, x))
+///     .map(|(e, &i)| (e, i))
 ///     .collect::<Vec<_>>();
-/// assert_eq!(entities.len(), 3);
-/// assert!(entities.contains(&(a, true)));
-/// assert!(entities.contains(&(b, true)));
-/// assert!(entities.contains(&(c, false)));
+/// assert_eq!(entities, [(a, 123)]);
 /// ```
-pub struct Satisfies<Q>(PhantomData<Q>);
+pub struct AllOf<T>(PhantomData<T>);
 
-impl<Q: Query> Query for Satisfies<Q> {
-    type Item<'q> = bool;
+impl<T: Query> Query for AllOf<T> {
+    type Item<'q> = ();
 
-    type Fetch = FetchSatisfies<Q::Fetch>;
+    type Fetch = FetchAllOf<T::Fetch>;
 
-    unsafe fn get<'q>(fetch: &Self::Fetch, _: usize) -> Self::Item<'q> {
-        fetch.0
-    }
+    unsafe fn get<'q>(_: &Self::Fetch, _: usize) -> Self::Item<'q> {}
 }
 
-unsafe impl<Q> QueryShared for Satisfies<Q> {}
+unsafe impl<T> QueryShared for AllOf<T> {}
+
+impl<T: Query> QueryCloned for AllOf<T> {
+    type Cloned = ();
+
+    fn cloned(_: ()) {}
+}
 
 #[doc(hidden)]
-pub struct FetchSatisfies<F>(bool, PhantomData<F>);
+pub struct FetchAllOf<F>(PhantomData<F>);
 
-unsafe impl<F: Fetch> Fetch for FetchSatisfies<F> {
-    type State = bool;
+unsafe impl<F: Fetch> Fetch for FetchAllOf<F> {
+    type State = ();
 
     fn dangling() -> Self {
-        Self(false, PhantomData)
+        Self(PhantomData)
     }
 
-    fn access(_archetype: &Archetype) -> Option<Access> {
-        Some(Access::Iterate)
+    fn access(archetype: &Archetype) -> Option<Access> {
+        // Tuples' `access` and `prepare` already stop at the first mismatch
+        F::access(archetype).map(|_| Access::Iterate)
     }
 
     fn borrow(_archetype: &Archetype, _state: Self::State) {}
     fn prepare(archetype: &Archetype) -> Option<Self::State> {
-        Some(F::prepare(archetype).is_some())
+        F::prepare(archetype).map(|_| ())
     }
-    fn execute(_archetype: &Archetype, state: Self::State) -> Self {
-        Self(state, PhantomData)
+    fn execute(_archetype: &Archetype, _state: Self::State) -> Self {
+        Self(PhantomData)
     }
     fn release(_archetype: &Archetype, _state: Self::State) {}
 
     fn for_each_borrow(_: impl FnMut(TypeId, bool)) {}
 }
 
-impl<T> Clone for FetchSatisfies<T> {
+impl<T> Clone for FetchAllOf<T> {
     #[inline]
     fn clone(&self) -> Self {
-        Self(self.0, PhantomData)
+        Self(PhantomData)
     }
 }
 
@@ -617,14 +1256,37 @@ impl<'w, Q: Query> QueryBorrow<'w, Q> {
         }
     }
 
+    /// Borrow immediately, failing rather than panicking on conflict
+    pub(crate) fn try_new(world: &'w World) -> Result<Self, BorrowConflict> {
+        try_start_borrow::<Q>(world.archetypes_inner())?;
+        #[cfg(feature = "trace")]
+        world.record(crate::TraceKind::Borrow {
+            query: core::any::type_name::<Q>(),
+        });
+        Ok(Self {
+            world,
+            borrowed: true,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Describe which archetypes the query matches, and how it accesses them
+    ///
+    /// Doesn't borrow any components. See [`QueryExplanation`].
+    pub fn explain(&self) -> QueryExplanation {
+        QueryExplanation::new::<Q>(self.world.archetypes_inner())
+    }
+
     /// Execute the query
     // The lifetime narrowing here is required for soundness.
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
     pub fn iter(&mut self) -> QueryIter<'_, Q> {
         self.borrow();
         unsafe { QueryIter::new(self.world) }
     }
 
     /// Provide random access to the query results
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
     pub fn view(&mut self) -> View<'_, Q> {
         self.borrow();
         unsafe { View::new(self.world.entities_meta(), self.world.archetypes_inner()) }
@@ -634,6 +1296,7 @@ impl<'w, Q: Query> QueryBorrow<'w, Q> {
     ///
     /// Useful for distributing work over a threadpool.
     // The lifetime narrowing here is required for soundness.
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
     pub fn iter_batched(&mut self, batch_size: u32) -> BatchedIter<'_, Q> {
         self.borrow();
         unsafe {
@@ -645,11 +1308,173 @@ impl<'w, Q: Query> QueryBorrow<'w, Q> {
         }
     }
 
+    /// Like `iter_batched`, but each child iterator covers exactly one archetype
+    ///
+    /// Suitable for work-stealing schedulers, which perform best when batch boundaries coincide
+    /// with archetype boundaries. Archetypes with no entities are skipped.
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
+    pub fn iter_batched_by_archetype(&mut self) -> BatchedIter<'_, Q> {
+        // No archetype can hold more than `u32::MAX` entities
+        self.iter_batched(u32::MAX)
+    }
+
+    /// Execute the query, cloning each result so it can outlive the borrow
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// fn snapshot(world: &World) -> Vec<(Entity, (i32, Option<bool>))> {
+    ///     world.query::<(&i32, Option<&bool>)>().iter_cloned().collect()
+    /// }
+    ///
+    /// let mut world = World::new();
+    /// let a = world.spawn((123, true));
+    /// let b = world.spawn((456,));
+    /// let results = snapshot(&world);
+    /// assert!(results.contains(&(a, (123, Some(true)))));
+    /// assert!(results.contains(&(b, (456, None))));
+    /// ```
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
+    pub fn iter_cloned(&mut self) -> impl Iterator<Item = (Entity, Q::Cloned)> + '_
+    where
+        Q: QueryCloned,
+    {
+        self.iter().map(|(entity, item)| (entity, Q::cloned(item)))
+    }
+
+    /// Execute the query, grouping results by the value of each entity's `K` component
+    ///
+    /// Entities without a `K` component are skipped. Groups are yielded in arbitrary order, and
+    /// results within a group in the order they would be yielded by [`iter`](Self::iter). The
+    /// grouping is cached in `index` and only recomputed when the world has changed in a way that
+    /// could affect it, so reuse the same `index` every frame.
+    ///
+    /// Panics if `Q` borrows `K` uniquely.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// #[derive(Clone, PartialEq, Eq, Hash, Debug)]
+    /// struct Material(u32);
+    ///
+    /// let mut world = World::new();
+    /// let a = world.spawn((Material(0), 1.0_f32));
+    /// let b = world.spawn((Material(1), 2.0_f32));
+    /// let c = world.spawn((Material(0), 3.0_f32, true));
+    /// let mut index = GroupIndex::new();
+    /// let mut query = world.query::<&f32>();
+    /// let mut groups = query
+    ///     .group_by::<Material>(&mut index)
+    ///     .map(|(k, xs)| (k.clone(), xs.map(|(e, _)| e).collect::<Vec<_>>()))
+    ///     .collect::<Vec<_>>();
+    /// groups.sort_by_key(|(k, _)| k.0);
+    /// assert_eq!(groups, [(Material(0), vec![a, c]), (Material(1), vec![b])]);
+    /// ```
+    // The lifetime narrowing here is required for soundness.
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
+    pub fn group_by<'q, K: Component + Clone + Eq + Hash>(
+        &'q mut self,
+        index: &'q mut GroupIndex<K>,
+    ) -> impl Iterator<Item = (&'q K, GroupIter<'q, Q>)> + 'q {
+        self.borrow();
+        index.refresh::<Q>(self.world);
+        let meta = self.world.entities_meta();
+        let archetypes = self.world.archetypes_inner();
+        index.groups().iter().map(move |(key, members)| {
+            // Safety: groups are disjoint, so no result is yielded twice
+            (key, unsafe { GroupIter::new(meta, archetypes, members) })
+        })
+    }
+
+    /// Execute the query, stopping at the first entity for which `f` returns `Some`
+    ///
+    /// Faster than the equivalent [`Iterator::find_map`] on [`iter`](Self::iter) in hot loops, as
+    /// each archetype is visited directly rather than through the iterator's state machine.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// struct Position(f32);
+    ///
+    /// let mut world = World::new();
+    /// world.spawn((Position(-4.0),));
+    /// let a = world.spawn((Position(1.5),));
+    /// let nearby = world
+    ///     .query::<&Position>()
+    ///     .find_map(|e, p| (p.0.abs() < 2.0).then_some((e, p.0)));
+    /// assert_eq!(nearby, Some((a, 1.5)));
+    /// ```
+    // The lifetime narrowing here is required for soundness.
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
+    pub fn find_map<'q, R>(
+        &'q mut self,
+        mut f: impl FnMut(Entity, Q::Item<'q>) -> Option<R>,
+    ) -> Option<R> {
+        self.borrow();
+        let meta = self.world.entities_meta();
+        for archetype in self.world.archetypes_inner() {
+            if archetype.is_empty() {
+                continue;
+            }
+            let Some(state) = Q::Fetch::prepare(archetype) else {
+                continue;
+            };
+            let fetch = Q::Fetch::execute(archetype, state);
+            for (n, &id) in archetype.ids().iter().enumerate() {
+                let entity = meta[id as usize].entity(id);
+                if let Some(x) = f(entity, unsafe { Q::get(&fetch, n) }) {
+                    return Some(x);
+                }
+            }
+        }
+        None
+    }
+
+    /// Execute the query, stopping at the first entity satisfying `f`
+    ///
+    /// See [`find_map`](Self::find_map).
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
+    pub fn find<'q>(
+        &'q mut self,
+        mut f: impl FnMut(Entity, &Q::Item<'q>) -> bool,
+    ) -> Option<(Entity, Q::Item<'q>)> {
+        self.find_map(|entity, item| f(entity, &item).then_some((entity, item)))
+    }
+
+    /// Whether any entity satisfies `f`, stopping at the first that does
+    ///
+    /// See [`find_map`](Self::find_map).
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
+    pub fn any(&mut self, mut f: impl FnMut(Entity, Q::Item<'_>) -> bool) -> bool {
+        self.find_map(|entity, item| f(entity, item).then_some(()))
+            .is_some()
+    }
+
+    /// Index of the first entity satisfying `f`, in the order yielded by [`iter`](Self::iter)
+    ///
+    /// See [`find_map`](Self::find_map).
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
+    pub fn position(&mut self, mut f: impl FnMut(Entity, Q::Item<'_>) -> bool) -> Option<usize> {
+        let mut i = 0;
+        self.find_map(|entity, item| {
+            if f(entity, item) {
+                return Some(i);
+            }
+            i += 1;
+            None
+        })
+    }
+
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
     fn borrow(&mut self) {
         if self.borrowed {
             return;
         }
         start_borrow::<Q>(self.world.archetypes_inner());
+        #[cfg(feature = "trace")]
+        self.world.record(crate::TraceKind::Borrow {
+            query: core::any::type_name::<Q>(),
+        });
         self.borrowed = true;
     }
 
@@ -721,6 +1546,10 @@ impl<'w, Q: Query> Drop for QueryBorrow<'w, Q> {
     fn drop(&mut self) {
         if self.borrowed {
             release_borrow::<Q>(self.world.archetypes_inner());
+            #[cfg(feature = "trace")]
+            self.world.record(crate::TraceKind::Release {
+                query: core::any::type_name::<Q>(),
+            });
         }
     }
 }
@@ -729,6 +1558,7 @@ impl<'q, 'w, Q: Query> IntoIterator for &'q mut QueryBorrow<'w, Q> {
     type Item = (Entity, Q::Item<'q>);
     type IntoIter = QueryIter<'q, Q>;
 
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
     fn into_iter(self) -> Self::IntoIter {
         self.iter()
     }
@@ -739,6 +1569,11 @@ pub struct QueryIter<'q, Q: Query> {
     world: &'q World,
     archetypes: core::ops::Range<usize>,
     iter: ChunkIter<Q>,
+    #[cfg(feature = "prefetch")]
+    prefetch: bool,
+    /// Whether this iteration has been counted by `World::query_metrics`
+    #[cfg(feature = "metrics")]
+    counted: bool,
 }
 
 impl<'q, Q: Query> QueryIter<'q, Q> {
@@ -746,22 +1581,65 @@ impl<'q, Q: Query> QueryIter<'q, Q> {
     ///
     /// `'q` must be sufficient to guarantee that `Q` cannot violate borrow safety, either with
     /// dynamic borrow checks or by representing exclusive access to the `World`.
-    unsafe fn new(world: &'q World) -> Self {
+    pub(crate) unsafe fn new(world: &'q World) -> Self {
         let n = world.archetypes().len();
         Self {
             world,
             archetypes: 0..n,
             iter: ChunkIter::empty(),
+            #[cfg(feature = "prefetch")]
+            prefetch: false,
+            #[cfg(feature = "metrics")]
+            counted: false,
         }
     }
 
+    /// Issue software prefetches for components some rows ahead of those being yielded
+    ///
+    /// May improve throughput when traversing large archetypes with wide components, where
+    /// iteration is dominated by memory stalls. Has no effect on targets without a supported
+    /// prefetch instruction. Benchmark before adopting: for small or cache-resident archetypes,
+    /// the extra instructions are pure overhead.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// world.spawn_batch((0..1000).map(|i| (i, [0.0f32; 16])));
+    /// let sum = world
+    ///     .query::<&i32>()
+    ///     .iter()
+    ///     .hinted()
+    ///     .map(|(_, &i)| i)
+    ///     .sum::<i32>();
+    /// assert_eq!(sum, 499500);
+    /// ```
+    #[cfg(feature = "prefetch")]
+    pub fn hinted(mut self) -> Self {
+        self.prefetch = true;
+        self
+    }
+
     /// Advance query to the next archetype
     ///
     /// Outlined from `Iterator::next` for improved iteration performance.
     fn next_archetype(&mut self) -> Option<()> {
+        #[cfg(feature = "metrics")]
+        if !self.counted {
+            self.counted = true;
+            self.world
+                .metrics()
+                .execute(TypeId::of::<Q::Fetch>(), type_name::<Q>());
+        }
         let archetype = self.archetypes.next()?;
         let archetype = unsafe { self.world.archetypes_inner().get_unchecked(archetype) };
         let state = Q::Fetch::prepare(archetype);
+        #[cfg(feature = "metrics")]
+        if state.is_some() {
+            self.world
+                .metrics()
+                .visit(TypeId::of::<Q::Fetch>(), type_name::<Q>(), archetype.len());
+        }
         let fetch = state.map(|state| Q::Fetch::execute(archetype, state));
         self.iter = fetch.map_or(ChunkIter::empty(), |fetch| ChunkIter::new(archetype, fetch));
         Some(())
@@ -783,16 +1661,12 @@ impl<'q, Q: Query> Iterator for QueryIter<'q, Q> {
                     continue;
                 }
                 Some((id, components)) => {
+                    #[cfg(feature = "prefetch")]
+                    if self.prefetch {
+                        self.iter.prefetch();
+                    }
                     return Some((
-                        Entity {
-                            id,
-                            generation: unsafe {
-                                self.world
-                                    .entities_meta()
-                                    .get_unchecked(id as usize)
-                                    .generation
-                            },
-                        },
+                        unsafe { self.world.entities_meta().get_unchecked(id as usize) }.entity(id),
                         components,
                     ));
                 }
@@ -804,6 +1678,17 @@ impl<'q, Q: Query> Iterator for QueryIter<'q, Q> {
         let n = self.len();
         (n, Some(n))
     }
+
+    fn nth(&mut self, mut n: usize) -> Option<Self::Item> {
+        // Skip whole archetypes without visiting their elements
+        loop {
+            n = self.iter.advance(n);
+            if n == 0 {
+                return self.next();
+            }
+            self.next_archetype()?;
+        }
+    }
 }
 
 impl<'q, Q: Query> ExactSizeIterator for QueryIter<'q, Q> {
@@ -875,6 +1760,57 @@ impl<'q, Q: Query> QueryMut<'q, Q> {
             )
         }
     }
+
+    /// Like `into_iter_batched`, but each child iterator covers exactly one archetype
+    ///
+    /// See [`QueryBorrow::iter_batched_by_archetype`].
+    pub fn into_iter_batched_by_archetype(self) -> BatchedIter<'q, Q> {
+        self.into_iter_batched(u32::MAX)
+    }
+
+    /// Call `f` once for each matching archetype with the IDs of its entities and slices of their
+    /// components
+    ///
+    /// The IDs and slices passed to each call all have the same length, with matching elements
+    /// belonging to the same entity. Operating on whole slices rather than individual items allows
+    /// the compiler to vectorize simple loops.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// struct Position(f32);
+    /// struct Velocity(f32);
+    /// let mut world = World::new();
+    /// let a = world.spawn((Position(0.0), Velocity(1.0)));
+    /// let b = world.spawn((Position(1.0), Velocity(2.0), true));
+    /// world
+    ///     .query_mut::<(&mut Position, &Velocity)>()
+    ///     .for_each_chunk(|ids, (pos, vel)| {
+    ///         assert_eq!(ids.len(), pos.len());
+    ///         for (p, v) in pos.iter_mut().zip(vel) {
+    ///             p.0 += v.0;
+    ///         }
+    ///     });
+    /// assert_eq!(world.get::<&Position>(a).unwrap().0, 1.0);
+    /// assert_eq!(world.get::<&Position>(b).unwrap().0, 3.0);
+    /// ```
+    pub fn for_each_chunk(self, mut f: impl FnMut(&[u32], Q::Slice<'q>))
+    where
+        Q: QuerySlice,
+    {
+        for archetype in self.iter.world.archetypes_inner() {
+            if archetype.is_empty() {
+                continue;
+            }
+            let Some(state) = Q::Fetch::prepare(archetype) else {
+                continue;
+            };
+            let fetch = Q::Fetch::execute(archetype, state);
+            f(archetype.ids(), unsafe {
+                Q::get_slice(&fetch, archetype.len() as usize)
+            });
+        }
+    }
 }
 
 impl<'q, Q: Query> IntoIterator for QueryMut<'q, Q> {
@@ -946,6 +1882,26 @@ impl<Q: Query> ChunkIter<Q> {
     fn remaining(&self) -> usize {
         self.len - self.position
     }
+
+    /// Hint that the components `PREFETCH_DISTANCE` rows ahead will be accessed soon
+    #[cfg(feature = "prefetch")]
+    #[inline(always)]
+    fn prefetch(&self) {
+        /// Number of rows ahead of the current position to prefetch
+        const PREFETCH_DISTANCE: usize = 8;
+        let n = self.position + PREFETCH_DISTANCE;
+        if n < self.len {
+            self.fetch.prefetch(n);
+        }
+    }
+
+    /// Skip up to `n` elements, returning the number of elements that could not be skipped
+    #[inline]
+    fn advance(&mut self, n: usize) -> usize {
+        let skipped = n.min(self.remaining());
+        self.position += skipped;
+        n - skipped
+    }
 }
 
 /// Batched version of [`QueryIter`]
@@ -1027,19 +1983,74 @@ impl<'q, Q: Query> Iterator for Batch<'q, Q> {
 
     fn next(&mut self) -> Option<Self::Item> {
         let (id, components) = unsafe { self.state.next()? };
-        Some((
-            Entity {
-                id,
-                generation: self.meta[id as usize].generation,
-            },
-            components,
-        ))
+        Some((self.meta[id as usize].entity(id), components))
     }
 }
 
 unsafe impl<'q, Q: Query> Send for Batch<'q, Q> where for<'a> Q::Item<'a>: Send {}
 unsafe impl<'q, Q: Query> Sync for Batch<'q, Q> where for<'a> Q::Item<'a>: Send {}
 
+impl<'q, Q: Query> Batch<'q, Q> {
+    /// Iterate over every entity in `archetype`, or none if it doesn't satisfy `Q`
+    ///
+    /// # Safety
+    ///
+    /// `'q` must be sufficient to guarantee that `Q` cannot violate borrow safety, either with
+    /// dynamic borrow checks or by representing exclusive access to the `World`.
+    pub(crate) unsafe fn for_archetype(meta: &'q [EntityMeta], archetype: &'q Archetype) -> Self {
+        let state = match Q::Fetch::prepare(archetype) {
+            Some(state) => ChunkIter::new(archetype, Q::Fetch::execute(archetype, state)),
+            None => ChunkIter::empty(),
+        };
+        Self { meta, state }
+    }
+}
+
+/// A borrow of a single archetype sufficient to execute the query `Q` on its entities
+///
+/// Constructed by [`World::query_archetype`]. Note that borrows are not released until this
+/// object is dropped.
+pub struct QueryArchetype<'w, Q: Query> {
+    meta: &'w [EntityMeta],
+    archetype: &'w Archetype,
+    /// State of the acquired borrow, if any
+    borrowed: Option<<Q::Fetch as Fetch>::State>,
+}
+
+impl<'w, Q: Query> QueryArchetype<'w, Q> {
+    pub(crate) fn new(meta: &'w [EntityMeta], archetype: &'w Archetype) -> Self {
+        Self {
+            meta,
+            archetype,
+            borrowed: None,
+        }
+    }
+
+    /// Execute the query
+    // The lifetime narrowing here is required for soundness.
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
+    pub fn iter(&mut self) -> Batch<'_, Q> {
+        if self.borrowed.is_none() {
+            if let Some(state) = Q::Fetch::prepare(self.archetype) {
+                Q::Fetch::borrow(self.archetype, state);
+                self.borrowed = Some(state);
+            }
+        }
+        unsafe { Batch::for_archetype(self.meta, self.archetype) }
+    }
+}
+
+unsafe impl<'w, Q: Query> Send for QueryArchetype<'w, Q> where for<'a> Q::Item<'a>: Send {}
+unsafe impl<'w, Q: Query> Sync for QueryArchetype<'w, Q> where for<'a> Q::Item<'a>: Send {}
+
+impl<'w, Q: Query> Drop for QueryArchetype<'w, Q> {
+    fn drop(&mut self) {
+        if let Some(state) = self.borrowed {
+            Q::Fetch::release(self.archetype, state);
+        }
+    }
+}
+
 macro_rules! tuple_impl {
     ($($name: ident),*) => {
         unsafe impl<$($name: Fetch),*> Fetch for ($($name,)*) {
@@ -1064,6 +2075,29 @@ macro_rules! tuple_impl {
                 let ($($name,)*) = state;
                 $($name::borrow(archetype, $name);)*
             }
+            #[allow(unused_variables, unused_mut, unused_assignments, non_snake_case)]
+            fn try_borrow(archetype: &Archetype, state: Self::State) -> bool {
+                let ($($name,)*) = state;
+                // Release the first `taken` elements' borrows
+                let release = |taken: usize| {
+                    let mut i = 0;
+                    $(
+                        if i < taken {
+                            $name::release(archetype, $name);
+                        }
+                        i += 1;
+                    )*
+                };
+                let mut taken = 0;
+                $(
+                    if !$name::try_borrow(archetype, $name) {
+                        release(taken);
+                        return false;
+                    }
+                    taken += 1;
+                )*
+                true
+            }
             #[allow(unused_variables)]
             #[cold]
             fn prepare(archetype: &Archetype) -> Option<Self::State> {
@@ -1085,6 +2119,35 @@ macro_rules! tuple_impl {
             fn for_each_borrow(mut f: impl FnMut(TypeId, bool)) {
                 $($name::for_each_borrow(&mut f);)*
             }
+
+            #[allow(unused_variables, non_snake_case)]
+            #[inline(always)]
+            fn prefetch(&self, n: usize) {
+                let ($($name,)*) = self;
+                $($name.prefetch(n);)*
+            }
+        }
+
+        impl<$($name: Fetch),*> FetchTuple for ($($name,)*) {
+            #[allow(unused_variables)]
+            fn any_access(archetype: &Archetype) -> bool {
+                $(
+                    if $name::access(archetype).is_some() {
+                        return true;
+                    }
+                )*
+                false
+            }
+
+            #[allow(unused_variables)]
+            fn any_prepare(archetype: &Archetype) -> bool {
+                $(
+                    if $name::prepare(archetype).is_some() {
+                        return true;
+                    }
+                )*
+                false
+            }
         }
 
         impl<$($name: Query),*> Query for ($($name,)*) {
@@ -1101,19 +2164,143 @@ macro_rules! tuple_impl {
         }
 
         unsafe impl<$($name: QueryShared),*> QueryShared for ($($name,)*) {}
+
+        impl<$($name: QuerySlice),*> QuerySlice for ($($name,)*) {
+            type Slice<'q> = ($($name::Slice<'q>,)*);
+
+            #[allow(unused_variables, clippy::unused_unit)]
+            unsafe fn get_slice<'q>(fetch: &Self::Fetch, len: usize) -> Self::Slice<'q> {
+                #[allow(non_snake_case)]
+                let ($(ref $name,)*) = *fetch;
+                ($($name::get_slice($name, len),)*)
+            }
+        }
+
+        impl<$($name: QueryCloned),*> QueryCloned for ($($name,)*) {
+            type Cloned = ($($name::Cloned,)*);
+
+            #[allow(clippy::unused_unit)]
+            fn cloned(item: Self::Item<'_>) -> Self::Cloned {
+                #[allow(non_snake_case)]
+                let ($($name,)*) = item;
+                ($($name::cloned($name),)*)
+            }
+        }
     };
 }
 
 //smaller_tuples_too!(tuple_impl, B, A);
 smaller_tuples_too!(tuple_impl, O, N, M, L, K, J, I, H, G, F, E, D, C, B, A);
+#[cfg(feature = "large-tuples")]
+larger_tuples_only!(tuple_impl, [O N M L K J I H G F E D C B A] Y X W V U T S R P);
+
+/// A query item which can be divided into the parts borrowed uniquely and those borrowed shared
+///
+/// Implemented for references, `Option`s of references, and tuples of those. Each part of a tuple
+/// has the same shape as the tuple itself, with `()` in place of elements belonging to the other
+/// part. The shared part is `Copy`, so it can be handed to any number of helper functions while the
+/// unique part remains usable.
+///
+/// # Example
+/// ```
+/// # use hecs::*;
+/// struct Position(f32);
+/// struct Velocity(f32);
+/// struct Drag(f32);
+///
+/// fn speed((_, v, d): ((), &Velocity, &Drag)) -> f32 {
+///     v.0 * (1.0 - d.0)
+/// }
+///
+/// let mut world = World::new();
+/// let e = world.spawn((Position(0.0), Velocity(2.0), Drag(0.5)));
+/// for (_, item) in world.query_mut::<(&mut Position, &Velocity, &Drag)>() {
+///     let ((p, (), ()), shared) = item.split();
+///     p.0 += speed(shared);
+/// }
+/// assert_eq!(world.get::<&Position>(e).unwrap().0, 1.0);
+/// ```
+pub trait SplitItem: Sized {
+    /// The parts borrowed uniquely
+    type Unique;
+    /// The parts borrowed shared
+    type Shared: Copy;
+
+    /// Divide into the parts borrowed uniquely and those borrowed shared
+    fn split(self) -> (Self::Unique, Self::Shared);
+}
+
+impl<'a, T: ?Sized> SplitItem for &'a T {
+    type Unique = ();
+    type Shared = &'a T;
+
+    fn split(self) -> ((), &'a T) {
+        ((), self)
+    }
+}
+
+impl<'a, T: ?Sized> SplitItem for &'a mut T {
+    type Unique = &'a mut T;
+    type Shared = ();
+
+    fn split(self) -> (&'a mut T, ()) {
+        (self, ())
+    }
+}
+
+impl<T: SplitItem> SplitItem for Option<T> {
+    type Unique = Option<T::Unique>;
+    type Shared = Option<T::Shared>;
+
+    fn split(self) -> (Self::Unique, Self::Shared) {
+        match self {
+            Some(x) => {
+                let (unique, shared) = x.split();
+                (Some(unique), Some(shared))
+            }
+            None => (None, None),
+        }
+    }
+}
+
+macro_rules! split_impl {
+    ($($name: ident),*) => {
+        impl<$($name: SplitItem),*> SplitItem for ($($name,)*) {
+            type Unique = ($($name::Unique,)*);
+            type Shared = ($($name::Shared,)*);
+
+            #[allow(clippy::unused_unit, non_snake_case)]
+            fn split(self) -> (Self::Unique, Self::Shared) {
+                let ($($name,)*) = self;
+                $(let $name = $name.split();)*
+                (($($name.0,)*), ($($name.1,)*))
+            }
+        }
+    };
+}
+
+smaller_tuples_too!(split_impl, O, N, M, L, K, J, I, H, G, F, E, D, C, B, A);
 
 /// A prepared query can be stored independently of the [`World`] to amortize query set-up costs.
+///
+/// A single prepared query may be used with any number of worlds. Preparation is retained
+/// separately for each, so alternating between worlds does not incur repeated set-up costs.
 pub struct PreparedQuery<Q: Query> {
     memo: (u64, u32),
     state: Box<[(usize, <Q::Fetch as Fetch>::State)]>,
     fetch: Box<[Option<Q::Fetch>]>,
+    /// Preparations for worlds other than the most recently queried
+    others: Vec<PreparedState<Q>>,
+    /// Number of times a preparation was discarded because its world gained archetypes
+    invalidations: u64,
 }
 
+type PreparedState<Q> = (
+    (u64, u32),
+    Box<[(usize, <<Q as Query>::Fetch as Fetch>::State)]>,
+    Box<[Option<<Q as Query>::Fetch>]>,
+);
+
 impl<Q: Query> Default for PreparedQuery<Q> {
     fn default() -> Self {
         Self::new()
@@ -1128,22 +2315,89 @@ impl<Q: Query> PreparedQuery<Q> {
             memo: (0, 0),
             state: Default::default(),
             fetch: Default::default(),
+            others: Vec::new(),
+            invalidations: 0,
         }
     }
 
     #[cold]
-    fn prepare(world: &World) -> Self {
+    fn prepare(&mut self, world: &World) {
         let memo = world.memo();
 
-        let state = world
+        if self.memo.0 == memo.0 {
+            self.invalidations += 1;
+        }
+
+        if self.memo.0 != 0 && self.memo.0 != memo.0 {
+            let state = mem::take(&mut self.state);
+            let fetch = mem::take(&mut self.fetch);
+            self.others.push((self.memo, state, fetch));
+        }
+        if let Some(i) = self.others.iter().position(|x| x.0 .0 == memo.0) {
+            let (old_memo, state, fetch) = self.others.swap_remove(i);
+            if old_memo == memo {
+                self.memo = memo;
+                self.state = state;
+                self.fetch = fetch;
+                return;
+            }
+            self.invalidations += 1;
+        }
+
+        self.memo = memo;
+        self.state = world
             .archetypes()
             .enumerate()
             .filter_map(|(idx, x)| Q::Fetch::prepare(x).map(|state| (idx, state)))
             .collect();
+        self.fetch = world.archetypes().map(|_| None).collect();
+    }
 
-        let fetch = world.archetypes().map(|_| None).collect();
+    /// Discard any preparation retained for worlds other than the most recently queried
+    ///
+    /// Useful when worlds this query was previously used with have been dropped.
+    pub fn forget_others(&mut self) {
+        self.others.clear();
+    }
+
+    /// Whether this query must be prepared again before it can be used with `world`
+    ///
+    /// Preparation is required the first time a query is used with a world, and again whenever
+    /// that world has gained archetypes since.
+    pub fn is_stale(&self, world: &World) -> bool {
+        self.memo != world.memo()
+    }
+
+    /// Prepare this query for use with `world` if it [is stale](Self::is_stale)
+    ///
+    /// Preparation otherwise happens on demand when the query is next used. Calling this at a
+    /// predictable time, such as the start of a frame, keeps that cost out of later queries.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let mut query = PreparedQuery::<&i32>::new();
+    /// query.refresh(&world);
+    /// assert!(!query.is_stale(&world));
+    /// world.spawn((123,));
+    /// assert!(query.is_stale(&world));
+    /// query.refresh(&world);
+    /// assert_eq!(query.invalidations(), 1);
+    /// ```
+    pub fn refresh(&mut self, world: &World) {
+        if self.is_stale(world) {
+            self.prepare(world);
+        }
+    }
 
-        Self { memo, state, fetch }
+    /// Number of times this query has been prepared again because a world it was previously
+    /// prepared for gained archetypes
+    ///
+    /// Frequent invalidation indicates archetype churn, such as components being added to
+    /// entities in many different combinations.
+    pub fn invalidations(&self) -> u64 {
+        self.invalidations
     }
 
     /// Query `world`, using dynamic borrow checking
@@ -1152,7 +2406,7 @@ impl<Q: Query> PreparedQuery<Q> {
     /// or construct an invalid unique reference.
     pub fn query<'q>(&'q mut self, world: &'q World) -> PreparedQueryBorrow<'q, Q> {
         if self.memo != world.memo() {
-            *self = Self::prepare(world);
+            self.prepare(world);
         }
 
         let meta = world.entities_meta();
@@ -1168,7 +2422,7 @@ impl<Q: Query> PreparedQuery<Q> {
         assert_borrow::<Q>();
 
         if self.memo != world.memo() {
-            *self = Self::prepare(world);
+            self.prepare(world);
         }
 
         let meta = world.entities_meta();
@@ -1182,7 +2436,7 @@ impl<Q: Query> PreparedQuery<Q> {
         assert_borrow::<Q>();
 
         if self.memo != world.memo() {
-            *self = Self::prepare(world);
+            self.prepare(world);
         }
 
         let meta = world.entities_meta();
@@ -1290,10 +2544,7 @@ impl<'q, Q: Query> Iterator for PreparedQueryIter<'q, Q> {
                 }
                 Some((id, components)) => {
                     return Some((
-                        Entity {
-                            id,
-                            generation: unsafe { self.meta.get_unchecked(id as usize).generation },
-                        },
+                        unsafe { self.meta.get_unchecked(id as usize) }.entity(id),
                         components,
                     ));
                 }
@@ -1305,6 +2556,23 @@ impl<'q, Q: Query> Iterator for PreparedQueryIter<'q, Q> {
         let n = self.len();
         (n, Some(n))
     }
+
+    fn nth(&mut self, mut n: usize) -> Option<Self::Item> {
+        // Skip whole archetypes without visiting their elements
+        loop {
+            n = self.iter.advance(n);
+            if n == 0 {
+                return self.next();
+            }
+            let (idx, state) = self.state.next()?;
+            let archetype = &self.archetypes[*idx];
+            if archetype.len() as usize <= n {
+                n -= archetype.len() as usize;
+                continue;
+            }
+            self.iter = ChunkIter::new(archetype, Q::Fetch::execute(archetype, *state));
+        }
+    }
 }
 
 impl<Q: Query> ExactSizeIterator for PreparedQueryIter<'_, Q> {
@@ -1317,6 +2585,85 @@ impl<Q: Query> ExactSizeIterator for PreparedQueryIter<'_, Q> {
     }
 }
 
+/// A tuple of queries which can be viewed simultaneously by [`World::views_mut`]
+pub trait DisjointQueries {
+    /// A [`View`] of each query
+    type Views<'q>;
+
+    #[doc(hidden)]
+    /// Panic if any query violates a unique borrow on its own or together with another
+    fn check();
+
+    #[doc(hidden)]
+    /// # Safety
+    ///
+    /// `check` must have succeeded, and `world` must be uniquely borrowed for `'q`
+    unsafe fn views(world: &World) -> Self::Views<'_>;
+}
+
+/// Panic if any of the given query types would conflict with one another
+///
+/// Two queries conflict if one borrows a component uniquely that the other borrows at all, in
+/// which case they cannot run concurrently, nor be passed together to [`World::views_mut`]. Also
+/// panics if any single query violates a unique borrow on its own. Since component type IDs
+/// cannot be compared in a `const` context, checking takes place at runtime; place invocations in
+/// a unit test to validate a set of systems statically.
+///
+/// # Example
+/// ```
+/// # use hecs::*;
+/// struct Position(f32);
+/// struct Velocity(f32);
+///
+/// assert_disjoint!((&mut Position, &Velocity), &Velocity, Option<&i32>);
+/// ```
+/// ```should_panic
+/// # use hecs::*;
+/// # struct Position(f32);
+/// # struct Velocity(f32);
+/// assert_disjoint!((&mut Position, &Velocity), &Position);
+/// ```
+#[macro_export]
+macro_rules! assert_disjoint {
+    ($($query:ty),+ $(,)?) => {
+        <($($query,)+) as $crate::DisjointQueries>::check()
+    };
+}
+
+macro_rules! disjoint_impl {
+    ($($name: ident),*) => {
+        impl<$($name: Query),*> DisjointQueries for ($($name,)*) {
+            type Views<'q> = ($(View<'q, $name>,)*);
+
+            #[allow(unused_variables, unused_mut, unused_assignments)]
+            fn check() {
+                $(assert_borrow::<$name>();)*
+                let mut borrows = Vec::<(usize, TypeId, bool)>::new();
+                let mut i = 0;
+                $(
+                    $name::Fetch::for_each_borrow(|id, unique| borrows.push((i, id, unique)));
+                    i += 1;
+                )*
+                for (n, &(i, a, unique)) in borrows.iter().enumerate() {
+                    for &(j, b, other_unique) in &borrows[n + 1..] {
+                        core::assert!(
+                            i == j || a != b || !(unique || other_unique),
+                            "queries violate a unique borrow"
+                        );
+                    }
+                }
+            }
+
+            #[allow(unused_variables, clippy::unused_unit)]
+            unsafe fn views(world: &World) -> Self::Views<'_> {
+                ($(View::<$name>::new(world.entities_meta(), world.archetypes_inner()),)*)
+            }
+        }
+    };
+}
+
+smaller_tuples_too!(disjoint_impl, O, N, M, L, K, J, I, H, G, F, E, D, C, B, A);
+
 /// Provides random access to the results of a query
 pub struct View<'q, Q: Query> {
     meta: &'q [EntityMeta],
@@ -1357,7 +2704,7 @@ impl<'q, Q: Query> View<'q, Q> {
         Q: QueryShared,
     {
         let meta = self.meta.get(entity.id as usize)?;
-        if meta.generation != entity.generation {
+        if !meta.matches(entity) {
             return None;
         }
 
@@ -1378,7 +2725,7 @@ impl<'q, Q: Query> View<'q, Q> {
         let Some(meta) = self.meta.get(entity.id as usize) else {
             return false;
         };
-        if meta.generation != entity.generation {
+        if !meta.matches(entity) {
             return false;
         }
         self.fetch[meta.location.archetype as usize].is_some()
@@ -1391,7 +2738,7 @@ impl<'q, Q: Query> View<'q, Q> {
     /// Must not be invoked while any unique borrow of the fetched components of `entity` is live.
     pub unsafe fn get_unchecked(&self, entity: Entity) -> Option<Q::Item<'_>> {
         let meta = self.meta.get(entity.id as usize)?;
-        if meta.generation != entity.generation {
+        if !meta.matches(entity) {
             return None;
         }
 
@@ -1445,9 +2792,66 @@ impl<'q, Q: Query> View<'q, Q> {
         self.get_many_mut(entities)
     }
 
+    /// Iterate over the query results for each of `entities`, in order
+    ///
+    /// Entities which do not exist or do not match the query are skipped. Much faster than
+    /// separately looking up each entity with [`World::query_one`](crate::World::query_one), as
+    /// the query is prepared only once per archetype.
+    ///
+    /// Does not require exclusive access to the map, but is defined only for queries yielding only
+    /// shared references.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let a = world.spawn((1,));
+    /// let b = world.spawn((2, true));
+    /// let c = world.spawn((true,));
+    /// let mut query = world.query::<&i32>();
+    /// let view = query.view();
+    /// let found = view.iter_over(&[b, c, a]).map(|(_, &x)| x).collect::<Vec<_>>();
+    /// assert_eq!(found, [2, 1]);
+    /// ```
+    pub fn iter_over<'a>(&'a self, entities: &'a [Entity]) -> ViewIterOver<'a, Q>
+    where
+        Q: QueryShared,
+    {
+        ViewIterOver {
+            meta: self.meta,
+            fetch: &self.fetch,
+            entities: entities.iter(),
+        }
+    }
+
+    /// Like [`iter_over`](Self::iter_over), but does not require `Q: QueryShared`
+    ///
+    /// Panics if `entities` contains duplicates.
+    pub fn iter_over_mut<'a>(&'a mut self, entities: &'a [Entity]) -> ViewIterOver<'a, Q> {
+        assert_distinct_slice(entities);
+        ViewIterOver {
+            meta: self.meta,
+            fetch: &self.fetch,
+            entities: entities.iter(),
+        }
+    }
+
     /// Iterate over all entities satisfying `Q`
     ///
-    /// Equivalent to [`QueryBorrow::iter`].
+    /// Equivalent to [`QueryBorrow::iter`]. Each entity's handle is yielded alongside its query
+    /// results, so `Q` need not include [`Entity`].
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let a = world.spawn((1,));
+    /// let mut view = world.view_mut::<&mut i32>();
+    /// for (entity, x) in view.iter_mut() {
+    ///     assert_eq!(entity, a);
+    ///     *x += 1;
+    /// }
+    /// ```
     pub fn iter_mut(&mut self) -> ViewIter<'_, Q> {
         ViewIter {
             meta: self.meta,
@@ -1456,6 +2860,72 @@ impl<'q, Q: Query> View<'q, Q> {
             iter: ChunkIter::empty(),
         }
     }
+
+    /// Divide the view's results into two disjoint parts at `index`, in iteration order
+    ///
+    /// The first part holds the first `index` results yielded by [`iter_mut`](Self::iter_mut),
+    /// and the second holds the remainder. Each part is `Send` when `Q`'s items are, so they may
+    /// be handed to separate threads.
+    ///
+    /// Panics if `index` exceeds the number of results.
+    pub fn split_at_mut(&mut self, index: usize) -> (ViewPart<'_, Q>, ViewPart<'_, Q>) {
+        self.whole().split_at(index)
+    }
+
+    /// Divide the view's results into disjoint parts of at most `size` results each, in iteration
+    /// order
+    ///
+    /// Panics if `size` is zero.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// world.spawn_batch((0..10).map(|i| (i,)));
+    /// let mut view = world.view_mut::<&mut i32>();
+    /// std::thread::scope(|scope| {
+    ///     for mut part in view.chunks_mut(3) {
+    ///         scope.spawn(move || {
+    ///             for (_, x) in part.iter_mut() {
+    ///                 *x *= 2;
+    ///             }
+    ///         });
+    ///     }
+    /// });
+    /// assert_eq!(view.iter_mut().map(|(_, &mut x)| x).sum::<i32>(), 90);
+    /// ```
+    pub fn chunks_mut(&mut self, size: usize) -> Vec<ViewPart<'_, Q>> {
+        assert!(size != 0, "chunk size must be non-zero");
+        let mut rest = self.whole();
+        let mut parts = Vec::with_capacity((rest.len + size - 1) / size);
+        while rest.len > size {
+            let (part, next) = rest.split_at(size);
+            parts.push(part);
+            rest = next;
+        }
+        if rest.len != 0 {
+            parts.push(rest);
+        }
+        parts
+    }
+
+    fn whole(&mut self) -> ViewPart<'_, Q> {
+        let len = self
+            .archetypes
+            .iter()
+            .zip(&self.fetch)
+            .filter(|(_, fetch)| fetch.is_some())
+            .map(|(archetype, _)| archetype.len() as usize)
+            .sum();
+        ViewPart {
+            meta: self.meta,
+            archetypes: self.archetypes,
+            fetch: &self.fetch,
+            start: (0, 0),
+            end: (self.archetypes.len() as u32, 0),
+            len,
+        }
+    }
 }
 
 impl<'a, 'q, Q: Query> IntoIterator for &'a mut View<'q, Q> {
@@ -1492,10 +2962,7 @@ impl<'a, Q: Query> Iterator for ViewIter<'a, Q> {
                 }
                 Some((id, components)) => {
                     return Some((
-                        Entity {
-                            id,
-                            generation: unsafe { self.meta.get_unchecked(id as usize).generation },
-                        },
+                        unsafe { self.meta.get_unchecked(id as usize) }.entity(id),
                         components,
                     ));
                 }
@@ -1504,6 +2971,237 @@ impl<'a, Q: Query> Iterator for ViewIter<'a, Q> {
     }
 }
 
+/// Iterator over the results of a [`View`] for a caller-supplied list of entities
+///
+/// Constructed by [`View::iter_over`] or [`View::iter_over_mut`].
+pub struct ViewIterOver<'a, Q: Query> {
+    meta: &'a [EntityMeta],
+    fetch: &'a [Option<Q::Fetch>],
+    entities: SliceIter<'a, Entity>,
+}
+
+impl<'a, Q: Query> Iterator for ViewIterOver<'a, Q> {
+    type Item = (Entity, Q::Item<'a>);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let &entity = self.entities.next()?;
+            let Some(meta) = self.meta.get(entity.id as usize) else {
+                continue;
+            };
+            if !meta.matches(entity) {
+                continue;
+            }
+            let Some(fetch) = &self.fetch[meta.location.archetype as usize] else {
+                continue;
+            };
+            // Safety: entities are distinct or `Q: QueryShared`, so items never alias uniquely
+            return Some((entity, unsafe {
+                Q::get(fetch, meta.location.index as usize)
+            }));
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.entities.len()))
+    }
+}
+
+/// A contiguous, disjoint portion of a [`View`]'s results
+///
+/// Constructed by [`View::split_at_mut`] or [`View::chunks_mut`]. Random access is limited to
+/// entities whose results lie within the part, so that parts may be used concurrently.
+pub struct ViewPart<'a, Q: Query> {
+    meta: &'a [EntityMeta],
+    archetypes: &'a [Archetype],
+    fetch: &'a [Option<Q::Fetch>],
+    /// Location of the first result, as (archetype, index)
+    start: (u32, u32),
+    /// Location one past the last result
+    end: (u32, u32),
+    len: usize,
+}
+
+unsafe impl<'a, Q: Query> Send for ViewPart<'a, Q> where for<'x> Q::Item<'x>: Send {}
+unsafe impl<'a, Q: Query> Sync for ViewPart<'a, Q> where for<'x> Q::Item<'x>: Send {}
+
+impl<'a, Q: Query> ViewPart<'a, Q> {
+    /// Number of results in this part
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether this part contains no results
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Divide this part into two at `index`, in iteration order
+    ///
+    /// Panics if `index` exceeds [`len`](Self::len).
+    pub fn split_at(self, index: usize) -> (Self, Self) {
+        assert!(index <= self.len, "split index out of bounds");
+        let mid = self.locate(index);
+        (
+            Self {
+                end: mid,
+                len: index,
+                ..self
+            },
+            Self {
+                start: mid,
+                len: self.len - index,
+                ..self
+            },
+        )
+    }
+
+    /// Retrieve the query results corresponding to `entity`
+    ///
+    /// Will yield `None` if the entity does not exist, does not match the query, or lies outside
+    /// this part.
+    pub fn get(&self, entity: Entity) -> Option<Q::Item<'_>>
+    where
+        Q: QueryShared,
+    {
+        unsafe { self.get_unchecked(entity) }
+    }
+
+    /// Retrieve the query results corresponding to `entity`
+    ///
+    /// Will yield `None` if the entity does not exist, does not match the query, or lies outside
+    /// this part.
+    pub fn get_mut(&mut self, entity: Entity) -> Option<Q::Item<'_>> {
+        unsafe { self.get_unchecked(entity) }
+    }
+
+    /// Equivalent to `get(entity).is_some()`, but does not require `Q: QueryShared`
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.location(entity).is_some()
+    }
+
+    /// Iterate over all results in this part
+    pub fn iter_mut(&mut self) -> ViewPartIter<'_, Q> {
+        ViewPartIter {
+            meta: self.meta,
+            archetypes: self.archetypes,
+            fetch: self.fetch,
+            position: self.start,
+            end: self.end,
+            remaining: self.len,
+        }
+    }
+
+    /// # Safety
+    ///
+    /// Must not be invoked while any unique borrow of the fetched components of `entity` is live.
+    unsafe fn get_unchecked(&self, entity: Entity) -> Option<Q::Item<'_>> {
+        let (archetype, index) = self.location(entity)?;
+        self.fetch[archetype as usize]
+            .as_ref()
+            .map(|fetch| Q::get(fetch, index as usize))
+    }
+
+    fn location(&self, entity: Entity) -> Option<(u32, u32)> {
+        let meta = self.meta.get(entity.id as usize)?;
+        if !meta.matches(entity) {
+            return None;
+        }
+        let location = (meta.location.archetype, meta.location.index);
+        if location < self.start || location >= self.end {
+            return None;
+        }
+        self.fetch[location.0 as usize].as_ref()?;
+        Some(location)
+    }
+
+    /// Find the location of the `index`th result, or `end` if there is none
+    fn locate(&self, mut index: usize) -> (u32, u32) {
+        for archetype in self.start.0..self.end.0.min(self.archetypes.len() as u32) {
+            if self.fetch[archetype as usize].is_none() {
+                continue;
+            }
+            let first = if archetype == self.start.0 {
+                self.start.1
+            } else {
+                0
+            };
+            let last = if archetype == self.end.0 {
+                self.end.1
+            } else {
+                self.archetypes[archetype as usize].len()
+            };
+            let count = (last - first) as usize;
+            if index < count {
+                return (archetype, first + index as u32);
+            }
+            index -= count;
+        }
+        self.end
+    }
+}
+
+impl<'a, 'b, Q: Query> IntoIterator for &'a mut ViewPart<'b, Q> {
+    type IntoIter = ViewPartIter<'a, Q>;
+    type Item = (Entity, Q::Item<'a>);
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+/// Iterator over the results of a [`ViewPart`]
+pub struct ViewPartIter<'a, Q: Query> {
+    meta: &'a [EntityMeta],
+    archetypes: &'a [Archetype],
+    fetch: &'a [Option<Q::Fetch>],
+    position: (u32, u32),
+    end: (u32, u32),
+    remaining: usize,
+}
+
+impl<'a, Q: Query> Iterator for ViewPartIter<'a, Q> {
+    type Item = (Entity, Q::Item<'a>);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.remaining == 0 {
+                return None;
+            }
+            let (archetype_index, index) = self.position;
+            let archetype = &self.archetypes[archetype_index as usize];
+            let last = if archetype_index == self.end.0 {
+                self.end.1
+            } else {
+                archetype.len()
+            };
+            match &self.fetch[archetype_index as usize] {
+                Some(fetch) if index < last => {
+                    self.position.1 += 1;
+                    self.remaining -= 1;
+                    let id = archetype.entity_id(index);
+                    return Some((
+                        unsafe { self.meta.get_unchecked(id as usize) }.entity(id),
+                        // Safety: each result within a part is yielded at most once, and parts
+                        // are disjoint
+                        unsafe { Q::get(fetch, index as usize) },
+                    ));
+                }
+                _ => self.position = (archetype_index + 1, 0),
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, Q: Query> ExactSizeIterator for ViewPartIter<'a, Q> {}
+
 /// Provides random access to the results of a prepared query
 pub struct PreparedView<'q, Q: Query> {
     meta: &'q [EntityMeta],
@@ -1549,7 +3247,7 @@ impl<'q, Q: Query> PreparedView<'q, Q> {
         Q: QueryShared,
     {
         let meta = self.meta.get(entity.id as usize)?;
-        if meta.generation != entity.generation {
+        if !meta.matches(entity) {
             return None;
         }
 
@@ -1570,7 +3268,7 @@ impl<'q, Q: Query> PreparedView<'q, Q> {
         let Some(meta) = self.meta.get(entity.id as usize) else {
             return false;
         };
-        if meta.generation != entity.generation {
+        if !meta.matches(entity) {
             return false;
         }
         self.fetch[meta.location.archetype as usize].is_some()
@@ -1583,7 +3281,7 @@ impl<'q, Q: Query> PreparedView<'q, Q> {
     /// Must not be invoked while any unique borrow of the fetched components of `entity` is live.
     pub unsafe fn get_unchecked(&self, entity: Entity) -> Option<Q::Item<'_>> {
         let meta = self.meta.get(entity.id as usize)?;
-        if meta.generation != entity.generation {
+        if !meta.matches(entity) {
             return None;
         }
 
@@ -1648,14 +3346,40 @@ impl<'a, 'q, Q: Query> IntoIterator for &'a mut PreparedView<'q, Q> {
 /// This struct is a thin wrapper around [`View`]. See it for more documentation.
 pub struct ViewBorrow<'w, Q: Query> {
     view: View<'w, Q>,
+    #[cfg(feature = "trace")]
+    world: &'w World,
 }
 
 impl<'w, Q: Query> ViewBorrow<'w, Q> {
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
     pub(crate) fn new(world: &'w World) -> Self {
         start_borrow::<Q>(world.archetypes_inner());
+        #[cfg(feature = "trace")]
+        world.record(crate::TraceKind::Borrow {
+            query: core::any::type_name::<Q>(),
+        });
+        let view = unsafe { View::<Q>::new(world.entities_meta(), world.archetypes_inner()) };
+
+        Self {
+            view,
+            #[cfg(feature = "trace")]
+            world,
+        }
+    }
+
+    pub(crate) fn try_new(world: &'w World) -> Result<Self, BorrowConflict> {
+        try_start_borrow::<Q>(world.archetypes_inner())?;
+        #[cfg(feature = "trace")]
+        world.record(crate::TraceKind::Borrow {
+            query: core::any::type_name::<Q>(),
+        });
         let view = unsafe { View::<Q>::new(world.entities_meta(), world.archetypes_inner()) };
 
-        Self { view }
+        Ok(Self {
+            view,
+            #[cfg(feature = "trace")]
+            world,
+        })
     }
 
     /// Retrieve the query results corresponding to `entity`
@@ -1735,11 +3459,52 @@ impl<'w, Q: Query> ViewBorrow<'w, Q> {
     pub fn iter_mut(&mut self) -> ViewIter<'_, Q> {
         self.view.iter_mut()
     }
+
+    /// Iterate over the query results for each of `entities`, in order
+    ///
+    /// See [`View::iter_over`]
+    pub fn iter_over<'a>(&'a self, entities: &'a [Entity]) -> ViewIterOver<'a, Q>
+    where
+        Q: QueryShared,
+    {
+        self.view.iter_over(entities)
+    }
+
+    /// Iterate over the query results for each of `entities`, in order
+    ///
+    /// See [`View::iter_over_mut`]
+    pub fn iter_over_mut<'a>(&'a mut self, entities: &'a [Entity]) -> ViewIterOver<'a, Q> {
+        self.view.iter_over_mut(entities)
+    }
+}
+
+/// Borrows the same components again, so that each clone can be handed to a different thread
+impl<'w, Q: Query + QueryShared> Clone for ViewBorrow<'w, Q> {
+    fn clone(&self) -> Self {
+        start_borrow::<Q>(self.view.archetypes);
+        #[cfg(feature = "trace")]
+        self.world.record(crate::TraceKind::Borrow {
+            query: core::any::type_name::<Q>(),
+        });
+        Self {
+            view: View {
+                meta: self.view.meta,
+                archetypes: self.view.archetypes,
+                fetch: self.view.fetch.clone(),
+            },
+            #[cfg(feature = "trace")]
+            world: self.world,
+        }
+    }
 }
 
 impl<'w, Q: Query> Drop for ViewBorrow<'w, Q> {
     fn drop(&mut self) {
-        release_borrow::<Q>(self.view.archetypes)
+        release_borrow::<Q>(self.view.archetypes);
+        #[cfg(feature = "trace")]
+        self.world.record(crate::TraceKind::Release {
+            query: core::any::type_name::<Q>(),
+        });
     }
 }
 
@@ -1772,7 +3537,17 @@ pub(crate) fn assert_distinct<const N: usize>(entities: &[Entity; N]) {
     }
 }
 
+/// Panics if `entities` contains duplicates, in O(N log N) time
+pub(crate) fn assert_distinct_slice(entities: &[Entity]) {
+    let mut entities = entities.to_vec();
+    entities.sort_unstable();
+    for pair in entities.windows(2) {
+        assert_ne!(pair[0], pair[1]);
+    }
+}
+
 /// Start the borrow
+#[cfg_attr(feature = "borrow-diagnostics", track_caller)]
 fn start_borrow<Q: Query>(archetypes: &[Archetype]) {
     for x in archetypes {
         if x.is_empty() {
@@ -1785,6 +3560,44 @@ fn start_borrow<Q: Query>(archetypes: &[Archetype]) {
     }
 }
 
+/// Like `start_borrow`, but leaves nothing borrowed and returns an error on conflict
+fn try_start_borrow<Q: Query>(archetypes: &[Archetype]) -> Result<(), BorrowConflict> {
+    for (i, x) in archetypes.iter().enumerate() {
+        if x.is_empty() {
+            continue;
+        }
+        if let Some(state) = Q::Fetch::prepare(x) {
+            if !Q::Fetch::try_borrow(x, state) {
+                release_borrow::<Q>(&archetypes[..i]);
+                return Err(BorrowConflict::new::<Q>());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Error indicating that a query or component borrow conflicts with an outstanding borrow
+///
+/// Returned by fallible alternatives to operations which would otherwise panic, such as
+/// [`World::try_query`] and [`EntityRef::try_get`](crate::EntityRef::try_get).
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct BorrowConflict(&'static str);
+
+impl BorrowConflict {
+    pub(crate) fn new<T: ?Sized>() -> Self {
+        Self(type_name::<T>())
+    }
+}
+
+impl fmt::Display for BorrowConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} conflicts with an outstanding borrow", self.0)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BorrowConflict {}
+
 /// Releases the borrow
 fn release_borrow<Q: Query>(archetypes: &[Archetype]) {
     for x in archetypes {
@@ -1807,4 +3620,20 @@ mod tests {
         assert!(Access::Read > Access::Iterate);
         assert!(Some(Access::Iterate) > None);
     }
+
+    #[test]
+    fn borrows() {
+        let borrows =
+            Borrows::of::<(&i32, Option<&mut i32>, With<&bool, &u8>, Without<(), &u16>)>();
+        assert_eq!(borrows.iter().len(), 2);
+        assert_eq!(borrows.get(TypeId::of::<i32>()), Some(Access::Write));
+        assert_eq!(borrows.get(TypeId::of::<bool>()), Some(Access::Read));
+        assert_eq!(borrows.get(TypeId::of::<u8>()), None);
+        assert_eq!(borrows.get(TypeId::of::<u16>()), None);
+
+        assert!(borrows.conflicts(&Borrows::of::<&i32>()));
+        assert!(!borrows.conflicts(&Borrows::of::<&bool>()));
+        assert!(Borrows::of::<&mut bool>().conflicts(&borrows));
+        assert!(!borrows.conflicts(&Borrows::of::<()>()));
+    }
 }
@@ -0,0 +1,151 @@
+use core::iter::FusedIterator;
+
+use crate::alloc::vec::Vec;
+use crate::query::{assert_borrow, Fetch};
+use crate::{Entity, Query, World};
+
+/// A resumable position in a pass over the entities matching a query
+///
+/// Unlike a [`QueryIter`](crate::QueryIter), a cursor does not borrow the world between uses, so
+/// iteration can be suspended, the world modified arbitrarily, and iteration resumed. This suits
+/// systems that spread expensive work over several frames.
+///
+/// Entities are visited in order of their IDs rather than by archetype, so an entity whose
+/// components change between uses is neither skipped nor visited twice. Within a single pass,
+/// every entity that matches the query throughout is visited exactly once; entities that are
+/// despawned before being reached are not visited, and entities spawned during the pass are
+/// visited only if their ID is ahead of the cursor. Each pass costs time proportional to the
+/// number of entity IDs ever allocated by the world, rather than the number of matching entities.
+///
+/// # Example
+/// ```
+/// # use hecs::*;
+/// let mut world = World::new();
+/// let a = world.spawn((1,));
+/// let b = world.spawn((2,));
+/// let c = world.spawn((3,));
+/// let mut cursor = QueryCursor::<&mut i32>::new();
+/// for (_, x) in cursor.iter(&mut world).take(1) {
+///     *x *= 10;
+/// }
+/// world.insert_one(b, true).unwrap();
+/// world.despawn(c).unwrap();
+/// for (_, x) in cursor.iter(&mut world) {
+///     *x *= 10;
+/// }
+/// assert!(cursor.iter(&mut world).next().is_some()); // Next pass
+/// assert_eq!(*world.get::<&i32>(a).unwrap(), 10);
+/// assert_eq!(*world.get::<&i32>(b).unwrap(), 20);
+/// ```
+pub struct QueryCursor<Q: Query> {
+    /// ID of the next entity to consider
+    next: u32,
+    /// ID of the world `states` was computed for
+    world: u64,
+    /// Whether each archetype matches the query, and its state if so
+    states: Vec<Option<<Q::Fetch as Fetch>::State>>,
+}
+
+impl<Q: Query> Default for QueryCursor<Q> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Q: Query> QueryCursor<Q> {
+    /// Create a cursor at the start of a pass
+    pub fn new() -> Self {
+        Self {
+            next: 0,
+            // No world has ID 0.
+            world: 0,
+            states: Vec::new(),
+        }
+    }
+
+    /// Whether no entities have been visited in the current pass
+    pub fn is_at_start(&self) -> bool {
+        self.next == 0
+    }
+
+    /// Abandon the current pass, so that the next use begins a new one
+    pub fn reset(&mut self) {
+        self.next = 0;
+    }
+
+    /// Resume the current pass over `world`
+    ///
+    /// The returned iterator ends when the pass is complete, after which the cursor is reset so
+    /// that the next call begins a new pass. Stop consuming the iterator early to suspend the
+    /// pass.
+    pub fn iter<'q>(&'q mut self, world: &'q mut World) -> QueryCursorIter<'q, Q> {
+        assert_borrow::<Q>();
+        let world = &*world;
+        let (id, _) = world.memo();
+        if self.world != id {
+            self.world = id;
+            self.states.clear();
+        }
+        let archetypes = world.archetypes_inner();
+        for archetype in &archetypes[self.states.len()..] {
+            self.states.push(Q::Fetch::prepare(archetype));
+        }
+        QueryCursorIter {
+            cursor: self,
+            world,
+            fetch: None,
+            done: false,
+        }
+    }
+}
+
+/// Iterator over the remainder of a [`QueryCursor`]'s current pass
+pub struct QueryCursorIter<'q, Q: Query> {
+    cursor: &'q mut QueryCursor<Q>,
+    world: &'q World,
+    /// Index of the archetype `Q::Fetch` was most recently executed on, and the result
+    fetch: Option<(u32, Q::Fetch)>,
+    /// Whether the pass has been completed, so the cursor must not be advanced further
+    done: bool,
+}
+
+impl<'q, Q: Query> Iterator for QueryCursorIter<'q, Q> {
+    type Item = (Entity, Q::Item<'q>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let meta = self.world.entities_meta();
+        while let Some(entry) = meta.get(self.cursor.next as usize) {
+            let id = self.cursor.next;
+            self.cursor.next += 1;
+            let location = entry.location;
+            if location.index == u32::MAX {
+                // Free or not yet flushed
+                continue;
+            }
+            let Some(state) = self.cursor.states[location.archetype as usize] else {
+                continue;
+            };
+            let fetch = match self.fetch {
+                Some((archetype, ref fetch)) if archetype == location.archetype => fetch,
+                _ => {
+                    let archetype = &self.world.archetypes_inner()[location.archetype as usize];
+                    &self
+                        .fetch
+                        .insert((location.archetype, Q::Fetch::execute(archetype, state)))
+                        .1
+                }
+            };
+            // Safety: the world is uniquely borrowed and each entity is visited at most once
+            let item = unsafe { Q::get(fetch, location.index as usize) };
+            return Some((entry.entity(id), item));
+        }
+        self.cursor.next = 0;
+        self.done = true;
+        None
+    }
+}
+
+impl<Q: Query> FusedIterator for QueryCursorIter<'_, Q> {}
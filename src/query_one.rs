@@ -1,10 +1,12 @@
 use core::marker::PhantomData;
 
-use crate::query::{assert_borrow, Fetch, With, Without};
-use crate::{Archetype, Query};
+use crate::entities::EntityMeta;
+use crate::query::{assert_borrow, assert_distinct, Fetch, With, Without};
+use crate::{Archetype, Entity, Query, QueryShared};
 
 /// A borrow of a [`World`](crate::World) sufficient to execute the query `Q` on a single entity
 pub struct QueryOne<'a, Q: Query> {
+    meta: &'a [EntityMeta],
     archetype: &'a Archetype,
     index: u32,
     borrowed: bool,
@@ -17,10 +19,11 @@ impl<'a, Q: Query> QueryOne<'a, Q> {
     /// # Safety
     ///
     /// `index` must be in-bounds for `archetype`
-    pub(crate) unsafe fn new(archetype: &'a Archetype, index: u32) -> Self {
+    pub(crate) unsafe fn new(meta: &'a [EntityMeta], archetype: &'a Archetype, index: u32) -> Self {
         assert_borrow::<Q>();
 
         Self {
+            meta,
             archetype,
             index,
             borrowed: false,
@@ -35,6 +38,7 @@ impl<'a, Q: Query> QueryOne<'a, Q> {
     /// Panics if called more than once or if it would construct a borrow that clashes with another
     /// pre-existing borrow.
     // Note that this uses self's lifetime, not 'a, for soundness.
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
     pub fn get(&mut self) -> Option<Q::Item<'_>> {
         if self.borrowed {
             panic!("called QueryOnce::get twice; construct a new query instead");
@@ -46,6 +50,47 @@ impl<'a, Q: Query> QueryOne<'a, Q> {
         unsafe { Some(Q::get(&fetch, self.index as usize)) }
     }
 
+    /// Like [`get`](Self::get), but also provide access to the query results of other entities in
+    /// the same archetype
+    ///
+    /// Useful for pairwise interactions between similar entities without borrowing again for each
+    /// one. Subject to the same restrictions as `get`.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let a = world.spawn((1, true));
+    /// let b = world.spawn((2, true));
+    /// let c = world.spawn((3,));
+    /// let mut query = world.query_one::<&mut i32>(a).unwrap();
+    /// let (x, mut siblings) = query.get_with_siblings().unwrap();
+    /// *x += *siblings.get_mut(b).unwrap();
+    /// assert!(siblings.get_mut(c).is_none()); // Different archetype
+    /// assert!(siblings.get_mut(a).is_none()); // Already borrowed by `x`
+    /// assert_eq!(*x, 3);
+    /// ```
+    // Note that this uses self's lifetime, not 'a, for soundness.
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
+    pub fn get_with_siblings(&mut self) -> Option<(Q::Item<'_>, Siblings<'_, Q>)> {
+        if self.borrowed {
+            panic!("called QueryOnce::get twice; construct a new query instead");
+        }
+        let state = Q::Fetch::prepare(self.archetype)?;
+        Q::Fetch::borrow(self.archetype, state);
+        let fetch = Q::Fetch::execute(self.archetype, state);
+        self.borrowed = true;
+        let item = unsafe { Q::get(&fetch, self.index as usize) };
+        let siblings = Siblings {
+            meta: self.meta,
+            archetype: self.archetype,
+            exclude: self.index,
+            fetch,
+            _marker: PhantomData,
+        };
+        Some((item, siblings))
+    }
+
     /// Transform the query into one that requires another query be satisfied
     ///
     /// See `QueryBorrow::with`
@@ -63,6 +108,7 @@ impl<'a, Q: Query> QueryOne<'a, Q> {
     /// Helper to change the type of the query
     fn transform<R: Query>(mut self) -> QueryOne<'a, R> {
         let x = QueryOne {
+            meta: self.meta,
             archetype: self.archetype,
             index: self.index,
             borrowed: self.borrowed,
@@ -85,3 +131,132 @@ impl<Q: Query> Drop for QueryOne<'_, Q> {
 
 unsafe impl<Q: Query> Send for QueryOne<'_, Q> {}
 unsafe impl<Q: Query> Sync for QueryOne<'_, Q> {}
+
+/// A borrow of the components of a single entity accessed by a read-only query `Q`
+///
+/// Constructed by [`World::query_one_shared`](crate::World::query_one_shared). Components are
+/// borrowed for as long as this value exists.
+pub struct QueryOneShared<'a, Q: Query + QueryShared> {
+    archetype: &'a Archetype,
+    state: <Q::Fetch as Fetch>::State,
+    fetch: Q::Fetch,
+    index: u32,
+}
+
+impl<'a, Q: Query + QueryShared> QueryOneShared<'a, Q> {
+    /// Borrow the components of the entity in `archetype` at `index`, if it satisfies `Q`
+    ///
+    /// # Safety
+    ///
+    /// `index` must be in-bounds for `archetype`
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
+    pub(crate) unsafe fn new(archetype: &'a Archetype, index: u32) -> Option<Self> {
+        let state = Q::Fetch::prepare(archetype)?;
+        Q::Fetch::borrow(archetype, state);
+        Some(Self {
+            archetype,
+            state,
+            fetch: Q::Fetch::execute(archetype, state),
+            index,
+        })
+    }
+
+    /// Get the query result
+    ///
+    /// May be called any number of times, as the query only produces shared references.
+    // Note that this uses self's lifetime, not 'a, for soundness.
+    pub fn get(&self) -> Q::Item<'_> {
+        unsafe { Q::get(&self.fetch, self.index as usize) }
+    }
+}
+
+impl<Q: Query + QueryShared> Drop for QueryOneShared<'_, Q> {
+    fn drop(&mut self) {
+        Q::Fetch::release(self.archetype, self.state);
+    }
+}
+
+unsafe impl<Q: Query + QueryShared> Send for QueryOneShared<'_, Q> {}
+unsafe impl<Q: Query + QueryShared> Sync for QueryOneShared<'_, Q> {}
+
+/// Random access to query results for entities in the same archetype as a [`QueryOne`] target
+///
+/// Obtained from [`QueryOne::get_with_siblings`]. Yields `None` for the target entity itself.
+pub struct Siblings<'q, Q: Query> {
+    meta: &'q [EntityMeta],
+    archetype: &'q Archetype,
+    exclude: u32,
+    fetch: Q::Fetch,
+    _marker: PhantomData<Q>,
+}
+
+impl<'q, Q: Query> Siblings<'q, Q> {
+    /// Retrieve the query results corresponding to `entity`
+    ///
+    /// Will yield `None` if the entity does not exist, is not in the same archetype, or is the
+    /// target of the originating [`QueryOne`].
+    pub fn get(&self, entity: Entity) -> Option<Q::Item<'_>>
+    where
+        Q: QueryShared,
+    {
+        unsafe { self.get_unchecked(entity) }
+    }
+
+    /// Retrieve the query results corresponding to `entity`
+    ///
+    /// See [`get`](Self::get).
+    pub fn get_mut(&mut self, entity: Entity) -> Option<Q::Item<'_>> {
+        unsafe { self.get_unchecked(entity) }
+    }
+
+    /// Equivalent to `get(entity).is_some()`, but does not require `Q: QueryShared`
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.index(entity).is_some()
+    }
+
+    /// Like `get_mut`, but allows checked simultaneous access to multiple entities
+    pub fn get_many_mut<const N: usize>(
+        &mut self,
+        entities: [Entity; N],
+    ) -> [Option<Q::Item<'_>>; N] {
+        assert_distinct(&entities);
+
+        let mut items = [(); N].map(|()| None);
+
+        for (item, entity) in items.iter_mut().zip(entities) {
+            unsafe {
+                *item = self.get_unchecked(entity);
+            }
+        }
+
+        items
+    }
+
+    /// # Safety
+    ///
+    /// Must not be invoked while any unique borrow of the fetched components of `entity` is live.
+    unsafe fn get_unchecked(&self, entity: Entity) -> Option<Q::Item<'_>> {
+        let index = self.index(entity)?;
+        Some(Q::get(&self.fetch, index as usize))
+    }
+
+    fn index(&self, entity: Entity) -> Option<u32> {
+        let meta = self.meta.get(entity.id as usize)?;
+        if !meta.matches(entity) {
+            return None;
+        }
+        let index = meta.location.index;
+        // Entity IDs are unique, so if this archetype stores `entity` at the location's index,
+        // then the location must refer to this archetype.
+        if index == self.exclude
+            || index >= self.archetype.len()
+            || self.archetype.entity_id(index) != entity.id
+        {
+            return None;
+        }
+        Some(index)
+    }
+}
+
+unsafe impl<Q: Query> Send for Siblings<'_, Q> where for<'a> Q::Item<'a>: Send {}
+unsafe impl<Q: Query> Sync for Siblings<'_, Q> where for<'a> Q::Item<'a>: Send {}
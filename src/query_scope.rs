@@ -0,0 +1,82 @@
+use core::any::TypeId;
+use core::cell::RefCell;
+
+use crate::alloc::vec::Vec;
+use crate::query::{assert_borrow, QueryIter, View};
+use crate::{Fetch, Query, World};
+
+/// Hands out any number of simultaneous queries on a uniquely borrowed [`World`]
+///
+/// Constructed by [`World::query_scope`]. Because the scope represents exclusive access to the
+/// world, queries skip the per-archetype dynamic borrow checks performed by [`World::query`].
+/// Instead, each query's borrows are recorded for the remainder of the scope, and a query that
+/// would uniquely borrow a component already borrowed by an earlier query, or borrow a component
+/// already uniquely borrowed, panics when it is constructed. Read-only queries therefore never
+/// conflict with one another.
+///
+/// # Example
+/// ```
+/// # use hecs::*;
+/// let mut world = World::new();
+/// world.spawn((1, 2.0f32, true));
+/// world.spawn((3, 4.0f32));
+/// world.query_scope(|scope| {
+///     let ints = scope.query::<&i32>();
+///     let floats = scope.query::<&mut f32>();
+///     for ((_, &i), (_, f)) in ints.zip(floats) {
+///         *f += i as f32;
+///     }
+///     let mut bools = scope.view::<&bool>();
+///     assert_eq!(bools.into_iter().count(), 1);
+/// });
+/// ```
+pub struct QueryScope<'w> {
+    world: &'w World,
+    /// Components borrowed by queries constructed so far, and whether each borrow is unique
+    borrows: RefCell<Vec<(TypeId, bool)>>,
+}
+
+impl<'w> QueryScope<'w> {
+    pub(crate) fn new(world: &'w mut World) -> Self {
+        Self {
+            world,
+            borrows: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Iterate over all entities that have certain components
+    ///
+    /// Panics if `Q` conflicts with a query previously constructed in this scope.
+    pub fn query<Q: Query>(&self) -> QueryIter<'_, Q> {
+        self.claim::<Q>();
+        // Safety: the scope uniquely borrows the world, and `claim` rules out aliasing between
+        // queries it hands out
+        unsafe { QueryIter::new(self.world) }
+    }
+
+    /// Provide random access to any entity for a given query
+    ///
+    /// Panics if `Q` conflicts with a query previously constructed in this scope.
+    pub fn view<Q: Query>(&self) -> View<'_, Q> {
+        self.claim::<Q>();
+        // Safety: as for `query`
+        unsafe { View::new(self.world.entities_meta(), self.world.archetypes_inner()) }
+    }
+
+    /// Record the borrows made by `Q`, panicking if any conflict with earlier ones
+    fn claim<Q: Query>(&self) {
+        assert_borrow::<Q>();
+        let mut borrows = self.borrows.borrow_mut();
+        let existing = borrows.len();
+        Q::Fetch::for_each_borrow(|id, unique| {
+            let conflict = borrows[..existing]
+                .iter()
+                .any(|&(other, other_unique)| other == id && (unique || other_unique));
+            core::assert!(
+                !conflict,
+                "query conflicts with an earlier query in this scope"
+            );
+            borrows.push((id, unique));
+        });
+    }
+}
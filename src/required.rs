@@ -0,0 +1,55 @@
+use alloc::vec::Vec;
+use core::any::TypeId;
+
+use crate::archetype::TypeIdMap;
+use crate::{Component, DefaultRegistry};
+
+/// Companion components registered through `World::require`
+#[derive(Default)]
+pub(crate) struct Requirements {
+    /// Component types required by each component type
+    edges: TypeIdMap<Vec<TypeId>>,
+    /// Constructors for every required component type
+    defaults: DefaultRegistry,
+}
+
+impl Requirements {
+    pub(crate) fn register<C: Component, R: Component + Default>(&mut self) {
+        self.defaults.register::<R>();
+        let required = self.edges.entry(TypeId::of::<C>()).or_default();
+        if !required.contains(&TypeId::of::<R>()) {
+            required.push(TypeId::of::<R>());
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.edges.is_empty()
+    }
+
+    pub(crate) fn defaults(&self) -> &DefaultRegistry {
+        &self.defaults
+    }
+
+    /// Component types required, directly or transitively, by those in `added` which are neither
+    /// in `added` nor `present`
+    pub(crate) fn missing(&self, added: &[TypeId], present: &[TypeId]) -> Vec<TypeId> {
+        let mut missing = Vec::new();
+        if !added.iter().any(|id| self.edges.contains_key(id)) {
+            return missing;
+        }
+        let mut pending = added.to_vec();
+        while let Some(id) = pending.pop() {
+            let Some(required) = self.edges.get(&id) else {
+                continue;
+            };
+            for &req in required {
+                if added.contains(&req) || present.contains(&req) || missing.contains(&req) {
+                    continue;
+                }
+                missing.push(req);
+                pending.push(req);
+            }
+        }
+        missing
+    }
+}
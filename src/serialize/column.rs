@@ -12,6 +12,13 @@
 //! user-controlled component IDs, and a `k+1`-tuple of `n`-tuples of components, such that the
 //! first `n`-tuple contains `Entity` values and the remainder each contain components of the type
 //! identified by the corresponding component ID.
+//!
+//! With the `bytemuck` feature enabled, columns of plain-old-data components may instead be
+//! written as a single byte string by [`try_serialize_pod`] and read by [`deserialize_column_pod`].
+//!
+//! [`serialize_versioned`] additionally wraps the above in a 2-tuple preceded by a `u32` version
+//! number, allowing [`deserialize_versioned`] to upgrade data written by older versions of an
+//! application.
 
 use crate::alloc::vec::Vec;
 use core::{any::type_name, cell::RefCell, fmt, marker::PhantomData};
@@ -136,6 +143,75 @@ where
     Ok(())
 }
 
+/// If `archetype` has `T` components, serialize the result of applying `transform` to them into
+/// `out`
+///
+/// Allows columns to be encoded more compactly than a tuple of individual components, e.g. by delta
+/// encoding or quantization. The result must be decoded with a matching call to
+/// [`deserialize_column_with`].
+///
+/// # Example
+/// ```
+/// # use hecs::{*, serialize::column::*};
+/// # use serde::ser::SerializeTuple;
+/// struct Index(u32);
+///
+/// fn serialize_index<S: SerializeTuple>(archetype: &Archetype, out: &mut S) -> Result<(), S::Error> {
+///     // Store each index as an offset from the previous one
+///     try_serialize_with::<Index, _, _>(archetype, out, |xs| {
+///         let mut prev = 0;
+///         xs.iter()
+///             .map(|x| {
+///                 let delta = x.0.wrapping_sub(prev);
+///                 prev = x.0;
+///                 delta
+///             })
+///             .collect::<Vec<_>>()
+///     })
+/// }
+/// ```
+pub fn try_serialize_with<T, U, S>(
+    archetype: &Archetype,
+    out: &mut S,
+    transform: impl FnOnce(&[T]) -> U,
+) -> Result<(), S::Error>
+where
+    T: Component,
+    U: Serialize,
+    S: SerializeTuple,
+{
+    if let Some(xs) = archetype.get::<&T>() {
+        out.serialize_element(&transform(&xs))?;
+    }
+    Ok(())
+}
+
+/// If `archetype` has `T` components, serialize their in-memory representation into `out` as a
+/// single byte string
+///
+/// Much faster than [`try_serialize`] for simple numeric components, as no per-component work is
+/// done. The result must be decoded with [`deserialize_column_pod`] on a platform with the same
+/// endianness, and is not portable across changes to `T`'s layout.
+#[cfg(feature = "bytemuck")]
+pub fn try_serialize_pod<T, S>(archetype: &Archetype, out: &mut S) -> Result<(), S::Error>
+where
+    T: Component + bytemuck::Pod,
+    S: SerializeTuple,
+{
+    struct Bytes<'a>(&'a [u8]);
+
+    impl Serialize for Bytes<'_> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_bytes(self.0)
+        }
+    }
+
+    if let Some(xs) = archetype.get::<&T>() {
+        out.serialize_element(&Bytes(bytemuck::cast_slice::<T, u8>(&xs)))?;
+    }
+    Ok(())
+}
+
 /// Serialize components from `collection` into a single element of `out`
 fn serialize_collection<I, S>(collection: I, out: &mut S) -> Result<(), S::Error>
 where
@@ -168,6 +244,9 @@ where
 }
 
 /// Serialize a [`World`] through a [`SerializeContext`] to a [`Serializer`]
+///
+/// Entities which have been reserved but not yet flushed are not included; see
+/// [`World::reserved_entities`].
 pub fn serialize<C, S>(world: &World, context: &mut C, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
@@ -302,6 +381,47 @@ where
     seq.end()
 }
 
+/// Serialize a [`World`] like [`serialize`], tagged with a `version` number
+///
+/// The version is passed to [`DeserializeContext::set_version`] by [`deserialize_versioned`],
+/// which should be used to read the result. Increment it whenever the serialized form of a
+/// component changes, so that old data can be recognized and upgraded.
+pub fn serialize_versioned<C, S>(
+    world: &World,
+    context: &mut C,
+    version: u32,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    C: SerializeContext,
+{
+    struct SerializeWorld<'a, C> {
+        world: &'a World,
+        ctx: RefCell<&'a mut C>,
+    }
+
+    impl<C> Serialize for SerializeWorld<'_, C>
+    where
+        C: SerializeContext,
+    {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            serialize(self.world, *self.ctx.borrow_mut(), serializer)
+        }
+    }
+
+    let mut tuple = serializer.serialize_tuple(2)?;
+    tuple.serialize_element(&version)?;
+    tuple.serialize_element(&SerializeWorld {
+        world,
+        ctx: RefCell::new(context),
+    })?;
+    tuple.end()
+}
+
 /// Implements deserialization of archetypes
 ///
 /// # Example
@@ -391,6 +511,18 @@ pub trait DeserializeContext {
     ) -> Result<(), A::Error>
     where
         A: SeqAccess<'de>;
+
+    /// Prepare to deserialize data written by [`serialize_versioned`] with `version`
+    ///
+    /// Called by [`deserialize_versioned`] before any archetypes are deserialized. Implementers
+    /// that support multiple versions should store `version` in `self` and consult it in
+    /// `deserialize_component_ids` and `deserialize_components`, e.g. to upgrade columns of a
+    /// component's old representation with [`deserialize_column_with`]. Return an error to reject
+    /// unsupported versions.
+    fn set_version<E: de::Error>(&mut self, version: u32) -> Result<(), E> {
+        let _ = version;
+        Ok(())
+    }
 }
 
 /// Deserialize a column of `entity_count` `T`s from `seq` into `out`
@@ -412,6 +544,155 @@ where
         })
 }
 
+/// Deserialize a column of `entity_count` `T`s from `seq` into `out` from a `U` produced by
+/// [`try_serialize_with`], using `transform` to undo the transformation applied there
+pub fn deserialize_column_with<'de, T, U, A, I>(
+    entity_count: u32,
+    seq: &mut A,
+    out: &mut ColumnBatchBuilder,
+    transform: impl FnOnce(U) -> I,
+) -> Result<(), A::Error>
+where
+    T: Component,
+    U: Deserialize<'de>,
+    A: SeqAccess<'de>,
+    I: IntoIterator<Item = T>,
+{
+    let encoded = seq.next_element::<U>()?.ok_or_else(|| {
+        de::Error::invalid_value(
+            Unexpected::Other("end of components"),
+            &"a column of components",
+        )
+    })?;
+    let mut writer = out.writer::<T>().expect("unexpected component type");
+    for component in transform(encoded) {
+        if writer.push(component).is_err() {
+            return Err(de::Error::invalid_value(
+                Unexpected::Other("extra component"),
+                &"a column of components",
+            ));
+        }
+    }
+    if writer.fill() < entity_count {
+        return Err(de::Error::invalid_length(
+            writer.fill() as usize,
+            &"a column of components",
+        ));
+    }
+    Ok(())
+}
+
+/// Deserialize a column of `entity_count` `T`s from `seq` into `out` from bytes written by
+/// [`try_serialize_pod`]
+#[cfg(feature = "bytemuck")]
+pub fn deserialize_column_pod<'de, T, A>(
+    entity_count: u32,
+    seq: &mut A,
+    out: &mut ColumnBatchBuilder,
+) -> Result<(), A::Error>
+where
+    T: Component + bytemuck::Pod,
+    A: SeqAccess<'de>,
+{
+    seq.next_element_seed(DeserializePodColumn::<T> {
+        entity_count,
+        out,
+        marker: PhantomData,
+    })?
+    .ok_or_else(|| {
+        de::Error::invalid_value(
+            Unexpected::Other("end of components"),
+            &"a column of components",
+        )
+    })
+}
+
+/// Deserializer for a column written by [`try_serialize_pod`]
+#[cfg(feature = "bytemuck")]
+struct DeserializePodColumn<'a, T> {
+    entity_count: u32,
+    out: &'a mut ColumnBatchBuilder,
+    marker: PhantomData<fn() -> T>,
+}
+
+#[cfg(feature = "bytemuck")]
+impl<'de, 'a, T> DeserializeSeed<'de> for DeserializePodColumn<'a, T>
+where
+    T: Component + bytemuck::Pod,
+{
+    type Value = ();
+
+    fn deserialize<D>(self, deserializer: D) -> Result<(), D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_bytes(self)
+    }
+}
+
+#[cfg(feature = "bytemuck")]
+impl<'de, 'a, T> Visitor<'de> for DeserializePodColumn<'a, T>
+where
+    T: Component + bytemuck::Pod,
+{
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "the bytes of {} {} values",
+            self.entity_count,
+            type_name::<T>()
+        )
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<(), E>
+    where
+        E: de::Error,
+    {
+        let size = core::mem::size_of::<T>();
+        if v.len() != size * self.entity_count as usize {
+            return Err(de::Error::invalid_length(v.len(), &self));
+        }
+        let mut out = self.out.writer::<T>().expect("unexpected component type");
+        // `v` needn't be suitably aligned for `T`
+        for i in 0..self.entity_count as usize {
+            let bytes = &v[i * size..(i + 1) * size];
+            if out.push(bytemuck::pod_read_unaligned(bytes)).is_err() {
+                return Err(de::Error::invalid_value(
+                    Unexpected::Other("extra component"),
+                    &self,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<(), A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        // Formats without native byte strings represent them as sequences
+        let expected = core::mem::size_of::<T>() * self.entity_count as usize;
+        // Size hints come from untrusted input, so bound the preallocation like serde's own
+        // collection impls do
+        const MAX_PREALLOC_BYTES: usize = 1024 * 1024;
+        let capacity = seq
+            .size_hint()
+            .unwrap_or(0)
+            .min(expected)
+            .min(MAX_PREALLOC_BYTES);
+        let mut bytes = Vec::with_capacity(capacity);
+        while let Some(byte) = seq.next_element::<u8>()? {
+            if bytes.len() == expected {
+                return Err(de::Error::invalid_length(expected + 1, &self));
+            }
+            bytes.push(byte);
+        }
+        self.visit_bytes(&bytes)
+    }
+}
+
 /// Deserializer for a single component type, for use in [`DeserializeContext::deserialize_components()`]
 struct DeserializeColumn<'a, T> {
     entity_count: u32,
@@ -504,6 +785,59 @@ where
     deserializer.deserialize_seq(WorldVisitor(context))
 }
 
+/// Deserialize a [`World`] written by [`serialize_versioned`]
+///
+/// The version number is passed to [`DeserializeContext::set_version`] before any archetypes are
+/// deserialized.
+pub fn deserialize_versioned<'de, C, D>(context: &mut C, deserializer: D) -> Result<World, D::Error>
+where
+    C: DeserializeContext,
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_tuple(2, VersionedWorldVisitor(context))
+}
+
+struct VersionedWorldVisitor<'a, C>(&'a mut C);
+
+impl<'de, 'a, C> Visitor<'de> for VersionedWorldVisitor<'a, C>
+where
+    C: DeserializeContext,
+{
+    type Value = World;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a version number followed by a sequence of archetypes")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<World, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let version = seq
+            .next_element::<u32>()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        self.0.set_version(version)?;
+        seq.next_element_seed(DeserializeWorld(self.0))?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))
+    }
+}
+
+struct DeserializeWorld<'a, C>(&'a mut C);
+
+impl<'de, 'a, C> DeserializeSeed<'de> for DeserializeWorld<'a, C>
+where
+    C: DeserializeContext,
+{
+    type Value = World;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<World, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize(self.0, deserializer)
+    }
+}
+
 struct WorldVisitor<'a, C>(&'a mut C);
 
 impl<'de, 'a, C> Visitor<'de> for WorldVisitor<'a, C>
@@ -773,7 +1107,7 @@ mod tests {
     impl<Q> PartialEq for SerWorld<Q> {
         fn eq(&self, other: &Self) -> bool {
             fn same_components<T: Component + PartialEq>(x: &EntityRef, y: &EntityRef) -> bool {
-                x.get::<&T>().as_ref().map(|x| &**x) == y.get::<&T>().as_ref().map(|x| &**x)
+                x.get::<&T>().as_deref() == y.get::<&T>().as_deref()
             }
 
             for (x, y) in self.0.iter().zip(other.0.iter()) {
@@ -808,7 +1142,7 @@ mod tests {
 
     impl<'a, Q: Query> Serialize for SerWorldInner<'a, Q> {
         fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
-            helpers::serialize::<Q, S>(&self.0, s)
+            helpers::serialize::<Q, S>(self.0, s)
         }
     }
 
@@ -1051,4 +1385,282 @@ mod tests {
             Token::TupleStructEnd,
         ])
     }
+
+    #[test]
+    #[rustfmt::skip]
+    fn transformed_column() {
+        use serde_test::{Token, assert_tokens};
+
+        #[derive(Debug, Copy, Clone, PartialEq)]
+        struct Index(u32);
+
+        struct DeltaContext;
+
+        impl SerializeContext for DeltaContext {
+            fn component_count(&self, archetype: &Archetype) -> usize {
+                archetype.has::<Index>() as usize
+            }
+
+            fn serialize_component_ids<S: SerializeTuple>(
+                &mut self,
+                archetype: &Archetype,
+                mut out: S,
+            ) -> Result<S::Ok, S::Error> {
+                try_serialize_id::<Index, _, _>(archetype, &0u8, &mut out)?;
+                out.end()
+            }
+
+            fn serialize_components<S: SerializeTuple>(
+                &mut self,
+                archetype: &Archetype,
+                mut out: S,
+            ) -> Result<S::Ok, S::Error> {
+                try_serialize_with::<Index, _, _>(archetype, &mut out, |xs| {
+                    let mut prev = 0;
+                    xs.iter()
+                        .map(|x| {
+                            let delta = x.0.wrapping_sub(prev);
+                            prev = x.0;
+                            delta
+                        })
+                        .collect::<Vec<_>>()
+                })?;
+                out.end()
+            }
+        }
+
+        impl DeserializeContext for DeltaContext {
+            fn deserialize_component_ids<'de, A>(
+                &mut self,
+                mut seq: A,
+            ) -> Result<ColumnBatchType, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let mut batch = ColumnBatchType::new();
+                while seq.next_element::<u8>()?.is_some() {
+                    batch.add::<Index>();
+                }
+                Ok(batch)
+            }
+
+            fn deserialize_components<'de, A>(
+                &mut self,
+                entity_count: u32,
+                mut seq: A,
+                batch: &mut ColumnBatchBuilder,
+            ) -> Result<(), A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                deserialize_column_with::<Index, Vec<u32>, _, _>(entity_count, &mut seq, batch, |xs| {
+                    let mut prev = 0;
+                    xs.into_iter().map(move |x| {
+                        prev = x.wrapping_add(prev);
+                        Index(prev)
+                    })
+                })
+            }
+        }
+
+        struct DeltaWorld(World);
+
+        impl fmt::Debug for DeltaWorld {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_map()
+                    .entries(self.0.iter().map(|e| (e.entity(), e.get::<&Index>().map(|x| *x))))
+                    .finish()
+            }
+        }
+
+        impl PartialEq for DeltaWorld {
+            fn eq(&self, other: &Self) -> bool {
+                self.0.iter().zip(other.0.iter()).all(|(x, y)| {
+                    x.entity() == y.entity() && x.get::<&Index>().as_deref() == y.get::<&Index>().as_deref()
+                })
+            }
+        }
+
+        impl Serialize for DeltaWorld {
+            fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+                serialize(&self.0, &mut DeltaContext, s)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for DeltaWorld {
+            fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+                deserialize(&mut DeltaContext, d).map(DeltaWorld)
+            }
+        }
+
+        let mut world = World::new();
+        let e0 = world.spawn((Index(100),));
+        let e1 = world.spawn((Index(101),));
+        let e2 = world.spawn((Index(103),));
+
+        assert_tokens(&DeltaWorld(world), &[
+            Token::Seq { len: Some(1) },
+
+            Token::Tuple { len: 4 },
+            Token::U32(3),
+            Token::U32(1),
+            Token::Tuple { len: 1 },
+            Token::U8(0),
+            Token::TupleEnd,
+            Token::Tuple { len: 2 },
+            Token::Tuple { len: 3 },
+            Token::U64(e0.to_bits().into()),
+            Token::U64(e1.to_bits().into()),
+            Token::U64(e2.to_bits().into()),
+            Token::TupleEnd,
+            Token::Seq { len: Some(3) },
+            Token::U32(100),
+            Token::U32(1),
+            Token::U32(2),
+            Token::SeqEnd,
+            Token::TupleEnd,
+            Token::TupleEnd,
+
+            Token::SeqEnd,
+        ])
+    }
+
+    #[test]
+    #[cfg(feature = "bytemuck")]
+    #[rustfmt::skip]
+    fn pod_column() {
+        use serde_test::{Token, assert_de_tokens, assert_de_tokens_error, assert_tokens};
+
+        struct PodContext;
+
+        impl SerializeContext for PodContext {
+            fn component_count(&self, archetype: &Archetype) -> usize {
+                archetype.has::<u32>() as usize
+            }
+
+            fn serialize_component_ids<S: SerializeTuple>(
+                &mut self,
+                archetype: &Archetype,
+                mut out: S,
+            ) -> Result<S::Ok, S::Error> {
+                try_serialize_id::<u32, _, _>(archetype, &0u8, &mut out)?;
+                out.end()
+            }
+
+            fn serialize_components<S: SerializeTuple>(
+                &mut self,
+                archetype: &Archetype,
+                mut out: S,
+            ) -> Result<S::Ok, S::Error> {
+                try_serialize_pod::<u32, _>(archetype, &mut out)?;
+                out.end()
+            }
+        }
+
+        impl DeserializeContext for PodContext {
+            fn deserialize_component_ids<'de, A>(
+                &mut self,
+                mut seq: A,
+            ) -> Result<ColumnBatchType, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let mut batch = ColumnBatchType::new();
+                while seq.next_element::<u8>()?.is_some() {
+                    batch.add::<u32>();
+                }
+                Ok(batch)
+            }
+
+            fn deserialize_components<'de, A>(
+                &mut self,
+                entity_count: u32,
+                mut seq: A,
+                batch: &mut ColumnBatchBuilder,
+            ) -> Result<(), A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                deserialize_column_pod::<u32, _>(entity_count, &mut seq, batch)
+            }
+        }
+
+        struct PodWorld(World);
+
+        impl fmt::Debug for PodWorld {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_map()
+                    .entries(self.0.iter().map(|e| (e.entity(), e.get::<&u32>().map(|x| *x))))
+                    .finish()
+            }
+        }
+
+        impl PartialEq for PodWorld {
+            fn eq(&self, other: &Self) -> bool {
+                self.0.iter().zip(other.0.iter()).all(|(x, y)| {
+                    x.entity() == y.entity() && x.get::<&u32>().as_deref() == y.get::<&u32>().as_deref()
+                })
+            }
+        }
+
+        impl Serialize for PodWorld {
+            fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+                serialize(&self.0, &mut PodContext, s)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for PodWorld {
+            fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+                deserialize(&mut PodContext, d).map(PodWorld)
+            }
+        }
+
+        let mut world = World::new();
+        let e0 = world.spawn((7u32,));
+        let e1 = world.spawn((0x0102_0304u32,));
+        const A: [u8; 4] = 7u32.to_ne_bytes();
+        const B: [u8; 4] = 0x0102_0304u32.to_ne_bytes();
+        static BYTES: [u8; 8] = [A[0], A[1], A[2], A[3], B[0], B[1], B[2], B[3]];
+        let bytes = &BYTES[..];
+
+        let header = [
+            Token::Seq { len: Some(1) },
+            Token::Tuple { len: 4 },
+            Token::U32(2),
+            Token::U32(1),
+            Token::Tuple { len: 1 },
+            Token::U8(0),
+            Token::TupleEnd,
+            Token::Tuple { len: 2 },
+            Token::Tuple { len: 2 },
+            Token::U64(e0.to_bits().into()),
+            Token::U64(e1.to_bits().into()),
+            Token::TupleEnd,
+        ];
+        let footer = [Token::TupleEnd, Token::TupleEnd, Token::SeqEnd];
+
+        let tokens = [&header[..], &[Token::Bytes(bytes)], &footer].concat();
+        assert_tokens(&PodWorld(world), &tokens);
+
+        // Formats without byte strings may represent them as sequences
+        let seq = core::iter::once(Token::Seq { len: Some(bytes.len()) })
+            .chain(bytes.iter().map(|&b| Token::U8(b)))
+            .chain([Token::SeqEnd]);
+        let tokens = header.iter().cloned().chain(seq).chain(footer).collect::<Vec<_>>();
+        let mut world = World::new();
+        world.spawn_at(e0, (7u32,));
+        world.spawn_at(e1, (0x0102_0304u32,));
+        assert_de_tokens(&PodWorld(world), &tokens);
+
+        // Size hints and excess data are not trusted; deserialization stops at the first excess byte
+        let seq = core::iter::once(Token::Seq {
+            len: Some(usize::MAX),
+        })
+        .chain(bytes.iter().chain(&[0]).map(|&b| Token::U8(b)));
+        let tokens = header.iter().cloned().chain(seq).collect::<Vec<_>>();
+        assert_de_tokens_error::<PodWorld>(
+            &tokens,
+            "invalid length 9, expected the bytes of 2 u32 values",
+        );
+    }
 }
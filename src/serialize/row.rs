@@ -8,16 +8,28 @@
 //!
 //! In terms of the serde data model, we treat a [`World`] as a map of entity IDs to user-controlled
 //! maps of component IDs to data.
+//!
+//! [`serialize_versioned`] additionally wraps the above in a 2-tuple preceded by a `u32` version
+//! number, allowing [`deserialize_versioned`] to upgrade data written by older versions of an
+//! application.
+//!
+//! [`ChunkedSerializer`] writes a world as a sequence of smaller maps of the same form, each of
+//! which may be read into an existing world by [`deserialize_chunk`].
+//!
+//! [`serialize_entity`] and [`deserialize_entity`] read and write a single entity's component map
+//! on its own, e.g. for replicating individual entities over a network.
 
 use core::{cell::RefCell, fmt};
 
 use serde::{
-    de::{DeserializeSeed, MapAccess, Visitor},
-    ser::SerializeMap,
+    de::{self, DeserializeSeed, MapAccess, SeqAccess, Visitor},
+    ser::{self, SerializeMap, SerializeTuple},
     Deserializer, Serialize, Serializer,
 };
 
-use crate::{Component, EntityBuilder, EntityRef, Query, World};
+use crate::alloc::vec::Vec;
+
+use crate::{Archetype, Component, Entity, EntityBuilder, EntityRef, Query, World};
 
 /// Implements serialization of individual entities
 ///
@@ -90,6 +102,9 @@ pub fn try_serialize<T: Component + Serialize, K: Serialize + ?Sized, S: Seriali
 }
 
 /// Serialize a [`World`] through a [`SerializeContext`] to a [`Serializer`]
+///
+/// Entities which have been reserved but not yet flushed are not included; see
+/// [`World::reserved_entities`].
 // Note: deliberately not implemented in terms of `serialize_satisying::<(), _, _>` to avoid an
 // extra loop over the archetypes
 pub fn serialize<C, S>(world: &World, context: &mut C, serializer: S) -> Result<S::Ok, S::Error>
@@ -106,6 +121,10 @@ where
 }
 
 /// Serialize all entities in a [`World`] that satisfy the given [`Query`] through a [`SerializeContext`] to a [`Serializer`]
+///
+/// Useful for omitting transient entities without first copying the rest into a scratch world,
+/// e.g. by passing `With<(), &Persistent>` as `Q` to serialize only entities having a `Persistent`
+/// marker component. Archetypes which don't satisfy `Q` are skipped entirely.
 pub fn serialize_satisfying<Q: Query, C, S>(
     world: &World,
     context: &mut C,
@@ -115,14 +134,18 @@ where
     C: SerializeContext,
     S: Serializer,
 {
+    let predicate = |x: &&Archetype| -> bool { !x.is_empty() && x.satisfies::<Q>() };
     let entity_count = world
         .archetypes()
-        .filter(|a| a.satisfies::<Q>())
+        .filter(predicate)
         .map(|a| a.len() as usize)
         .sum();
+    let meta = world.entities_meta();
     let mut seq = serializer.serialize_map(Some(entity_count))?;
-    for entity in world {
-        if entity.satisfies::<Q>() {
+    for archetype in world.archetypes().filter(predicate) {
+        for (index, &id) in archetype.ids().iter().enumerate() {
+            let entity = meta[id as usize].entity(id);
+            let entity = unsafe { EntityRef::new(meta, archetype, entity, index as u32) };
             seq.serialize_key(&entity.entity())?;
             seq.serialize_value(&SerializeComponents(RefCell::new((context, Some(entity)))))?;
         }
@@ -130,6 +153,197 @@ where
     seq.end()
 }
 
+/// Serialize a [`World`] like [`serialize`], tagged with a `version` number
+///
+/// The version is passed to [`DeserializeContext::set_version`] by [`deserialize_versioned`],
+/// which should be used to read the result. Increment it whenever the serialized form of a
+/// component changes, so that old data can be recognized and upgraded.
+pub fn serialize_versioned<C, S>(
+    world: &World,
+    context: &mut C,
+    version: u32,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    C: SerializeContext,
+    S: Serializer,
+{
+    struct SerializeWorld<'a, C> {
+        world: &'a World,
+        ctx: RefCell<&'a mut C>,
+    }
+
+    impl<C> Serialize for SerializeWorld<'_, C>
+    where
+        C: SerializeContext,
+    {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            serialize(self.world, *self.ctx.borrow_mut(), serializer)
+        }
+    }
+
+    let mut tuple = serializer.serialize_tuple(2)?;
+    tuple.serialize_element(&version)?;
+    tuple.serialize_element(&SerializeWorld {
+        world,
+        ctx: RefCell::new(context),
+    })?;
+    tuple.end()
+}
+
+/// Serializes a [`World`] a bounded number of entities at a time
+///
+/// Useful for spreading the cost of serializing a large world over several frames. Each call to
+/// [`serialize_chunk`](Self::serialize_chunk) writes a map in the same form as [`serialize`],
+/// containing the next entities in sequence. The world must not be structurally modified, e.g. by
+/// spawning or despawning entities or adding or removing components, until every chunk has been
+/// written; this is detected and reported as an error. Component values may change freely in
+/// between chunks.
+///
+/// # Example
+/// ```
+/// # struct Context;
+/// # impl hecs::serialize::row::SerializeContext for Context {
+/// #     fn serialize_entity<S>(&mut self, _: hecs::EntityRef<'_>, map: S) -> Result<S::Ok, S::Error>
+/// #     where S: serde::ser::SerializeMap { map.end() }
+/// # }
+/// use hecs::{*, serialize::row::*};
+///
+/// /// Called once per frame until `state.is_finished()`
+/// fn save_incrementally<S: serde::Serializer>(
+///     world: &World,
+///     state: &mut ChunkedSerializer,
+///     out: S,
+/// ) -> Result<S::Ok, S::Error> {
+///     state.serialize_chunk(world, &mut Context, 10_000, out)
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ChunkedSerializer {
+    /// The world and archetype generation being serialized, or `(0, 0)` before the first chunk
+    memo: (u64, u32),
+    /// Version of each archetype when the first chunk was written
+    versions: Vec<u64>,
+    /// Index of the archetype containing the next entity to write
+    archetype: usize,
+    /// Index of the next entity to write within its archetype
+    index: u32,
+}
+
+impl ChunkedSerializer {
+    /// Create a serializer that has not yet written any entities
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether every entity has been written
+    ///
+    /// Always `false` before the first chunk has been written, even if the world is empty.
+    pub fn is_finished(&self) -> bool {
+        self.memo.0 != 0 && self.archetype == self.versions.len()
+    }
+
+    /// Forget all progress, so that the next chunk starts again from the first entity
+    ///
+    /// Required before serializing a different world, or the same world after it has been
+    /// structurally modified.
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Serialize at most `max` entities following those written by previous calls
+    ///
+    /// Writes an empty map once [`is_finished`](Self::is_finished). Fails if `world` is not the
+    /// world previously serialized, or if it has been structurally modified since.
+    pub fn serialize_chunk<C, S>(
+        &mut self,
+        world: &World,
+        context: &mut C,
+        max: usize,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        C: SerializeContext,
+        S: Serializer,
+    {
+        let archetypes = world.archetypes_inner();
+        if self.memo.0 == 0 {
+            self.memo = world.memo();
+            self.versions = archetypes.iter().map(|x| x.version()).collect();
+        } else if self.memo != world.memo()
+            || archetypes
+                .iter()
+                .zip(&self.versions)
+                .any(|(x, &version)| x.version() != version)
+        {
+            return Err(ser::Error::custom(
+                "world was structurally modified during chunked serialization",
+            ));
+        }
+
+        // Find the end of the chunk
+        let (mut end_archetype, mut end_index) = (self.archetype, self.index);
+        let mut count = 0;
+        while end_archetype < archetypes.len() && count < max {
+            let available = (archetypes[end_archetype].len() - end_index) as usize;
+            let taken = available.min(max - count);
+            count += taken;
+            if taken == available {
+                end_archetype += 1;
+                end_index = 0;
+            } else {
+                end_index += taken as u32;
+            }
+        }
+
+        let meta = world.entities_meta();
+        let mut map = serializer.serialize_map(Some(count))?;
+        let (mut archetype, mut index) = (self.archetype, self.index);
+        let mut written = 0;
+        while written < count {
+            let current = &archetypes[archetype];
+            if index == current.len() {
+                archetype += 1;
+                index = 0;
+                continue;
+            }
+            let id = current.entity_id(index);
+            let entity = meta[id as usize].entity(id);
+            let entity = unsafe { EntityRef::new(meta, current, entity, index) };
+            map.serialize_key(&entity.entity())?;
+            map.serialize_value(&SerializeComponents(RefCell::new((context, Some(entity)))))?;
+            index += 1;
+            written += 1;
+        }
+        let ok = map.end()?;
+        // Only advance once the whole chunk is written, so a failed chunk can be retried
+        self.archetype = end_archetype;
+        self.index = end_index;
+        Ok(ok)
+    }
+}
+
+/// Serialize the components of a single entity through a [`SerializeContext`] to a [`Serializer`]
+///
+/// Writes the same map of component IDs to data that [`serialize`] writes for each entity, without
+/// the entity's ID. Fails if `entity` does not exist.
+pub fn serialize_entity<C, S>(
+    world: &World,
+    entity: Entity,
+    context: &mut C,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    C: SerializeContext,
+    S: Serializer,
+{
+    let entity = world.entity(entity).map_err(ser::Error::custom)?;
+    SerializeComponents(RefCell::new((context, Some(entity)))).serialize(serializer)
+}
+
 struct SerializeComponents<'a, C>(RefCell<(&'a mut C, Option<EntityRef<'a>>)>);
 
 impl<'a, C: SerializeContext> Serialize for SerializeComponents<'a, C> {
@@ -153,6 +367,59 @@ where
     deserializer.deserialize_map(WorldVisitor(context))
 }
 
+/// Spawn the entities in a chunk written by [`ChunkedSerializer`] into `world`
+///
+/// Chunks may be read in any order. Also accepts the output of [`serialize`], in which case
+/// `world` should not already contain any of the serialized entities' IDs.
+pub fn deserialize_chunk<'de, C, D>(
+    context: &mut C,
+    world: &mut World,
+    deserializer: D,
+) -> Result<(), D::Error>
+where
+    C: DeserializeContext,
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_map(ChunkVisitor(context, world))
+}
+
+/// Spawn a single entity written by [`serialize_entity`] into `world`
+///
+/// If `entity` is `Some`, the entity is spawned with that handle as if by [`World::spawn_at`];
+/// otherwise, a fresh handle is allocated. Returns the spawned entity.
+pub fn deserialize_entity<'de, C, D>(
+    context: &mut C,
+    world: &mut World,
+    entity: Option<Entity>,
+    deserializer: D,
+) -> Result<Entity, D::Error>
+where
+    C: DeserializeContext,
+    D: Deserializer<'de>,
+{
+    let mut builder = EntityBuilder::new();
+    DeserializeComponents(context, &mut builder).deserialize(deserializer)?;
+    Ok(match entity {
+        Some(entity) => {
+            world.spawn_at(entity, builder.build());
+            entity
+        }
+        None => world.spawn(builder.build()),
+    })
+}
+
+/// Deserialize a [`World`] written by [`serialize_versioned`]
+///
+/// The version number is passed to [`DeserializeContext::set_version`] before any entities are
+/// deserialized.
+pub fn deserialize_versioned<'de, C, D>(context: &mut C, deserializer: D) -> Result<World, D::Error>
+where
+    C: DeserializeContext,
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_tuple(2, VersionedWorldVisitor(context))
+}
+
 /// Implements deserialization of entities from a serde [`MapAccess`] into an [`EntityBuilder`]
 ///
 /// Data external to the [`World`] can be populated during deserialization by storing mutable
@@ -205,6 +472,58 @@ pub trait DeserializeContext {
     ) -> Result<(), M::Error>
     where
         M: MapAccess<'de>;
+
+    /// Prepare to deserialize data written by [`serialize_versioned`] with `version`
+    ///
+    /// Called by [`deserialize_versioned`] before any entities are deserialized. Implementers
+    /// that support multiple versions should store `version` in `self` and consult it in
+    /// `deserialize_entity`, e.g. to read a component's old representation and convert it to the
+    /// current one. Return an error to reject unsupported versions.
+    fn set_version<E: de::Error>(&mut self, version: u32) -> Result<(), E> {
+        let _ = version;
+        Ok(())
+    }
+}
+
+struct VersionedWorldVisitor<'a, C>(&'a mut C);
+
+impl<'de, 'a, C> Visitor<'de> for VersionedWorldVisitor<'a, C>
+where
+    C: DeserializeContext,
+{
+    type Value = World;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a version number followed by a world")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<World, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let version = seq
+            .next_element::<u32>()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        self.0.set_version(version)?;
+        seq.next_element_seed(DeserializeWorld(self.0))?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))
+    }
+}
+
+struct DeserializeWorld<'a, C>(&'a mut C);
+
+impl<'de, 'a, C> DeserializeSeed<'de> for DeserializeWorld<'a, C>
+where
+    C: DeserializeContext,
+{
+    type Value = World;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<World, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize(self.0, deserializer)
+    }
 }
 
 struct WorldVisitor<'a, C>(&'a mut C);
@@ -233,6 +552,31 @@ where
     }
 }
 
+struct ChunkVisitor<'a, C>(&'a mut C, &'a mut World);
+
+impl<'de, 'a, C> Visitor<'de> for ChunkVisitor<'a, C>
+where
+    C: DeserializeContext,
+{
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map of entities")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<(), A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut builder = EntityBuilder::new();
+        while let Some(id) = map.next_key()? {
+            map.next_value_seed(DeserializeComponents(self.0, &mut builder))?;
+            self.1.spawn_at(id, builder.build());
+        }
+        Ok(())
+    }
+}
+
 struct DeserializeComponents<'a, C>(&'a mut C, &'a mut EntityBuilder);
 
 impl<'de, 'a, C> DeserializeSeed<'de> for DeserializeComponents<'a, C>
@@ -298,7 +642,7 @@ mod tests {
     impl PartialEq for SerWorld {
         fn eq(&self, other: &Self) -> bool {
             fn same_components<T: Component + PartialEq>(x: &EntityRef, y: &EntityRef) -> bool {
-                x.get::<&T>().as_ref().map(|x| &**x) == y.get::<&T>().as_ref().map(|x| &**x)
+                x.get::<&T>().as_deref() == y.get::<&T>().as_deref()
             }
 
             for (x, y) in self.0.iter().zip(other.0.iter()) {
@@ -441,7 +785,7 @@ mod tests {
     impl<Q> PartialEq for SerSatisfyingWorld<Q> {
         fn eq(&self, other: &Self) -> bool {
             fn same_components<T: Component + PartialEq>(x: &EntityRef, y: &EntityRef) -> bool {
-                x.get::<&T>().as_ref().map(|x| &**x) == y.get::<&T>().as_ref().map(|x| &**x)
+                x.get::<&T>().as_deref() == y.get::<&T>().as_deref()
             }
 
             for (x, y) in self.0.iter().zip(other.0.iter()) {
@@ -476,7 +820,7 @@ mod tests {
 
     impl<'a, Q: Query> Serialize for SerSatisfyingWorldInner<'a, Q> {
         fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
-            crate::serialize::row::serialize_satisfying::<Q, Context, S>(&self.0, &mut Context, s)
+            crate::serialize::row::serialize_satisfying::<Q, Context, S>(self.0, &mut Context, s)
         }
     }
 
@@ -579,4 +923,370 @@ mod tests {
             Token::TupleStructEnd,
         ])
     }
+
+    /// Reads `Position`s written before they gained a Z coordinate
+    struct MigratingContext {
+        version: u32,
+    }
+
+    impl DeserializeContext for MigratingContext {
+        fn deserialize_entity<'de, M>(
+            &mut self,
+            mut map: M,
+            entity: &mut EntityBuilder,
+        ) -> Result<(), M::Error>
+        where
+            M: serde::de::MapAccess<'de>,
+        {
+            while let Some(key) = map.next_key()? {
+                match key {
+                    ComponentId::Position if self.version == 0 => {
+                        let [x, y] = map.next_value::<[f32; 2]>()?;
+                        entity.add(Position([x, y, 0.0]));
+                    }
+                    ComponentId::Position => {
+                        entity.add::<Position>(map.next_value()?);
+                    }
+                    ComponentId::Velocity => {
+                        entity.add::<Velocity>(map.next_value()?);
+                    }
+                }
+            }
+            Ok(())
+        }
+
+        fn set_version<E: serde::de::Error>(&mut self, version: u32) -> Result<(), E> {
+            if version > 1 {
+                return Err(E::custom("unsupported version"));
+            }
+            self.version = version;
+            Ok(())
+        }
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    /// Bodge into serde_test's very strict interface
+    struct SerVersionedWorld(#[serde(with = "versioned_helpers")] SerWorld);
+
+    mod versioned_helpers {
+        use super::*;
+        pub fn serialize<S: Serializer>(x: &SerWorld, s: S) -> Result<S::Ok, S::Error> {
+            crate::serialize::row::serialize_versioned(&x.0, &mut Context, 1, s)
+        }
+        pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<SerWorld, D::Error> {
+            let mut context = MigratingContext { version: 1 };
+            crate::serialize::row::deserialize_versioned(&mut context, d).map(SerWorld)
+        }
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn versioned() {
+        use serde_test::{Token, assert_de_tokens, assert_de_tokens_error, assert_tokens};
+
+        let mut world = World::new();
+        let e = world.spawn((Position([1.0, 2.0, 3.0]),));
+        assert_tokens(&SerVersionedWorld(SerWorld(world)), &[
+            Token::NewtypeStruct { name: "SerVersionedWorld" },
+            Token::Tuple { len: 2 },
+            Token::U32(1),
+            Token::Map { len: Some(1) },
+            Token::U64(e.to_bits().into()),
+            Token::Map { len: None },
+            Token::UnitVariant { name: "ComponentId", variant: "Position" },
+            Token::NewtypeStruct { name: "Position" },
+            Token::Tuple { len: 3 },
+            Token::F32(1.0),
+            Token::F32(2.0),
+            Token::F32(3.0),
+            Token::TupleEnd,
+            Token::MapEnd,
+            Token::MapEnd,
+            Token::TupleEnd,
+        ]);
+
+        let mut world = World::new();
+        let e = world.spawn((Position([1.0, 2.0, 0.0]),));
+        assert_de_tokens(&SerVersionedWorld(SerWorld(world)), &[
+            Token::NewtypeStruct { name: "SerVersionedWorld" },
+            Token::Tuple { len: 2 },
+            Token::U32(0),
+            Token::Map { len: Some(1) },
+            Token::U64(e.to_bits().into()),
+            Token::Map { len: None },
+            Token::UnitVariant { name: "ComponentId", variant: "Position" },
+            Token::Tuple { len: 2 },
+            Token::F32(1.0),
+            Token::F32(2.0),
+            Token::TupleEnd,
+            Token::MapEnd,
+            Token::MapEnd,
+            Token::TupleEnd,
+        ]);
+
+        assert_de_tokens_error::<SerVersionedWorld>(&[
+            Token::NewtypeStruct { name: "SerVersionedWorld" },
+            Token::Tuple { len: 2 },
+            Token::U32(2),
+        ], "unsupported version");
+    }
+
+    /// Writes one chunk from a shared [`ChunkedSerializer`]
+    struct Chunk<'a>(&'a World, RefCell<&'a mut ChunkedSerializer>, usize);
+
+    impl Serialize for Chunk<'_> {
+        fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+            self.1
+                .borrow_mut()
+                .serialize_chunk(self.0, &mut Context, self.2, s)
+        }
+    }
+
+    /// Reads a pair of chunks into a single world
+    #[derive(PartialEq, Debug)]
+    struct TwoChunks(SerWorld);
+
+    impl<'de> Deserialize<'de> for TwoChunks {
+        fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+            struct ChunkSeed<'a>(&'a mut World);
+
+            impl<'de> DeserializeSeed<'de> for ChunkSeed<'_> {
+                type Value = ();
+
+                fn deserialize<D: Deserializer<'de>>(self, d: D) -> Result<(), D::Error> {
+                    deserialize_chunk(&mut Context, self.0, d)
+                }
+            }
+
+            struct PairVisitor;
+
+            impl<'de> Visitor<'de> for PairVisitor {
+                type Value = TwoChunks;
+
+                fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    f.write_str("two chunks")
+                }
+
+                fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<TwoChunks, A::Error> {
+                    let mut world = World::new();
+                    seq.next_element_seed(ChunkSeed(&mut world))?;
+                    seq.next_element_seed(ChunkSeed(&mut world))?;
+                    Ok(TwoChunks(SerWorld(world)))
+                }
+            }
+
+            d.deserialize_tuple(2, PairVisitor)
+        }
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn chunked() {
+        use serde_test::{Token, assert_de_tokens, assert_ser_tokens, assert_ser_tokens_error};
+
+        let mut world = World::new();
+        let p0 = Position([0.0, 0.0, 0.0]);
+        let a = world.spawn((p0,));
+        let b = world.spawn((p0,));
+        let c = world.spawn((p0, Velocity([0.0, 0.0, 0.0])));
+
+        let position = [
+            Token::UnitVariant { name: "ComponentId", variant: "Position" },
+            Token::NewtypeStruct { name: "Position" },
+            Token::Tuple { len: 3 },
+            Token::F32(0.0),
+            Token::F32(0.0),
+            Token::F32(0.0),
+            Token::TupleEnd,
+        ];
+        let velocity = [
+            Token::UnitVariant { name: "ComponentId", variant: "Velocity" },
+            Token::NewtypeStruct { name: "Velocity" },
+            Token::Tuple { len: 3 },
+            Token::F32(0.0),
+            Token::F32(0.0),
+            Token::F32(0.0),
+            Token::TupleEnd,
+        ];
+        let entity = |e: Entity, velocity: Option<&[Token]>| {
+            let mut tokens = std::vec![Token::U64(e.to_bits().into()), Token::Map { len: None }];
+            tokens.extend_from_slice(&position);
+            tokens.extend_from_slice(velocity.unwrap_or_default());
+            tokens.push(Token::MapEnd);
+            tokens
+        };
+        let first = [
+            &[Token::Map { len: Some(2) }][..],
+            &entity(a, None),
+            &entity(b, None),
+            &[Token::MapEnd],
+        ]
+        .concat();
+        let second = [
+            &[Token::Map { len: Some(1) }][..],
+            &entity(c, Some(&velocity)),
+            &[Token::MapEnd],
+        ]
+        .concat();
+
+        let mut state = ChunkedSerializer::new();
+        assert!(!state.is_finished());
+        assert_ser_tokens(&Chunk(&world, RefCell::new(&mut state), 2), &first);
+        assert!(!state.is_finished());
+        assert_ser_tokens(&Chunk(&world, RefCell::new(&mut state), 2), &second);
+        assert!(state.is_finished());
+        assert_ser_tokens(
+            &Chunk(&world, RefCell::new(&mut state), 2),
+            &[Token::Map { len: Some(0) }, Token::MapEnd],
+        );
+
+        let tokens = [&[Token::Tuple { len: 2 }][..], &second, &first, &[Token::TupleEnd]].concat();
+        let mut expected = World::new();
+        expected.spawn_at(c, (p0, Velocity([0.0, 0.0, 0.0])));
+        expected.spawn_at(a, (p0,));
+        expected.spawn_at(b, (p0,));
+        assert_de_tokens(&TwoChunks(SerWorld(expected)), &tokens);
+
+        // Structural changes are rejected
+        state.reset();
+        let single = [
+            &[Token::Map { len: Some(1) }][..],
+            &entity(a, None),
+            &[Token::MapEnd],
+        ]
+        .concat();
+        assert_ser_tokens(&Chunk(&world, RefCell::new(&mut state), 1), &single);
+        world.despawn(a).unwrap();
+        assert_ser_tokens_error(
+            &Chunk(&world, RefCell::new(&mut state), 1),
+            &[],
+            "world was structurally modified during chunked serialization",
+        );
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn chunked_retry() {
+        use serde_test::{Token, assert_ser_tokens, assert_ser_tokens_error};
+
+        struct Failing;
+
+        impl SerializeContext for Failing {
+            fn serialize_entity<S>(&mut self, _: EntityRef<'_>, _: S) -> Result<S::Ok, S::Error>
+            where
+                S: serde::ser::SerializeMap,
+            {
+                Err(ser::Error::custom("failed"))
+            }
+        }
+
+        struct FailingChunk<'a>(&'a World, RefCell<&'a mut ChunkedSerializer>);
+
+        impl Serialize for FailingChunk<'_> {
+            fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+                self.1.borrow_mut().serialize_chunk(self.0, &mut Failing, 1, s)
+            }
+        }
+
+        let mut world = World::new();
+        let a = world.spawn((Position([0.0, 0.0, 0.0]),));
+        let mut state = ChunkedSerializer::new();
+        assert_ser_tokens_error(
+            &FailingChunk(&world, RefCell::new(&mut state)),
+            &[
+                Token::Map { len: Some(1) },
+                Token::U64(a.to_bits().into()),
+                Token::Map { len: None },
+            ],
+            "failed",
+        );
+        assert!(!state.is_finished());
+
+        // A failed chunk is written again in full
+        assert_ser_tokens(
+            &Chunk(&world, RefCell::new(&mut state), 1),
+            &[
+                Token::Map { len: Some(1) },
+                Token::U64(a.to_bits().into()),
+                Token::Map { len: None },
+                Token::UnitVariant { name: "ComponentId", variant: "Position" },
+                Token::NewtypeStruct { name: "Position" },
+                Token::Tuple { len: 3 },
+                Token::F32(0.0),
+                Token::F32(0.0),
+                Token::F32(0.0),
+                Token::TupleEnd,
+                Token::MapEnd,
+                Token::MapEnd,
+            ],
+        );
+        assert!(state.is_finished());
+    }
+
+    /// A single entity, serialized on its own and respawned at a fixed handle
+    #[derive(PartialEq, Debug)]
+    struct SingleEntity(SerWorld);
+
+    const SINGLE: Entity = match Entity::from_bits(1 << 32 | 7) {
+        Some(e) => e,
+        None => panic!(),
+    };
+
+    impl Serialize for SingleEntity {
+        fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+            serialize_entity(&self.0 .0, SINGLE, &mut Context, s)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for SingleEntity {
+        fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+            let mut world = World::new();
+            let entity = deserialize_entity(&mut Context, &mut world, Some(SINGLE), d)?;
+            assert_eq!(entity, SINGLE);
+            Ok(SingleEntity(SerWorld(world)))
+        }
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn single_entity() {
+        use serde_test::{Token, assert_ser_tokens_error, assert_tokens};
+
+        let mut world = World::new();
+        world.spawn_at(SINGLE, (Position([1.0, 2.0, 3.0]),));
+        assert_tokens(&SingleEntity(SerWorld(world)), &[
+            Token::Map { len: None },
+            Token::UnitVariant { name: "ComponentId", variant: "Position" },
+            Token::NewtypeStruct { name: "Position" },
+            Token::Tuple { len: 3 },
+            Token::F32(1.0),
+            Token::F32(2.0),
+            Token::F32(3.0),
+            Token::TupleEnd,
+            Token::MapEnd,
+        ]);
+
+        assert_ser_tokens_error(&SingleEntity(SerWorld(World::new())), &[], "no such entity");
+    }
+
+    #[cfg(feature = "json-debug")]
+    #[test]
+    fn debug_json() {
+        let mut world = World::new();
+        let a = world.spawn((Position([1.0, 2.0, 3.0]),));
+        let b = world.spawn((Position([0.0; 3]), Velocity([4.0; 3])));
+        let json = world.to_debug_json(&mut Context).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                std::string::ToString::to_string(&a.to_bits()): { "Position": [1.0, 2.0, 3.0] },
+                std::string::ToString::to_string(&b.to_bits()): {
+                    "Position": [0.0, 0.0, 0.0],
+                    "Velocity": [4.0, 4.0, 4.0],
+                },
+            })
+        );
+        let restored = World::from_debug_json(&mut Context, &json).unwrap();
+        assert_eq!(SerWorld(restored), SerWorld(world));
+    }
 }
@@ -0,0 +1,144 @@
+use core::borrow::Borrow;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::ops::Deref;
+
+use crate::alloc::sync::Arc;
+use hashbrown::HashSet;
+
+/// A component whose value may be shared by many entities
+///
+/// Stores a pointer to a value owned jointly by every entity with an equal `Shared<T>`, so that
+/// large values identical across many entities, like configuration or asset handles, occupy
+/// memory only once. Query for `&Shared<T>` as with any other component; the value is reachable
+/// through `Deref`. Shared values are immutable; replace the component to change one entity's
+/// value.
+///
+/// Obtain deduplicated values from a [`SharedPool`], or clone an existing `Shared<T>`.
+///
+/// # Example
+/// ```
+/// # use hecs::*;
+/// #[derive(Hash, PartialEq, Eq)]
+/// struct Config { speed: u32 }
+///
+/// let mut pool = SharedPool::new();
+/// let mut world = World::new();
+/// for _ in 0..100 {
+///     world.spawn((pool.get(Config { speed: 3 }),));
+/// }
+/// assert_eq!(pool.len(), 1);
+/// let total = world
+///     .query_mut::<&Shared<Config>>()
+///     .into_iter()
+///     .map(|(_, c)| c.speed)
+///     .sum::<u32>();
+/// assert_eq!(total, 300);
+/// ```
+pub struct Shared<T: ?Sized>(Arc<T>);
+
+impl<T> Shared<T> {
+    /// Share `value`, without deduplication
+    pub fn new(value: T) -> Self {
+        Self(Arc::new(value))
+    }
+}
+
+impl<T: ?Sized> Shared<T> {
+    /// Whether `a` and `b` refer to the same allocation, rather than merely equal values
+    pub fn ptr_eq(a: &Self, b: &Self) -> bool {
+        Arc::ptr_eq(&a.0, &b.0)
+    }
+
+    /// Number of `Shared` values referring to the same allocation as `this`
+    pub fn count(this: &Self) -> usize {
+        Arc::strong_count(&this.0)
+    }
+}
+
+impl<T: ?Sized> Clone for Shared<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T: ?Sized> Deref for Shared<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: ?Sized> Borrow<T> for Shared<T> {
+    fn borrow(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: ?Sized + PartialEq> PartialEq for Shared<T> {
+    fn eq(&self, other: &Self) -> bool {
+        Self::ptr_eq(self, other) || *self.0 == *other.0
+    }
+}
+
+impl<T: ?Sized + Eq> Eq for Shared<T> {}
+
+impl<T: ?Sized + Hash> Hash for Shared<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for Shared<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (*self.0).fmt(f)
+    }
+}
+
+/// Deduplicates values to be stored in [`Shared`] components
+///
+/// Values remain in the pool until [`purge`](Self::purge) is called after every entity referring
+/// to them has been despawned or had its component replaced.
+pub struct SharedPool<T> {
+    values: HashSet<Shared<T>>,
+}
+
+impl<T: Hash + Eq> SharedPool<T> {
+    /// Create an empty pool
+    pub fn new() -> Self {
+        Self {
+            values: HashSet::default(),
+        }
+    }
+
+    /// Get a `Shared` equal to `value`, reusing an existing allocation if possible
+    pub fn get(&mut self, value: T) -> Shared<T> {
+        if let Some(x) = self.values.get(&value) {
+            return x.clone();
+        }
+        let x = Shared::new(value);
+        self.values.insert(x.clone());
+        x
+    }
+
+    /// Forget values that are no longer referred to by any `Shared` outside the pool
+    pub fn purge(&mut self) {
+        self.values.retain(|x| Shared::count(x) > 1);
+    }
+
+    /// Number of distinct values in the pool
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Whether the pool contains no values
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+impl<T: Hash + Eq> Default for SharedPool<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,145 @@
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::fmt;
+
+use spin::Mutex;
+
+use crate::Entity;
+
+/// Number of events retained by a new `World`
+const DEFAULT_CAPACITY: usize = 256;
+
+/// Ring buffer of the most recent events in a `World`
+pub(crate) struct Trace {
+    inner: Mutex<TraceInner>,
+}
+
+struct TraceInner {
+    events: VecDeque<TraceEvent>,
+    capacity: usize,
+    next_time: u64,
+}
+
+impl Trace {
+    /// Append an event, discarding the oldest event if full
+    pub(crate) fn record(&self, kind: TraceKind) {
+        let mut inner = self.inner.lock();
+        let time = inner.next_time;
+        inner.next_time += 1;
+        if inner.capacity == 0 {
+            return;
+        }
+        if inner.events.len() == inner.capacity {
+            inner.events.pop_front();
+        }
+        inner.events.push_back(TraceEvent { time, kind });
+    }
+
+    /// Copy out the retained events, oldest first
+    pub(crate) fn events(&self) -> Vec<TraceEvent> {
+        self.inner.lock().events.iter().copied().collect()
+    }
+
+    pub(crate) fn set_capacity(&mut self, capacity: usize) {
+        let inner = self.inner.get_mut();
+        let excess = inner.events.len().saturating_sub(capacity);
+        inner.events.drain(..excess);
+        inner.capacity = capacity;
+    }
+}
+
+impl Default for Trace {
+    fn default() -> Self {
+        Self {
+            inner: Mutex::new(TraceInner {
+                events: VecDeque::new(),
+                capacity: DEFAULT_CAPACITY,
+                next_time: 0,
+            }),
+        }
+    }
+}
+
+/// An event recorded by a [`World`](crate::World) with the `trace` feature enabled
+///
+/// See [`World::trace`](crate::World::trace).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TraceEvent {
+    /// Position of the event in the sequence of all events recorded by the world
+    ///
+    /// Gaps indicate events that were discarded.
+    pub time: u64,
+    /// What happened
+    pub kind: TraceKind,
+}
+
+impl fmt::Display for TraceEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:>8}: {}", self.time, self.kind)
+    }
+}
+
+/// Operations recorded in a [`TraceEvent`]
+///
+/// Archetypes are identified by their index in [`World::archetypes`](crate::World::archetypes).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TraceKind {
+    /// An entity was created in an archetype
+    Spawn {
+        /// The new entity
+        entity: Entity,
+        /// Archetype the entity was created in
+        archetype: u32,
+    },
+    /// An entity was destroyed
+    Despawn {
+        /// The destroyed entity
+        entity: Entity,
+    },
+    /// Components were added to an entity, possibly moving it to another archetype
+    Insert {
+        /// The modified entity
+        entity: Entity,
+        /// Archetype the entity was in
+        from: u32,
+        /// Archetype the entity is now in
+        to: u32,
+    },
+    /// Components were removed from an entity, moving it to another archetype
+    Remove {
+        /// The modified entity
+        entity: Entity,
+        /// Archetype the entity was in
+        from: u32,
+        /// Archetype the entity is now in
+        to: u32,
+    },
+    /// A query or view dynamically borrowed components
+    Borrow {
+        /// Type name of the query
+        query: &'static str,
+    },
+    /// A query or view released its dynamic borrows
+    Release {
+        /// Type name of the query
+        query: &'static str,
+    },
+}
+
+impl fmt::Display for TraceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use TraceKind::*;
+        match *self {
+            Spawn { entity, archetype } => write!(f, "spawn {entity:?} in archetype {archetype}"),
+            Despawn { entity } => write!(f, "despawn {entity:?}"),
+            Insert { entity, from, to } => {
+                write!(f, "insert into {entity:?}, archetype {from} -> {to}")
+            }
+            Remove { entity, from, to } => {
+                write!(f, "remove from {entity:?}, archetype {from} -> {to}")
+            }
+            Borrow { query } => write!(f, "borrow {query}"),
+            Release { query } => write!(f, "release {query}"),
+        }
+    }
+}
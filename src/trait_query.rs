@@ -0,0 +1,142 @@
+use core::any::TypeId;
+use core::mem;
+
+use crate::archetype::TypeIdMap;
+use crate::{Component, Entity, World};
+
+/// Type-erased form of a `fn(&C) -> &T` supplied to [`TraitRegistry::register`]
+type Cast<T> = unsafe fn(*const u8) -> *const T;
+
+/// Set of component types which may be accessed as `T`, typically a trait object type
+///
+/// Used with [`World::query_trait`] to visit every component implementing a trait, regardless of
+/// its concrete type.
+///
+/// # Example
+/// ```
+/// # use hecs::*;
+/// trait Shape {
+///     fn area(&self) -> f32;
+/// }
+/// struct Square(f32);
+/// impl Shape for Square {
+///     fn area(&self) -> f32 { self.0 * self.0 }
+/// }
+/// struct Circle(f32);
+/// impl Shape for Circle {
+///     fn area(&self) -> f32 { 3.0 * self.0 * self.0 }
+/// }
+///
+/// let mut shapes = TraitRegistry::<dyn Shape>::new();
+/// shapes.register::<Square>(|x| x).register::<Circle>(|x| x);
+///
+/// let mut world = World::new();
+/// world.spawn((Square(2.0),));
+/// world.spawn((Circle(1.0), true));
+/// let total = world
+///     .query_trait(&shapes)
+///     .iter()
+///     .map(|(_, shape)| shape.area())
+///     .sum::<f32>();
+/// assert_eq!(total, 7.0);
+/// ```
+pub struct TraitRegistry<T: ?Sized + 'static> {
+    casts: TypeIdMap<Cast<T>>,
+}
+
+impl<T: ?Sized + 'static> TraitRegistry<T> {
+    /// Create a registry containing no component types
+    pub fn new() -> Self {
+        Self {
+            casts: TypeIdMap::default(),
+        }
+    }
+
+    /// Allow `C` components to be accessed as `T`
+    ///
+    /// `cast` is usually the identity closure `|x| x`, which performs an unsizing coercion.
+    pub fn register<C: Component>(&mut self, cast: fn(&C) -> &T) -> &mut Self {
+        // Safety: references and raw pointers to sized types have the same ABI, as do references
+        // and raw pointers to `T` with identical metadata. Pointers passed to the erased function
+        // always address a valid `C`.
+        let cast = unsafe { mem::transmute::<fn(&C) -> &T, Cast<T>>(cast) };
+        self.casts.insert(TypeId::of::<C>(), cast);
+        self
+    }
+
+    /// Whether `C` components may be accessed as `T`
+    pub fn contains<C: Component>(&self) -> bool {
+        self.casts.contains_key(&TypeId::of::<C>())
+    }
+}
+
+impl<T: ?Sized + 'static> Default for TraitRegistry<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A borrow of every component in a [`World`] registered in a [`TraitRegistry`]
+///
+/// Constructed by [`World::query_trait`]. Registered components are borrowed for as long as this
+/// value exists.
+pub struct QueryTrait<'w, T: ?Sized + 'static> {
+    world: &'w World,
+    registry: &'w TraitRegistry<T>,
+}
+
+impl<'w, T: ?Sized + 'static> QueryTrait<'w, T> {
+    pub(crate) fn new(world: &'w World, registry: &'w TraitRegistry<T>) -> Self {
+        for archetype in world.archetypes_inner() {
+            for (state, ty) in archetype.types().iter().enumerate() {
+                if registry.casts.contains_key(&ty.id()) {
+                    unsafe {
+                        archetype.borrow_raw(state);
+                    }
+                }
+            }
+        }
+        Self { world, registry }
+    }
+
+    /// Iterate over every registered component, accessed as `T`
+    ///
+    /// An entity having multiple registered components is visited once for each.
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, &'_ T)> + '_ {
+        let meta = self.world.entities_meta();
+        self.world
+            .archetypes_inner()
+            .iter()
+            .filter(|archetype| !archetype.is_empty())
+            .flat_map(move |archetype| {
+                archetype.types().iter().filter_map(move |ty| {
+                    let cast = *self.registry.casts.get(&ty.id())?;
+                    let size = ty.layout().size();
+                    let base = unsafe { archetype.get_dynamic(ty.id(), size, 0)? };
+                    Some((0..archetype.len()).map(move |index| {
+                        let id = archetype.entity_id(index);
+                        let entity = meta[id as usize].entity(id);
+                        let component = unsafe {
+                            &*cast(base.as_ptr().add(size * index as usize).cast_const())
+                        };
+                        (entity, component)
+                    }))
+                })
+            })
+            .flatten()
+    }
+}
+
+impl<T: ?Sized + 'static> Drop for QueryTrait<'_, T> {
+    fn drop(&mut self) {
+        for archetype in self.world.archetypes_inner() {
+            for (state, ty) in archetype.types().iter().enumerate() {
+                if self.registry.casts.contains_key(&ty.id()) {
+                    unsafe {
+                        archetype.release_raw(state);
+                    }
+                }
+            }
+        }
+    }
+}
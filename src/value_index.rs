@@ -0,0 +1,125 @@
+use core::any::{Any, TypeId};
+use core::hash::Hash;
+
+use crate::alloc::boxed::Box;
+use hashbrown::{HashMap, HashSet};
+
+use crate::archetype::TypeIdMap;
+use crate::entities::EntityMeta;
+use crate::{Archetype, Component, Entity};
+
+/// Indexes registered through `World::index`, kept up to date as components are added
+#[derive(Default)]
+pub(crate) struct ValueIndices {
+    indices: TypeIdMap<Box<dyn ErasedIndex>>,
+}
+
+impl ValueIndices {
+    /// Replace any existing index of `T` components with `index`
+    pub(crate) fn register<T: Component, K: Hash + Eq + Clone + Send + Sync + 'static>(
+        &mut self,
+        key: fn(&T) -> K,
+        archetypes: &[Archetype],
+        meta: &[EntityMeta],
+    ) {
+        let mut index = ValueIndex::new(key);
+        for archetype in archetypes.iter().filter(|x| x.has::<T>()) {
+            for (i, &id) in archetype.ids().iter().enumerate() {
+                index.insert(archetype, i as u32, meta[id as usize].entity(id));
+            }
+        }
+        self.indices.insert(TypeId::of::<T>(), Box::new(index));
+    }
+
+    pub(crate) fn get<T: Component>(&self) -> Option<&dyn Any> {
+        self.indices
+            .get(&TypeId::of::<T>())
+            .map(|index| index.as_any())
+    }
+
+    /// Account for the components of `entity` at `index` in `archetype` having been written
+    #[inline]
+    pub(crate) fn insert(&mut self, archetype: &Archetype, index: u32, entity: Entity) {
+        for value_index in self.indices.values_mut() {
+            value_index.insert(archetype, index, entity);
+        }
+    }
+
+    /// Forget the entity with ID `id`
+    #[inline]
+    pub(crate) fn remove(&mut self, id: u32) {
+        for value_index in self.indices.values_mut() {
+            value_index.remove(id);
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        for value_index in self.indices.values_mut() {
+            value_index.clear();
+        }
+    }
+}
+
+trait ErasedIndex: Send + Sync {
+    fn insert(&mut self, archetype: &Archetype, index: u32, entity: Entity);
+    fn remove(&mut self, id: u32);
+    fn clear(&mut self);
+    fn as_any(&self) -> &dyn Any;
+}
+
+/// Map from the keys of `T` components to the entities which had them when last written
+pub(crate) struct ValueIndex<T, K> {
+    pub(crate) key: fn(&T) -> K,
+    by_key: HashMap<K, HashSet<Entity>>,
+    by_id: HashMap<u32, (Entity, K)>,
+}
+
+impl<T: Component, K: Hash + Eq + Clone + Send + Sync + 'static> ValueIndex<T, K> {
+    fn new(key: fn(&T) -> K) -> Self {
+        Self {
+            key,
+            by_key: HashMap::default(),
+            by_id: HashMap::default(),
+        }
+    }
+
+    /// Entities that may have a `T` whose key is `key`
+    pub(crate) fn candidates(&self, key: &K) -> Option<&HashSet<Entity>> {
+        self.by_key.get(key)
+    }
+}
+
+impl<T: Component, K: Hash + Eq + Clone + Send + Sync + 'static> ErasedIndex for ValueIndex<T, K> {
+    fn insert(&mut self, archetype: &Archetype, index: u32, entity: Entity) {
+        let Some(state) = archetype.get_state::<T>() else {
+            return;
+        };
+        // Safety: `index` is in bounds, and the world is uniquely borrowed by the caller
+        let key =
+            unsafe { (self.key)(&*archetype.get_base::<T>(state).as_ptr().add(index as usize)) };
+        self.remove(entity.id());
+        self.by_key.entry(key.clone()).or_default().insert(entity);
+        self.by_id.insert(entity.id(), (entity, key));
+    }
+
+    fn remove(&mut self, id: u32) {
+        let Some((entity, key)) = self.by_id.remove(&id) else {
+            return;
+        };
+        if let Some(set) = self.by_key.get_mut(&key) {
+            set.remove(&entity);
+            if set.is_empty() {
+                self.by_key.remove(&key);
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.by_key.clear();
+        self.by_id.clear();
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
@@ -9,10 +9,10 @@ use crate::alloc::{vec, vec::Vec};
 use core::any::TypeId;
 use core::borrow::Borrow;
 use core::convert::TryFrom;
-use core::hash::{BuildHasherDefault, Hasher};
+use core::hash::{BuildHasherDefault, Hash, Hasher};
 use spin::Mutex;
 
-use core::{fmt, ptr};
+use core::{fmt, mem, ptr};
 
 #[cfg(feature = "std")]
 use std::error::Error;
@@ -20,12 +20,28 @@ use std::error::Error;
 use hashbrown::hash_map::{Entry, HashMap};
 
 use crate::alloc::boxed::Box;
-use crate::archetype::{Archetype, TypeIdMap, TypeInfo};
-use crate::entities::{Entities, EntityMeta, Location, ReserveEntitiesIterator};
+use crate::archetype::{Archetype, TypeIdHasher, TypeIdMap, TypeInfo};
+use crate::checksum;
+use crate::entities::{
+    Entities, EntityMeta, EntityStats, Location, NonContiguousReservation, ReserveEntitiesIterator,
+    ReservedBlock,
+};
+#[cfg(feature = "metrics")]
+use crate::metrics::{Metrics, QueryMetrics};
+use crate::names::NameIndex;
+use crate::observer::Observers;
 use crate::query::{assert_borrow, assert_distinct};
+use crate::required::Requirements;
+#[cfg(feature = "trace")]
+use crate::trace::{Trace, TraceEvent, TraceKind};
+use crate::value_index::{ValueIndex, ValueIndices};
 use crate::{
-    Bundle, ColumnBatch, ComponentRef, DynamicBundle, Entity, EntityRef, Fetch, MissingComponent,
-    NoSuchEntity, Query, QueryBorrow, QueryMut, QueryOne, TakenEntity, View, ViewBorrow,
+    Batch, BorrowConflict, BuilderPool, Bundle, ColumnBatch, ComponentRef, DefaultRegistry,
+    DisjointQueries, DynamicBundle, DynamicQuery, DynamicQueryBorrow, Entity, EntityBuilder,
+    EntityMap, EntityRef, EntityTranslator, Fetch, FrozenWorld, HashRegistry, MissingComponent,
+    NoSuchEntity, Query, QueryArchetype, QueryBorrow, QueryMut, QueryOne, QueryOneShared,
+    QueryScope, QueryShared, QueryTrait, RefMut, TakenBundle, TakenEntity, TraitRegistry, View,
+    ViewBorrow,
 };
 
 /// An unordered collection of entities, each having any number of distinctly typed components
@@ -45,21 +61,45 @@ use crate::{
 /// If an entity is despawned and its `Entity` handle is preserved over the course of billions of
 /// following spawns and despawns, that handle may, in rare circumstances, collide with a
 /// newly-allocated `Entity` handle. Very long-lived applications should therefore limit the period
-/// over which they may retain handles of despawned entities.
+/// over which they may retain handles of despawned entities, or construct their world with
+/// [`with_id_quarantine`](Self::with_id_quarantine) to multiply the number of despawns required
+/// before any given handle can repeat.
 pub struct World {
     entities: Entities,
     archetypes: ArchetypeSet,
     /// Maps statically-typed bundle types to archetypes
     bundle_to_archetype: TypeIdMap<u32>,
+    /// Maps precomputed hashes of dynamic bundles' type sets to archetypes, subject to collisions
+    hashed_bundle_to_archetype: HashMap<u64, u32, BuildHasherDefault<TypeIdHasher>>,
     /// Maps source archetype and static bundle types to the archetype that an entity is moved to
     /// after inserting the components from that bundle.
     insert_edges: IndexTypeIdMap<InsertTarget>,
     /// Maps source archetype and static bundle types to the archetype that an entity is moved to
     /// after removing the components from that bundle.
     remove_edges: IndexTypeIdMap<u32>,
+    names: NameIndex,
+    indices: ValueIndices,
+    /// Set by `with_capacity_limits`
+    limits: Option<CapacityLimits>,
+    observers: Observers,
+    requirements: Requirements,
+    /// Scratch space for adding required components
+    required_builder: EntityBuilder,
+    #[cfg(feature = "trace")]
+    trace: Trace,
+    #[cfg(feature = "metrics")]
+    metrics: Metrics,
     id: u64,
 }
 
+/// Bounds enforced by `World::try_spawn` and `World::try_insert`
+#[derive(Debug, Copy, Clone)]
+struct CapacityLimits {
+    entities: u32,
+    archetypes: u32,
+    per_archetype: u32,
+}
+
 impl World {
     /// Create an empty world
     pub fn new() -> Self {
@@ -73,15 +113,152 @@ impl World {
             next
         };
         Self {
-            entities: Entities::default(),
+            entities: Entities::new(id),
             archetypes: ArchetypeSet::new(),
             bundle_to_archetype: HashMap::default(),
+            hashed_bundle_to_archetype: HashMap::default(),
             insert_edges: HashMap::default(),
             remove_edges: HashMap::default(),
+            names: NameIndex::default(),
+            indices: ValueIndices::default(),
+            limits: None,
+            observers: Observers::default(),
+            requirements: Requirements::default(),
+            required_builder: EntityBuilder::new(),
+            #[cfg(feature = "trace")]
+            trace: Trace::default(),
+            #[cfg(feature = "metrics")]
+            metrics: Metrics::default(),
             id,
         }
     }
 
+    /// Create an empty world with storage for a fixed number of entities allocated up front
+    ///
+    /// Suitable for targets with little or no allocator headroom. Storage is preallocated for up
+    /// to `max_entities` entity IDs and `max_archetypes` archetypes, and each archetype is
+    /// allocated room for `per_archetype` entities when first created. [`try_spawn`] and
+    /// [`try_insert`] return [`CapacityExhausted`] rather than exceeding these limits.
+    ///
+    /// Other operations are not limited, and may allocate as usual. Avoid them, or use them only
+    /// in ways known to fit, if allocation after construction must be avoided. Spawning or
+    /// inserting a combination of component types for the first time also records it in internal
+    /// lookup tables, which may allocate.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::with_capacity_limits(2, 4, 1);
+    /// let a = world.try_spawn((1,)).unwrap();
+    /// assert_eq!(world.try_spawn((2,)), Err(CapacityExhausted));
+    /// world.try_insert(a, (true,)).unwrap();
+    /// world.try_spawn((3,)).unwrap();
+    /// assert_eq!(world.try_spawn(()), Err(CapacityExhausted));
+    /// ```
+    ///
+    /// [`try_spawn`]: Self::try_spawn
+    /// [`try_insert`]: Self::try_insert
+    pub fn with_capacity_limits(
+        max_entities: u32,
+        max_archetypes: u32,
+        per_archetype: u32,
+    ) -> Self {
+        let mut world = Self::new();
+        world.entities.reserve_exact(max_entities);
+        world
+            .archetypes
+            .reserve_exact(max_archetypes, per_archetype);
+        world.bundle_to_archetype.reserve(max_archetypes as usize);
+        world
+            .hashed_bundle_to_archetype
+            .reserve(max_archetypes as usize);
+        world.limits = Some(CapacityLimits {
+            entities: max_entities,
+            archetypes: max_archetypes,
+            per_archetype,
+        });
+        world
+    }
+
+    /// Create an empty world which delays reuse of each despawned entity's ID until at least
+    /// `len` other entities have been despawned
+    ///
+    /// Freed IDs are otherwise reused most-recently-freed first, so a workload which repeatedly
+    /// spawns and despawns a single entity cycles one ID through all of its generations. With a
+    /// quarantine of `len`, a stale handle can only collide after about `len` times as many
+    /// despawns, at the cost of `len` IDs of extra bookkeeping. A `len` of 0 matches
+    /// [`new`](Self::new).
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::with_id_quarantine(2);
+    /// let a = world.spawn(());
+    /// world.despawn(a).unwrap();
+    /// let b = world.spawn(());
+    /// assert_ne!(a.id(), b.id());
+    /// ```
+    pub fn with_id_quarantine(len: usize) -> Self {
+        let mut world = Self::new();
+        world.entities.set_quarantine_len(len);
+        world
+    }
+
+    /// Create a world containing exactly the entities of `batches`
+    ///
+    /// Returns the new world and the handles of its entities, in the order of `batches`. Each
+    /// batch whose component types differ from those of every earlier batch becomes an archetype
+    /// without copying any component data, making this the fastest way to load a scene whose
+    /// batches were built independently, e.g. in parallel.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut ty = ColumnBatchType::new();
+    /// ty.add::<i32>();
+    /// let mut batch = ty.into_batch(2);
+    /// let mut writer = batch.writer::<i32>().unwrap();
+    /// writer.push(7).unwrap();
+    /// writer.push(8).unwrap();
+    /// let (world, entities) = World::from_column_batches(vec![batch.build().unwrap()]);
+    /// assert_eq!(*world.get::<&i32>(entities[1]).unwrap(), 8);
+    /// ```
+    pub fn from_column_batches(batches: Vec<ColumnBatch>) -> (Self, Vec<Entity>) {
+        let mut world = Self::new();
+        let total = batches
+            .iter()
+            .map(|batch| batch.0.len() as usize)
+            .sum::<usize>();
+        world
+            .entities
+            .reserve(u32::try_from(total).expect("too many entities"));
+        world.archetypes.archetypes.reserve(batches.len());
+        let mut entities = Vec::with_capacity(total);
+        for batch in batches {
+            entities.extend(world.spawn_column_batch(batch));
+        }
+        (world, entities)
+    }
+
+    /// Deserialize a world from a JSON value in the form produced by
+    /// [`to_debug_json`](Self::to_debug_json)
+    ///
+    /// Convenient for writing test fixtures by hand. Entity IDs are preserved.
+    #[cfg(feature = "json-debug")]
+    pub fn from_debug_json<C: crate::serialize::row::DeserializeContext>(
+        context: &mut C,
+        value: &serde_json::Value,
+    ) -> Result<Self, serde_json::Error> {
+        crate::serialize::row::deserialize(context, value)
+    }
+
+    /// Prevent further modification, permitting fast, `Sync` read-only access
+    ///
+    /// See [`FrozenWorld`].
+    pub fn freeze(self) -> FrozenWorld {
+        FrozenWorld::new(self)
+    }
+
     /// Create an entity with certain components
     ///
     /// Returns the ID of the newly created entity.
@@ -104,7 +281,7 @@ impl World {
     pub fn spawn(&mut self, components: impl DynamicBundle) -> Entity {
         // Ensure all entity allocations are accounted for so `self.entities` can realloc if
         // necessary
-        self.flush();
+        self.flush_reserved();
 
         let entity = self.entities.alloc();
 
@@ -113,6 +290,67 @@ impl World {
         entity
     }
 
+    /// Spawn an entity with the components added by `f` to a builder borrowed from `pool`
+    ///
+    /// The builder is returned to `pool` afterwards, so once the pool has warmed up, spawning
+    /// entities with dynamic sets of components needn't allocate.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let mut pool = BuilderPool::new();
+    /// let e = world.spawn_into(&mut pool, |b| {
+    ///     b.add(123).add("abc");
+    /// });
+    /// assert_eq!(*world.get::<&i32>(e).unwrap(), 123);
+    /// assert_eq!(pool.len(), 1);
+    /// ```
+    pub fn spawn_into(
+        &mut self,
+        pool: &mut BuilderPool,
+        f: impl FnOnce(&mut EntityBuilder),
+    ) -> Entity {
+        let mut builder = pool.take();
+        f(&mut builder);
+        let entity = self.spawn(builder.build());
+        pool.give(builder);
+        entity
+    }
+
+    /// Like [`spawn`](Self::spawn), but fails rather than exceed the limits of a world constructed
+    /// with [`with_capacity_limits`](Self::with_capacity_limits)
+    ///
+    /// Equivalent to `spawn` for worlds without limits.
+    pub fn try_spawn(
+        &mut self,
+        components: impl DynamicBundle,
+    ) -> Result<Entity, CapacityExhausted> {
+        self.flush_reserved();
+
+        if let Some(limits) = self.limits {
+            if self.entities.alloc_exceeds(limits.entities) {
+                return Err(CapacityExhausted);
+            }
+            self.check_capacity(None, &components, limits)?;
+        }
+
+        let entity = self.entities.alloc();
+        self.spawn_inner(entity, components);
+        Ok(entity)
+    }
+
+    /// Create an entity with default-constructed components of each type in `types`
+    ///
+    /// Panics if any type was not registered with `registry`. See [`DefaultRegistry`].
+    pub fn spawn_defaults(&mut self, registry: &DefaultRegistry, types: &[TypeId]) -> Entity {
+        let mut builder = EntityBuilder::new();
+        for &id in types {
+            builder.add_default(registry, id);
+        }
+        self.spawn(builder.build())
+    }
+
     /// Create an entity with certain components and a specific [`Entity`] handle.
     ///
     /// See [`spawn`](Self::spawn).
@@ -137,10 +375,13 @@ impl World {
     pub fn spawn_at(&mut self, handle: Entity, components: impl DynamicBundle) {
         // Ensure all entity allocations are accounted for so `self.entities` can realloc if
         // necessary
-        self.flush();
+        self.flush_reserved();
 
         let loc = self.entities.alloc_at(handle);
         if let Some(loc) = loc {
+            self.names.remove(handle.id());
+            self.indices.remove(handle.id());
+            self.observers.changed(handle, Some(loc.archetype));
             if let Some(moved) = unsafe {
                 self.archetypes.archetypes[loc.archetype as usize].remove(loc.index, true)
             } {
@@ -152,18 +393,64 @@ impl World {
     }
 
     fn spawn_inner(&mut self, entity: Entity, components: impl DynamicBundle) {
-        let archetype_id = match components.key() {
+        if !self.requirements.is_empty() {
+            let missing = components.with_ids(|ids| self.requirements.missing(ids, &[]));
+            if !missing.is_empty() {
+                let mut builder = mem::take(&mut self.required_builder);
+                builder.add_bundle(components);
+                for id in missing {
+                    builder.add_default(self.requirements.defaults(), id);
+                }
+                self.spawn_inner(entity, builder.build());
+                self.required_builder = builder;
+                return;
+            }
+        }
+        let archetype_id = self.spawn_target(&components);
+        self.spawn_in_archetype(entity, archetype_id, components);
+    }
+
+    /// Find the archetype that an entity with exactly `components` belongs in
+    fn spawn_target(&mut self, components: &impl DynamicBundle) -> u32 {
+        match components.key() {
             Some(k) => {
                 let archetypes = &mut self.archetypes;
                 *self.bundle_to_archetype.entry(k).or_insert_with(|| {
                     components.with_ids(|ids| archetypes.get(ids, || components.type_info()))
                 })
             }
-            None => components.with_ids(|ids| self.archetypes.get(ids, || components.type_info())),
-        };
+            None => components.with_ids(|ids| match components.key_hash() {
+                Some(hash) => {
+                    let archetypes = &mut self.archetypes;
+                    match self.hashed_bundle_to_archetype.entry(hash) {
+                        Entry::Occupied(mut x)
+                            if archetypes.archetypes[*x.get() as usize].type_ids() != ids =>
+                        {
+                            // Hash collision; fall back to the full lookup
+                            let id = archetypes.get(ids, || components.type_info());
+                            x.insert(id);
+                            id
+                        }
+                        Entry::Occupied(x) => *x.get(),
+                        Entry::Vacant(x) => {
+                            *x.insert(archetypes.get(ids, || components.type_info()))
+                        }
+                    }
+                }
+                None => self.archetypes.get(ids, || components.type_info()),
+            }),
+        }
+    }
 
+    /// Store `components` for the newly allocated `entity` in the archetype `archetype_id`
+    fn spawn_in_archetype(
+        &mut self,
+        entity: Entity,
+        archetype_id: u32,
+        components: impl DynamicBundle,
+    ) {
         let archetype = &mut self.archetypes.archetypes[archetype_id as usize];
-        unsafe {
+        let index = unsafe {
             let index = archetype.allocate(entity.id);
             components.put(|ptr, ty| {
                 archetype.put_dynamic(ptr, ty.id(), ty.layout().size(), index);
@@ -172,7 +459,76 @@ impl World {
                 archetype: archetype_id,
                 index,
             };
+            index
+        };
+        self.indices.insert(archetype, index, entity);
+        self.observers.changed(entity, None);
+        #[cfg(feature = "trace")]
+        self.trace.record(TraceKind::Spawn {
+            entity,
+            archetype: archetype_id,
+        });
+    }
+
+    /// Spawn an entity for each element of `iter`, returning their handles in order
+    ///
+    /// Like calling [`spawn`](Self::spawn) for each element, but consecutive elements with the same
+    /// component types share a single archetype lookup. Suited to bundles whose types aren't known
+    /// statically, such as [`BuiltEntity`](crate::BuiltEntity)s loaded from a scene made up of a
+    /// few kinds of prefab.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let mut builders = (0..4).map(|_| EntityBuilder::new()).collect::<Vec<_>>();
+    /// let entities = world.spawn_many(builders.iter_mut().enumerate().map(|(i, builder)| {
+    ///     builder.add(i as i32);
+    ///     if i >= 2 {
+    ///         builder.add(true);
+    ///     }
+    ///     builder.build()
+    /// }));
+    /// assert_eq!(*world.get::<&i32>(entities[3]).unwrap(), 3);
+    /// assert!(world.satisfies::<&bool>(entities[2]).unwrap());
+    /// assert!(!world.satisfies::<&bool>(entities[1]).unwrap());
+    /// ```
+    pub fn spawn_many<I>(&mut self, iter: I) -> Vec<Entity>
+    where
+        I: IntoIterator,
+        I::Item: DynamicBundle,
+    {
+        self.flush_reserved();
+
+        let iter = iter.into_iter();
+        let mut entities = Vec::with_capacity(iter.size_hint().0);
+        // Component types and archetype of the previous element
+        let mut types = Vec::new();
+        let mut previous = None;
+        for components in iter {
+            if !self.requirements.is_empty() {
+                // Required components may vary with more than the bundle's own types
+                let entity = self.entities.alloc();
+                self.spawn_inner(entity, components);
+                entities.push(entity);
+                continue;
+            }
+            let archetype_id =
+                match previous.filter(|_| components.with_ids(|ids| types[..] == *ids)) {
+                    Some(archetype_id) => archetype_id,
+                    None => {
+                        let archetype_id = self.spawn_target(&components);
+                        types.clear();
+                        components.with_ids(|ids| types.extend_from_slice(ids));
+                        previous = Some(archetype_id);
+                        archetype_id
+                    }
+                };
+            let entity = self.entities.alloc();
+            self.spawn_in_archetype(entity, archetype_id, components);
+            entities.push(entity);
         }
+        entities
     }
 
     /// Efficiently spawn a large number of entities with the same statically-typed components
@@ -196,7 +552,7 @@ impl World {
     {
         // Ensure all entity allocations are accounted for so `self.entities` can realloc if
         // necessary
-        self.flush();
+        self.flush_reserved();
 
         let iter = iter.into_iter();
         let (lower, upper) = iter.size_hint();
@@ -209,6 +565,10 @@ impl World {
             entities: &mut self.entities,
             archetype_id,
             archetype: &mut self.archetypes.archetypes[archetype_id as usize],
+            indices: &mut self.indices,
+            observers: &mut self.observers,
+            #[cfg(feature = "trace")]
+            trace: &self.trace,
         }
     }
 
@@ -217,7 +577,7 @@ impl World {
     /// The fastest, but most specialized, way to spawn large numbers of entities. Useful for high
     /// performance deserialization. Supports dynamic component types.
     pub fn spawn_column_batch(&mut self, batch: ColumnBatch) -> SpawnColumnBatchIter<'_> {
-        self.flush();
+        self.flush_reserved();
 
         let archetype = batch.0;
         let entity_count = archetype.len();
@@ -232,6 +592,9 @@ impl World {
         let mut index = base as usize;
         while let Some(id) = id_alloc_clone.next(&self.entities) {
             archetype.set_entity_id(index, id);
+            let entity = self.entities.meta[id as usize].entity(id);
+            self.indices.insert(archetype, index as u32, entity);
+            self.observers.changed(entity, None);
             index += 1;
         }
 
@@ -258,6 +621,9 @@ impl World {
         for &handle in handles {
             let loc = self.entities.alloc_at(handle);
             if let Some(loc) = loc {
+                self.names.remove(handle.id());
+                self.indices.remove(handle.id());
+                self.observers.changed(handle, Some(loc.archetype));
                 if let Some(moved) = unsafe {
                     self.archetypes.archetypes[loc.archetype as usize].remove(loc.index, true)
                 } {
@@ -277,6 +643,8 @@ impl World {
                 archetype: archetype_id,
                 index: index as u32,
             };
+            self.indices.insert(archetype, index as u32, handle);
+            self.observers.changed(handle, None);
         }
     }
 
@@ -290,10 +658,37 @@ impl World {
     /// can also be done explicitly by calling [`flush`](Self::flush).
     ///
     /// Useful for reserving an ID that will later have components attached to it with `insert`.
-    pub fn reserve_entities(&self, count: u32) -> ReserveEntitiesIterator {
+    pub fn reserve_entities(&self, count: u32) -> ReserveEntitiesIterator<'_> {
         self.entities.reserve_entities(count)
     }
 
+    /// Allocate `count` entities with consecutive IDs concurrently
+    ///
+    /// Like [`reserve_entities`](Self::reserve_entities), but guarantees the IDs form a single
+    /// range, allowing external per-entity storage to be indexed by `entity.id() - base` rather
+    /// than hashed. Only never-before-used IDs can be reserved this way, so this fails if any
+    /// despawned entities' IDs have yet to be reused.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let block = world.reserve_entities_contiguous(3).unwrap();
+    /// let base = block.ids().start;
+    /// let scratch = block.iter().map(|e| e.id() - base).collect::<Vec<_>>();
+    /// assert_eq!(scratch, [0, 1, 2]);
+    ///
+    /// let a = world.spawn(());
+    /// world.despawn(a).unwrap();
+    /// assert!(world.reserve_entities_contiguous(3).is_err());
+    /// ```
+    pub fn reserve_entities_contiguous(
+        &self,
+        count: u32,
+    ) -> Result<ReservedBlock, NonContiguousReservation> {
+        self.entities.reserve_entities_contiguous(count)
+    }
+
     /// Allocate an entity ID concurrently
     ///
     /// See [`reserve_entities`](Self::reserve_entities).
@@ -305,23 +700,114 @@ impl World {
     ///
     /// See also [`take`](Self::take).
     pub fn despawn(&mut self, entity: Entity) -> Result<(), NoSuchEntity> {
-        self.flush();
+        self.flush_reserved();
         let loc = self.entities.free(entity)?;
+        self.names.remove(entity.id());
+        self.indices.remove(entity.id());
+        self.observers.changed(entity, Some(loc.archetype));
         if let Some(moved) =
             unsafe { self.archetypes.archetypes[loc.archetype as usize].remove(loc.index, true) }
         {
             self.entities.meta[moved as usize].location.index = loc.index;
         }
+        #[cfg(feature = "trace")]
+        self.trace.record(TraceKind::Despawn { entity });
         Ok(())
     }
 
+    /// Renumber live entities so that their IDs are contiguous, starting from zero
+    ///
+    /// Entity IDs are reused after despawning, but a world that once held many more entities than
+    /// it does now retains bookkeeping for every ID ever allocated, as may external arrays indexed
+    /// by [`Entity::id`]. This moves the highest-numbered entities into the lowest free IDs,
+    /// leaving their components in place, and returns a map from the old to the new handles so
+    /// that stored handles can be updated. Entities that are not renumbered keep their handles.
+    ///
+    /// Stale handles, including the old handles of renumbered entities, never refer to entities
+    /// spawned later. IDs quarantined by [`with_id_quarantine`](Self::with_id_quarantine) become
+    /// available immediately.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let entities = (0..4).map(|i| world.spawn((i,))).collect::<Vec<_>>();
+    /// world.despawn(entities[0]).unwrap();
+    /// world.despawn(entities[2]).unwrap();
+    /// let map = world.compact_ids();
+    /// assert_eq!(map.len(), 1);
+    /// let new = map.map(entities[3]);
+    /// assert_eq!(new.id(), 0);
+    /// assert_eq!(*world.get::<&i32>(new).unwrap(), 3);
+    /// assert_eq!(map.map(entities[1]), entities[1]);
+    /// ```
+    pub fn compact_ids(&mut self) -> EntityMap {
+        self.flush_reserved();
+        let moves = self.entities.compact();
+        for &(old, new, loc) in &moves {
+            let archetype = &mut self.archetypes.archetypes[loc.archetype as usize];
+            archetype.set_entity_id(loc.index as usize, new.id());
+            if let Some(name) = self.names.remove(old.id()) {
+                self.names.insert(new, name);
+            }
+            self.indices.remove(old.id());
+            self.indices.insert(archetype, loc.index, new);
+            self.observers.changed(old, Some(loc.archetype));
+            self.observers.changed(new, None);
+        }
+        moves.into_iter().map(|(old, new, _)| (old, new)).collect()
+    }
+
+    /// Invalidate every outstanding handle to `entity` while preserving its components
+    ///
+    /// Equivalent to despawning `entity` and spawning its components again at the same ID, but
+    /// without moving any data. Returns the new handle. Names and indexed values are retained.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let a = world.spawn((123,));
+    /// let b = world.invalidate(a).unwrap();
+    /// assert!(!world.contains(a));
+    /// assert_eq!(a.id(), b.id());
+    /// assert_eq!(*world.get::<&i32>(b).unwrap(), 123);
+    /// ```
+    pub fn invalidate(&mut self, entity: Entity) -> Result<Entity, NoSuchEntity> {
+        self.flush_reserved();
+        let (new, loc) = self.entities.bump_generation(entity)?;
+        // The ID is unchanged, but this signals cached views that the handle stored at this index
+        // is stale.
+        self.archetypes.archetypes[loc.archetype as usize]
+            .set_entity_id(loc.index as usize, new.id());
+        if let Some(name) = self.names.remove(entity.id()) {
+            self.names.insert(new, name);
+        }
+        self.indices.insert(
+            &self.archetypes.archetypes[loc.archetype as usize],
+            loc.index,
+            new,
+        );
+        self.observers.changed(entity, Some(loc.archetype));
+        self.observers.changed(new, None);
+        #[cfg(feature = "trace")]
+        {
+            self.trace.record(TraceKind::Despawn { entity });
+            self.trace.record(TraceKind::Spawn {
+                entity: new,
+                archetype: loc.archetype,
+            });
+        }
+        Ok(new)
+    }
+
     /// Ensure at least `additional` entities with exact components `T` can be spawned without reallocating
     pub fn reserve<T: Bundle + 'static>(&mut self, additional: u32) {
         self.reserve_inner::<T>(additional);
     }
 
     fn reserve_inner<T: Bundle + 'static>(&mut self, additional: u32) -> u32 {
-        self.flush();
+        self.flush_reserved();
         self.entities.reserve(additional);
 
         let archetypes = &mut self.archetypes;
@@ -342,10 +828,14 @@ impl World {
     ///
     /// Preserves allocated storage for reuse but clears metadata so that [`Entity`] values will repeat (in contrast to [`despawn`][Self::despawn]).
     pub fn clear(&mut self) {
-        for x in &mut self.archetypes.archetypes {
+        self.observers
+            .clear(&self.archetypes.archetypes, &self.entities);
+        for x in self.archetypes.archetypes.iter_mut() {
             x.clear();
         }
         self.entities.clear();
+        self.names.clear();
+        self.indices.clear();
     }
 
     /// Whether `entity` still exists
@@ -353,6 +843,177 @@ impl World {
         self.entities.contains(entity)
     }
 
+    /// Associate a unique name with `entity`
+    ///
+    /// Replaces any name previously given to `entity`. If another entity already had this name,
+    /// that entity's name is removed. Names are forgotten when their entity is despawned.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let a = world.spawn((123,));
+    /// world.set_name(a, "player").unwrap();
+    /// assert_eq!(world.find_by_name("player"), Some(a));
+    /// assert_eq!(world.name(a), Some("player"));
+    /// world.despawn(a).unwrap();
+    /// assert_eq!(world.find_by_name("player"), None);
+    /// ```
+    pub fn set_name(
+        &mut self,
+        entity: Entity,
+        name: impl Into<Box<str>>,
+    ) -> Result<(), NoSuchEntity> {
+        if !self.contains(entity) {
+            return Err(NoSuchEntity);
+        }
+        self.names.insert(entity, name.into());
+        Ok(())
+    }
+
+    /// Remove the name of `entity`, if any, returning it
+    pub fn remove_name(&mut self, entity: Entity) -> Option<Box<str>> {
+        if !self.contains(entity) {
+            return None;
+        }
+        self.names.remove(entity.id())
+    }
+
+    /// Look up the name given to `entity` by [`set_name`](Self::set_name)
+    pub fn name(&self, entity: Entity) -> Option<&str> {
+        if !self.contains(entity) {
+            return None;
+        }
+        self.names.get(entity.id())
+    }
+
+    /// Look up the entity given `name` by [`set_name`](Self::set_name)
+    pub fn find_by_name(&self, name: &str) -> Option<Entity> {
+        self.names.find(name)
+    }
+
+    /// Maintain an index of entities by the value of `key` applied to their `T` component
+    ///
+    /// Replaces any existing index of `T` components. The index is updated whenever a `T` is
+    /// written by spawning or inserting, so [`find_by`](Self::find_by) need not visit every `T`.
+    /// Modifying a `T` in place, e.g. through a query, is not observed: call
+    /// [`reindex`](Self::reindex) afterwards for the entity to be found by its new key.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// struct Team(u32);
+    ///
+    /// let mut world = World::new();
+    /// world.index::<Team, _>(|t| t.0);
+    /// let a = world.spawn((Team(1),));
+    /// let b = world.spawn((Team(2), "abc"));
+    /// assert_eq!(world.find_by::<Team>(&1u32).unwrap().collect::<Vec<_>>(), [a]);
+    ///
+    /// world.get::<&mut Team>(b).unwrap().0 = 1;
+    /// world.reindex(b).unwrap();
+    /// assert_eq!(world.find_by::<Team>(&2u32).unwrap().count(), 0);
+    /// assert_eq!(world.find_by::<Team>(&1u32).unwrap().count(), 2);
+    /// ```
+    pub fn index<T: Component, K: Hash + Eq + Clone + Send + Sync + 'static>(
+        &mut self,
+        key: fn(&T) -> K,
+    ) {
+        self.flush_reserved();
+        self.indices
+            .register(key, &self.archetypes.archetypes, &self.entities.meta);
+    }
+
+    /// Entities whose `T` component has the key `key`, according to the index registered with
+    /// [`index`](Self::index)
+    ///
+    /// Entities are yielded in arbitrary order. Each candidate's current `T` is checked against
+    /// `key`, so entities which have since lost their `T` or had it modified to a different key
+    /// are never yielded.
+    ///
+    /// Fails if no index of `T` components has keys of the same type as `key`. Panics if a `T` is
+    /// uniquely borrowed.
+    pub fn find_by<T: Component>(
+        &self,
+        key: &(impl Hash + Eq + Clone + Send + Sync + 'static),
+    ) -> Result<impl Iterator<Item = Entity> + '_, NoSuchIndex> {
+        self.find_by_inner::<T, _>(key)
+    }
+
+    fn find_by_inner<T: Component, K: Hash + Eq + Clone + Send + Sync + 'static>(
+        &self,
+        key: &K,
+    ) -> Result<impl Iterator<Item = Entity> + '_, NoSuchIndex> {
+        let index = self
+            .indices
+            .get::<T>()
+            .and_then(|x| x.downcast_ref::<ValueIndex<T, K>>())
+            .ok_or(NoSuchIndex {
+                component: core::any::type_name::<T>(),
+                key: core::any::type_name::<K>(),
+            })?;
+        let key = key.clone();
+        Ok(index
+            .candidates(&key)
+            .into_iter()
+            .flatten()
+            .copied()
+            .filter(move |&entity| {
+                self.get::<&T>(entity)
+                    .map_or(false, |x| (index.key)(&x) == key)
+            }))
+    }
+
+    /// Update indices registered with [`index`](Self::index) to reflect `entity`'s current
+    /// components
+    pub fn reindex(&mut self, entity: Entity) -> Result<(), NoSuchEntity> {
+        let loc = self.entities.get(entity)?;
+        self.indices.insert(
+            &self.archetypes.archetypes[loc.archetype as usize],
+            loc.index,
+            entity,
+        );
+        Ok(())
+    }
+
+    /// Overwrite every `T` component in the world with a clone of `value`
+    ///
+    /// Much faster than assigning to each component through a query, as each archetype's `T`s are
+    /// written as a single slice. Indices of `T` registered with [`index`](Self::index) are
+    /// updated.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let a = world.spawn((1.5f32,));
+    /// let b = world.spawn((2.5f32, true));
+    /// world.fill(0.0f32);
+    /// assert_eq!(*world.get::<&f32>(a).unwrap(), 0.0);
+    /// assert_eq!(*world.get::<&f32>(b).unwrap(), 0.0);
+    /// ```
+    pub fn fill<T: Component + Clone>(&mut self, value: T) {
+        let reindex = self.indices.get::<T>().is_some();
+        for archetype in &self.archetypes.archetypes {
+            let Some(state) = archetype.get_state::<T>() else {
+                continue;
+            };
+            let column = unsafe {
+                core::slice::from_raw_parts_mut(
+                    archetype.get_base::<T>(state).as_ptr(),
+                    archetype.len() as usize,
+                )
+            };
+            column.fill(value.clone());
+            if reindex {
+                for (index, &id) in archetype.ids().iter().enumerate() {
+                    let entity = self.entities.meta[id as usize].entity(id);
+                    self.indices.insert(archetype, index as u32, entity);
+                }
+            }
+        }
+    }
+
     /// Efficiently iterate over all entities that have certain components, using dynamic borrow
     /// checking
     ///
@@ -399,11 +1060,117 @@ impl World {
         QueryBorrow::new(self)
     }
 
+    /// Like [`query`](Self::query), but borrows immediately, returning an error rather than
+    /// panicking if the borrow conflicts with another
+    ///
+    /// Lets long-running applications skip work that can't proceed, rather than crash.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// world.spawn((123, true));
+    /// let mut ints = world.query::<&mut i32>();
+    /// let _ints = ints.iter();
+    /// assert!(world.try_query::<(&i32, &bool)>().is_err());
+    /// assert!(world.try_query::<&bool>().is_ok());
+    /// ```
+    pub fn try_query<Q: Query>(&self) -> Result<QueryBorrow<'_, Q>, BorrowConflict> {
+        QueryBorrow::try_new(self)
+    }
+
+    /// Render every entity and its components as a JSON value, for inspection while debugging
+    ///
+    /// Produces an object mapping each entity's ID, as given by [`Entity::to_bits`], to an object
+    /// of the components written by `context`. Component keys must serialize as strings or
+    /// integers. See [`serialize::row`](crate::serialize::row) for details of the format.
+    #[cfg(feature = "json-debug")]
+    pub fn to_debug_json<C: crate::serialize::row::SerializeContext>(
+        &self,
+        context: &mut C,
+    ) -> Result<serde_json::Value, serde_json::Error> {
+        crate::serialize::row::serialize(self, context, serde_json::value::Serializer)
+    }
+
+    /// Feed the IDs of all entities and the values of their components registered in `registry`
+    /// into `state`, in a deterministic order
+    ///
+    /// Entities are visited in order of ID, and their components in registration order, so two
+    /// worlds with identical contents produce identical hashes regardless of spawn order or
+    /// archetype layout. Useful for detecting desynchronization between peers in lockstep
+    /// simulations. To compare checksums across machines, use a `Hasher` whose output is portable,
+    /// and components whose `Hash` implementations are too (`usize` is not).
+    ///
+    /// Registered components are borrowed immutably for the duration of the call. See
+    /// [`HashRegistry`] for an example.
+    pub fn checksum(&self, registry: &HashRegistry, state: &mut impl Hasher) {
+        checksum::checksum(self, registry, state);
+    }
+
+    /// Borrow every component registered in `registry`, accessed as `T`
+    ///
+    /// Useful for visiting all components implementing a trait regardless of their concrete type.
+    /// Registered components are borrowed immutably until the returned [`QueryTrait`] is dropped.
+    /// See [`TraitRegistry`] for an example.
+    pub fn query_trait<'a, T: ?Sized + 'static>(
+        &'a self,
+        registry: &'a TraitRegistry<T>,
+    ) -> QueryTrait<'a, T> {
+        QueryTrait::new(self, registry)
+    }
+
+    /// Borrow the components matched by a query constructed at runtime
+    ///
+    /// Components requested for reading or writing are borrowed until the returned
+    /// [`DynamicQueryBorrow`] is dropped. See [`DynamicQuery`] for an example.
+    ///
+    /// Panics if the borrow would conflict with an existing borrow.
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
+    pub fn query_dynamic<'a>(&'a self, query: &DynamicQuery) -> DynamicQueryBorrow<'a> {
+        DynamicQueryBorrow::new(self, query)
+    }
+
     /// Provide random access to any entity for a given Query.
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
     pub fn view<Q: Query>(&self) -> ViewBorrow<'_, Q> {
         ViewBorrow::new(self)
     }
 
+    /// Like [`view`](Self::view), but returns an error rather than panicking if the borrow
+    /// conflicts with another
+    pub fn try_view<Q: Query>(&self) -> Result<ViewBorrow<'_, Q>, BorrowConflict> {
+        ViewBorrow::try_new(self)
+    }
+
+    /// Provide random access to any entity for a read-only query
+    ///
+    /// Like [`view`](Self::view), but restricted to queries which borrow components only shared.
+    /// Any number of such views may coexist, and each is `Send + Sync` when its components are,
+    /// and cheaply cloned, so one may be handed to each of several worker threads.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let entities = (0..8).map(|i| world.spawn((i,))).collect::<Vec<_>>();
+    /// let view = world.view_shared::<&i32>();
+    /// let sum = std::thread::scope(|s| {
+    ///     let workers = entities
+    ///         .chunks(4)
+    ///         .map(|chunk| {
+    ///             let view = view.clone();
+    ///             s.spawn(move || chunk.iter().map(|&e| *view.get(e).unwrap()).sum::<i32>())
+    ///         })
+    ///         .collect::<Vec<_>>();
+    ///     workers.into_iter().map(|w| w.join().unwrap()).sum::<i32>()
+    /// });
+    /// assert_eq!(sum, 28);
+    /// ```
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
+    pub fn view_shared<Q: Query + QueryShared>(&self) -> ViewBorrow<'_, Q> {
+        ViewBorrow::new(self)
+    }
+
     /// Provide random access to any entity for a given Query on a uniquely
     /// borrowed world. Like [`view`](Self::view), but faster because dynamic borrow checks can be skipped.
     pub fn view_mut<Q: Query>(&mut self) -> View<'_, Q> {
@@ -411,6 +1178,27 @@ impl World {
         unsafe { View::<Q>::new(self.entities_meta(), self.archetypes_inner()) }
     }
 
+    /// Provide random access to the results of several queries at once on a uniquely borrowed
+    /// world
+    ///
+    /// Like [`view_mut`](Self::view_mut), but for a tuple of queries. Panics if a query uniquely
+    /// borrows a component that another query also borrows.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let a = world.spawn((1, 2.0f32));
+    /// let b = world.spawn((3, 4.0f32));
+    /// let (mut ints, mut floats) = world.views_mut::<(&i32, &mut f32)>();
+    /// *floats.get_mut(a).unwrap() += *ints.get_mut(b).unwrap() as f32;
+    /// assert_eq!(*floats.get_mut(a).unwrap(), 5.0);
+    /// ```
+    pub fn views_mut<V: DisjointQueries>(&mut self) -> V::Views<'_> {
+        V::check();
+        unsafe { V::views(self) }
+    }
+
     /// Query a uniquely borrowed world
     ///
     /// Like [`query`](Self::query), but faster because dynamic borrow checks can be skipped. Note
@@ -420,10 +1208,104 @@ impl World {
         QueryMut::new(self)
     }
 
+    /// Run `f` with a [`QueryScope`] that can construct many simultaneous queries on a uniquely
+    /// borrowed world
+    ///
+    /// Like [`query_mut`](Self::query_mut), dynamic borrow checks are skipped; conflicts between
+    /// queries within the scope are instead detected when each query is constructed.
+    pub fn query_scope<R>(&mut self, f: impl FnOnce(&QueryScope<'_>) -> R) -> R {
+        f(&QueryScope::new(self))
+    }
+
+    /// The most recent events in this world, oldest first
+    ///
+    /// Records spawns, despawns, component insertions and removals that change an entity's
+    /// archetype, and dynamic borrows taken and released by [`query`](Self::query) and
+    /// [`view`](Self::view). Handy to dump after a borrow conflict panic or when diagnosing
+    /// unexpected archetype churn. Events are ordered by a per-world sequence number rather than
+    /// wall-clock time, since no clock is available in `no_std` environments.
+    ///
+    /// By default, the most recent 256 events are retained; see
+    /// [`set_trace_capacity`](Self::set_trace_capacity).
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let e = world.spawn((123,));
+    /// world.insert_one(e, true).unwrap();
+    /// world.despawn(e).unwrap();
+    /// let kinds = world.trace().into_iter().map(|x| x.kind).collect::<Vec<_>>();
+    /// assert!(matches!(kinds[..], [
+    ///     TraceKind::Spawn { .. },
+    ///     TraceKind::Insert { .. },
+    ///     TraceKind::Despawn { .. },
+    /// ]));
+    /// ```
+    #[cfg(feature = "trace")]
+    pub fn trace(&self) -> Vec<TraceEvent> {
+        self.trace.events()
+    }
+
+    /// Retain at most `capacity` events for [`trace`](Self::trace), discarding the oldest
+    ///
+    /// A capacity of 0 disables recording.
+    #[cfg(feature = "trace")]
+    pub fn set_trace_capacity(&mut self, capacity: usize) {
+        self.trace.set_capacity(capacity);
+    }
+
+    #[cfg(feature = "trace")]
+    pub(crate) fn record(&self, kind: TraceKind) {
+        self.trace.record(kind);
+    }
+
+    /// Counters recorded for each query type iterated on this world, in arbitrary order
+    ///
+    /// Queries are identified by the `TypeId` of their [`Fetch`], so e.g. `&'a T` for every `'a`
+    /// is counted together. An execution is counted each time iteration of a [`QueryIter`] begins,
+    /// as created by [`QueryBorrow::iter`], [`query_mut`](Self::query_mut), and similar. Random
+    /// access through views and single-entity queries isn't counted.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// world.spawn((1, true));
+    /// world.spawn((2,));
+    /// for _ in 0..3 {
+    ///     world.query::<&i32>().iter().count();
+    /// }
+    /// let metrics = world.query_metrics();
+    /// assert_eq!(metrics.len(), 1);
+    /// assert_eq!(metrics[0].executions, 3);
+    /// assert_eq!(metrics[0].archetypes, 6);
+    /// assert_eq!(metrics[0].entities, 6);
+    /// ```
+    #[cfg(feature = "metrics")]
+    pub fn query_metrics(&self) -> Vec<QueryMetrics> {
+        self.metrics.snapshot()
+    }
+
+    /// Reset all counters reported by [`query_metrics`](Self::query_metrics)
+    #[cfg(feature = "metrics")]
+    pub fn reset_query_metrics(&mut self) {
+        self.metrics.clear();
+    }
+
+    #[cfg(feature = "metrics")]
+    pub(crate) fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
     pub(crate) fn memo(&self) -> (u64, u32) {
         (self.id, self.archetypes.generation())
     }
 
+    pub(crate) fn location(&self, entity: Entity) -> Result<Location, NoSuchEntity> {
+        self.entities.get(entity)
+    }
+
     #[inline(always)]
     pub(crate) fn entities_meta(&self) -> &[EntityMeta] {
         &self.entities.meta
@@ -460,12 +1342,39 @@ impl World {
         let loc = self.entities.get(entity)?;
         Ok(unsafe {
             QueryOne::new(
+                &self.entities.meta,
                 &self.archetypes.archetypes[loc.archetype as usize],
                 loc.index,
             )
         })
     }
 
+    /// Borrow the results of a read-only query for a single entity
+    ///
+    /// Like [`query_one`](Self::query_one), but borrows immediately, so the results can be
+    /// retrieved from the returned [`QueryOneShared`] with no further checks. Fails if the entity
+    /// does not exist or does not satisfy `Q`.
+    ///
+    /// Panics if `Q` conflicts with an existing unique borrow.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let a = world.spawn((123, true, "abc"));
+    /// let query = world.query_one_shared::<(&i32, &bool)>(a).unwrap();
+    /// assert_eq!(query.get(), (&123, &true));
+    /// ```
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
+    pub fn query_one_shared<Q: Query + QueryShared>(
+        &self,
+        entity: Entity,
+    ) -> Result<QueryOneShared<'_, Q>, QueryOneError> {
+        let loc = self.entities.get(entity)?;
+        let archetype = &self.archetypes.archetypes[loc.archetype as usize];
+        unsafe { QueryOneShared::new(archetype, loc.index).ok_or(QueryOneError::Unsatisfied) }
+    }
+
     /// Query a single entity in a uniquely borrowed world
     ///
     /// Like [`query_one`](Self::query_one), but faster because dynamic borrow checks can be
@@ -505,7 +1414,37 @@ impl World {
         })
     }
 
+    /// Uniquely borrow the `T` component of each of a fixed number of distinct entities
+    ///
+    /// Short-hand for [`query_many_mut::<&mut T, N>`](Self::query_many_mut) that reports which
+    /// component was missing. Panics if the same entity occurs more than once.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let a = world.spawn((1,));
+    /// let b = world.spawn((2, true));
+    /// let c = world.spawn((true,));
+    /// let [x, y, z] = world.get_many_mut::<i32, 3>([a, b, c]);
+    /// core::mem::swap(x.unwrap(), y.unwrap());
+    /// assert!(z.is_err());
+    /// assert_eq!(*world.get::<&i32>(a).unwrap(), 2);
+    /// ```
+    pub fn get_many_mut<T: Component, const N: usize>(
+        &mut self,
+        entities: [Entity; N],
+    ) -> [Result<&mut T, ComponentError>; N] {
+        self.query_many_mut::<&mut T, N>(entities).map(|result| {
+            result.map_err(|e| match e {
+                QueryOneError::NoSuchEntity => ComponentError::NoSuchEntity,
+                QueryOneError::Unsatisfied => MissingComponent::new::<T>().into(),
+            })
+        })
+    }
+
     /// Short-hand for [`entity`](Self::entity) followed by [`EntityRef::get`]
+    #[cfg_attr(feature = "borrow-diagnostics", track_caller)]
     pub fn get<'a, T: ComponentRef<'a>>(
         &'a self,
         entity: Entity,
@@ -528,6 +1467,7 @@ impl World {
         let loc = self.entities.get(entity)?;
         unsafe {
             Ok(EntityRef::new(
+                &self.entities.meta,
                 &self.archetypes.archetypes[loc.archetype as usize],
                 entity,
                 loc.index,
@@ -537,6 +1477,8 @@ impl World {
 
     /// Given an id obtained from [`Entity::id`], reconstruct the still-live [`Entity`].
     ///
+    /// See [`resolve_id`](Self::resolve_id) for a safe alternative.
+    ///
     /// # Safety
     ///
     /// `id` must correspond to a currently live [`Entity`]. A despawned or never-allocated `id`
@@ -545,6 +1487,25 @@ impl World {
         self.entities.resolve_unknown_gen(id)
     }
 
+    /// Given an id obtained from [`Entity::id`], reconstruct the [`Entity`] currently using it
+    ///
+    /// Returns `None` if no live entity has `id`. Useful for external systems that store raw `u32`
+    /// IDs. Note that if the original entity was despawned and its ID reused, the new entity is
+    /// returned.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let a = world.spawn(());
+    /// assert_eq!(world.resolve_id(a.id()), Some(a));
+    /// world.despawn(a).unwrap();
+    /// assert_eq!(world.resolve_id(a.id()), None);
+    /// ```
+    pub fn resolve_id(&self, id: u32) -> Option<Entity> {
+        self.entities.resolve_id(id)
+    }
+
     /// Iterate over all entities in the world
     ///
     /// Entities are yielded in arbitrary order. Prefer [`query`](Self::query) for better
@@ -562,7 +1523,78 @@ impl World {
     /// assert!(ids.contains(&b));
     /// ```
     pub fn iter(&self) -> Iter<'_> {
-        Iter::new(&self.archetypes.archetypes, &self.entities)
+        Iter::new(
+            &self.archetypes.archetypes,
+            &self.entities,
+            self.entities.len() as usize,
+        )
+    }
+
+    /// Iterate over the entities in the archetype identified by `id`
+    ///
+    /// Useful for visiting every entity having exactly the same component types as another,
+    /// identified by [`archetype_id`](Self::archetype_id).
+    ///
+    /// Panics if `id` is out of range, which is only possible if it came from a different world.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let a = world.spawn((123, true));
+    /// let b = world.spawn((456, false));
+    /// world.spawn((789,));
+    /// let id = world.archetype_id(a).unwrap();
+    /// let ids = world.iter_archetype(id).map(|e| e.entity()).collect::<Vec<_>>();
+    /// assert_eq!(ids, [a, b]);
+    /// ```
+    pub fn iter_archetype(&self, id: ArchetypeId) -> Iter<'_> {
+        let index = id.0 as usize;
+        let archetypes = &self.archetypes.archetypes[index..index + 1];
+        Iter::new(archetypes, &self.entities, archetypes[0].len() as usize)
+    }
+
+    /// Identify the archetype containing `entity`
+    ///
+    /// An entity's archetype changes when components are inserted or removed.
+    pub fn archetype_id(&self, entity: Entity) -> Result<ArchetypeId, NoSuchEntity> {
+        Ok(ArchetypeId(self.entities.get(entity)?.archetype))
+    }
+
+    /// Query the entities in a single archetype, using dynamic borrow checking
+    ///
+    /// Like [`query`](Self::query), but skips the search for matching archetypes. Useful when
+    /// entities are already grouped by archetype, e.g. by [`archetype_id`](Self::archetype_id).
+    /// Yields nothing if the archetype does not satisfy `Q`.
+    ///
+    /// Panics if `id` is out of range, which is only possible if it came from a different world.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let a = world.spawn((123, true));
+    /// world.spawn((456,));
+    /// let id = world.archetype_id(a).unwrap();
+    /// let mut query = world.query_archetype::<&i32>(id);
+    /// let results = query.iter().map(|(e, &i)| (e, i)).collect::<Vec<_>>();
+    /// assert_eq!(results, [(a, 123)]);
+    /// ```
+    pub fn query_archetype<Q: Query>(&self, id: ArchetypeId) -> QueryArchetype<'_, Q> {
+        QueryArchetype::new(
+            &self.entities.meta,
+            &self.archetypes.archetypes[id.0 as usize],
+        )
+    }
+
+    /// Query the entities in a single archetype of a uniquely borrowed world
+    ///
+    /// Like [`query_archetype`](Self::query_archetype), but faster because dynamic borrow checks
+    /// can be skipped.
+    pub fn query_archetype_mut<Q: Query>(&mut self, id: ArchetypeId) -> Batch<'_, Q> {
+        assert_borrow::<Q>();
+        let archetype = &self.archetypes.archetypes[id.0 as usize];
+        unsafe { Batch::for_archetype(&self.entities.meta, archetype) }
     }
 
     /// Add `components` to `entity`
@@ -586,13 +1618,62 @@ impl World {
         entity: Entity,
         components: impl DynamicBundle,
     ) -> Result<(), NoSuchEntity> {
-        self.flush();
+        self.flush_reserved();
 
         let loc = self.entities.get(entity)?;
         self.insert_inner(entity, components, loc.archetype, loc);
         Ok(())
     }
 
+    /// Like [`insert`](Self::insert), but fails rather than exceed the limits of a world
+    /// constructed with [`with_capacity_limits`](Self::with_capacity_limits)
+    ///
+    /// Equivalent to `insert` for worlds without limits.
+    pub fn try_insert(
+        &mut self,
+        entity: Entity,
+        components: impl DynamicBundle,
+    ) -> Result<(), InsertError> {
+        self.flush_reserved();
+
+        let loc = self.entities.get(entity)?;
+        if let Some(limits) = self.limits {
+            self.check_capacity(Some(loc.archetype), &components, limits)?;
+        }
+        self.insert_inner(entity, components, loc.archetype, loc);
+        Ok(())
+    }
+
+    /// Check whether adding `components` and the components they require to an entity in
+    /// archetype `source`, or to a new entity, would exceed `limits`
+    fn check_capacity(
+        &self,
+        source: Option<u32>,
+        components: &impl DynamicBundle,
+        limits: CapacityLimits,
+    ) -> Result<(), CapacityExhausted> {
+        let source = source.map(|x| &self.archetypes.archetypes[x as usize]);
+        let mut types = source.map_or_else(Vec::new, |x| x.types().to_vec());
+        types.extend(components.type_info());
+        if !self.requirements.is_empty() {
+            let present = source.map_or(&[][..], |x| x.type_ids());
+            let missing = components.with_ids(|ids| self.requirements.missing(ids, present));
+            types.extend(
+                missing
+                    .into_iter()
+                    .map(|id| self.requirements.defaults().get(id).unwrap().0),
+            );
+        }
+        types.sort_unstable();
+        types.dedup_by_key(|ty| ty.id());
+        if source.map_or(false, |x| x.types().len() == types.len()) {
+            // The entity stays put
+            return Ok(());
+        }
+        let ids = types.iter().map(|ty| ty.id()).collect::<Vec<_>>();
+        self.archetypes.check_capacity(&ids, limits)
+    }
+
     /// The implementation backing [`insert`](Self::insert) exposed so that it can also be used by [`exchange`](Self::exchange).
     ///
     /// Note that `graph_origin` is always equal to `loc.archetype` during insertion. Only for exchange, `graph_origin` identifies
@@ -605,6 +1686,20 @@ impl World {
         graph_origin: u32,
         loc: Location,
     ) {
+        if !self.requirements.is_empty() {
+            let present = self.archetypes.archetypes[loc.archetype as usize].type_ids();
+            let missing = components.with_ids(|ids| self.requirements.missing(ids, present));
+            if !missing.is_empty() {
+                let mut builder = mem::take(&mut self.required_builder);
+                builder.add_bundle(components);
+                for id in missing {
+                    builder.add_default(self.requirements.defaults(), id);
+                }
+                self.insert_inner(entity, builder.build(), graph_origin, loc);
+                self.required_builder = builder;
+                return;
+            }
+        }
         let target_storage;
         let target = match components.key() {
             None => {
@@ -620,6 +1715,16 @@ impl World {
             },
         };
 
+        if target.index != loc.archetype {
+            self.observers.changed(entity, Some(loc.archetype));
+        }
+        #[cfg(feature = "trace")]
+        self.trace.record(TraceKind::Insert {
+            entity,
+            from: loc.archetype,
+            to: target.index,
+        });
+
         let source_arch = &mut self.archetypes.archetypes[loc.archetype as usize];
         unsafe {
             // Drop the components we're overwriting
@@ -636,6 +1741,7 @@ impl World {
                 components.put(|ptr, ty| {
                     arch.put_dynamic(ptr, ty.id(), ty.layout().size(), loc.index);
                 });
+                self.indices.insert(arch, loc.index, entity);
                 return;
             }
 
@@ -658,12 +1764,17 @@ impl World {
 
             // Move the components we're keeping
             for &ty in &target.retained {
+                if ty.layout().size() == 0 {
+                    continue;
+                }
                 let src = source_arch
                     .get_dynamic(ty.id(), ty.layout().size(), loc.index)
                     .unwrap();
                 target_arch.put_dynamic(src.as_ptr(), ty.id(), ty.layout().size(), target_index)
             }
 
+            self.indices.insert(target_arch, target_index, entity);
+
             // Free storage in the old archetype
             if let Some(moved) = source_arch.remove(loc.index, false) {
                 self.entities.meta[moved as usize].location.index = loc.index;
@@ -682,6 +1793,33 @@ impl World {
         self.insert(entity, (component,))
     }
 
+    /// Borrow `entity`'s `T`, first inserting `f()` if it has none
+    ///
+    /// `f` is only called, and `entity` only moved to a new archetype, if `T` is absent.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let e = world.spawn(());
+    /// *world.get_or_insert_with(e, || 0i32).unwrap() += 1;
+    /// *world.get_or_insert_with(e, || 0i32).unwrap() += 1;
+    /// assert_eq!(*world.get::<&i32>(e).unwrap(), 2);
+    /// ```
+    pub fn get_or_insert_with<T: Component>(
+        &mut self,
+        entity: Entity,
+        f: impl FnOnce() -> T,
+    ) -> Result<RefMut<'_, T>, NoSuchEntity> {
+        if !self.entity(entity)?.has::<T>() {
+            self.insert_one(entity, f())?;
+        }
+        Ok(self
+            .entity(entity)?
+            .get::<&mut T>()
+            .expect("component was just inserted"))
+    }
+
     /// Remove components from `entity`
     ///
     /// Computational cost is proportional to the number of components `entity` has. The entity
@@ -702,46 +1840,349 @@ impl World {
     /// assert_eq!(*world.get::<&bool>(e).unwrap(), true);
     /// ```
     pub fn remove<T: Bundle + 'static>(&mut self, entity: Entity) -> Result<T, ComponentError> {
-        self.flush();
+        self.flush_reserved();
 
         // Gather current metadata
-        let loc = self.entities.get_mut(entity)?;
-        let old_index = loc.index;
+        let loc = self.entities.get(entity)?;
         let source_arch = &self.archetypes.archetypes[loc.archetype as usize];
 
-        // Move out of the source archetype, or bail out if a component is missing
-        let bundle = unsafe {
-            T::get(|ty| source_arch.get_dynamic(ty.id(), ty.layout().size(), old_index))?
-        };
+        // Move out of the source archetype, or bail out if a component is missing
+        let bundle = unsafe {
+            T::get(|ty| source_arch.get_dynamic(ty.id(), ty.layout().size(), loc.index))?
+        };
+
+        // Find the target archetype ID
+        let target =
+            Self::remove_target::<T>(&mut self.archetypes, &mut self.remove_edges, loc.archetype);
+
+        self.move_remaining(entity, loc, target);
+
+        Ok(bundle)
+    }
+
+    /// Remove the components `T` from each of `entities`
+    ///
+    /// Equivalent to calling [`remove`](Self::remove) for each entity in turn, yielding the result
+    /// for each entity in the same order, but entities sharing an archetype are handled together
+    /// so that their destination archetype is looked up and allocated for only once.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let a = world.spawn((123, true));
+    /// let b = world.spawn((456, true, "abc"));
+    /// let c = world.spawn(("def",));
+    /// let removed = world.remove_batch::<(i32,)>(&[a, b, c]);
+    /// assert_eq!(removed[0], Ok((123,)));
+    /// assert_eq!(removed[1], Ok((456,)));
+    /// assert!(removed[2].is_err());
+    /// assert!(!world.satisfies::<&i32>(a).unwrap());
+    /// ```
+    pub fn remove_batch<T: Bundle + 'static>(
+        &mut self,
+        entities: &[Entity],
+    ) -> Vec<Result<T, ComponentError>> {
+        let mut results = (0..entities.len()).map(|_| None).collect::<Vec<_>>();
+        self.remove_batch_inner::<T>(entities, |i, result| results[i] = Some(result));
+        results.into_iter().map(|x| x.unwrap()).collect()
+    }
+
+    /// Remove and drop the components `T` from each of `entities`
+    ///
+    /// Like [`remove_batch`](Self::remove_batch), but discards the removed components. Entities
+    /// which don't exist or lack any of `T` are ignored.
+    pub fn remove_batch_drop<T: Bundle + 'static>(&mut self, entities: &[Entity]) {
+        self.remove_batch_inner::<T>(entities, |_, _| {});
+    }
+
+    fn remove_batch_inner<T: Bundle + 'static>(
+        &mut self,
+        entities: &[Entity],
+        mut f: impl FnMut(usize, Result<T, ComponentError>),
+    ) {
+        self.flush_reserved();
+
+        // Group the entities by their archetype
+        let mut order = Vec::with_capacity(entities.len());
+        for (i, &entity) in entities.iter().enumerate() {
+            match self.entities.get(entity) {
+                Ok(loc) => order.push((loc.archetype, i)),
+                Err(e) => f(i, Err(e.into())),
+            }
+        }
+        order.sort_unstable();
+
+        let mut rest = &order[..];
+        while let Some(&(source, _)) = rest.first() {
+            let len = rest.iter().take_while(|x| x.0 == source).count();
+            let (group, next) = rest.split_at(len);
+            rest = next;
+
+            let mut target = None;
+            for &(_, i) in group {
+                let entity = entities[i];
+                let loc = self.entities.meta[entity.id as usize].location;
+                if loc.archetype != source {
+                    // Already moved by an earlier occurrence of the same entity
+                    f(i, self.remove::<T>(entity));
+                    continue;
+                }
+                let source_arch = &self.archetypes.archetypes[source as usize];
+                let bundle = match unsafe {
+                    T::get(|ty| source_arch.get_dynamic(ty.id(), ty.layout().size(), loc.index))
+                } {
+                    Ok(x) => x,
+                    Err(e) => {
+                        f(i, Err(e.into()));
+                        continue;
+                    }
+                };
+                let target = *target.get_or_insert_with(|| {
+                    let target = Self::remove_target::<T>(
+                        &mut self.archetypes,
+                        &mut self.remove_edges,
+                        source,
+                    );
+                    if target != source {
+                        self.archetypes.archetypes[target as usize].reserve(len as u32);
+                    }
+                    target
+                });
+                self.move_remaining(entity, loc, target);
+                f(i, Ok(bundle));
+            }
+        }
+    }
+
+    /// Remove the components `T` from every entity that has all of them
+    ///
+    /// Yields each affected entity along with its removed components. Entities are processed an
+    /// archetype at a time, so the destination archetype is looked up and allocated for only once
+    /// per source archetype. See also [`drain_despawn`](Self::drain_despawn).
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let a = world.spawn((123, true));
+    /// let b = world.spawn((456,));
+    /// world.spawn((true,));
+    /// let mut drained = world.drain::<(i32,)>();
+    /// drained.sort_unstable();
+    /// assert_eq!(drained, [(a, (123,)), (b, (456,))]);
+    /// assert!(world.contains(a));
+    /// assert_eq!(world.query_mut::<&i32>().into_iter().count(), 0);
+    /// ```
+    pub fn drain<T: Bundle + 'static>(&mut self) -> Vec<(Entity, T)> {
+        self.flush_reserved();
+
+        let mut out = Vec::new();
+        // Archetypes created by `remove_target` lack `T`, so needn't be visited.
+        for source in 0..self.archetypes.archetypes.len() as u32 {
+            let source_arch = &self.archetypes.archetypes[source as usize];
+            let len = source_arch.len();
+            if len == 0
+                || !T::with_static_ids(|ids| ids.iter().all(|&x| source_arch.has_dynamic(x)))
+            {
+                continue;
+            }
+            let target =
+                Self::remove_target::<T>(&mut self.archetypes, &mut self.remove_edges, source);
+            self.archetypes.archetypes[target as usize].reserve(len);
+            out.reserve(len as usize);
+            // Visit in reverse so that no other entity is moved to fill each vacancy
+            for index in (0..len).rev() {
+                let source_arch = &self.archetypes.archetypes[source as usize];
+                let id = source_arch.entity_id(index);
+                let entity = self.entities.meta[id as usize].entity(id);
+                let bundle = unsafe {
+                    T::get(|ty| source_arch.get_dynamic(ty.id(), ty.layout().size(), index))
+                        .unwrap()
+                };
+                let loc = Location {
+                    archetype: source,
+                    index,
+                };
+                self.move_remaining(entity, loc, target);
+                out.push((entity, bundle));
+            }
+        }
+        out
+    }
+
+    /// Despawn every entity that has all of the components `T`, yielding those components
+    ///
+    /// Like [`drain`](Self::drain), but the entities' remaining components are dropped. Suited to
+    /// short-lived message or event entities.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// struct Damage(u32);
+    ///
+    /// let mut world = World::new();
+    /// let a = world.spawn((Damage(5), "target"));
+    /// let b = world.spawn((Damage(7),));
+    /// let total = world
+    ///     .drain_despawn::<(Damage,)>()
+    ///     .into_iter()
+    ///     .map(|(_, (x,))| x.0)
+    ///     .sum::<u32>();
+    /// assert_eq!(total, 12);
+    /// assert!(!world.contains(a) && !world.contains(b));
+    /// ```
+    pub fn drain_despawn<T: Bundle + 'static>(&mut self) -> Vec<(Entity, T)> {
+        self.flush_reserved();
+
+        let mut out = Vec::new();
+        for source in 0..self.archetypes.archetypes.len() {
+            let source_arch = &mut self.archetypes.archetypes[source];
+            let len = source_arch.len();
+            if len == 0
+                || !T::with_static_ids(|ids| ids.iter().all(|&x| source_arch.has_dynamic(x)))
+            {
+                continue;
+            }
+            let others = T::with_static_ids(|ids| {
+                source_arch
+                    .types()
+                    .iter()
+                    .filter(|x| !ids.contains(&x.id()))
+                    .cloned()
+                    .collect::<Vec<_>>()
+            });
+            out.reserve(len as usize);
+            // Visit in reverse so that no other entity is moved to fill each vacancy
+            for index in (0..len).rev() {
+                let id = source_arch.entity_id(index);
+                let entity = self.entities.meta[id as usize].entity(id);
+                let bundle = unsafe {
+                    T::get(|ty| source_arch.get_dynamic(ty.id(), ty.layout().size(), index))
+                        .unwrap()
+                };
+                self.entities.free(entity).unwrap();
+                self.names.remove(id);
+                self.indices.remove(id);
+                self.observers.changed(entity, Some(source as u32));
+                unsafe {
+                    source_arch.move_to(index, |ptr, id, _| {
+                        if let Some(ty) = others.iter().find(|x| x.id() == id) {
+                            ty.drop(ptr);
+                        }
+                    });
+                }
+                #[cfg(feature = "trace")]
+                self.trace.record(TraceKind::Despawn { entity });
+                out.push((entity, bundle));
+            }
+        }
+        out
+    }
+
+    /// Remove the components of `entity` whose types are in `types`
+    ///
+    /// Like [`remove`](Self::remove), but for components whose types are only known at runtime.
+    /// Fails without modifying `entity` if it lacks any of `types`. See [`TakenBundle`] for an
+    /// example.
+    pub fn remove_dynamic(
+        &mut self,
+        entity: Entity,
+        types: &[TypeId],
+    ) -> Result<TakenBundle, ComponentError> {
+        let mut bundle = TakenBundle::default();
+        self.remove_dynamic_inner(entity, types, |ptr, ty| unsafe { bundle.push(ptr, ty) })?;
+        Ok(bundle)
+    }
+
+    /// Remove and drop the components of `entity` whose types are in `types`
+    ///
+    /// Fails without modifying `entity` if it lacks any of `types`.
+    pub(crate) fn remove_dynamic_drop(
+        &mut self,
+        entity: Entity,
+        types: &[TypeId],
+    ) -> Result<(), ComponentError> {
+        self.remove_dynamic_inner(entity, types, |ptr, ty| unsafe { ty.drop(ptr) })
+    }
+
+    /// Remove the components of `entity` whose types are in `types`, passing each to `f` to be
+    /// moved out
+    fn remove_dynamic_inner(
+        &mut self,
+        entity: Entity,
+        types: &[TypeId],
+        mut f: impl FnMut(*mut u8, TypeInfo),
+    ) -> Result<(), ComponentError> {
+        self.flush_reserved();
 
-        // Find the target archetype ID
-        let target =
-            Self::remove_target::<T>(&mut self.archetypes, &mut self.remove_edges, loc.archetype);
+        let loc = self.entities.get(entity)?;
+        let source_arch = &self.archetypes.archetypes[loc.archetype as usize];
+        if !types.iter().all(|&ty| source_arch.has_dynamic(ty)) {
+            return Err(MissingComponent::dynamic().into());
+        }
+        let info = source_arch
+            .types()
+            .iter()
+            .filter(|x| !types.contains(&x.id()))
+            .cloned()
+            .collect::<Vec<_>>();
+        let elements = info.iter().map(|x| x.id()).collect::<Box<_>>();
+        let target = self.archetypes.get(&*elements, move || info);
 
-        // Store components to the target archetype and update metadata
-        if loc.archetype != target {
-            // If we actually removed any components, the entity needs to be moved into a new archetype
-            let (source_arch, target_arch) = index2(
-                &mut self.archetypes.archetypes,
-                loc.archetype as usize,
-                target as usize,
-            );
-            let target_index = unsafe { target_arch.allocate(entity.id) };
-            loc.archetype = target;
-            loc.index = target_index;
-            if let Some(moved) = unsafe {
-                source_arch.move_to(old_index, |src, ty, size| {
-                    // Only move the components present in the target archetype, i.e. the non-removed ones.
-                    if let Some(dst) = target_arch.get_dynamic(ty, size, target_index) {
-                        ptr::copy_nonoverlapping(src, dst.as_ptr(), size);
-                    }
-                })
-            } {
-                self.entities.meta[moved as usize].location.index = old_index;
+        let source_arch = &self.archetypes.archetypes[loc.archetype as usize];
+        for ty in source_arch.types() {
+            if types.contains(&ty.id()) {
+                let ptr = unsafe {
+                    source_arch
+                        .get_dynamic(ty.id(), ty.layout().size(), loc.index)
+                        .unwrap()
+                };
+                f(ptr.as_ptr(), *ty);
             }
         }
+        self.move_remaining(entity, loc, target);
+        Ok(())
+    }
 
-        Ok(bundle)
+    /// Move the components of `entity` at `loc` which are present in `target` into `target`,
+    /// assuming any others have already been moved out
+    fn move_remaining(&mut self, entity: Entity, loc: Location, target: u32) {
+        if loc.archetype == target {
+            return;
+        }
+        self.observers.changed(entity, Some(loc.archetype));
+        #[cfg(feature = "trace")]
+        self.trace.record(TraceKind::Remove {
+            entity,
+            from: loc.archetype,
+            to: target,
+        });
+        // If we actually removed any components, the entity needs to be moved into a new archetype
+        let (source_arch, target_arch) = index2(
+            &mut self.archetypes.archetypes,
+            loc.archetype as usize,
+            target as usize,
+        );
+        let target_index = unsafe { target_arch.allocate(entity.id) };
+        self.entities.meta[entity.id as usize].location = Location {
+            archetype: target,
+            index: target_index,
+        };
+        if let Some(moved) = unsafe {
+            source_arch.move_to(loc.index, |src, ty, size| {
+                // Only move the components present in the target archetype, i.e. the non-removed ones.
+                // Zero-sized components have nothing to move.
+                if size == 0 {
+                    return;
+                }
+                if let Some(dst) = target_arch.get_dynamic(ty, size, target_index) {
+                    ptr::copy_nonoverlapping(src, dst.as_ptr(), size);
+                }
+            })
+        } {
+            self.entities.meta[moved as usize].location.index = loc.index;
+        }
     }
 
     fn remove_target<T: Bundle + 'static>(
@@ -774,16 +2215,85 @@ impl World {
         self.remove::<(T,)>(entity).map(|(x,)| x)
     }
 
+    /// Ensure `entity` has a `T` component, adding `T::default()` if it lacks one
+    ///
+    /// Intended for zero-sized marker components, which occupy no storage and are never copied when
+    /// an entity moves between archetypes. Unlike [`insert_one`](Self::insert_one), an existing `T`
+    /// is left untouched.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// #[derive(Default)]
+    /// struct Selected;
+    ///
+    /// let mut world = World::new();
+    /// let a = world.spawn((123,));
+    /// world.tag::<Selected>(a).unwrap();
+    /// assert!(world.satisfies::<&Selected>(a).unwrap());
+    /// assert!(world.untag::<Selected>(a).unwrap());
+    /// assert!(!world.untag::<Selected>(a).unwrap());
+    /// ```
+    pub fn tag<T: Component + Default>(&mut self, entity: Entity) -> Result<(), NoSuchEntity> {
+        self.flush_reserved();
+
+        let loc = self.entities.get(entity)?;
+        if !self.archetypes.archetypes[loc.archetype as usize].has::<T>() {
+            self.insert_inner(entity, (T::default(),), loc.archetype, loc);
+        }
+        Ok(())
+    }
+
+    /// Remove and drop the `T` component of `entity`, returning whether it had one
+    ///
+    /// See [`tag`](Self::tag).
+    pub fn untag<T: Component>(&mut self, entity: Entity) -> Result<bool, NoSuchEntity> {
+        self.flush_reserved();
+
+        let loc = self.entities.get(entity)?;
+        let source_arch = &self.archetypes.archetypes[loc.archetype as usize];
+        let size = mem::size_of::<T>();
+        unsafe {
+            let Some(component) = source_arch.get_dynamic(TypeId::of::<T>(), size, loc.index)
+            else {
+                return Ok(false);
+            };
+            component.as_ptr().cast::<T>().drop_in_place();
+        }
+        let target = Self::remove_target::<(T,)>(
+            &mut self.archetypes,
+            &mut self.remove_edges,
+            loc.archetype,
+        );
+        self.move_remaining(entity, loc, target);
+        Ok(true)
+    }
+
     /// Remove `S` components from `entity` and then add `components`
     ///
     /// This has the same effect as calling [`remove::<S>`](Self::remove) and then [`insert::<T>`](Self::insert),
     /// but is more efficient as the intermediate archetype after removal but before insertion is skipped.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// struct Idle;
+    /// struct Running { speed: f32 }
+    ///
+    /// let mut world = World::new();
+    /// let a = world.spawn((Idle, 123));
+    /// // Move directly from the `(Idle, i32)` archetype to the `(Running, i32)` archetype
+    /// let Idle = world.exchange_one::<Idle, _>(a, Running { speed: 1.0 }).unwrap();
+    /// assert!(world.satisfies::<&Running>(a).unwrap());
+    /// assert!(!world.satisfies::<&Idle>(a).unwrap());
+    /// ```
+    #[doc(alias = "replace")]
     pub fn exchange<S: Bundle + 'static, T: DynamicBundle>(
         &mut self,
         entity: Entity,
         components: T,
     ) -> Result<S, ComponentError> {
-        self.flush();
+        self.flush_reserved();
 
         // Gather current metadata
         let loc = self.entities.get(entity)?;
@@ -807,6 +2317,7 @@ impl World {
     /// Remove the `S` component from `entity` and then add `component`
     ///
     /// See [`exchange`](Self::exchange).
+    #[doc(alias = "replace_one")]
     pub fn exchange_one<S: Component, T: Component>(
         &mut self,
         entity: Entity,
@@ -843,14 +2354,184 @@ impl World {
         ))
     }
 
-    /// Convert all reserved entities into empty entities that can be iterated and accessed
+    /// Convert all reserved entities into empty entities that can be iterated and accessed, then
+    /// run observers registered with [`on_match`](Self::on_match) or
+    /// [`on_unmatch`](Self::on_unmatch)
     ///
-    /// Invoked implicitly by operations that add or remove components or entities, i.e. all
-    /// variations of `spawn`, `despawn`, `insert`, and `remove`.
+    /// Reserved entities are also converted implicitly by operations that add or remove components
+    /// or entities, i.e. all variations of `spawn`, `despawn`, `insert`, and `remove`, but
+    /// observers are only run by an explicit call.
     pub fn flush(&mut self) {
+        self.flush_with(|_| {});
+    }
+
+    /// Like [`flush`](Self::flush), but invokes `f` on each entity as it becomes real
+    ///
+    /// Useful for registering reserved entities with external data structures exactly when they
+    /// become visible to queries. Because reserved entities are also flushed implicitly by
+    /// operations such as `spawn` and `insert`, this must be called before any such operation for
+    /// `f` to observe every reserved entity.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let a = world.reserve_entity();
+    /// assert_eq!(world.pending_reserved(), 1);
+    /// let mut flushed = Vec::new();
+    /// world.flush_with(|e| flushed.push(e));
+    /// assert_eq!(flushed, [a]);
+    /// assert_eq!(world.pending_reserved(), 0);
+    /// ```
+    pub fn flush_with(&mut self, f: impl FnMut(Entity)) {
+        self.flush_reserved_with(f);
+        self.observers
+            .run(&self.archetypes.archetypes, &self.entities);
+    }
+
+    /// Convert all reserved entities into empty entities, without running observers
+    fn flush_reserved(&mut self) {
+        self.flush_reserved_with(|_| {});
+    }
+
+    fn flush_reserved_with(&mut self, mut f: impl FnMut(Entity)) {
         let arch = &mut self.archetypes.archetypes[0];
-        self.entities
-            .flush(|id, location| location.index = unsafe { arch.allocate(id) });
+        let observers = &mut self.observers;
+        self.entities.flush(|entity, location| {
+            location.index = unsafe { arch.allocate(entity.id) };
+            observers.changed(entity, None);
+            f(entity);
+        });
+    }
+
+    /// Call `f` on each entity which has started to match `Q` when observers are next run by
+    /// [`flush`](Self::flush)
+    ///
+    /// An entity starts to match when it's spawned with, or gains, the components required by `Q`.
+    /// Only the net effect of changes since observers last ran is considered, so an entity which
+    /// gains and then loses a component in the meantime is not reported. Entities are visited in
+    /// arbitrary order.
+    ///
+    /// Changes are accumulated until observers run, so a world with observers must be flushed
+    /// regularly to bound its memory use.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// # use std::sync::{Arc, Mutex};
+    /// struct Burning;
+    /// struct Wooden;
+    ///
+    /// let mut world = World::new();
+    /// let ignited = Arc::new(Mutex::new(Vec::new()));
+    /// let sink = ignited.clone();
+    /// world.on_match::<(&Burning, &Wooden)>(move |e| sink.lock().unwrap().push(e));
+    ///
+    /// let a = world.spawn((Wooden,));
+    /// world.insert_one(a, Burning).unwrap();
+    /// world.flush();
+    /// assert_eq!(*ignited.lock().unwrap(), [a]);
+    /// ```
+    pub fn on_match<Q: Query>(&mut self, f: impl FnMut(Entity) + Send + Sync + 'static) {
+        self.observers.register::<Q>(true, f);
+    }
+
+    /// Call `f` on each entity which has stopped matching `Q` when observers are next run by
+    /// [`flush`](Self::flush)
+    ///
+    /// An entity stops matching when it's despawned or loses the components required by `Q`. The
+    /// entity may no longer exist when `f` is called. See [`on_match`](Self::on_match) for
+    /// details.
+    pub fn on_unmatch<Q: Query>(&mut self, f: impl FnMut(Entity) + Send + Sync + 'static) {
+        self.observers.register::<Q>(false, f);
+    }
+
+    /// Call `f` whenever the range of entity IDs which may be in use changes, with its new length
+    ///
+    /// Every live entity's [`Entity::id`] is less than the last length passed to `f`, so
+    /// per-entity data mirrored in arrays indexed by ID can be grown or truncated in lockstep with
+    /// the world, rather than bounds-checked on every access. The range grows as new IDs are
+    /// allocated, and shrinks on [`clear`](Self::clear) and [`compact_ids`](Self::compact_ids).
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// # use std::sync::{Arc, Mutex};
+    /// let mut world = World::new();
+    /// let len = Arc::new(Mutex::new(0));
+    /// let sink = len.clone();
+    /// world.on_entities_resize(move |n| *sink.lock().unwrap() = n);
+    /// let e = world.spawn(());
+    /// assert_eq!(*len.lock().unwrap(), e.id() + 1);
+    /// ```
+    pub fn on_entities_resize(&mut self, f: impl FnMut(u32) + Send + Sync + 'static) {
+        self.entities.on_resize(f);
+    }
+
+    /// Call `f` with a previously freed entity ID whenever it's allocated again
+    ///
+    /// Lets data mirrored in arrays indexed by [`Entity::id`] be reset before it's associated with
+    /// a new entity. Also called for each ID that [`compact_ids`](Self::compact_ids) moves an
+    /// entity into. Reserved entities are reported when they're [`flush`](Self::flush)ed.
+    pub fn on_id_recycle(&mut self, f: impl FnMut(u32) + Send + Sync + 'static) {
+        self.entities.on_recycle(f);
+    }
+
+    /// Ensure that entities which gain a `C` component also have an `R` component
+    ///
+    /// When [`spawn`](Self::spawn), [`insert`](Self::insert), or similar add a `C` to an entity
+    /// which has no `R` and isn't being given one, a default `R` is added in the same archetype
+    /// transition. Requirements are transitive, so `R`'s own requirements are added too.
+    ///
+    /// Only affects later operations; existing entities are left alone. Entities spawned in bulk
+    /// by [`spawn_batch`](Self::spawn_batch) or [`spawn_column_batch`](Self::spawn_column_batch)
+    /// aren't checked, nor is removing an `R` from an entity that still has a `C`.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// #[derive(Default, Debug, PartialEq)]
+    /// struct Transform([f32; 2]);
+    /// struct Sprite;
+    ///
+    /// let mut world = World::new();
+    /// world.require::<Sprite, Transform>();
+    /// let a = world.spawn((Sprite,));
+    /// assert_eq!(*world.get::<&Transform>(a).unwrap(), Transform([0.0; 2]));
+    /// let b = world.spawn((Sprite, Transform([1.0; 2])));
+    /// assert_eq!(*world.get::<&Transform>(b).unwrap(), Transform([1.0; 2]));
+    /// ```
+    pub fn require<C: Component, R: Component + Default>(&mut self) {
+        self.requirements.register::<C, R>();
+    }
+
+    /// Number of entities reserved by [`reserve_entity`](Self::reserve_entity) or
+    /// [`reserve_entities`](Self::reserve_entities) which have not yet been flushed
+    pub fn pending_reserved(&self) -> usize {
+        self.entities.pending_reserved()
+    }
+
+    /// Enumerate entities reserved by [`reserve_entity`](Self::reserve_entity) or
+    /// [`reserve_entities`](Self::reserve_entities) which have not yet been flushed
+    ///
+    /// Such entities have no components and are not yet visible to queries, iteration, or
+    /// serialization. Snapshots taken without exclusive access to the world, and hence without the
+    /// opportunity to [`flush`](Self::flush), can use this to account for them, e.g. by recording
+    /// them as empty entities.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let a = world.spawn(());
+    /// world.despawn(a).unwrap();
+    /// let b = world.reserve_entity();
+    /// let c = world.reserve_entity();
+    /// assert_eq!(world.reserved_entities().collect::<Vec<_>>(), [b, c]);
+    /// assert_eq!(world.iter().count(), 0);
+    /// ```
+    pub fn reserved_entities(&self) -> ReserveEntitiesIterator<'_> {
+        self.entities.reserved()
     }
 
     /// Inspect the archetypes that entities are organized into
@@ -862,12 +2543,50 @@ impl World {
         self.archetypes_inner().iter()
     }
 
+    /// Inspect the archetype identified by `id`
+    ///
+    /// Panics if `id` is out of range, which is only possible if it came from a different world.
+    pub fn archetype(&self, id: ArchetypeId) -> &Archetype {
+        &self.archetypes_inner()[id.0 as usize]
+    }
+
+    /// Inspect the archetypes created since `generation` was obtained from
+    /// [`archetypes_generation`](Self::archetypes_generation)
+    ///
+    /// Archetypes are never removed, so this is exactly the set of archetypes which external
+    /// per-archetype data derived at `generation` is missing.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// world.spawn((123,));
+    /// let generation = world.archetypes_generation();
+    /// world.spawn((456,));
+    /// world.spawn((789, "abc"));
+    /// let new = world.archetypes_since(generation).collect::<Vec<_>>();
+    /// assert_eq!(new.len(), 1);
+    /// assert!(new[0].has::<i32>() && new[0].has::<&str>());
+    /// ```
+    pub fn archetypes_since(
+        &self,
+        generation: ArchetypesGeneration,
+    ) -> impl ExactSizeIterator<Item = &'_ Archetype> + '_ {
+        self.archetypes_inner()
+            .get(generation.0 as usize..)
+            .unwrap_or_default()
+            .iter()
+    }
+
     /// Despawn `entity`, yielding a [`DynamicBundle`] of its components
     ///
     /// Useful for moving entities between worlds.
     pub fn take(&mut self, entity: Entity) -> Result<TakenEntity<'_>, NoSuchEntity> {
-        self.flush();
+        self.flush_reserved();
         let loc = self.entities.get(entity)?;
+        self.names.remove(entity.id());
+        self.indices.remove(entity.id());
+        self.observers.changed(entity, Some(loc.archetype));
         let archetype = &mut self.archetypes.archetypes[loc.archetype as usize];
         unsafe {
             Ok(TakenEntity::new(
@@ -879,6 +2598,48 @@ impl World {
         }
     }
 
+    /// Move `entity` and its components into `dest`, recording its new handle in `translator`
+    ///
+    /// Returns the new handle. Entity handles stored in components are not updated; see
+    /// [`EntityTranslator`] for an example of remapping them.
+    pub fn transfer(
+        &mut self,
+        entity: Entity,
+        dest: &mut World,
+        translator: &mut EntityTranslator,
+    ) -> Result<Entity, NoSuchEntity> {
+        let source = self.id();
+        let new = dest.spawn(self.take(entity)?);
+        translator.record(source, entity, new);
+        Ok(new)
+    }
+
+    /// Allocate storage for `T` components with at least `align` bytes of alignment
+    ///
+    /// Applies to every archetype, including those created later, reallocating existing storage if
+    /// necessary. Useful for copying or mapping columns obtained from [`Archetype::get`] directly
+    /// into memory with stricter requirements, such as GPU buffers. Components are always stored at
+    /// a stride of `size_of::<T>()`; to pad them, wrap them in a `#[repr(align(N))]` type instead.
+    ///
+    /// Panics if `align` is not a power of two.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// world.set_column_alignment::<[f32; 3]>(256);
+    /// world.spawn(([1.0f32, 2.0, 3.0], true));
+    /// for archetype in world.archetypes() {
+    ///     if let Some(column) = archetype.get::<&[f32; 3]>() {
+    ///         assert_eq!(column.as_ptr() as usize % 256, 0);
+    ///     }
+    /// }
+    /// ```
+    pub fn set_column_alignment<T: Component>(&mut self, align: usize) {
+        assert!(align.is_power_of_two(), "alignment must be a power of two");
+        self.archetypes.set_column_align(TypeId::of::<T>(), align);
+    }
+
     /// Returns a distinct value after `archetypes` is changed
     ///
     /// Store the current value after deriving information from [`archetypes`](Self::archetypes),
@@ -901,6 +2662,22 @@ impl World {
         ArchetypesGeneration(self.archetypes.generation())
     }
 
+    /// Identifier unique to this world among all worlds created by this process
+    ///
+    /// Together with [`archetypes_generation`](Self::archetypes_generation), suitable for keying
+    /// and invalidating external caches of information derived from a world.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let a = World::new();
+    /// let b = World::new();
+    /// assert_ne!(a.id(), b.id());
+    /// ```
+    pub fn id(&self) -> WorldId {
+        WorldId(self.id)
+    }
+
     /// Number of currently live entities
     #[inline]
     pub fn len(&self) -> u32 {
@@ -912,6 +2689,61 @@ impl World {
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
+
+    /// Number of entities having a `T` component
+    ///
+    /// Computed from archetype lengths, without iterating over entities or borrowing components.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// world.spawn((123, true));
+    /// world.spawn((456,));
+    /// let _borrow = world.query::<&mut i32>();
+    /// assert_eq!(world.count::<i32>(), 2);
+    /// assert_eq!(world.count::<bool>(), 1);
+    /// ```
+    pub fn count<T: Component>(&self) -> u32 {
+        self.archetypes()
+            .filter(|x| x.has::<T>())
+            .map(|x| x.len())
+            .sum()
+    }
+
+    /// Number of entities satisfying `Q`
+    ///
+    /// Like [`count`](Self::count), does not borrow any components.
+    pub fn count_query<Q: Query>(&self) -> u32 {
+        self.archetypes()
+            .filter(|x| x.satisfies::<Q>())
+            .map(|x| x.len())
+            .sum()
+    }
+
+    /// Summarize entity ID usage, counting IDs whose generation is within `margin` of wrapping
+    ///
+    /// Generations advance each time an ID is freed. Once an ID's generation wraps around, handles
+    /// to the entities that previously used it may collide with new entities; see
+    /// [Collisions](Self#collisions). Cost is proportional to the number of entity IDs ever
+    /// allocated.
+    ///
+    /// # Example
+    /// ```
+    /// # use hecs::*;
+    /// let mut world = World::new();
+    /// let a = world.spawn(());
+    /// world.spawn(());
+    /// world.despawn(a).unwrap();
+    /// let stats = world.entity_stats(1 << 16);
+    /// assert_eq!(stats.slots, 2);
+    /// assert_eq!(stats.free, 1);
+    /// assert_eq!(stats.max_generation, 2);
+    /// assert_eq!(stats.near_wraparound, 0);
+    /// ```
+    pub fn entity_stats(&self, margin: u32) -> EntityStats {
+        self.entities.stats(margin)
+    }
 }
 
 unsafe impl Send for World {}
@@ -973,6 +2805,71 @@ impl From<MissingComponent> for ComponentError {
     }
 }
 
+/// Error indicating that an operation would exceed the limits of a world constructed with
+/// [`World::with_capacity_limits`]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct CapacityExhausted;
+
+#[cfg(feature = "std")]
+impl Error for CapacityExhausted {}
+
+impl fmt::Display for CapacityExhausted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("world capacity exhausted")
+    }
+}
+
+/// Error indicating that no index of a component type by a key type was registered with
+/// [`World::index`]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct NoSuchIndex {
+    component: &'static str,
+    key: &'static str,
+}
+
+#[cfg(feature = "std")]
+impl Error for NoSuchIndex {}
+
+impl fmt::Display for NoSuchIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "no index of {} by {}", self.component, self.key)
+    }
+}
+
+/// Errors that arise from [`World::try_insert`]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum InsertError {
+    /// The entity was already despawned
+    NoSuchEntity,
+    /// The world's capacity limits would be exceeded
+    CapacityExhausted,
+}
+
+#[cfg(feature = "std")]
+impl Error for InsertError {}
+
+impl fmt::Display for InsertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use InsertError::*;
+        match *self {
+            NoSuchEntity => f.write_str("no such entity"),
+            CapacityExhausted => f.write_str("world capacity exhausted"),
+        }
+    }
+}
+
+impl From<NoSuchEntity> for InsertError {
+    fn from(NoSuchEntity: NoSuchEntity) -> Self {
+        InsertError::NoSuchEntity
+    }
+}
+
+impl From<CapacityExhausted> for InsertError {
+    fn from(CapacityExhausted: CapacityExhausted) -> Self {
+        InsertError::CapacityExhausted
+    }
+}
+
 /// Errors that arise when querying a single entity
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum QueryOneError {
@@ -1014,15 +2911,17 @@ pub struct Iter<'a> {
     entities: &'a Entities,
     current: Option<&'a Archetype>,
     index: u32,
+    remaining: usize,
 }
 
 impl<'a> Iter<'a> {
-    fn new(archetypes: &'a [Archetype], entities: &'a Entities) -> Self {
+    fn new(archetypes: &'a [Archetype], entities: &'a Entities, len: usize) -> Self {
         Self {
             archetypes: archetypes.iter(),
             entities,
             current: None,
             index: 0,
+            remaining: len,
         }
     }
 }
@@ -1046,14 +2945,13 @@ impl<'a> Iterator for Iter<'a> {
                     }
                     let index = self.index;
                     self.index += 1;
+                    self.remaining -= 1;
                     let id = current.entity_id(index);
                     return Some(unsafe {
                         EntityRef::new(
+                            &self.entities.meta,
                             current,
-                            Entity {
-                                id,
-                                generation: self.entities.meta[id as usize].generation,
-                            },
+                            self.entities.meta[id as usize].entity(id),
                             index,
                         )
                     });
@@ -1070,7 +2968,7 @@ impl<'a> Iterator for Iter<'a> {
 impl ExactSizeIterator for Iter<'_> {
     #[inline]
     fn len(&self) -> usize {
-        self.entities.len() as usize
+        self.remaining
     }
 }
 
@@ -1093,10 +2991,30 @@ impl<A: DynamicBundle> core::iter::FromIterator<A> for World {
     }
 }
 
+/// Identifies an archetype within a [`World`]
+///
+/// Obtained from [`World::archetype_id`] or [`EntityRef::archetype_id`](crate::EntityRef::archetype_id).
+/// Remains valid for the lifetime of the world, as archetypes are never removed.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct ArchetypeId(pub(crate) u32);
+
+impl ArchetypeId {
+    /// Position of the archetype in [`World::archetypes`]
+    pub fn index(self) -> u32 {
+        self.0
+    }
+}
+
 /// Determines freshness of information derived from [`World::archetypes`]
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub struct ArchetypesGeneration(u32);
 
+/// Uniquely identifies a [`World`] within a process
+///
+/// Obtained from [`World::id`]. Never reused, even after the world is dropped.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct WorldId(u64);
+
 /// Entity IDs created by [`World::spawn_batch`]
 pub struct SpawnBatchIter<'a, I>
 where
@@ -1107,6 +3025,10 @@ where
     entities: &'a mut Entities,
     archetype_id: u32,
     archetype: &'a mut Archetype,
+    indices: &'a mut ValueIndices,
+    observers: &'a mut Observers,
+    #[cfg(feature = "trace")]
+    trace: &'a Trace,
 }
 
 impl<I> Drop for SpawnBatchIter<'_, I>
@@ -1140,6 +3062,13 @@ where
             archetype: self.archetype_id,
             index,
         };
+        self.indices.insert(self.archetype, index, entity);
+        self.observers.changed(entity, None);
+        #[cfg(feature = "trace")]
+        self.trace.record(TraceKind::Spawn {
+            entity,
+            archetype: self.archetype_id,
+        });
         Some(entity)
     }
 
@@ -1195,6 +3124,10 @@ struct ArchetypeSet {
     /// Maps sorted component type sets to archetypes
     index: HashMap<Box<[TypeId]>, u32>,
     archetypes: Vec<Archetype>,
+    /// Number of entities to allocate storage for in newly created archetypes
+    initial_capacity: u32,
+    /// Minimum alignment of storage for components of certain types
+    column_align: TypeIdMap<usize>,
 }
 
 impl ArchetypeSet {
@@ -1203,7 +3136,56 @@ impl ArchetypeSet {
         Self {
             index: Some((Box::default(), 0)).into_iter().collect(),
             archetypes: vec![Archetype::new(Vec::new())],
+            initial_capacity: 0,
+            column_align: TypeIdMap::default(),
+        }
+    }
+
+    /// Allocate storage for `ty` components with at least `align` alignment
+    fn set_column_align(&mut self, ty: TypeId, align: usize) {
+        self.column_align.insert(ty, align);
+        for archetype in &mut self.archetypes {
+            if let Some(state) = archetype.get_state_dynamic(ty) {
+                archetype.set_column_align(state, align);
+            }
+        }
+    }
+
+    /// Apply alignments from `set_column_align` to a new archetype
+    fn align_columns(column_align: &TypeIdMap<usize>, archetype: &mut Archetype) {
+        for (&ty, &align) in column_align {
+            if let Some(state) = archetype.get_state_dynamic(ty) {
+                archetype.set_column_align(state, align);
+            }
+        }
+    }
+
+    /// Preallocate storage for `archetypes` archetypes, each with room for `entities` entities
+    fn reserve_exact(&mut self, archetypes: u32, entities: u32) {
+        self.index.reserve(archetypes as usize);
+        self.archetypes
+            .reserve_exact((archetypes as usize).saturating_sub(self.archetypes.len()));
+        self.initial_capacity = entities;
+        for archetype in &mut self.archetypes {
+            archetype.reserve(entities);
+        }
+    }
+
+    /// Check whether an entity can be added to the archetype with exactly `components` without
+    /// exceeding `limits`
+    fn check_capacity(
+        &self,
+        components: &[TypeId],
+        limits: CapacityLimits,
+    ) -> Result<(), CapacityExhausted> {
+        let full = match self.index.get(components) {
+            Some(&id) => self.archetypes[id as usize].len() >= limits.per_archetype,
+            None => self.archetypes.len() >= limits.archetypes as usize,
+        };
+        if full {
+            return Err(CapacityExhausted);
         }
+        Ok(())
     }
 
     /// Find the archetype ID that has exactly `components`
@@ -1220,14 +3202,19 @@ impl ArchetypeSet {
 
     fn insert(&mut self, components: Box<[TypeId]>, info: Vec<TypeInfo>) -> u32 {
         let x = self.archetypes.len() as u32;
-        self.archetypes.push(Archetype::new(info));
+        let mut archetype = Archetype::new(info);
+        Self::align_columns(&self.column_align, &mut archetype);
+        if self.initial_capacity != 0 {
+            archetype.reserve(self.initial_capacity);
+        }
+        self.archetypes.push(archetype);
         let old = self.index.insert(components, x);
         debug_assert!(old.is_none(), "inserted duplicate archetype");
         x
     }
 
     /// Returns archetype ID and starting location index
-    fn insert_batch(&mut self, archetype: Archetype) -> (u32, u32) {
+    fn insert_batch(&mut self, mut archetype: Archetype) -> (u32, u32) {
         let ids = archetype
             .types()
             .iter()
@@ -1246,6 +3233,7 @@ impl ArchetypeSet {
             }
             Entry::Vacant(x) => {
                 // Brand new archetype
+                Self::align_columns(&self.column_align, &mut archetype);
                 let id = self.archetypes.len() as u32;
                 self.archetypes.push(archetype);
                 x.insert(id);
@@ -1343,6 +3331,24 @@ mod tests {
         assert_ne!(a.generation, b.generation);
     }
 
+    #[test]
+    fn key_hash_collision() {
+        let mut builder = crate::EntityBuilderClone::new();
+        builder.add(123_i32);
+        let bundle = builder.build();
+        let hash = (&bundle).key_hash().unwrap();
+
+        let mut world = World::new();
+        let other = world.spawn((true,));
+        let other_arch = world.entities.get(other).unwrap().archetype;
+        // Simulate a different type set having the same hash
+        world.hashed_bundle_to_archetype.insert(hash, other_arch);
+
+        let e = world.spawn(&bundle);
+        assert_eq!(*world.get::<&i32>(e).unwrap(), 123);
+        assert!(world.get::<&bool>(e).is_err());
+    }
+
     #[test]
     fn clear_repeats_entity_id() {
         let mut world = World::new();
@@ -1,5 +1,3 @@
-//!
-
 #[cfg(feature = "macros")]
 #[cfg_attr(miri, ignore)]
 #[test]
@@ -6,6 +6,7 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
+use std::any::TypeId;
 use std::borrow::Cow;
 
 use hecs::*;
@@ -166,6 +167,49 @@ fn query_optional_component() {
     assert!(ents.contains(&(f, Some(true), 456)));
 }
 
+#[test]
+fn query_not() {
+    let mut world = World::new();
+    let e = world.spawn(("abc", 123));
+    let f = world.spawn(("def", 456, true));
+    let g = world.spawn(());
+    let h = world.spawn((true,));
+    world.despawn(h).unwrap();
+    let mut ents = world
+        .query::<Not<(&i32, &bool)>>()
+        .iter()
+        .map(|(e, ())| e)
+        .collect::<Vec<_>>();
+    ents.sort();
+    assert_eq!(ents, [e, g]);
+
+    // Borrows nothing
+    let _borrow = world.get::<&mut bool>(f).unwrap();
+    assert_eq!(world.query::<(&&str, Not<&bool>)>().iter().count(), 1);
+}
+
+#[test]
+fn query_cloned() {
+    let mut world = World::new();
+    let e = world.spawn((String::from("abc"), 123));
+    let f = world.spawn((String::from("def"), 456, true));
+    let mut ents = world
+        .query::<(&String, Option<&bool>, Satisfies<&bool>)>()
+        .with::<&i32>()
+        .iter_cloned()
+        .collect::<Vec<_>>();
+    ents.sort_by_key(|x| x.0);
+    // The query's borrows have been released
+    world.get::<&mut String>(e).unwrap().push('!');
+    assert_eq!(
+        ents,
+        [
+            (e, (String::from("abc"), None, false)),
+            (f, (String::from("def"), Some(true), true))
+        ]
+    );
+}
+
 #[test]
 fn prepare_query() {
     let mut world = World::new();
@@ -222,6 +266,106 @@ fn invalidate_prepared_query() {
     assert!(ents.contains(&(g, 789, "ghi")));
 }
 
+#[test]
+fn prepared_query_multiple_worlds() {
+    let mut a = World::new();
+    let mut b = World::new();
+    let e = a.spawn((123, "abc"));
+    let f = b.spawn((true, 456));
+    b.spawn((789,));
+
+    let mut query = PreparedQuery::<&i32>::default();
+    for _ in 0..2 {
+        let ents = query
+            .query_mut(&mut a)
+            .map(|(e, &i)| (e, i))
+            .collect::<Vec<_>>();
+        assert_eq!(ents, [(e, 123)]);
+        let ents = query
+            .query(&b)
+            .iter()
+            .map(|(e, &i)| (e, i))
+            .collect::<Vec<_>>();
+        assert_eq!(ents.len(), 2);
+        assert!(ents.contains(&(f, 456)));
+    }
+
+    // Changes to one world are still observed after querying another
+    let g = a.spawn((42, true));
+    query.query_mut(&mut b).count();
+    let ents = query
+        .query_mut(&mut a)
+        .map(|(e, &i)| (e, i))
+        .collect::<Vec<_>>();
+    assert_eq!(ents.len(), 2);
+    assert!(ents.contains(&(g, 42)));
+
+    query.forget_others();
+    assert_eq!(query.query_mut(&mut b).count(), 2);
+}
+
+#[test]
+fn entity_names() {
+    let mut world = World::new();
+    let a = world.spawn((123,));
+    let b = world.spawn((456,));
+    world.set_name(a, "a").unwrap();
+    world.set_name(b, "b").unwrap();
+    assert_eq!(world.find_by_name("a"), Some(a));
+    assert_eq!(world.name(b), Some("b"));
+
+    // Renaming frees the old name
+    world.set_name(a, "c").unwrap();
+    assert_eq!(world.find_by_name("a"), None);
+    assert_eq!(world.find_by_name("c"), Some(a));
+
+    // Names are unique
+    world.set_name(b, "c").unwrap();
+    assert_eq!(world.find_by_name("c"), Some(b));
+    assert_eq!(world.name(a), None);
+
+    assert_eq!(world.remove_name(b).as_deref(), Some("c"));
+    assert_eq!(world.find_by_name("c"), None);
+
+    world.set_name(a, "a").unwrap();
+    world.set_name(b, "b").unwrap();
+    world.despawn(a).unwrap();
+    assert_eq!(world.find_by_name("a"), None);
+    assert_eq!(world.name(a), None);
+    assert_eq!(world.set_name(a, "a"), Err(NoSuchEntity));
+    drop(world.take(b).unwrap());
+    assert_eq!(world.find_by_name("b"), None);
+
+    let c = world.spawn(());
+    world.set_name(c, "c").unwrap();
+    world.spawn_at(c, (true,));
+    assert_eq!(world.find_by_name("c"), None);
+    world.set_name(c, "c").unwrap();
+    world.clear();
+    assert_eq!(world.find_by_name("c"), None);
+}
+
+#[test]
+fn flush_with() {
+    let mut world = World::new();
+    let a = world.spawn(());
+    world.despawn(a).unwrap();
+    let reserved = world.reserve_entities(3).collect::<Vec<_>>();
+    assert_eq!(world.pending_reserved(), 3);
+    let mut flushed = Vec::new();
+    world.flush_with(|e| flushed.push(e));
+    flushed.sort();
+    let mut expected = reserved.clone();
+    expected.sort();
+    assert_eq!(flushed, expected);
+    assert_eq!(world.pending_reserved(), 0);
+    for e in reserved {
+        assert!(world.contains(e));
+    }
+
+    world.flush_with(|_| panic!("no entities should be flushed"));
+}
+
 #[test]
 fn random_access_via_view() {
     let mut world = World::new();
@@ -321,6 +465,39 @@ fn view_mut_on_world() {
     assert_eq!(int_str_view.get_mut(e2), None);
 }
 
+#[test]
+fn view_iter_over() {
+    let mut world = World::new();
+    let a = world.spawn((1, "a"));
+    let b = world.spawn((2,));
+    let c = world.spawn(("c",));
+    let d = world.spawn((4, true));
+    world.despawn(d).unwrap();
+
+    let mut view = world.view::<&mut i32>();
+    for (_, x) in view.iter_over_mut(&[c, b, d, a]) {
+        *x *= 10;
+    }
+    drop(view);
+
+    let mut query = world.query::<&i32>();
+    let view = query.view();
+    let found = view
+        .iter_over(&[b, a, c, b])
+        .map(|(e, &x)| (e, x))
+        .collect::<Vec<_>>();
+    assert_eq!(found, [(b, 20), (a, 10), (b, 20)]);
+}
+
+#[test]
+#[should_panic]
+fn view_iter_over_mut_duplicates() {
+    let mut world = World::new();
+    let a = world.spawn((1,));
+    let mut view = world.view::<&mut i32>();
+    view.iter_over_mut(&[a, a]);
+}
+
 #[should_panic]
 #[test]
 fn view_mut_panic() {
@@ -422,6 +599,30 @@ fn cloned_builder() {
     assert_eq!(*world.get::<&i32>(e).unwrap(), 123);
 }
 
+#[test]
+fn spawn_cloned_builders_repeatedly() {
+    let mut a = EntityBuilderClone::new();
+    a.add(String::from("abc")).add(123);
+    let a = a.build();
+    let mut b = EntityBuilderClone::new();
+    b.add(true);
+    let b = b.build();
+
+    let mut world = World::new();
+    let mut spawned = Vec::new();
+    for _ in 0..4 {
+        spawned.push((world.spawn(&a), world.spawn(&b)));
+    }
+    for (e, f) in spawned {
+        assert_eq!(*world.get::<&String>(e).unwrap(), "abc");
+        assert_eq!(*world.get::<&i32>(e).unwrap(), 123);
+        assert!(world.get::<&bool>(e).is_err());
+        assert!(*world.get::<&bool>(f).unwrap());
+        assert!(world.get::<&i32>(f).is_err());
+    }
+    assert_eq!(world.archetypes().filter(|x| !x.is_empty()).count(), 2);
+}
+
 #[test]
 #[cfg(feature = "macros")]
 fn build_dynamic_bundle() {
@@ -709,6 +910,58 @@ fn bad_bundle_derive() {
     world.spawn(Foo { x: 42, y: 42 });
 }
 
+#[test]
+#[cfg(feature = "macros")]
+fn derived_bundle_nested_and_skip() {
+    #[derive(Bundle, DynamicBundleClone, Clone)]
+    struct Physics {
+        position: i32,
+        velocity: u8,
+    }
+
+    #[derive(Bundle, DynamicBundleClone, Clone)]
+    struct Character {
+        name: &'static str,
+        #[bundle(nested)]
+        physics: Physics,
+        #[bundle(skip)]
+        scratch: Vec<u8>,
+    }
+
+    let character = Character {
+        name: "abc",
+        physics: Physics {
+            position: 42,
+            velocity: 7,
+        },
+        scratch: vec![1, 2, 3],
+    };
+    assert!(character.has::<u8>());
+    assert!(!character.has::<Vec<u8>>());
+
+    let mut world = World::new();
+    let e = world.spawn(character.clone());
+    assert_eq!(*world.get::<&i32>(e).unwrap(), 42);
+    assert_eq!(*world.get::<&u8>(e).unwrap(), 7);
+    assert!(world.get::<&Vec<u8>>(e).is_err());
+    assert!(world.satisfies::<(&&str, &i32, &u8)>(e).unwrap());
+
+    let mut builder = EntityBuilderClone::new();
+    builder.add_bundle(character);
+    let f = world.spawn(&builder.build());
+    assert_eq!(*world.get::<&u8>(f).unwrap(), 7);
+
+    world.remove_one::<u8>(f).unwrap();
+    assert!(world.remove::<Character>(f).is_err());
+    assert_eq!(*world.get::<&i32>(f).unwrap(), 42);
+
+    let removed = world.remove::<Character>(e).unwrap();
+    assert_eq!(removed.name, "abc");
+    assert_eq!(removed.physics.position, 42);
+    assert!(removed.scratch.is_empty());
+    assert!(world.entity(e).unwrap().is_empty());
+}
+
 #[test]
 #[cfg_attr(miri, ignore)]
 fn spawn_many() {
@@ -798,6 +1051,178 @@ fn query_batched() {
     assert!(entities.contains(&c));
 }
 
+#[test]
+fn query_group_by() {
+    let mut world = World::new();
+    let a = world.spawn((1u8, 10));
+    let b = world.spawn((2u8, 20));
+    let c = world.spawn((1u8, 30, true));
+    world.spawn((40,));
+    let mut index = GroupIndex::new();
+    for (&key, group) in world.query::<&mut i32>().group_by::<u8>(&mut index) {
+        for (_, x) in group {
+            *x += i32::from(key);
+        }
+    }
+    let mut groups = world
+        .query::<&i32>()
+        .group_by::<u8>(&mut index)
+        .map(|(&k, xs)| (k, xs.map(|(e, &x)| (e, x)).collect::<Vec<_>>()))
+        .collect::<Vec<_>>();
+    groups.sort_by_key(|&(k, _)| k);
+    assert_eq!(groups, [(1, vec![(a, 11), (c, 31)]), (2, vec![(b, 22)])]);
+}
+
+#[test]
+fn query_group_by_cached() {
+    fn groups(world: &World, index: &mut GroupIndex<u8>) -> Vec<(u8, Vec<Entity>)> {
+        let mut groups = world
+            .query::<&i32>()
+            .group_by::<u8>(index)
+            .map(|(&k, xs)| (k, xs.map(|(e, _)| e).collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+        groups.sort();
+        groups
+    }
+
+    let mut world = World::new();
+    let a = world.spawn((1u8, 10));
+    let b = world.spawn((2u8, 20));
+    let mut index = GroupIndex::new();
+    assert_eq!(groups(&world, &mut index), [(1, vec![a]), (2, vec![b])]);
+    assert_eq!(groups(&world, &mut index), [(1, vec![a]), (2, vec![b])]);
+
+    // Key changed in place
+    *world.get::<&mut u8>(b).unwrap() = 1;
+    assert_eq!(groups(&world, &mut index), [(1, vec![a, b])]);
+
+    // Entity added
+    let c = world.spawn((3u8, 30));
+    assert_eq!(groups(&world, &mut index), [(1, vec![a, b]), (3, vec![c])]);
+
+    // Different query
+    assert_eq!(
+        world
+            .query::<&bool>()
+            .group_by::<u8>(&mut index)
+            .map(|(_, xs)| xs.len())
+            .sum::<usize>(),
+        0
+    );
+
+    // Different world
+    let mut other = World::new();
+    let d = other.spawn((3u8, 40));
+    assert_eq!(groups(&other, &mut index), [(3, vec![d])]);
+}
+
+#[test]
+#[should_panic(expected = "already borrowed uniquely")]
+fn illegal_group_by_borrow() {
+    let mut world = World::new();
+    world.spawn((1u8,));
+    world
+        .query::<&mut u8>()
+        .group_by::<u8>(&mut GroupIndex::new())
+        .count();
+}
+
+#[test]
+fn query_trait() {
+    trait Named {
+        fn name(&self) -> String;
+    }
+    impl Named for i32 {
+        fn name(&self) -> String {
+            format!("i32 {}", self)
+        }
+    }
+    impl Named for bool {
+        fn name(&self) -> String {
+            format!("bool {}", self)
+        }
+    }
+
+    let mut registry = TraitRegistry::<dyn Named>::new();
+    registry.register::<i32>(|x| x).register::<bool>(|x| x);
+    assert!(registry.contains::<i32>());
+    assert!(!registry.contains::<&str>());
+
+    let mut world = World::new();
+    let a = world.spawn((1, "abc"));
+    let b = world.spawn((2, true));
+    world.spawn(("def",));
+    let mut names = world
+        .query_trait(&registry)
+        .iter()
+        .map(|(e, x)| (e, x.name()))
+        .collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(
+        names,
+        [
+            (a, "i32 1".to_owned()),
+            (b, "bool true".to_owned()),
+            (b, "i32 2".to_owned()),
+        ]
+    );
+
+    let query = world.query_trait(&registry);
+    assert_eq!(query.iter().count(), 3);
+    // Shared borrows may coexist
+    assert_eq!(world.query::<&i32>().iter().count(), 2);
+}
+
+#[test]
+#[should_panic(expected = "already borrowed")]
+fn illegal_query_trait_borrow() {
+    let mut world = World::new();
+    world.spawn((1,));
+    let mut registry = TraitRegistry::<dyn core::fmt::Debug>::new();
+    registry.register::<i32>(|x| x);
+    let _query = world.query_trait(&registry);
+    world.query::<&mut i32>().iter();
+}
+
+#[test]
+fn query_nth() {
+    let mut world = World::new();
+    for i in 0..10 {
+        world.spawn((i,));
+        world.spawn((i, true));
+        world.spawn(("abc",));
+        world.spawn((i, 'c'));
+    }
+    let expected = world
+        .query::<&i32>()
+        .iter()
+        .map(|(e, _)| e)
+        .collect::<Vec<_>>();
+    let mut prepared = PreparedQuery::<&i32>::new();
+    for n in 0..=expected.len() {
+        assert_eq!(
+            world.query::<&i32>().iter().nth(n).map(|(e, _)| e),
+            expected.get(n).copied()
+        );
+        assert_eq!(
+            prepared.query(&world).iter().nth(n).map(|(e, _)| e),
+            expected.get(n).copied()
+        );
+        assert_eq!(
+            world.query_mut::<&i32>().into_iter().skip(n).count(),
+            expected.len() - n
+        );
+    }
+
+    // Skipping resumes correctly mid-archetype
+    let mut query = world.query::<&i32>();
+    let mut iter = query.iter();
+    assert_eq!(iter.nth(3).map(|(e, _)| e), Some(expected[3]));
+    assert_eq!(iter.nth(12).map(|(e, _)| e), Some(expected[16]));
+    assert_eq!(iter.len(), expected.len() - 17);
+    assert!(iter.nth(expected.len()).is_none());
+}
+
 #[test]
 fn query_mut_batched() {
     let mut world = World::new();
@@ -957,6 +1382,48 @@ fn empty_entity_ref() {
     assert_eq!(r.entity(), e);
 }
 
+#[test]
+fn query_one_siblings() {
+    let mut world = World::new();
+    let a = world.spawn((1, true));
+    let b = world.spawn((2, true));
+    let c = world.spawn((3, true));
+    let d = world.spawn((4,));
+    world.despawn(c).unwrap();
+    let e = world.spawn((5, true));
+
+    let mut query = world.query_one::<&mut i32>(a).unwrap();
+    let (x, mut siblings) = query.get_with_siblings().unwrap();
+    assert!(siblings.contains(b));
+    assert!(!siblings.contains(a));
+    assert!(!siblings.contains(c));
+    assert!(!siblings.contains(d));
+    let [y, z] = siblings.get_many_mut([b, e]);
+    let (y, z) = (y.unwrap(), z.unwrap());
+    *x += *y + *z;
+    *y = 0;
+    assert!(siblings.get_mut(d).is_none());
+    drop(query);
+
+    assert_eq!(*world.get::<&i32>(a).unwrap(), 8);
+    assert_eq!(*world.get::<&i32>(b).unwrap(), 0);
+
+    let mut query = world.query_one::<(&i32, &bool)>(b).unwrap();
+    let (_, siblings) = query.get_with_siblings().unwrap();
+    assert_eq!(siblings.get(e), Some((&5, &true)));
+    assert!(world.query_one::<&i32>(d).unwrap().get().is_some());
+}
+
+#[test]
+#[should_panic(expected = "called QueryOnce::get twice")]
+fn query_one_siblings_twice() {
+    let mut world = World::new();
+    let a = world.spawn((1,));
+    let mut query = world.query_one::<&mut i32>(a).unwrap();
+    query.get();
+    query.get_with_siblings();
+}
+
 #[test]
 fn query_or() {
     let mut world = World::new();
@@ -1062,3 +1529,2371 @@ fn query_many_duplicate() {
     let e = world.spawn(());
     _ = world.query_many_mut::<(), 2>([e, e]);
 }
+
+#[test]
+fn checksum() {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::Hasher;
+
+    let mut registry = HashRegistry::new();
+    registry.register::<i32>().register::<&'static str>();
+    let checksum = |world: &World| {
+        let mut hasher = DefaultHasher::new();
+        world.checksum(&registry, &mut hasher);
+        hasher.finish()
+    };
+
+    let mut a = World::new();
+    let a0 = a.spawn((1, "a"));
+    let a1 = a.spawn((2, true));
+
+    // Same entities and registered components, but different archetypes and storage order
+    let mut b = World::new();
+    b.spawn_at(a1, (false, 2));
+    b.spawn_at(a0, (1,));
+    b.insert_one(a0, "a").unwrap();
+    assert_eq!(checksum(&a), checksum(&b));
+
+    *b.get::<&mut i32>(a1).unwrap() = 3;
+    assert_ne!(checksum(&a), checksum(&b));
+    *b.get::<&mut i32>(a1).unwrap() = 2;
+
+    b.remove_one::<&str>(a0).unwrap();
+    assert_ne!(checksum(&a), checksum(&b));
+}
+
+#[test]
+#[should_panic(expected = "already borrowed uniquely")]
+fn checksum_borrow_conflict() {
+    let mut registry = HashRegistry::new();
+    registry.register::<i32>();
+    let mut world = World::new();
+    let e = world.spawn((1,));
+    let _borrow = world.get::<&mut i32>(e).unwrap();
+    world.checksum(
+        &registry,
+        &mut std::collections::hash_map::DefaultHasher::new(),
+    );
+}
+
+#[test]
+fn frozen_world() {
+    let mut world = World::new();
+    let a = world.spawn((1, "a"));
+    let b = world.spawn((2,));
+    let c = world.reserve_entity();
+
+    let frozen = world.freeze();
+    assert!(frozen.contains(c));
+    assert_eq!(frozen.len(), 3);
+    // Overlapping shared borrows of the same components
+    let x = frozen.get::<i32>(a).unwrap();
+    let mut sum = 0;
+    for (_, (&i, s)) in frozen.query::<(&i32, Option<&&str>)>() {
+        sum += i;
+        assert_eq!(s.is_some(), i == 1);
+    }
+    assert_eq!(sum, 3);
+    assert_eq!(*x, 1);
+    assert_eq!(*frozen.view::<&i32>().get(b).unwrap(), 2);
+    assert_eq!(
+        frozen.query_one::<&&str>(b).unwrap_err(),
+        QueryOneError::Unsatisfied
+    );
+    assert!(matches!(
+        frozen.get::<bool>(a),
+        Err(ComponentError::MissingComponent(_))
+    ));
+
+    let mut world = frozen.thaw();
+    world.insert_one(c, true).unwrap();
+    assert!(world.get::<&mut i32>(a).is_ok());
+}
+
+#[test]
+fn weak_ref() {
+    let mut world = World::new();
+    let a = world.spawn((1, true));
+    let b = world.spawn((2, true));
+    let weak = WeakRef::<i32>::new(a);
+    assert_eq!(weak.entity(), a);
+    assert_eq!(*weak.upgrade(&world).unwrap(), 1);
+
+    // Moved within the archetype
+    world.despawn(b).unwrap();
+    world.spawn((3, true));
+    assert_eq!(*weak.upgrade(&world).unwrap(), 1);
+
+    // Moved to another archetype
+    world.insert_one(a, "abc").unwrap();
+    *weak.upgrade_mut(&world).unwrap() = 4;
+    assert_eq!(*world.get::<&i32>(a).unwrap(), 4);
+
+    // A different world with an identical layout
+    if !cfg!(feature = "world-id-checks") {
+        let mut other = World::new();
+        let c = other.spawn(("def", false));
+        assert_eq!(c.id(), a.id());
+        assert!(weak.upgrade(&other).is_none());
+    }
+
+    world.remove_one::<i32>(a).unwrap();
+    assert!(weak.upgrade(&world).is_none());
+    world.despawn(a).unwrap();
+    assert!(weak.upgrade(&world).is_none());
+}
+
+#[test]
+#[should_panic(expected = "already borrowed")]
+fn weak_ref_borrow_conflict() {
+    let mut world = World::new();
+    let a = world.spawn((1,));
+    let weak = WeakRef::<i32>::new(a);
+    let _shared = weak.upgrade(&world).unwrap();
+    weak.upgrade_mut(&world).unwrap();
+}
+
+#[test]
+fn archetypes_since() {
+    let mut world = World::new();
+    let start = world.archetypes_generation();
+    let a = world.spawn((1u8,));
+    let mid = world.archetypes_generation();
+    world.insert_one(a, 2u64).unwrap();
+    world.spawn((3u8,));
+
+    let types = world
+        .archetypes_since(mid)
+        .map(|archetype| archetype.component_type_info().to_vec())
+        .collect::<Vec<_>>();
+    assert_eq!(types, [vec![TypeInfo::of::<u64>(), TypeInfo::of::<u8>()]]);
+    assert_eq!(world.archetypes_since(start).len(), 2);
+    assert_eq!(
+        world.archetypes_since(world.archetypes_generation()).len(),
+        0
+    );
+}
+
+#[test]
+#[cfg(feature = "world-id-checks")]
+#[should_panic(expected = "used with a World other than the one it belongs to")]
+fn world_id_checks() {
+    let mut a = World::new();
+    let mut b = World::new();
+    let entity = a.spawn((1,));
+    b.spawn((2,));
+    let _ = b.get::<&i32>(entity);
+}
+
+#[test]
+#[cfg(feature = "world-id-checks")]
+fn world_id_checks_spawn_at() {
+    let mut a = World::new();
+    let mut b = World::new();
+    let entity = a.spawn((1,));
+    // Entities resurrected in another world may be used with either
+    b.spawn_at(entity, (2,));
+    assert_eq!(*a.get::<&i32>(entity).unwrap(), 1);
+    assert_eq!(*b.get::<&i32>(entity).unwrap(), 2);
+    assert_eq!(Entity::from_bits(entity.to_bits().get()), Some(entity));
+    assert!(b.query::<&i32>().view().get(entity).is_some());
+    b.despawn(entity).unwrap();
+}
+
+#[test]
+fn value_index() {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    struct Team(u32);
+
+    let mut world = World::new();
+    let a = world.spawn((Team(1),));
+    world.index::<Team, _>(|&t| t);
+    let b = world.spawn((Team(2), true));
+    let batch = world
+        .spawn_batch((0..3).map(|_| (Team(1), 'x')))
+        .collect::<Vec<_>>();
+    let c = world.spawn(("abc",));
+    world.insert_one(c, Team(2)).unwrap();
+
+    let find = |world: &World, team| {
+        let mut found = world
+            .find_by::<Team>(&Team(team))
+            .unwrap()
+            .collect::<Vec<_>>();
+        found.sort();
+        found
+    };
+    let mut ones = vec![a];
+    ones.extend(&batch);
+    ones.sort();
+    assert_eq!(find(&world, 1), ones);
+    let mut twos = vec![b, c];
+    twos.sort();
+    assert_eq!(find(&world, 2), twos);
+
+    // Overwritten in place
+    world.insert_one(b, Team(3)).unwrap();
+    assert_eq!(find(&world, 2), [c]);
+    assert_eq!(find(&world, 3), [b]);
+
+    world.remove_one::<Team>(c).unwrap();
+    assert_eq!(find(&world, 2), []);
+
+    world.despawn(a).unwrap();
+    let d = world.spawn((Team(4),));
+    assert_eq!(d.id(), a.id());
+    assert_eq!(find(&world, 1), batch);
+    assert_eq!(find(&world, 4), [d]);
+
+    // Mutated in place
+    world.get::<&mut Team>(d).unwrap().0 = 1;
+    assert_eq!(find(&world, 4), []);
+    world.reindex(d).unwrap();
+    assert_eq!(find(&world, 1).len(), 4);
+
+    world.clear();
+    assert_eq!(find(&world, 1), []);
+}
+
+#[test]
+fn value_index_missing() {
+    let mut world = World::new();
+    let err = world.find_by::<i32>(&0).err().unwrap();
+    assert_eq!(err.to_string(), "no index of i32 by i32");
+
+    // Keys must have exactly the registered type
+    world.index::<i32, _>(|&x| i64::from(x));
+    world.spawn((0,));
+    assert!(world.find_by::<i32>(&0i32).is_err());
+    assert_eq!(world.find_by::<i32>(&0i64).unwrap().count(), 1);
+}
+
+#[test]
+fn remove_batch() {
+    let mut world = World::new();
+    let a = world.spawn((1, true));
+    let b = world.spawn((2, true, "abc"));
+    let c = world.spawn((3, true));
+    let d = world.spawn(("def",));
+    world.despawn(d).unwrap();
+
+    let removed = world.remove_batch::<(i32, bool)>(&[c, b, d, a, c]);
+    assert_eq!(removed[0], Ok((3, true)));
+    assert_eq!(removed[1], Ok((2, true)));
+    assert_eq!(removed[2], Err(ComponentError::NoSuchEntity));
+    assert_eq!(removed[3], Ok((1, true)));
+    assert!(matches!(
+        removed[4],
+        Err(ComponentError::MissingComponent(_))
+    ));
+    assert_eq!(*world.get::<&&str>(b).unwrap(), "abc");
+    for entity in [a, b, c] {
+        assert!(world.contains(entity));
+        assert!(!world.satisfies::<Or<&i32, &bool>>(entity).unwrap());
+    }
+
+    let rc = std::sync::Arc::new(());
+    let e = world.spawn((4, rc.clone()));
+    let f = world.spawn((5,));
+    world.remove_batch_drop::<(std::sync::Arc<()>,)>(&[e, f]);
+    assert_eq!(std::sync::Arc::strong_count(&rc), 1);
+    assert_eq!(*world.get::<&i32>(e).unwrap(), 4);
+    assert_eq!(*world.get::<&i32>(f).unwrap(), 5);
+}
+
+#[test]
+#[cfg(feature = "borrow-diagnostics")]
+#[should_panic(expected = "i32 already borrowed (most recently borrowed at tests/tests.rs:")]
+fn borrow_diagnostics() {
+    let mut world = World::new();
+    let a = world.spawn((1,));
+    let mut query = world.query::<&i32>();
+    let _iter = query.iter();
+    let _x = world.get::<&mut i32>(a);
+}
+
+#[test]
+fn query_explain() {
+    let mut world = World::new();
+    world.spawn((1, true));
+    world.spawn((2, "abc"));
+    world.spawn(("def",));
+
+    let explanation = world.query::<(&mut i32, Satisfies<&bool>)>().explain();
+    let accesses = explanation
+        .archetypes()
+        .filter(|x| !x.is_empty())
+        .map(|x| (x.types().len(), x.access()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        accesses,
+        [
+            (2, Some(Access::Write)),
+            (2, Some(Access::Write)),
+            (1, None)
+        ]
+    );
+
+    let explanation = world.query::<With<(), &&str>>().explain();
+    assert_eq!(
+        explanation
+            .matched()
+            .map(|x| (x.len(), x.access()))
+            .collect::<Vec<_>>(),
+        [(1, Some(Access::Iterate)), (1, Some(Access::Iterate))]
+    );
+}
+
+#[test]
+fn default_registry() {
+    #[derive(Default)]
+    struct Name(String);
+    #[derive(Default)]
+    struct Marker;
+
+    let mut registry = DefaultRegistry::new();
+    registry
+        .register::<Name>()
+        .register::<Marker>()
+        .register::<u64>();
+    assert!(registry.contains(TypeId::of::<Marker>()));
+    assert!(!registry.contains(TypeId::of::<i32>()));
+
+    let mut builder = EntityBuilder::new();
+    builder
+        .add(Name("abc".into()))
+        .add_default(&registry, TypeId::of::<Name>())
+        .add_default(&registry, TypeId::of::<Marker>())
+        .add_default(&registry, TypeId::of::<u64>());
+    let mut world = World::new();
+    let e = world.spawn(builder.build());
+    assert_eq!(world.get::<&Name>(e).unwrap().0, "");
+    assert!(world.satisfies::<&Marker>(e).unwrap());
+    assert_eq!(*world.get::<&u64>(e).unwrap(), 0);
+
+    let f = world.spawn_defaults(&registry, &[TypeId::of::<u64>(), TypeId::of::<Name>()]);
+    assert_eq!(world.entity(f).unwrap().len(), 2);
+}
+
+#[test]
+#[should_panic(expected = "not registered for default construction")]
+fn default_registry_missing() {
+    let mut world = World::new();
+    world.spawn_defaults(&DefaultRegistry::new(), &[TypeId::of::<i32>()]);
+}
+
+#[test]
+#[cfg(feature = "prefetch")]
+fn query_hinted() {
+    let mut world = World::new();
+    world.spawn_batch((0..100).map(|i| (i, i as u64)));
+    world.spawn_batch((100..103).map(|i| (i, true)));
+    world.spawn((1000u64,));
+    let mut query = world.query::<(&i32, Option<&u64>)>();
+    let hinted = query
+        .iter()
+        .hinted()
+        .map(|(e, (&i, x))| (e, i, x.copied()))
+        .collect::<Vec<_>>();
+    let plain = query
+        .iter()
+        .map(|(e, (&i, x))| (e, i, x.copied()))
+        .collect::<Vec<_>>();
+    assert_eq!(hinted.len(), 103);
+    assert_eq!(hinted, plain);
+    drop(query);
+
+    for (_, x) in world.query_mut::<&mut i32>().into_iter().hinted() {
+        *x += 1;
+    }
+    assert_eq!(
+        world.query::<&i32>().iter().map(|(_, &x)| x).sum::<i32>(),
+        5356
+    );
+}
+
+#[test]
+fn patch_builder_clone() {
+    let mut template = EntityBuilderClone::new();
+    template.add(String::from("abc")).add(123).add(true);
+    let template = template.build();
+    assert_eq!(EntityBuilderClone::from(template.clone()).len(), 3);
+
+    let mut patched = EntityBuilderClone::from(template.clone());
+    *patched.get_mut::<&mut i32>().unwrap() = 456;
+    assert_eq!(patched.remove::<String>().as_deref(), Some("abc"));
+    assert_eq!(patched.remove::<String>(), None);
+    assert_eq!(patched.len(), 2);
+    assert!(!patched.has::<String>());
+    assert!(patched
+        .component_types()
+        .all(|x| x != TypeId::of::<String>()));
+    patched.add(1.5f32);
+    assert_eq!(patched.remove::<bool>(), Some(true));
+    assert_eq!(*patched.get::<&f32>().unwrap(), 1.5);
+    let patched = patched.build();
+
+    let mut world = World::new();
+    let e = world.spawn(&template);
+    let f = world.spawn(&patched);
+    assert_eq!(*world.get::<&i32>(e).unwrap(), 123);
+    assert_eq!(*world.get::<&String>(e).unwrap(), "abc");
+    assert_eq!(*world.get::<&i32>(f).unwrap(), 456);
+    assert_eq!(*world.get::<&f32>(f).unwrap(), 1.5);
+    assert_eq!(world.entity(f).unwrap().len(), 2);
+
+    let mut builder = EntityBuilder::new();
+    assert!(builder.is_empty());
+    builder.add(String::from("def")).add(7u8);
+    assert_eq!(builder.remove::<u8>(), Some(7));
+    assert_eq!(builder.len(), 1);
+    let g = world.spawn(builder.build());
+    assert_eq!(*world.get::<&String>(g).unwrap(), "def");
+    assert!(!world.satisfies::<&u8>(g).unwrap());
+}
+
+#[test]
+fn reserve_entities_contiguous() {
+    let mut world = World::new();
+    let a = world.spawn((1,));
+    let b = world.reserve_entity();
+    let block = world.reserve_entities_contiguous(4).unwrap();
+    assert_eq!(block.len(), 4);
+    assert_eq!(block.ids(), b.id() + 1..b.id() + 5);
+    assert_eq!(block.get(4), None);
+    let entities = block.iter().collect::<Vec<_>>();
+    assert_eq!(entities[2], block.get(2).unwrap());
+    assert!(entities.iter().all(|&e| world.contains(e)));
+    let c = world.reserve_entity();
+    assert_eq!(c.id(), block.ids().end);
+
+    world.flush();
+    for &e in &entities {
+        world.insert_one(e, e.id() - block.ids().start).unwrap();
+    }
+    assert_eq!(*world.get::<&u32>(entities[3]).unwrap(), 3);
+
+    assert!(world.reserve_entities_contiguous(0).unwrap().is_empty());
+    world.despawn(a).unwrap();
+    assert_eq!(
+        world.reserve_entities_contiguous(2).unwrap_err(),
+        NonContiguousReservation
+    );
+    world.spawn(());
+    assert!(world.reserve_entities_contiguous(2).is_ok());
+}
+
+#[test]
+fn capacity_limits() {
+    let mut world = World::with_capacity_limits(3, 3, 2);
+    let a = world.try_spawn((1,)).unwrap();
+    let b = world.try_spawn((2,)).unwrap();
+    assert_eq!(world.try_spawn((3,)), Err(CapacityExhausted));
+
+    // Replacing existing components never moves the entity
+    world.try_insert(a, (4,)).unwrap();
+    assert_eq!(*world.get::<&i32>(a).unwrap(), 4);
+
+    world.try_insert(a, ("abc",)).unwrap();
+    assert_eq!(
+        world.try_insert(b, (true,)),
+        Err(InsertError::CapacityExhausted)
+    );
+    assert!(world.get::<&bool>(b).is_err());
+    world.try_insert(b, ("def",)).unwrap();
+    assert_eq!(world.try_spawn((5, "ghi")).unwrap_err(), CapacityExhausted);
+
+    // Freed IDs can be reused without exceeding the entity limit
+    let c = world.try_spawn((5,)).unwrap();
+    assert_eq!(world.try_spawn(()), Err(CapacityExhausted));
+    world.despawn(c).unwrap();
+    world.try_spawn(()).unwrap();
+
+    world.despawn(a).unwrap();
+    assert_eq!(world.try_insert(a, (1,)), Err(InsertError::NoSuchEntity));
+
+    // Unlimited worlds never fail
+    let mut world = World::new();
+    let e = world.try_spawn((1,)).unwrap();
+    world.try_insert(e, (true,)).unwrap();
+}
+
+#[test]
+fn capacity_limits_required() {
+    let mut world = World::with_capacity_limits(10, 3, 1);
+    world.require::<u8, u16>();
+    let a = world.try_spawn((1u8,)).unwrap();
+    assert_eq!(world.try_spawn((2u8,)), Err(CapacityExhausted));
+    assert_eq!(world.try_spawn((3u8, 4u16)), Err(CapacityExhausted));
+    assert_eq!(world.len(), 1);
+    assert_eq!(*world.get::<&u16>(a).unwrap(), 0);
+
+    let b = world.try_spawn(()).unwrap();
+    assert_eq!(
+        world.try_insert(b, (5u8,)),
+        Err(InsertError::CapacityExhausted)
+    );
+
+    // Requirements already satisfied don't count against the archetype limit
+    world.try_insert(a, (6u8,)).unwrap();
+    world.despawn(a).unwrap();
+    world.try_insert(b, (7u8,)).unwrap();
+    assert_eq!(*world.get::<&u16>(b).unwrap(), 0);
+}
+
+#[test]
+#[cfg(feature = "trace")]
+fn trace() {
+    let mut world = World::new();
+    let e = world.spawn((1, true));
+    world.remove_one::<bool>(e).unwrap();
+    assert_eq!(world.query::<&i32>().iter().count(), 1);
+    let _ = world.view::<&mut i32>();
+    world.despawn(e).unwrap();
+
+    let events = world.trace();
+    let kinds = events.iter().map(|x| x.kind).collect::<Vec<_>>();
+    let from = world.archetypes().position(|a| a.has::<bool>()).unwrap() as u32;
+    let to = world
+        .archetypes()
+        .position(|a| a.has::<i32>() && !a.has::<bool>())
+        .unwrap() as u32;
+    let query = std::any::type_name::<&i32>();
+    let view = std::any::type_name::<&mut i32>();
+    assert_eq!(
+        kinds,
+        [
+            TraceKind::Spawn {
+                entity: e,
+                archetype: from
+            },
+            TraceKind::Remove {
+                entity: e,
+                from,
+                to
+            },
+            TraceKind::Borrow { query },
+            TraceKind::Release { query },
+            TraceKind::Borrow { query: view },
+            TraceKind::Release { query: view },
+            TraceKind::Despawn { entity: e },
+        ]
+    );
+    assert!(events.windows(2).all(|x| x[0].time < x[1].time));
+    assert_eq!(
+        events[1].to_string(),
+        format!("       1: remove from {e:?}, archetype {from} -> {to}")
+    );
+
+    world.set_trace_capacity(2);
+    assert_eq!(world.trace().len(), 2);
+    world.spawn_batch((0..3).map(|i| (i,))).for_each(drop);
+    let events = world.trace();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[1].time, 9);
+
+    world.set_trace_capacity(0);
+    world.spawn(());
+    assert!(world.trace().is_empty());
+}
+
+#[test]
+fn any_all_of() {
+    let mut world = World::new();
+    let a = world.spawn((1, true, "abc"));
+    let b = world.spawn((2, true));
+    let c = world.spawn((3, 'x'));
+    let d = world.spawn(("def",));
+
+    let mut any = world
+        .query::<AnyOf<(&bool, &char)>>()
+        .iter()
+        .map(|(e, ())| e)
+        .collect::<Vec<_>>();
+    any.sort();
+    assert_eq!(any, [a, b, c]);
+
+    let mut all = world
+        .query::<AllOf<(&i32, &bool)>>()
+        .iter()
+        .map(|(e, ())| e)
+        .collect::<Vec<_>>();
+    all.sort();
+    assert_eq!(all, [a, b]);
+
+    assert_eq!(world.query::<AnyOf<()>>().iter().count(), 0);
+    assert_eq!(world.query::<AllOf<()>>().iter().count(), 4);
+    assert!(world.satisfies::<AnyOf<(&u8, &&str)>>(d).unwrap());
+    assert!(!world.satisfies::<AllOf<(&i32, &&str)>>(d).unwrap());
+
+    // Doesn't borrow, so it may coexist with unique borrows of the same components
+    let mut query = world.query::<(&mut bool, AnyOf<(&bool,)>)>();
+    assert_eq!(query.iter().count(), 2);
+}
+
+#[test]
+fn materialized_view() {
+    let mut world = World::new();
+    let a = world.spawn((1, true));
+    let b = world.spawn((2,));
+    world.spawn(("abc",));
+    let mut view = MaterializedView::<&mut i32>::new();
+
+    let collect = |view: &mut MaterializedView<&mut i32>, world: &World| {
+        let mut results = view
+            .query(world)
+            .iter()
+            .map(|(e, &mut i)| (e, i))
+            .collect::<Vec<_>>();
+        results.sort();
+        results
+    };
+
+    assert_eq!(collect(&mut view, &world), [(a, 1), (b, 2)]);
+    for (_, x) in view.query_mut(&mut world) {
+        *x *= 10;
+    }
+    assert_eq!(collect(&mut view, &world), [(a, 10), (b, 20)]);
+
+    // Entities moving between, into, and out of matching archetypes
+    world.remove_one::<bool>(a).unwrap();
+    let c = world.spawn((3, 'x'));
+    world.despawn(b).unwrap();
+    assert_eq!(collect(&mut view, &world), [(a, 10), (c, 3)]);
+
+    // Borrows are released when the borrow is dropped
+    drop(view.query(&world));
+    assert_eq!(*world.get::<&mut i32>(a).unwrap(), 10);
+
+    // Switching worlds
+    let mut other = World::new();
+    let d = other.spawn((4,));
+    assert_eq!(collect(&mut view, &other), [(d, 4)]);
+    world.clear();
+    assert_eq!(collect(&mut view, &world), []);
+}
+
+#[test]
+#[should_panic(expected = "borrow")]
+fn materialized_view_borrow_conflict() {
+    let mut world = World::new();
+    let a = world.spawn((1,));
+    let mut view = MaterializedView::<&mut i32>::new();
+    let _borrow = view.query(&world);
+    let _x = world.get::<&i32>(a).unwrap();
+}
+
+#[test]
+fn archetype_borrow_state() {
+    let mut world = World::new();
+    world.spawn((1, true, 'x'));
+    let state = |world: &World| {
+        let archetype = world.archetypes().find(|a| !a.is_empty()).unwrap();
+        let mut state = archetype
+            .borrow_state()
+            .map(|(ty, state)| (ty.id(), state))
+            .collect::<Vec<_>>();
+        state.sort_by_key(|&(ty, _)| {
+            [
+                TypeId::of::<i32>(),
+                TypeId::of::<bool>(),
+                TypeId::of::<char>(),
+            ]
+            .iter()
+            .position(|&x| x == ty)
+        });
+        state.into_iter().map(|(_, x)| x).collect::<Vec<_>>()
+    };
+    assert_eq!(state(&world), [BorrowState::Unborrowed; 3]);
+
+    // Disjoint columns of the same archetype are borrowed independently
+    let mut a = world.query::<&mut i32>();
+    let mut b = world.query::<(&bool, &char)>();
+    let mut c = world.query::<&bool>();
+    a.iter();
+    b.iter();
+    c.iter();
+    assert_eq!(
+        state(&world),
+        [
+            BorrowState::Unique,
+            BorrowState::Shared(2),
+            BorrowState::Shared(1)
+        ]
+    );
+    drop((a, b, c));
+    assert_eq!(state(&world), [BorrowState::Unborrowed; 3]);
+}
+
+#[test]
+fn dynamic_query() {
+    let mut world = World::new();
+    let a = world.spawn((1i32, true, 'a'));
+    let b = world.spawn((2i32, false));
+    let c = world.spawn((3i32, "c"));
+    world.spawn((4u64,));
+
+    let query = DynamicQuery::builder()
+        .read(TypeId::of::<i32>())
+        .write(TypeId::of::<bool>())
+        .read(TypeId::of::<bool>())
+        .build();
+    let mut borrow = world.query_dynamic(&query);
+    let mut seen = Vec::new();
+    for (entity, mut item) in borrow.iter() {
+        assert!(item.get_raw_mut(TypeId::of::<i32>()).is_none());
+        let flag = item.get_mut::<bool>().unwrap();
+        *flag = !*flag;
+        seen.push((
+            entity,
+            *item.get::<i32>().unwrap(),
+            *item.get::<bool>().unwrap(),
+        ));
+        assert!(item.get::<char>().is_none());
+    }
+    seen.sort_by_key(|&(_, i, _)| i);
+    assert_eq!(seen, [(a, 1, false), (b, 2, true)]);
+    drop(borrow);
+
+    let query = DynamicQuery::builder()
+        .read(TypeId::of::<i32>())
+        .with(TypeId::of::<i32>())
+        .without(TypeId::of::<bool>())
+        .build();
+    let mut borrow = world.query_dynamic(&query);
+    let seen = borrow
+        .iter()
+        .map(|(e, item)| (e, *item.get::<i32>().unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(seen, [(c, 3)]);
+    // Shared borrows coexist with other shared borrows
+    assert_eq!(world.query::<&i32>().iter().count(), 3);
+}
+
+#[test]
+#[should_panic(expected = "already borrowed")]
+fn dynamic_query_borrow_conflict() {
+    let mut world = World::new();
+    world.spawn((1i32,));
+    let query = DynamicQuery::builder().write(TypeId::of::<i32>()).build();
+    let _borrow = world.query_dynamic(&query);
+    world.query::<&i32>().iter();
+}
+
+#[test]
+fn dynamic_item_raw() {
+    let mut world = World::new();
+    let e = world.spawn((7i32, 1.5f32, true));
+    let query = DynamicQuery::builder()
+        .read(TypeId::of::<i32>())
+        .write(TypeId::of::<f32>())
+        .with(TypeId::of::<bool>())
+        .build();
+    let mut borrow = world.query_dynamic(&query);
+    let (entity, item) = borrow.iter().next().unwrap();
+    assert_eq!(entity, e);
+
+    let mut components = item.iter().collect::<Vec<_>>();
+    components.sort_by_key(|&(_, _, access)| access);
+    assert_eq!(components.len(), 2);
+    assert_eq!(components[0].0, TypeId::of::<i32>());
+    assert_eq!(components[0].2, Access::Read);
+    assert_eq!(unsafe { *components[0].1.cast::<i32>() }, 7);
+    assert_eq!(components[1].0, TypeId::of::<f32>());
+    assert_eq!(components[1].2, Access::Write);
+
+    assert!(item.get_raw(TypeId::of::<bool>()).is_none());
+    let mut raw = item.take_raw(TypeId::of::<f32>()).unwrap();
+    assert_eq!(raw.type_info(), TypeInfo::of::<f32>());
+    assert_eq!(raw.access(), Access::Write);
+    unsafe {
+        *raw.as_mut_ptr().unwrap().cast::<f32>() = 2.5;
+    }
+    drop(borrow);
+    assert_eq!(*world.get::<&f32>(e).unwrap(), 2.5);
+}
+
+#[test]
+fn query_one_shared() {
+    let mut world = World::new();
+    let a = world.spawn((1i32, true));
+    let b = world.spawn((2i32,));
+    let query = world.query_one_shared::<(&i32, Option<&bool>)>(a).unwrap();
+    assert_eq!(query.get(), (&1, Some(&true)));
+    assert_eq!(query.get(), (&1, Some(&true)));
+    // Shared borrows may overlap
+    let other = world.query_one_shared::<&i32>(a).unwrap();
+    assert_eq!(*other.get(), 1);
+    drop((query, other));
+
+    assert!(matches!(
+        world.query_one_shared::<&bool>(b),
+        Err(QueryOneError::Unsatisfied)
+    ));
+    world.despawn(b).unwrap();
+    assert!(matches!(
+        world.query_one_shared::<&i32>(b),
+        Err(QueryOneError::NoSuchEntity)
+    ));
+    // Borrows are released on drop
+    assert_eq!(world.query_one_mut::<&mut i32>(a).map(|x| *x), Ok(1));
+}
+
+#[test]
+#[should_panic(expected = "already borrowed")]
+fn query_one_shared_borrow_conflict() {
+    let mut world = World::new();
+    let a = world.spawn((1i32,));
+    let mut query = world.query::<&mut i32>();
+    let _items = query.iter();
+    let _ = world.query_one_shared::<&i32>(a);
+}
+
+#[test]
+fn iter_archetype() {
+    let mut world = World::new();
+    let a = world.spawn((1i32, true));
+    let b = world.spawn((2i32,));
+    let c = world.spawn((3i32, false));
+    let index = world.archetype_id(a).unwrap();
+    assert_eq!(world.archetype_id(c), Ok(index));
+    assert_ne!(world.archetype_id(b), Ok(index));
+
+    let mut iter = world.iter_archetype(index);
+    assert_eq!(iter.len(), 2);
+    assert_eq!(iter.next().map(|e| e.entity()), Some(a));
+    assert_eq!(iter.len(), 1);
+    assert_eq!(iter.next().map(|e| e.entity()), Some(c));
+    assert!(iter.next().is_none());
+
+    world.remove_one::<bool>(a).unwrap();
+    assert_eq!(world.archetype_id(a), world.archetype_id(b));
+    let ids = world
+        .iter_archetype(index)
+        .map(|e| e.entity())
+        .collect::<Vec<_>>();
+    assert_eq!(ids, [c]);
+
+    world.despawn(a).unwrap();
+    assert!(world.archetype_id(a).is_err());
+}
+
+#[test]
+fn entity_stats() {
+    let mut world = World::new();
+    assert_eq!(
+        world.entity_stats(0),
+        EntityStats {
+            slots: 0,
+            free: 0,
+            max_generation: 0,
+            near_wraparound: 0,
+        }
+    );
+
+    let a = world.spawn(());
+    let b = world.spawn(());
+    world.despawn(a).unwrap();
+    let old = Entity::from_bits(u64::from(u32::MAX - 1) << 32 | 5).unwrap();
+    world.spawn_at(old, ());
+    let stats = world.entity_stats(10);
+    assert_eq!(stats.slots, 6);
+    assert_eq!(stats.free, 4);
+    assert_eq!(stats.max_generation, u32::MAX - 1);
+    assert_eq!(stats.near_wraparound, 1);
+    assert_eq!(world.entity_stats(0).near_wraparound, 0);
+
+    world.despawn(old).unwrap();
+    world.despawn(b).unwrap();
+    let stats = world.entity_stats(10);
+    assert_eq!(stats.free, 6);
+    assert_eq!(stats.max_generation, u32::MAX);
+    assert_eq!(stats.near_wraparound, 1);
+}
+
+#[test]
+fn fill() {
+    let mut world = World::new();
+    world.index::<u32, _>(|&x| x);
+    let a = world.spawn((1u32,));
+    let b = world.spawn((2u32, true));
+    let c = world.spawn((true,));
+    world.fill(7u32);
+    assert_eq!(*world.get::<&u32>(a).unwrap(), 7);
+    assert_eq!(*world.get::<&u32>(b).unwrap(), 7);
+    assert!(world.get::<&u32>(c).is_err());
+    let mut found = world.find_by::<u32>(&7u32).unwrap().collect::<Vec<_>>();
+    found.sort();
+    assert_eq!(found, [a, b]);
+
+    // Previous values are dropped
+    let rc = std::sync::Arc::new(());
+    world.spawn((rc.clone(),));
+    world.spawn((rc.clone(), 0u32));
+    assert_eq!(std::sync::Arc::strong_count(&rc), 3);
+    world.fill(std::sync::Arc::new(()));
+    assert_eq!(std::sync::Arc::strong_count(&rc), 1);
+}
+
+#[test]
+fn for_each_chunk() {
+    let mut world = World::new();
+    let a = world.spawn((1i32, 10u64));
+    let b = world.spawn((2i32, 20u64, true));
+    let c = world.spawn((3i32, 30u64, true));
+    world.spawn((4i32,));
+    let mut chunks = Vec::new();
+    world
+        .query_mut::<(&mut i32, &u64)>()
+        .for_each_chunk(|ids, (xs, ys)| {
+            assert_eq!(ids.len(), xs.len());
+            assert_eq!(ids.len(), ys.len());
+            for (x, &y) in xs.iter_mut().zip(ys) {
+                *x += y as i32;
+            }
+            chunks.push(ids.to_vec());
+        });
+    chunks.sort();
+    assert_eq!(chunks, [vec![a.id()], vec![b.id(), c.id()]]);
+    assert_eq!(*world.get::<&i32>(a).unwrap(), 11);
+    assert_eq!(*world.get::<&i32>(b).unwrap(), 22);
+    assert_eq!(*world.get::<&i32>(c).unwrap(), 33);
+
+    let mut total = 0;
+    world
+        .query_mut::<&i32>()
+        .for_each_chunk(|_, xs| total += xs.iter().sum::<i32>());
+    assert_eq!(total, 11 + 22 + 33 + 4);
+}
+
+#[test]
+fn remove_dynamic() {
+    let mut world = World::new();
+    let rc = std::sync::Arc::new(());
+    let a = world.spawn((1i32, true, rc.clone()));
+
+    assert!(matches!(
+        world.remove_dynamic(a, &[TypeId::of::<i32>(), TypeId::of::<char>()]),
+        Err(ComponentError::MissingComponent(_))
+    ));
+    assert!(world.satisfies::<(&i32, &bool)>(a).unwrap());
+
+    let mut taken = world
+        .remove_dynamic(
+            a,
+            &[TypeId::of::<std::sync::Arc<()>>(), TypeId::of::<i32>()],
+        )
+        .unwrap();
+    assert_eq!(taken.len(), 2);
+    assert!(taken.has::<i32>());
+    assert!(!taken.has::<bool>());
+    *taken.get_mut::<&mut i32>().unwrap() = 2;
+    assert!(!world.satisfies::<&i32>(a).unwrap());
+    assert!(world.satisfies::<&bool>(a).unwrap());
+    assert_eq!(std::sync::Arc::strong_count(&rc), 2);
+
+    let b = world.spawn(taken);
+    assert_eq!(*world.get::<&i32>(b).unwrap(), 2);
+    assert_eq!(std::sync::Arc::strong_count(&rc), 2);
+
+    drop(world.remove_dynamic(b, &[TypeId::of::<std::sync::Arc<()>>()]));
+    assert_eq!(std::sync::Arc::strong_count(&rc), 1);
+
+    world.despawn(b).unwrap();
+    assert!(matches!(
+        world.remove_dynamic(b, &[]),
+        Err(ComponentError::NoSuchEntity)
+    ));
+}
+
+#[test]
+fn observers() {
+    use std::sync::{Arc, Mutex};
+
+    let mut world = World::new();
+    let matched = Arc::new(Mutex::new(Vec::new()));
+    let unmatched = Arc::new(Mutex::new(Vec::new()));
+    let sink = matched.clone();
+    world.on_match::<(&i32, &bool)>(move |e| sink.lock().unwrap().push(e));
+    let sink = unmatched.clone();
+    world.on_unmatch::<(&i32, &bool)>(move |e| sink.lock().unwrap().push(e));
+    let take = |x: &Arc<Mutex<Vec<Entity>>>| {
+        let mut x = std::mem::take(&mut *x.lock().unwrap());
+        x.sort();
+        x
+    };
+
+    let a = world.spawn((1i32, true));
+    let b = world.spawn((2i32,));
+    let c = world.spawn((3i32, false, 'c'));
+    // Observers only run on explicit flushes
+    assert!(take(&matched).is_empty());
+    world.flush();
+    assert_eq!(take(&matched), [a, c]);
+    assert!(take(&unmatched).is_empty());
+
+    // Changes which don't affect matching are ignored
+    world.insert_one(a, 'a').unwrap();
+    world.remove_one::<char>(c).unwrap();
+    world.flush();
+    assert!(take(&matched).is_empty());
+    assert!(take(&unmatched).is_empty());
+
+    // Only net changes are reported
+    world.insert_one(b, true).unwrap();
+    world.remove_one::<bool>(b).unwrap();
+    world.remove_one::<bool>(a).unwrap();
+    world.insert_one(a, true).unwrap();
+    world.flush();
+    assert!(take(&matched).is_empty());
+    assert!(take(&unmatched).is_empty());
+
+    world.insert_one(b, false).unwrap();
+    world.remove_one::<i32>(a).unwrap();
+    world.despawn(c).unwrap();
+    world.flush();
+    assert_eq!(take(&matched), [b]);
+    assert_eq!(take(&unmatched), [a, c]);
+
+    let d = world.reserve_entity();
+    world.insert(d, (4i32, true)).unwrap();
+    let e = world.spawn_batch([(5i32, true)]).next().unwrap();
+    world.flush();
+    assert_eq!(take(&matched), [d, e]);
+
+    world.clear();
+    world.flush();
+    assert_eq!(take(&unmatched), [b, d, e]);
+}
+
+#[test]
+fn column_alignment() {
+    fn check(world: &World, align: usize) {
+        for archetype in world.archetypes() {
+            if let Some(column) = archetype.get::<&u8>() {
+                assert_eq!(column.as_ptr() as usize % align, 0);
+            }
+        }
+    }
+
+    let mut world = World::new();
+    let a = world.spawn((1u8,));
+    world.set_column_alignment::<u8>(128);
+    check(&world, 128);
+    assert_eq!(*world.get::<&u8>(a).unwrap(), 1);
+
+    // New archetypes, including those created by growing and by batches
+    for i in 0..100 {
+        world.spawn((i as u8, true));
+    }
+    world.insert_one(a, 'a').unwrap();
+    let mut batch = ColumnBatchType::new();
+    batch.add::<u8>().add::<u16>();
+    let mut batch = batch.into_batch(2);
+    let mut w = batch.writer::<u8>().unwrap();
+    w.push(7).unwrap();
+    w.push(8).unwrap();
+    let mut w = batch.writer::<u16>().unwrap();
+    w.push(9).unwrap();
+    w.push(10).unwrap();
+    world.spawn_column_batch(batch.build().unwrap());
+    check(&world, 128);
+    assert_eq!(*world.get::<&u8>(a).unwrap(), 1);
+    assert_eq!(world.query_mut::<&u8>().into_iter().count(), 103);
+
+    world.set_column_alignment::<u8>(4096);
+    check(&world, 4096);
+    let mut values = world
+        .query_mut::<&u8>()
+        .into_iter()
+        .map(|(_, &x)| x)
+        .collect::<Vec<_>>();
+    values.sort_unstable();
+    assert_eq!(values[..3], [0, 1, 1]);
+}
+
+#[test]
+fn query_cursor() {
+    let mut world = World::new();
+    let entities = (0..10).map(|i| world.spawn((i,))).collect::<Vec<_>>();
+    let mut cursor = QueryCursor::<&mut i32>::new();
+    assert!(cursor.is_at_start());
+    let mut seen = Vec::new();
+    for (e, x) in cursor.iter(&mut world).take(3) {
+        *x += 100;
+        seen.push(e);
+    }
+    assert!(!cursor.is_at_start());
+
+    // Move a visited and an unvisited entity to new archetypes, and despawn another
+    world.insert_one(entities[0], true).unwrap();
+    world.insert_one(entities[5], 'x').unwrap();
+    world.despawn(entities[7]).unwrap();
+    world.remove_one::<i32>(entities[8]).unwrap();
+
+    let mut iter = cursor.iter(&mut world);
+    for (e, x) in &mut iter {
+        *x += 100;
+        seen.push(e);
+    }
+    assert!(iter.next().is_none());
+    assert!(cursor.is_at_start());
+
+    let expected = entities
+        .iter()
+        .copied()
+        .filter(|&e| e != entities[7] && e != entities[8])
+        .collect::<Vec<_>>();
+    assert_eq!(seen, expected);
+    for (i, &e) in entities.iter().enumerate() {
+        if let Ok(x) = world.get::<&i32>(e) {
+            assert_eq!(*x, i as i32 + 100);
+        }
+    }
+
+    // A new pass visits everything again
+    assert_eq!(cursor.iter(&mut world).count(), 8);
+}
+
+#[test]
+fn tag_untag() {
+    #[derive(Default)]
+    struct Marker;
+
+    let mut world = World::new();
+    let a = world.spawn((1, "a"));
+    let b = world.spawn((2, "b"));
+    world.tag::<Marker>(a).unwrap();
+    world.tag::<Marker>(a).unwrap();
+    assert!(world.satisfies::<&Marker>(a).unwrap());
+    assert!(!world.satisfies::<&Marker>(b).unwrap());
+    assert_eq!(*world.get::<&i32>(a).unwrap(), 1);
+    assert_eq!(*world.get::<&&str>(a).unwrap(), "a");
+
+    // Tagging with a sized component keeps an existing value
+    world.insert_one(b, 5u8).unwrap();
+    world.tag::<u8>(b).unwrap();
+    assert_eq!(*world.get::<&u8>(b).unwrap(), 5);
+
+    assert!(world.untag::<Marker>(a).unwrap());
+    assert!(!world.untag::<Marker>(a).unwrap());
+    assert!(!world.satisfies::<&Marker>(a).unwrap());
+    assert_eq!(*world.get::<&i32>(a).unwrap(), 1);
+    assert!(world.untag::<u8>(b).unwrap());
+    assert_eq!(*world.get::<&&str>(b).unwrap(), "b");
+
+    world.despawn(a).unwrap();
+    assert!(world.tag::<Marker>(a).is_err());
+    assert!(world.untag::<Marker>(a).is_err());
+}
+
+#[test]
+fn batched_by_archetype() {
+    let mut world = World::new();
+    for i in 0..100 {
+        world.spawn((i,));
+    }
+    for i in 0..10 {
+        world.spawn((i, true));
+    }
+    let e = world.spawn((0, 'x'));
+    world.despawn(e).unwrap();
+    world.spawn(("unmatched",));
+
+    let mut query = world.query::<&i32>();
+    let mut lens = query
+        .iter_batched_by_archetype()
+        .map(|batch| batch.count())
+        .collect::<Vec<_>>();
+    lens.sort_unstable();
+    assert_eq!(lens, [10, 100]);
+    drop(query);
+
+    let mut lens = world
+        .query_mut::<&mut i32>()
+        .into_iter_batched_by_archetype()
+        .map(|batch| batch.count())
+        .collect::<Vec<_>>();
+    lens.sort_unstable();
+    assert_eq!(lens, [10, 100]);
+}
+
+#[test]
+fn prepared_query_refresh() {
+    let mut world = World::new();
+    let mut other = World::new();
+    world.spawn((1,));
+    let mut query = PreparedQuery::<&i32>::new();
+    assert!(query.is_stale(&world));
+    query.refresh(&world);
+    assert!(!query.is_stale(&world));
+    assert_eq!(query.invalidations(), 0);
+
+    // Spawning into an existing archetype leaves the preparation valid
+    world.spawn((2,));
+    assert!(!query.is_stale(&world));
+
+    // Using another world doesn't invalidate anything
+    other.spawn((3,));
+    assert_eq!(query.query(&other).iter().count(), 1);
+    assert!(query.is_stale(&world));
+    query.refresh(&world);
+    assert_eq!(query.invalidations(), 0);
+
+    // Gaining an archetype does, whether or not the world was most recently used
+    world.spawn((4, true));
+    query.refresh(&other);
+    query.refresh(&world);
+    assert_eq!(query.invalidations(), 1);
+    assert_eq!(query.query_mut(&mut world).count(), 3);
+    assert_eq!(query.invalidations(), 1);
+}
+
+#[test]
+fn spawn_many_builders() {
+    let mut world = World::new();
+    let mut builders = (0..6).map(|_| EntityBuilder::new()).collect::<Vec<_>>();
+    let entities = world.spawn_many(builders.iter_mut().enumerate().map(|(i, builder)| {
+        builder.add(i);
+        if i % 3 != 0 {
+            builder.add("x");
+        }
+        builder.build()
+    }));
+    assert_eq!(entities.len(), 6);
+    for (i, &e) in entities.iter().enumerate() {
+        assert_eq!(*world.get::<&usize>(e).unwrap(), i);
+        assert_eq!(world.satisfies::<&&str>(e).unwrap(), i % 3 != 0);
+    }
+    assert_eq!(world.len(), 6);
+
+    // Statically typed bundles work too, including empty ones
+    let entities = world.spawn_many([(), ()]);
+    assert!(world.contains(entities[1]));
+}
+
+#[test]
+fn get_many_mut() {
+    let mut world = World::new();
+    let a = world.spawn((1, "a"));
+    let b = world.spawn((2, "b"));
+    let c = world.spawn(("c",));
+    let d = world.spawn((4, "d"));
+    world.despawn(d).unwrap();
+
+    let [x, y, z, w] = world.get_many_mut::<i32, 4>([a, b, c, d]);
+    *x.unwrap() += 10;
+    *y.unwrap() += 20;
+    assert!(matches!(z, Err(ComponentError::MissingComponent(_))));
+    assert!(matches!(w, Err(ComponentError::NoSuchEntity)));
+    assert_eq!(*world.get::<&i32>(a).unwrap(), 11);
+    assert_eq!(*world.get::<&i32>(b).unwrap(), 22);
+}
+
+#[test]
+#[should_panic]
+fn get_many_mut_duplicate() {
+    let mut world = World::new();
+    let a = world.spawn((1,));
+    let _ = world.get_many_mut::<i32, 2>([a, a]);
+}
+
+#[test]
+fn query_archetype() {
+    let mut world = World::new();
+    let a = world.spawn((1, true));
+    let b = world.spawn((2, true));
+    let c = world.spawn((3,));
+    let id = world.archetype_id(a).unwrap();
+    assert_eq!(world.entity(b).unwrap().archetype_id(), id);
+    assert_ne!(world.entity(c).unwrap().archetype_id(), id);
+
+    let mut query = world.query_archetype::<&mut i32>(id);
+    for (_, x) in query.iter() {
+        *x *= 10;
+    }
+    assert_eq!(query.iter().count(), 2);
+    drop(query);
+
+    // Unsatisfied queries yield nothing and borrow nothing
+    let mut query = world.query_archetype::<&char>(id);
+    assert_eq!(query.iter().count(), 0);
+    drop(query);
+
+    let mut results = world
+        .query_archetype_mut::<&i32>(id)
+        .map(|(e, &x)| (e, x))
+        .collect::<Vec<_>>();
+    results.sort_unstable();
+    assert_eq!(results, [(a, 10), (b, 20)]);
+    assert_eq!(*world.get::<&i32>(c).unwrap(), 3);
+}
+
+#[test]
+#[should_panic]
+fn query_archetype_borrow_conflict() {
+    let mut world = World::new();
+    let a = world.spawn((1,));
+    let id = world.archetype_id(a).unwrap();
+    let mut query = world.query_archetype::<&mut i32>(id);
+    let _iter = query.iter();
+    let _ = world.get::<&i32>(a);
+}
+
+#[test]
+fn build_ref() {
+    let mut world = World::new();
+    let mut builder = EntityBuilder::new();
+    builder
+        .add_cloneable(String::from("abc"))
+        .add_bundle_cloneable((1u8, 2i32));
+    let bundle = builder.build_ref().unwrap();
+    let entities = (0..3).map(|_| world.spawn(bundle)).collect::<Vec<_>>();
+    for &e in &entities {
+        assert_eq!(*world.get::<&String>(e).unwrap(), "abc");
+        assert_eq!(*world.get::<&i32>(e).unwrap(), 2);
+    }
+
+    // The builder remains usable after sorting
+    assert_eq!(builder.get::<&u8>(), Some(&1));
+    builder.add_cloneable(3i32);
+    assert_eq!(builder.len(), 3);
+    let e = world.spawn(builder.build_ref().unwrap());
+    assert_eq!(*world.get::<&i32>(e).unwrap(), 3);
+
+    builder.add(true);
+    let Err(err) = builder.build_ref() else {
+        panic!("non-cloneable component accepted");
+    };
+    assert_eq!(err.type_id(), core::any::TypeId::of::<bool>());
+    #[cfg(debug_assertions)]
+    assert_eq!(err.to_string(), "bool component was not added as cloneable");
+
+    // Replacing a component with a cloneable one makes it cloneable
+    builder.add_cloneable(false);
+    builder.build_ref().unwrap();
+    builder.add(true);
+    assert!(builder.build_ref().is_err());
+
+    // `build` still moves everything out
+    let e = world.spawn(builder.build());
+    assert!(builder.is_empty());
+    assert!(world.satisfies::<(&String, &bool)>(e).unwrap());
+}
+
+#[test]
+fn debug_dump() {
+    #[derive(Debug)]
+    #[allow(dead_code)]
+    struct Health(u32);
+    struct Opaque;
+
+    let mut registry = DebugRegistry::new();
+    registry.register::<Health>().register::<bool>();
+    assert!(registry.contains(core::any::TypeId::of::<Health>()));
+    assert!(!registry.contains(core::any::TypeId::of::<Opaque>()));
+
+    let mut world = World::new();
+    let e = world.spawn((Health(10), true, Opaque));
+    let entity = world.entity(e).unwrap();
+    let infos = entity.component_infos().collect::<Vec<_>>();
+    assert_eq!(infos.len(), 3);
+    assert!(infos.contains(&TypeInfo::of::<Opaque>()));
+
+    let dump = format!("{:?}", entity.debug_dump(&registry));
+    assert!(dump.contains("Health(10)"), "{}", dump);
+    assert!(dump.contains("bool: true"), "{}", dump);
+    assert!(dump.contains("<unregistered>"), "{}", dump);
+
+    // Uniquely borrowed components are skipped rather than panicking
+    let health = world.get::<&mut Health>(e).unwrap();
+    let dump = format!("{:?}", entity.debug_dump(&registry));
+    assert!(dump.contains("<borrowed>"), "{}", dump);
+    drop(health);
+    assert!(world.get::<&mut Health>(e).is_ok());
+}
+
+#[test]
+fn world_id() {
+    use std::collections::HashMap;
+
+    let mut a = World::new();
+    let b = World::new();
+    assert_ne!(a.id(), b.id());
+    let mut cache = HashMap::new();
+    cache.insert((a.id(), a.archetypes_generation()), "a");
+    cache.insert((b.id(), b.archetypes_generation()), "b");
+    a.spawn((1,));
+    assert!(!cache.contains_key(&(a.id(), a.archetypes_generation())));
+    assert_eq!(cache[&(b.id(), b.archetypes_generation())], "b");
+}
+
+#[test]
+fn query_scope() {
+    let mut world = World::new();
+    let a = world.spawn((1, 2.0f32));
+    let b = world.spawn((3, 4.0f32, true));
+    world.query_scope(|scope| {
+        let ints = scope.query::<&i32>().collect::<Vec<_>>();
+        let more_ints = scope.view::<&i32>();
+        for (_, f) in scope.query::<&mut f32>() {
+            *f += 1.0;
+        }
+        assert_eq!(ints.len(), 2);
+        assert_eq!(*more_ints.get(b).unwrap(), 3);
+    });
+    assert_eq!(*world.get::<&f32>(a).unwrap(), 3.0);
+    assert_eq!(*world.get::<&f32>(b).unwrap(), 5.0);
+}
+
+#[test]
+#[should_panic(expected = "query conflicts with an earlier query in this scope")]
+fn query_scope_conflict() {
+    let mut world = World::new();
+    world.spawn((1,));
+    world.query_scope(|scope| {
+        let _shared = scope.query::<&i32>();
+        let _unique = scope.query::<&mut i32>();
+    });
+}
+
+#[test]
+fn count() {
+    let mut world = World::new();
+    world.spawn((1, true));
+    world.spawn((2,));
+    let e = world.spawn((3, "abc"));
+    assert_eq!(world.count::<i32>(), 3);
+    assert_eq!(world.count::<bool>(), 1);
+    assert_eq!(world.count::<f32>(), 0);
+    assert_eq!(world.count_query::<Without<&i32, &mut bool>>(), 2);
+    world.despawn(e).unwrap();
+    assert_eq!(world.count::<i32>(), 2);
+    assert_eq!(world.count::<&str>(), 0);
+}
+
+#[test]
+fn resolve_id() {
+    let mut world = World::new();
+    let a = world.spawn((1,));
+    assert_eq!(world.resolve_id(a.id()), Some(a));
+    assert_eq!(world.resolve_id(a.id() + 1), None);
+    let reserved = world.reserve_entity();
+    assert_eq!(world.resolve_id(reserved.id()), Some(reserved));
+    world.despawn(a).unwrap();
+    assert_eq!(world.resolve_id(a.id()), None);
+    let b = world.spawn((2,));
+    assert_eq!(b.id(), a.id());
+    assert_eq!(world.resolve_id(a.id()), Some(b));
+}
+
+#[test]
+fn drain() {
+    let mut world = World::new();
+    let a = world.spawn((1, "a"));
+    let b = world.spawn((2, "b", true));
+    let c = world.spawn((3, true));
+    let d = world.spawn((true,));
+    let mut drained = world.drain::<(i32, &str)>();
+    drained.sort_unstable();
+    assert_eq!(drained, [(a, (1, "a")), (b, (2, "b"))]);
+    assert!(world.contains(a));
+    assert!(world.entity(a).unwrap().component_types().next().is_none());
+    assert!(*world.get::<&bool>(b).unwrap());
+    assert_eq!(*world.get::<&i32>(c).unwrap(), 3);
+    assert!(world.drain::<(i32, &str)>().is_empty());
+
+    let drained = world.drain_despawn::<(bool,)>();
+    assert_eq!(drained.len(), 3);
+    assert!(world.contains(a));
+    assert!(!world.contains(b) && !world.contains(c) && !world.contains(d));
+    assert_eq!(world.len(), 1);
+}
+
+#[test]
+fn drain_despawn_drops_others() {
+    use std::sync::Arc;
+
+    let arc = Arc::new(());
+    let mut world = World::new();
+    let a = world.spawn((1, arc.clone()));
+    world.spawn((2, arc.clone()));
+    let b = world.spawn((arc.clone(),));
+    assert_eq!(Arc::strong_count(&arc), 4);
+    let mut drained = world.drain_despawn::<(i32,)>();
+    drained.sort_unstable();
+    assert_eq!(drained[0], (a, (1,)));
+    assert_eq!(Arc::strong_count(&arc), 2);
+    assert!(world.contains(b));
+    let e = world.spawn((3,));
+    assert_eq!(*world.get::<&i32>(e).unwrap(), 3);
+}
+
+#[test]
+#[should_panic(expected = "i32 already borrowed")]
+fn borrow_column_conflict() {
+    let mut world = World::new();
+    let e = world.spawn((1,));
+    let archetype = world.archetype(world.archetype_id(e).unwrap());
+    let _column = archetype.borrow_column_mut::<i32>().unwrap();
+    assert!(archetype.borrow_column::<bool>().is_none());
+    let _query = world.query::<&i32>().iter().count();
+}
+
+#[test]
+fn invalidate() {
+    let mut world = World::new();
+    let a = world.spawn((1, true));
+    let other = world.spawn((2,));
+    let b = world.invalidate(a).unwrap();
+    assert_ne!(a, b);
+    assert_eq!(a.id(), b.id());
+    assert!(!world.contains(a));
+    assert_eq!(world.invalidate(a), Err(NoSuchEntity));
+    assert!(world.get::<&i32>(a).is_err());
+    assert_eq!(*world.get::<&i32>(b).unwrap(), 1);
+    assert!(*world.get::<&bool>(b).unwrap());
+    assert_eq!(*world.get::<&i32>(other).unwrap(), 2);
+    assert_eq!(world.len(), 2);
+    let mut entities = world.iter().map(|e| e.entity()).collect::<Vec<_>>();
+    entities.sort();
+    let mut expected = vec![b, other];
+    expected.sort();
+    assert_eq!(entities, expected);
+    world.despawn(b).unwrap();
+    assert_eq!(world.len(), 1);
+}
+
+#[test]
+fn invalidate_names_and_indices() {
+    let mut world = World::new();
+    world.index::<i32, _>(|&x| x);
+    let a = world.spawn((7,));
+    world.set_name(a, "a").unwrap();
+    let b = world.invalidate(a).unwrap();
+    assert_eq!(world.find_by_name("a"), Some(b));
+    assert_eq!(world.name(b), Some("a"));
+    assert_eq!(world.find_by::<i32>(&7).unwrap().collect::<Vec<_>>(), [b]);
+}
+
+#[test]
+fn query_search() {
+    let mut world = World::new();
+    let a = world.spawn((1,));
+    let b = world.spawn((2, true));
+    let c = world.spawn((3, "c"));
+    let mut query = world.query::<&i32>();
+    assert_eq!(
+        query.find(|_, &&x| x > 1).map(|(e, &x)| (e, x)),
+        Some((b, 2))
+    );
+    assert_eq!(query.find_map(|e, &x| (x == 3).then_some(e)), Some(c));
+    assert!(query.any(|e, _| e == a));
+    assert!(!query.any(|_, &x| x > 3));
+    let order = query.iter().map(|(e, _)| e).collect::<Vec<_>>();
+    assert_eq!(
+        query.position(|e, _| e == c),
+        order.iter().position(|&e| e == c)
+    );
+    assert_eq!(query.position(|_, &x| x == 4), None);
+    let mut calls = 0;
+    query.any(|_, _| {
+        calls += 1;
+        true
+    });
+    assert_eq!(calls, 1);
+}
+
+#[test]
+fn transfer() {
+    let mut a = World::new();
+    let mut b = World::new();
+    let mut translator = EntityTranslator::new();
+    let e = a.spawn((1, "x"));
+    let f = b.spawn((0,));
+    // Same handle, distinct worlds
+    assert_eq!(e, f);
+    let f2 = b.transfer(f, &mut a, &mut translator).unwrap();
+    let e2 = a.transfer(e, &mut b, &mut translator).unwrap();
+    assert!(!a.contains(e));
+    assert_eq!(*b.get::<&i32>(e2).unwrap(), 1);
+    assert_eq!(*b.get::<&&str>(e2).unwrap(), "x");
+    assert_eq!(*a.get::<&i32>(f2).unwrap(), 0);
+    assert_eq!(translator.translate(a.id(), e), Some(e2));
+    assert_eq!(translator.translate(b.id(), f), Some(f2));
+    assert_eq!(translator.len(), 2);
+    assert_eq!(a.transfer(e, &mut b, &mut translator), Err(NoSuchEntity));
+    assert_eq!(translator.forget(a.id(), e), Some(e2));
+    assert_eq!(translator.translate(a.id(), e), None);
+    assert_eq!(translator.len(), 1);
+}
+
+#[test]
+fn views_mut() {
+    let mut world = World::new();
+    let a = world.spawn((1, 2.0f32, true));
+    let b = world.spawn((3,));
+    let (mut ints, bools, more_ints) = world.views_mut::<(&mut i32, &bool, ())>();
+    assert!(*bools.get(a).unwrap());
+    assert!(bools.get(b).is_none());
+    *ints.get_mut(b).unwrap() += 1;
+    assert!(more_ints.contains(b));
+    assert_eq!(*world.get::<&i32>(b).unwrap(), 4);
+}
+
+#[test]
+#[should_panic(expected = "queries violate a unique borrow")]
+fn views_mut_conflict() {
+    let mut world = World::new();
+    let _ = world.views_mut::<(&mut i32, (&bool, &i32))>();
+}
+
+#[test]
+fn assert_disjoint() {
+    assert_disjoint!(&mut i32, &bool, (&f32, &bool));
+    assert_disjoint!(Without<&mut i32, &bool>, Option<&mut f32>,);
+}
+
+#[test]
+#[should_panic(expected = "queries violate a unique borrow")]
+fn assert_disjoint_conflict() {
+    assert_disjoint!(&mut i32, (&bool, Option<&i32>));
+}
+
+#[test]
+fn compact_ids() {
+    let mut world = World::new();
+    world.index::<i32, _>(|&x| x);
+    let entities = (0..10).map(|i| world.spawn((i,))).collect::<Vec<_>>();
+    world.set_name(entities[9], "last").unwrap();
+    for &e in &entities[..6] {
+        world.despawn(e).unwrap();
+    }
+    let stale = entities[0];
+    let map = world.compact_ids();
+    assert_eq!(map.len(), 4);
+    assert_eq!(world.len(), 4);
+    let mut ids = world.iter().map(|e| e.entity().id()).collect::<Vec<_>>();
+    ids.sort_unstable();
+    assert_eq!(ids, [0, 1, 2, 3]);
+    for (i, &old) in entities.iter().enumerate().skip(6) {
+        let new = map.get(old).unwrap();
+        assert!(!world.contains(old));
+        assert_eq!(*world.get::<&i32>(new).unwrap(), i as i32);
+        assert_eq!(
+            world
+                .find_by::<i32>(&(i as i32))
+                .unwrap()
+                .collect::<Vec<_>>(),
+            [new]
+        );
+    }
+    assert_eq!(world.find_by_name("last"), Some(map.map(entities[9])));
+    assert!(!world.contains(stale));
+    for (_, &i) in world.query_mut::<&i32>() {
+        assert!(i >= 6);
+    }
+
+    // Nothing further to compact
+    let e = world.spawn((10,));
+    assert_eq!(e.id(), 4);
+    assert!(world.compact_ids().is_empty());
+}
+
+#[test]
+fn compact_ids_stale_handles() {
+    let mut world = World::new();
+    let a = world.spawn(());
+    let b = world.spawn(());
+    world.despawn(a).unwrap();
+    let map = world.compact_ids();
+    assert_eq!(map.map(b).id(), a.id());
+    let c = world.spawn(());
+    assert_eq!(c.id(), b.id());
+    assert_ne!(c, b);
+    assert!(!world.contains(b));
+    assert!(world.contains(c));
+    let reserved = world.reserve_entity();
+    assert!(!world.contains(reserved));
+    world.flush();
+    assert!(world.contains(reserved));
+    assert!(world.despawn(b).is_err());
+}
+
+#[test]
+fn split_item() {
+    fn total(shared: (&i32, (), Option<&bool>)) -> i32 {
+        *shared.0 + shared.2.map_or(0, |&b| b as i32)
+    }
+
+    let mut world = World::new();
+    let a = world.spawn((1, 2u8, true));
+    let b = world.spawn((3, 4u8));
+    for (_, item) in world.query_mut::<(&i32, &mut u8, Option<&bool>)>() {
+        let (((), x, _), shared) = item.split();
+        *x += total(shared) as u8 + total(shared) as u8;
+    }
+    assert_eq!(*world.get::<&u8>(a).unwrap(), 6);
+    assert_eq!(*world.get::<&u8>(b).unwrap(), 10);
+}
+
+#[test]
+fn shared_pool() {
+    let mut pool = SharedPool::new();
+    let mut world = World::new();
+    let a = world.spawn((pool.get([7u8; 200]),));
+    let b = world.spawn((pool.get([7u8; 200]), true));
+    world.spawn((pool.get([1u8; 200]),));
+    assert_eq!(pool.len(), 2);
+    {
+        let x = world.get::<&Shared<[u8; 200]>>(a).unwrap();
+        let y = world.get::<&Shared<[u8; 200]>>(b).unwrap();
+        assert!(Shared::ptr_eq(&x, &y));
+        assert_eq!(Shared::count(&x), 3);
+    }
+    world.clear();
+    pool.purge();
+    assert!(pool.is_empty());
+}
+
+#[test]
+fn view_shared() {
+    let mut world = World::new();
+    let a = world.spawn((1, true));
+    let view = world.view_shared::<&i32>();
+    let clone = view.clone();
+    let other = world.view_shared::<(&i32, &bool)>();
+    assert_eq!(*view.get(a).unwrap(), 1);
+    assert_eq!(*clone.get(a).unwrap(), 1);
+    assert!(*other.get(a).unwrap().1);
+    drop((view, clone, other));
+    assert_eq!(world.query::<&mut i32>().iter().count(), 1);
+}
+
+#[test]
+#[should_panic(expected = "already borrowed")]
+fn view_shared_clone_borrows() {
+    let mut world = World::new();
+    world.spawn((1,));
+    let view = world.view_shared::<&i32>();
+    let _clone = view.clone();
+    drop(view);
+    world.query::<&mut i32>().iter().count();
+}
+
+#[test]
+fn get_or_insert_with() {
+    let mut world = World::new();
+    let e = world.spawn(("abc",));
+    let mut calls = 0;
+    for _ in 0..3 {
+        *world
+            .get_or_insert_with(e, || {
+                calls += 1;
+                0i32
+            })
+            .unwrap() += 1;
+    }
+    assert_eq!(calls, 1);
+    assert_eq!(*world.get::<&i32>(e).unwrap(), 3);
+    assert_eq!(*world.get::<&&str>(e).unwrap(), "abc");
+
+    world.despawn(e).unwrap();
+    assert!(world.get_or_insert_with(e, || 0i32).is_err());
+}
+
+#[test]
+fn alloc_hooks() {
+    use std::sync::{Arc, Mutex};
+
+    let mut world = World::new();
+    let len = Arc::new(Mutex::new(0));
+    let recycled = Arc::new(Mutex::new(Vec::new()));
+    let sink = len.clone();
+    world.on_entities_resize(move |n| *sink.lock().unwrap() = n);
+    let sink = recycled.clone();
+    world.on_id_recycle(move |id| sink.lock().unwrap().push(id));
+
+    let a = world.spawn(());
+    let b = world.spawn(());
+    assert_eq!(*len.lock().unwrap(), 2);
+    world.despawn(a).unwrap();
+    assert!(recycled.lock().unwrap().is_empty());
+    let c = world.spawn(());
+    assert_eq!(c.id(), a.id());
+    assert_eq!(*recycled.lock().unwrap(), [a.id()]);
+
+    let reserved = world.reserve_entities(2).collect::<Vec<_>>();
+    assert_eq!(*len.lock().unwrap(), 2);
+    world.flush();
+    assert_eq!(*len.lock().unwrap(), 4);
+    assert!(reserved.iter().all(|e| e.id() < 4));
+
+    world.spawn_batch((0..3).map(|i| (i,)));
+    assert_eq!(*len.lock().unwrap(), 7);
+
+    world.despawn(b).unwrap();
+    world.compact_ids();
+    assert_eq!(*len.lock().unwrap(), 6);
+    assert_eq!(recycled.lock().unwrap().last(), Some(&b.id()));
+
+    world.clear();
+    assert_eq!(*len.lock().unwrap(), 0);
+}
+
+#[test]
+#[cfg(feature = "macros")]
+fn derived_query_satisfies() {
+    #[derive(Query, Debug, PartialEq)]
+    struct Foo<'a> {
+        x: &'a mut i32,
+        #[query(satisfies(&'a bool))]
+        flagged: bool,
+    }
+
+    #[derive(Query, Debug, PartialEq)]
+    struct Bar<'a>(&'a i32, #[query(satisfies((&'a bool, &'a char)))] bool);
+
+    let mut world = World::new();
+    let a = world.spawn((1,));
+    let b = world.spawn((2, true));
+    let c = world.spawn((3, false, 'c'));
+    for (_, foo) in world.query_mut::<Foo>() {
+        *foo.x *= if foo.flagged { 10 } else { -1 };
+    }
+    // A satisfies field doesn't borrow its query
+    let mut bools = world.query::<&mut bool>();
+    let _it = bools.iter();
+    let mut bars = world
+        .query::<Bar>()
+        .iter()
+        .map(|(e, bar)| (e, *bar.0, bar.1))
+        .collect::<Vec<_>>();
+    bars.sort_unstable_by_key(|x| x.1);
+    assert_eq!(bars, [(a, -1, false), (b, 20, false), (c, 30, true)]);
+}
+
+#[test]
+fn from_column_batches() {
+    fn batch(values: &[i32], flag: Option<bool>) -> ColumnBatch {
+        let mut ty = ColumnBatchType::new();
+        ty.add::<i32>();
+        if flag.is_some() {
+            ty.add::<bool>();
+        }
+        let mut batch = ty.into_batch(values.len() as u32);
+        let mut writer = batch.writer::<i32>().unwrap();
+        for &x in values {
+            writer.push(x).unwrap();
+        }
+        if let Some(flag) = flag {
+            let mut writer = batch.writer::<bool>().unwrap();
+            for _ in values {
+                writer.push(flag).unwrap();
+            }
+        }
+        batch.build().unwrap()
+    }
+
+    let (mut world, entities) = World::from_column_batches(vec![
+        batch(&[1, 2], None),
+        batch(&[3], Some(true)),
+        batch(&[4, 5], None),
+    ]);
+    assert_eq!(world.len(), 5);
+    assert_eq!(entities.len(), 5);
+    for (i, &e) in entities.iter().enumerate() {
+        assert_eq!(*world.get::<&i32>(e).unwrap(), i as i32 + 1);
+    }
+    assert!(*world.get::<&bool>(entities[2]).unwrap());
+    assert_eq!(world.query_mut::<&bool>().into_iter().count(), 1);
+    let e = world.spawn((6,));
+    assert!(!entities.contains(&e));
+}
+
+#[test]
+fn aliased() {
+    #[derive(Debug, PartialEq)]
+    #[repr(transparent)]
+    struct Health(u32);
+
+    unsafe impl Alias for Health {
+        type Target = u32;
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Vec2([f32; 2]);
+
+    #[repr(transparent)]
+    struct Position(Vec2);
+
+    // The wrapped type may alias its wrapper just as well
+    unsafe impl Alias for Vec2 {
+        type Target = Position;
+    }
+
+    let mut world = World::new();
+    let a = world.spawn((10u32,));
+    let b = world.spawn((20u32, Position(Vec2([1.0, 2.0]))));
+    for (_, health) in world.query_mut::<Aliased<&mut Health>>() {
+        health.0 -= 1;
+    }
+    assert_eq!(*world.get::<&u32>(a).unwrap(), 9);
+    let mut healths = world
+        .query::<Aliased<&Health>>()
+        .iter()
+        .map(|(e, h)| (e, h.0))
+        .collect::<Vec<_>>();
+    healths.sort_unstable_by_key(|x| x.1);
+    assert_eq!(healths, [(a, 9), (b, 19)]);
+    assert_eq!(
+        *world.query_one_mut::<Aliased<&Vec2>>(b).unwrap(),
+        Vec2([1.0, 2.0])
+    );
+    assert!(Borrows::of::<Aliased<&mut Health>>().conflicts(&Borrows::of::<&u32>()));
+}
+
+#[test]
+#[should_panic(expected = "borrowed")]
+fn aliased_conflict() {
+    #[repr(transparent)]
+    struct Health(u32);
+
+    unsafe impl Alias for Health {
+        type Target = u32;
+    }
+
+    let mut world = World::new();
+    world.spawn((10u32,));
+    let mut raw = world.query::<&u32>();
+    let _raw = raw.iter();
+    world.query::<Aliased<&mut Health>>().iter().count();
+}
+
+#[test]
+fn try_query() {
+    let mut world = World::new();
+    let e = world.spawn((1, true, 'a'));
+    world.spawn((2, 'b'));
+
+    let mut chars = world.query::<&mut char>();
+    assert_eq!(chars.iter().count(), 2);
+    // A conflict in a later element releases borrows taken by earlier ones
+    assert!(world.try_query::<(&mut i32, &char)>().is_err());
+    assert!(world.try_query::<(&mut bool, Option<&char>)>().is_err());
+    assert!(world.try_view::<&char>().is_err());
+    drop(chars);
+
+    let mut ints = world.try_query::<(&mut i32, Option<&bool>)>().unwrap();
+    assert!(world.try_query::<&i32>().is_err());
+    assert!(world.try_query::<&bool>().is_ok());
+    assert!(world.try_query::<&mut bool>().is_err());
+    assert_eq!(ints.iter().count(), 2);
+    drop(ints);
+
+    let view = world.try_view::<&bool>().unwrap();
+    assert!(*view.get(e).unwrap());
+    assert!(world.query::<&mut i32>().iter().next().is_some());
+    let err = world.try_query::<&mut bool>().err().unwrap();
+    assert_eq!(
+        err.to_string(),
+        "&mut bool conflicts with an outstanding borrow"
+    );
+    drop(view);
+    world
+        .query::<(&mut i32, &mut bool, &mut char)>()
+        .iter()
+        .count();
+}
+
+#[test]
+#[cfg(feature = "macros")]
+fn try_query_derived() {
+    #[derive(Query)]
+    struct Foo<'a> {
+        x: &'a mut i32,
+        y: &'a bool,
+    }
+
+    let mut world = World::new();
+    world.spawn((1, true));
+    let mut bools = world.query::<&mut bool>();
+    assert_eq!(bools.iter().count(), 1);
+    assert!(world.try_query::<Foo>().is_err());
+    drop(bools);
+    world.query::<&mut i32>().iter().count();
+    for (_, foo) in world.try_query::<Foo>().unwrap().iter() {
+        *foo.x += *foo.y as i32;
+    }
+    assert_eq!(world.query_mut::<&i32>().into_iter().next().unwrap().1, &2);
+}
+
+#[test]
+#[cfg(feature = "metrics")]
+fn query_metrics() {
+    let mut world = World::new();
+    world.spawn((1, true));
+    world.spawn((2,));
+    world.spawn(("abc",));
+    world.query::<&i32>().iter().count();
+    world.query_mut::<&i32>().into_iter().count();
+    world
+        .query_mut::<&i32>()
+        .with::<&bool>()
+        .into_iter()
+        .count();
+    // Not iterated
+    let _ = world.query_mut::<&i32>();
+    world.query::<(&i32, &bool)>().view();
+
+    let mut metrics = world.query_metrics();
+    metrics.sort_unstable_by_key(|x| x.name);
+    let ints = metrics
+        .iter()
+        .find(|x| x.fetch == std::any::TypeId::of::<<&i32 as Query>::Fetch>())
+        .unwrap();
+    assert_eq!(ints.executions, 2);
+    assert_eq!(ints.archetypes, 4);
+    assert_eq!(ints.entities, 4);
+    assert_eq!(metrics.len(), 2);
+
+    world.reset_query_metrics();
+    assert!(world.query_metrics().is_empty());
+}
+
+#[test]
+fn builder_pool() {
+    let mut world = World::new();
+    let mut pool = BuilderPool::new();
+    let a = world.spawn_into(&mut pool, |b| {
+        b.add(1).add(String::from("a"));
+    });
+    // Builders can be taken while another is in use
+    let b = world.spawn_into(&mut pool, |b| {
+        b.add(2);
+    });
+    let mut outer = pool.take();
+    let mut inner = pool.take();
+    outer.add(3);
+    inner.add(true);
+    let c = world.spawn(outer.build());
+    world.insert(c, inner.build()).unwrap();
+    pool.give(outer);
+    pool.give(inner);
+    assert_eq!(pool.len(), 2);
+
+    assert_eq!(*world.get::<&String>(a).unwrap(), "a");
+    assert_eq!(*world.get::<&i32>(b).unwrap(), 2);
+    assert!(*world.get::<&bool>(c).unwrap());
+
+    let mut builder = EntityBuilder::with_capacity(2, 16);
+    builder.add(4u64).add(5u32);
+    let d = world.spawn(builder.build());
+    assert_eq!(*world.get::<&u64>(d).unwrap(), 4);
+    assert_eq!(*world.get::<&u32>(d).unwrap(), 5);
+}
+
+#[test]
+#[cfg(feature = "large-tuples")]
+fn large_tuple_query() {
+    let mut world = World::new();
+    let e = world.spawn((1u8, 2u16, 3u32, 4u64, 5u128, 6i8, 7i16, 8i32, 9i64, 10i128));
+    world
+        .insert(
+            e,
+            (
+                11usize,
+                12isize,
+                13.0f32,
+                14.0f64,
+                true,
+                'x',
+                "abc",
+                String::from("def"),
+                [19u8],
+                [20u16],
+            ),
+        )
+        .unwrap();
+    let mut query = world.query::<(
+        &u8,
+        &u16,
+        &u32,
+        &u64,
+        &u128,
+        &i8,
+        &i16,
+        &i32,
+        &i64,
+        &i128,
+        &usize,
+        &isize,
+        &f32,
+        &f64,
+        &bool,
+        &char,
+        &&str,
+        &String,
+        &[u8; 1],
+        &mut [u16; 2],
+    )>();
+    assert_eq!(query.iter().count(), 0);
+    drop(query);
+
+    let mut query = world.query::<(
+        &u8,
+        &u16,
+        &u32,
+        &u64,
+        &u128,
+        &i8,
+        &i16,
+        &i32,
+        &i64,
+        &i128,
+        &usize,
+        &isize,
+        &f32,
+        &f64,
+        &bool,
+        &char,
+        &&str,
+        &String,
+        &[u8; 1],
+        &mut [u16; 1],
+    )>();
+    let (entity, item) = query.iter().next().unwrap();
+    assert_eq!(entity, e);
+    assert_eq!(*item.0, 1);
+    assert_eq!(item.17, "def");
+    item.19[0] += 1;
+    drop(query);
+    assert_eq!(*world.get::<&[u16; 1]>(e).unwrap(), [21]);
+}
+
+#[test]
+fn required_components() {
+    #[derive(Default, Debug, PartialEq)]
+    struct Transform(i32);
+    #[derive(Default, Debug, PartialEq)]
+    struct GlobalTransform(i32);
+    struct Sprite;
+
+    let mut world = World::new();
+    let before = world.spawn((Sprite,));
+    world.require::<Sprite, Transform>();
+    world.require::<Transform, GlobalTransform>();
+    assert!(world.get::<&Transform>(before).is_err());
+
+    // Transitive
+    let a = world.spawn((Sprite,));
+    assert_eq!(*world.get::<&Transform>(a).unwrap(), Transform(0));
+    assert_eq!(
+        *world.get::<&GlobalTransform>(a).unwrap(),
+        GlobalTransform(0)
+    );
+
+    // Supplied components take precedence
+    let b = world.spawn((Sprite, Transform(1)));
+    assert_eq!(*world.get::<&Transform>(b).unwrap(), Transform(1));
+    assert_eq!(
+        *world.get::<&GlobalTransform>(b).unwrap(),
+        GlobalTransform(0)
+    );
+
+    // Existing components are kept on insert
+    let c = world.spawn((Transform(2), GlobalTransform(3)));
+    world.insert_one(c, Sprite).unwrap();
+    assert_eq!(*world.get::<&Transform>(c).unwrap(), Transform(2));
+    assert_eq!(
+        *world.get::<&GlobalTransform>(c).unwrap(),
+        GlobalTransform(3)
+    );
+
+    let d = world.spawn((7,));
+    world.insert_one(d, Sprite).unwrap();
+    assert!(world
+        .satisfies::<(&i32, &Sprite, &Transform, &GlobalTransform)>(d)
+        .unwrap());
+
+    let mut builder = EntityBuilder::new();
+    builder.add(Sprite);
+    let e = world.spawn(builder.build());
+    assert!(world.satisfies::<&GlobalTransform>(e).unwrap());
+
+    let many = world.spawn_many([(Sprite,), (Sprite,)]);
+    for e in many {
+        assert!(world.satisfies::<&GlobalTransform>(e).unwrap());
+    }
+}
+
+#[test]
+fn view_parts() {
+    let mut world = World::new();
+    let a = world.spawn_batch((0..5).map(|i| (i,))).collect::<Vec<_>>();
+    world.spawn_batch((0..4).map(|_| (true,)));
+    let b = world
+        .spawn_batch((5..12).map(|i| (i, 'x')))
+        .collect::<Vec<_>>();
+
+    let mut view = world.view_mut::<&mut i32>();
+    let (mut left, mut right) = view.split_at_mut(7);
+    assert_eq!(left.len(), 7);
+    assert_eq!(right.len(), 5);
+    assert!(left.contains(a[0]));
+    assert!(left.contains(b[1]));
+    assert!(!left.contains(b[2]));
+    assert!(right.contains(b[2]));
+    assert!(right.get_mut(a[4]).is_none());
+    assert_eq!(*right.get_mut(b[6]).unwrap(), 11);
+    assert_eq!(
+        left.iter_mut().map(|(_, &mut x)| x).collect::<Vec<_>>(),
+        [0, 1, 2, 3, 4, 5, 6]
+    );
+    assert_eq!(right.iter_mut().map(|(e, _)| e).collect::<Vec<_>>(), b[2..]);
+
+    let parts = view.chunks_mut(5);
+    assert_eq!(
+        parts.iter().map(|part| part.len()).collect::<Vec<_>>(),
+        [5, 5, 2]
+    );
+    std::thread::scope(|scope| {
+        for mut part in parts {
+            scope.spawn(move || {
+                for (_, x) in part.iter_mut() {
+                    *x += 100;
+                }
+            });
+        }
+    });
+    assert_eq!(view.iter_mut().map(|(_, &mut x)| x).sum::<i32>(), 1266);
+
+    let (empty, rest) = view.split_at_mut(0);
+    assert!(empty.is_empty());
+    assert_eq!(rest.len(), 12);
+}
+
+#[test]
+fn component_mask() {
+    use core::any::TypeId;
+
+    let mut world = World::new();
+    world.spawn((1i32, true));
+    world.spawn((1i32,));
+    world.spawn(("x", 'c'));
+
+    let both = ComponentMask::from_types([
+        TypeId::of::<bool>(),
+        TypeId::of::<i32>(),
+        TypeId::of::<i32>(),
+    ]);
+    assert_eq!(both.types().len(), 2);
+    assert!(both.contains(TypeId::of::<i32>()));
+    assert!(!both.contains(TypeId::of::<char>()));
+    assert_eq!(world.archetypes().filter(|a| a.matches(&both)).count(), 1);
+    assert_eq!(
+        world.archetypes().filter(|a| a.intersects(&both)).count(),
+        2
+    );
+
+    let empty = ComponentMask::from_types([]);
+    assert!(world.archetypes().all(|a| a.matches(&empty)));
+    assert!(!world.archetypes().any(|a| a.intersects(&empty)));
+
+    // Enough types that some are likely to share bits
+    macro_rules! check {
+        ($($n:literal)*) => {$(
+            let mut builder = EntityBuilder::new();
+            builder.add([0u8; $n]);
+            let e = world.spawn(builder.build());
+            let mask = ComponentMask::from_types([TypeId::of::<[u8; $n]>()]);
+            assert_eq!(world.archetypes().filter(|a| a.matches(&mask)).count(), 1);
+            assert!(world.satisfies::<&[u8; $n]>(e).unwrap());
+            assert!(!world.satisfies::<&i32>(e).unwrap());
+        )*};
+    }
+    check!(1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26 27 28 29 30);
+    assert_eq!(world.query::<&i32>().iter().count(), 2);
+}
+
+#[test]
+fn reserved_entities() {
+    let mut world = World::new();
+    let a = world.spawn((1,));
+    let b = world.spawn((2,));
+    let c = world.spawn((3,));
+    world.despawn(a).unwrap();
+    world.despawn(b).unwrap();
+    assert_eq!(world.reserved_entities().len(), 0);
+
+    let mut reserved = world.reserve_entities(3).collect::<Vec<_>>();
+    reserved.push(world.reserve_entity());
+    let mut listed = world.reserved_entities().collect::<Vec<_>>();
+    assert_eq!(listed.len(), world.pending_reserved());
+    reserved.sort();
+    listed.sort();
+    assert_eq!(listed, reserved);
+    assert!(!listed.contains(&c));
+
+    let mut flushed = Vec::new();
+    world.flush_with(|e| flushed.push(e));
+    flushed.sort();
+    assert_eq!(flushed, reserved);
+    assert_eq!(world.reserved_entities().len(), 0);
+}
+
+#[test]
+fn materialized_view_invalidate() {
+    let mut world = World::new();
+    let a = world.spawn((1,));
+    let mut view = MaterializedView::<&i32>::new();
+    assert_eq!(
+        view.query(&world)
+            .iter()
+            .map(|(e, _)| e)
+            .collect::<Vec<_>>(),
+        [a]
+    );
+    let b = world.invalidate(a).unwrap();
+    assert_eq!(
+        view.query(&world)
+            .iter()
+            .map(|(e, _)| e)
+            .collect::<Vec<_>>(),
+        [b]
+    );
+}
+
+#[test]
+fn observers_clear_respawn() {
+    use std::sync::{Arc, Mutex};
+
+    let mut world = World::new();
+    let matched = Arc::new(Mutex::new(Vec::new()));
+    let unmatched = Arc::new(Mutex::new(Vec::new()));
+    let sink = matched.clone();
+    world.on_match::<(&i32, &bool)>(move |e| sink.lock().unwrap().push(e));
+    let sink = unmatched.clone();
+    world.on_unmatch::<(&i32, &bool)>(move |e| sink.lock().unwrap().push(e));
+    let take = |x: &Arc<Mutex<Vec<Entity>>>| std::mem::take(&mut *x.lock().unwrap());
+
+    let a = world.spawn((1i32, true));
+    world.flush();
+    assert_eq!(take(&matched), [a]);
+
+    // The new entity reuses `a`'s handle, but is distinct
+    world.clear();
+    let b = world.spawn((2i32, true));
+    assert_eq!(a, b);
+    world.flush();
+    assert_eq!(take(&unmatched), [a]);
+    assert_eq!(take(&matched), [b]);
+
+    // Entities created and cleared between runs are not reported
+    world.clear();
+    world.spawn((3i32, true));
+    world.clear();
+    world.flush();
+    assert_eq!(take(&unmatched), [b]);
+    assert!(take(&matched).is_empty());
+}